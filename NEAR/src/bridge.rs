@@ -0,0 +1,166 @@
+// Verification of names locked on Solana with `lock_for_bridge`.
+//
+// A set of bridge guardians (Wormhole-style observers of the Solana program)
+// commits every Solana BridgeLock message to a merkle tree and each signs the
+// root with its ed25519 key. A claim carries the inclusion path for its lock
+// message plus the root and at least `threshold` guardian signatures, so no
+// single key can mint a bridged name.
+
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::env;
+use near_sdk::json_types::{I64, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{AccountId, CurveType, PublicKey};
+
+use crate::NominalError;
+
+const BRIDGE_LOCK_DOMAIN: &[u8] = b"nominal:bridge_lock";
+const BRIDGE_ROOT_DOMAIN: &[u8] = b"nominal:bridge_root";
+const MAX_MERKLE_PROOF_LEN: usize = 32;
+pub const MAX_BRIDGE_GUARDIANS: usize = 19;
+// Must stay shorter than the Solana program's BRIDGE_UNLOCK_TIMEOUT_SECONDS so a
+// lock the owner takes back on Solana can no longer be claimed here
+pub const BRIDGE_CLAIM_WINDOW_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct GuardianSet {
+    pub keys: Vec<PublicKey>,
+    pub threshold: u8,
+}
+
+impl GuardianSet {
+    // A strict majority of distinct ed25519 keys
+    pub fn validate(&self) {
+        ensure!(
+            !self.keys.is_empty() && self.keys.len() <= MAX_BRIDGE_GUARDIANS,
+            NominalError::InvalidRequest("Invalid guardian count")
+        );
+        ensure!(
+            self.keys.iter().all(|key| key.curve_type() == CurveType::ED25519),
+            NominalError::InvalidRequest("Guardian keys must be ed25519")
+        );
+        for (i, key) in self.keys.iter().enumerate() {
+            ensure!(!self.keys[..i].contains(key), NominalError::InvalidRequest("Duplicate guardian"));
+        }
+        ensure!(
+            (self.threshold as usize) * 2 > self.keys.len() && (self.threshold as usize) <= self.keys.len(),
+            NominalError::InvalidRequest("Guardian threshold must be a majority")
+        );
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct GuardianSignature {
+    pub guardian_index: u8,
+    // bs58 ed25519 signature over sha256(BRIDGE_ROOT_DOMAIN || root)
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BridgeProof {
+    pub message_nonce: U64,
+    // Unix seconds from the Solana BridgeLock account
+    pub locked_at: I64,
+    // Hex sibling hashes from the lock message leaf up to `root`
+    pub merkle_proof: Vec<String>,
+    // Hex merkle root signed by the guardians
+    pub root: String,
+    // Ordered by strictly increasing guardian_index
+    pub signatures: Vec<GuardianSignature>,
+}
+
+// Leaf for a BridgeLock message; fields are length-prefixed so no two locks share an encoding
+pub fn lock_message_leaf(
+    name: &str,
+    solana_owner: &[u8; 32],
+    near_owner: &AccountId,
+    message_nonce: u64,
+    locked_at: i64,
+) -> [u8; 32] {
+    let mut message = Vec::with_capacity(128);
+    message.extend_from_slice(BRIDGE_LOCK_DOMAIN);
+    message.extend_from_slice(&(name.len() as u32).to_le_bytes());
+    message.extend_from_slice(name.as_bytes());
+    message.extend_from_slice(solana_owner);
+    message.extend_from_slice(&(near_owner.as_str().len() as u32).to_le_bytes());
+    message.extend_from_slice(near_owner.as_bytes());
+    message.extend_from_slice(&message_nonce.to_le_bytes());
+    message.extend_from_slice(&locked_at.to_le_bytes());
+    env::sha256_array(&message)
+}
+
+// Sorted-pair sha256 so the proof needs no left/right flags
+pub fn merkle_root(leaf: [u8; 32], proof: &[[u8; 32]]) -> [u8; 32] {
+    proof.iter().fold(leaf, |node, sibling| {
+        let (first, second) = if node <= *sibling { (node, *sibling) } else { (*sibling, node) };
+        let mut pair = [0u8; 64];
+        pair[..32].copy_from_slice(&first);
+        pair[32..].copy_from_slice(&second);
+        env::sha256_array(&pair)
+    })
+}
+
+pub fn decode_solana_pubkey(solana_owner: &str) -> [u8; 32] {
+    let bytes = bs58::decode(solana_owner).into_vec().unwrap_or_default();
+    ensure!(bytes.len() == 32, NominalError::InvalidRequest("Invalid Solana owner"));
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&bytes);
+    key
+}
+
+fn decode_hash(hash: &str) -> [u8; 32] {
+    let bytes = hex::decode(hash).unwrap_or_default();
+    ensure!(bytes.len() == 32, NominalError::InvalidSignature("Invalid bridge proof hash"));
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&bytes);
+    out
+}
+
+pub fn root_signing_message(root: &[u8; 32]) -> [u8; 32] {
+    let mut message = Vec::with_capacity(BRIDGE_ROOT_DOMAIN.len() + 32);
+    message.extend_from_slice(BRIDGE_ROOT_DOMAIN);
+    message.extend_from_slice(root);
+    env::sha256_array(&message)
+}
+
+// Panics unless `leaf` is under the proof's root and at least `threshold`
+// distinct guardians signed that root
+pub fn verify_bridge_proof(guardians: &GuardianSet, leaf: [u8; 32], proof: &BridgeProof) {
+    ensure!(proof.merkle_proof.len() <= MAX_MERKLE_PROOF_LEN, NominalError::InvalidSignature("Bridge proof too long"));
+    let siblings: Vec<[u8; 32]> = proof.merkle_proof.iter().map(|h| decode_hash(h)).collect();
+    let root = decode_hash(&proof.root);
+    ensure!(merkle_root(leaf, &siblings) == root, NominalError::InvalidSignature("Lock message not in bridge root"));
+
+    ensure!(
+        proof.signatures.len() >= guardians.threshold as usize,
+        NominalError::InvalidSignature("Not enough guardian signatures")
+    );
+    let message = root_signing_message(&root);
+    let mut last_index: Option<u8> = None;
+    for guardian_signature in proof.signatures.iter() {
+        let index = guardian_signature.guardian_index;
+        // Increasing indexes rule out counting one guardian twice
+        ensure!(last_index.map_or(true, |last| index > last), NominalError::InvalidSignature("Guardian signatures out of order"));
+        last_index = Some(index);
+        let key = guardians.keys.get(index as usize)
+            .unwrap_or_else(|| env::panic_str(&NominalError::InvalidSignature("Unknown guardian").to_string()));
+
+        let signature = bs58::decode(&guardian_signature.signature).into_vec().unwrap_or_default();
+        ensure!(signature.len() == 64, NominalError::InvalidSignature("Invalid ED25519 signature length"));
+        let mut sig_array = [0u8; 64];
+        sig_array.copy_from_slice(&signature);
+
+        let key_data = key.as_bytes();
+        ensure!(key_data.len() == 33, NominalError::InvalidSignature("Invalid public key length"));
+        let mut key_array = [0u8; 32];
+        key_array.copy_from_slice(&key_data[1..33]);
+
+        ensure!(
+            env::ed25519_verify(&sig_array, &message, &key_array),
+            NominalError::InvalidSignature("Invalid guardian signature")
+        );
+    }
+}
@@ -0,0 +1,392 @@
+// NEP-297 event envelope and payloads.
+//
+// Every event is logged as `EVENT_JSON:{"standard":"nep297","version":"1.0.0",
+// "event":"nominal_<name>","data":[{...}]}` so NEAR indexers (and the subgraph in
+// nominal-subgraph/) can decode logs by event name without parsing ad-hoc strings.
+// u64 and u128 amounts are strings, matching near_sdk's JSON types.
+
+use near_sdk::env;
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::Serialize;
+use near_sdk::AccountId;
+
+pub const EVENT_STANDARD: &str = "nep297";
+pub const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NominalEventStandard<T: Serialize> {
+    pub standard: &'static str,
+    pub version: &'static str,
+    pub event: &'static str,
+    pub data: Vec<T>,
+}
+
+impl<T: Serialize> NominalEventStandard<T> {
+    pub fn new(event: &'static str, data: T) -> Self {
+        Self {
+            standard: EVENT_STANDARD,
+            version: EVENT_STANDARD_VERSION,
+            event,
+            data: vec![data],
+        }
+    }
+}
+
+pub fn emit_nep297(event: &impl Serialize) {
+    let json = serde_json::to_string(event).unwrap_or_else(|_| env::panic_str("Failed to serialize event"));
+    env::log_str(&format!("EVENT_JSON:{}", json));
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AdminActionData<'a> {
+    pub action_type: &'a str,
+    pub value: &'a str,
+    pub timestamp_ms: U64,
+    pub admin: &'a AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RegisteredData<'a> {
+    pub name: &'a str,
+    pub owner: &'a AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PrimaryNameSetData<'a> {
+    pub owner: &'a AccountId,
+    pub name: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CoinFeeScheduleSetData<'a> {
+    pub coin: &'a AccountId,
+    pub discount_bps: u16,
+    pub start_ms: U64,
+    pub end_ms: U64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchRelayersUpdatedData<'a> {
+    pub added: &'a [AccountId],
+    pub removed: &'a [AccountId],
+}
+
+// CoinFeeRemoved and CoinFeeDisabled
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CoinFeeData<'a> {
+    pub coin: &'a AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OwnershipProofGeneratedData<'a> {
+    pub name: &'a str,
+    pub owner: &'a AccountId,
+    pub proof_hash: &'a str,
+    pub timestamp: U64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AdminForcedResolutionUpdateData<'a> {
+    pub name: &'a str,
+    pub old_resolved: &'a AccountId,
+    pub new_resolved: &'a AccountId,
+    pub admin: &'a AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MetaTxExecutedData<'a> {
+    pub name: &'a str,
+    pub owner: &'a AccountId,
+    pub relayer: &'a AccountId,
+    pub action: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ExcessFeeRefundedData<'a> {
+    pub name: &'a str,
+    pub payer: &'a AccountId,
+    pub refund_amount: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BioSetData<'a> {
+    pub name: &'a str,
+    pub owner: &'a AccountId,
+    pub bio_len: usize,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AvatarSetData<'a> {
+    pub name: &'a str,
+    pub owner: &'a AccountId,
+    pub mime_type: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NameNormalizedData<'a> {
+    pub original: &'a str,
+    pub normalized: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BridgedNameClaimedData<'a> {
+    pub name: &'a str,
+    pub solana_owner: &'a str,
+    pub owner: &'a AccountId,
+    pub nonce: U64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PrefixIndexFullData<'a> {
+    pub prefix: &'a str,
+    pub name: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProtocolPausedData {
+    pub until_ms: U64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProtocolUnpausedData {}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NameExpiringWarningData<'a> {
+    pub name: &'a str,
+    pub owner: &'a AccountId,
+    pub expires_at: U64,
+    pub days_left: u64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ExpiredNamePremiumSetData {
+    pub bps: u32,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StaleMultiplierSetData {
+    pub bps: u32,
+    pub window_ms: U64,
+}
+
+// NamePurged, NameLocked and ReservationCancelled
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NameOwnerData<'a> {
+    pub name: &'a str,
+    pub owner: &'a AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtReferrerPaidData<'a> {
+    pub name: &'a str,
+    pub token: &'a AccountId,
+    pub referrer: &'a AccountId,
+    pub amount: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RegistrationFailedData<'a> {
+    pub name: &'a str,
+    pub owner: &'a AccountId,
+    pub reason: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NameResolvedData<'a> {
+    pub name: &'a str,
+    pub resolution_count: U64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OwnerTransferInitiatedData<'a> {
+    pub owner: &'a AccountId,
+    pub new_owner: &'a AccountId,
+    pub accept_after_ms: U64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OwnerTransferAcceptedData<'a> {
+    pub new_owner: &'a AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OwnerTransferCancelledData<'a> {
+    pub owner: &'a AccountId,
+    pub cancelled_pending: &'a AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NameTransferredData<'a> {
+    pub name: &'a str,
+    pub old_owner: &'a AccountId,
+    pub new_owner: &'a AccountId,
+    pub timestamp_ms: U64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NameTransferInitiatedData<'a> {
+    pub name: &'a str,
+    pub owner: &'a AccountId,
+    pub new_owner: &'a AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NameTransferCancelledData<'a> {
+    pub name: &'a str,
+    pub owner: &'a AccountId,
+    pub cancelled_pending: &'a AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ResolvedResetData<'a> {
+    pub name: &'a str,
+    pub old_resolved: &'a AccountId,
+    pub new_resolved: &'a AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AclGrantedData<'a> {
+    pub name: &'a str,
+    pub member: &'a AccountId,
+    pub permissions: u8,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AclRevokedData<'a> {
+    pub name: &'a str,
+    pub member: &'a AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NameReservedData<'a> {
+    pub name: &'a str,
+    pub owner: &'a AccountId,
+    pub expires_ms: U64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeePaidData<'a> {
+    pub name: &'a str,
+    pub payer: &'a AccountId,
+    // "NEAR" or the FT contract the fee was paid in
+    pub currency: &'a str,
+    pub amount: U128,
+    // Only set for expired name claims, where `amount` is the whole bid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective_fee: Option<U128>,
+    pub referrer: Option<&'a AccountId>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NameRenewedData<'a> {
+    pub name: &'a str,
+    pub payer: &'a AccountId,
+    pub expiry_ms: U64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DeletedNameCleanedUpData<'a> {
+    pub name: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AdminNameRemovedData<'a> {
+    pub name: &'a str,
+    pub owner: &'a AccountId,
+    pub reason: &'a str,
+    pub admin: &'a AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TreasuryDepositFailedData<'a> {
+    pub memo: &'a str,
+    pub amount: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenTreasuryTransferFailedData<'a> {
+    pub token: &'a AccountId,
+    pub amount: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SigRegistrationFailedData<'a> {
+    pub name: &'a str,
+    pub step: &'a str,
+}
+
+// RoleGranted and RoleRevoked
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoleData<'a> {
+    pub role: &'a str,
+    pub account: &'a AccountId,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionStartedData<'a> {
+    pub name: &'a str,
+    pub reserve_price: U128,
+    pub end_ms: U64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionBidData<'a> {
+    pub name: &'a str,
+    pub bidder: &'a AccountId,
+    pub amount: U128,
+    pub end_ms: U64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuctionSettledData<'a> {
+    pub name: &'a str,
+    // None when the auction closed without a bid
+    pub winner: Option<&'a AccountId>,
+    pub amount: U128,
+}
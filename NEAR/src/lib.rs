@@ -1,18 +1,214 @@
 use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LookupMap, UnorderedMap, UnorderedSet};
+use near_sdk::collections::{LookupMap, TreeMap, UnorderedMap, UnorderedSet, Vector};
 use near_sdk::json_types::{U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
-    env, near_bindgen, require, AccountId, PanicOnDefault, Promise,
-    Gas, NearToken, BorshStorageKey, ext_contract, PublicKey, PromiseResult,
+    env, near_bindgen, AccountId, PanicOnDefault, Promise, PromiseOrValue,
+    Gas, NearToken, BorshStorageKey, ext_contract, PublicKey, PromiseResult, CurveType,
 };
 use std::str::FromStr;
 
 const GAS_FOR_FT_TRANSFER: Gas = Gas::from_tgas(10);
+// Callback forwards the fee (or refunds it), so it needs room for one more
+// ft_transfer and its treasury callback
+const GAS_FOR_FT_CALLBACK: Gas = Gas::from_tgas(30);
+const GAS_FOR_TREASURY_DEPOSIT: Gas = Gas::from_tgas(10);
+// Falls back to a plain transfer when the deposit call fails
+const GAS_FOR_TREASURY_DEPOSIT_CALLBACK: Gas = Gas::from_tgas(10);
+// Sig registration callback forwards both shares, each with its own callback
+const GAS_FOR_SIG_FT_CALLBACK: Gas = Gas::from_tgas(70);
+// Parks a failed token treasury transfer in unpaid_token_fees
+const GAS_FOR_TOKEN_TREASURY_CALLBACK: Gas = Gas::from_tgas(10);
+// Records the referral, or sends an unpaid NEAR referrer share to the treasury
+const GAS_FOR_NEAR_REFERRER_CALLBACK: Gas = Gas::from_tgas(10);
+const DEFAULT_TREASURY_DEPOSIT_METHOD: &str = "deposit";
+const PENDING_FT_TIMEOUT_MS: u64 = 10 * 60 * 1000;
+const CONTRACT_VERSION: &str = "2.0.0";
+const RESERVATION_PERIOD_MS: u64 = 24 * 60 * 60 * 1000;
+// After a reservation ends, its owner waits this long before reserving the same name
+// again, so a name can't be held indefinitely by re-reserving it
+const RESERVATION_COOLDOWN_MS: u64 = 24 * 60 * 60 * 1000;
+// Default reservation fee as a fraction of the registration fee
+const DEFAULT_RESERVATION_FEE_DIVISOR: u128 = 10;
+const DEFAULT_TTL_MS: u64 = 300_000;
+// Same default as the Solana program's max_ttl_seconds
+const DEFAULT_MAX_TTL_MS: u64 = 24 * 60 * 60 * 1000;
+// Sentinel stored in coin_fees to keep a token slot while rejecting registrations
+const COIN_FEE_DISABLED: u128 = u128::MAX;
+// Matches the Solana program's bounds; the maximum also sizes length_histogram.
+// Names registered before a bound was tightened keep working on every path that
+// takes an existing record; only new registrations are checked
+const DEFAULT_MIN_NAME_LEN: u8 = 3;
+const MAX_NAME_LEN: usize = 63;
+const MAX_RELAYER_NAME_LEN: usize = 64;
+const MAX_RELAYER_URL_LEN: usize = 128;
+// ACL permission bits
+const ACL_CAN_SET_RESOLVED: u8 = 0x01;
+// Registry roles; the owner implicitly holds all of them
+const ROLE_ADMIN: &str = "ADMIN";
+const ROLE_FEE_MANAGER: &str = "FEE_MANAGER";
+const ROLE_RELAYER_MANAGER: &str = "RELAYER_MANAGER";
+const ROLES: [&str; 3] = [ROLE_ADMIN, ROLE_FEE_MANAGER, ROLE_RELAYER_MANAGER];
+const MAX_ADMIN_LOG_ENTRIES: u64 = 1000;
+const MAX_BATCH_SIZE: usize = 20;
+const MAX_RELAYER_BATCH_SIZE: usize = 50;
+// Bounds revoke_all_keys and get_authorized_key_count
+const MAX_KEYS_PER_ACCOUNT: u64 = 50;
+const EXPIRY_GRACE_PERIOD_MS: u64 = 30 * 24 * 60 * 60 * 1000;
+const MAX_REGISTRATION_PERIOD_MS: u64 = 10 * 365 * 24 * 60 * 60 * 1000;
+// Window after the grace period during which expired names carry the premium
+const EXPIRED_NAME_PREMIUM_WINDOW_MS: u64 = 24 * 60 * 60 * 1000;
+const DEFAULT_EXPIRED_NAME_PREMIUM_BPS: u32 = 50_000; // 5x
+const DEFAULT_STALE_MULTIPLIER_BPS: u32 = 10_000; // 1x
+const MAX_STALE_MULTIPLIER_BPS: u32 = 50_000; // 5x
+const DEFAULT_MAX_NAMES_PER_OWNER: u32 = 100;
+const DEFAULT_EXPIRY_WARNING_WINDOW_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+const MS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+const MAX_BIO_LEN: usize = 280;
+const MAX_REMOVAL_REASON_LEN: usize = 128;
+const MAX_AVATAR_URI_LEN: usize = 200;
+const SUPPORTED_AVATAR_MIME_TYPES: [&str; 5] = ["image/jpeg", "image/png", "image/gif", "image/webp", "image/svg+xml"];
+const MAX_HISTORY_ENTRIES: usize = 50;
+const MAX_PAUSE_DURATION_MS: u64 = 72 * 60 * 60 * 1000;
+// Minimum gap between the end of one pause and the next, so pauses can't be chained
+const PAUSE_COOLDOWN_MS: u64 = 24 * 60 * 60 * 1000;
+const DEFAULT_DELETION_COOLDOWN_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+const MAX_DELETION_COOLDOWN_MS: u64 = 30 * 24 * 60 * 60 * 1000;
+const DEFAULT_MAX_SIG_VALIDITY_MS: u64 = 24 * 60 * 60 * 1000;
+const DEFAULT_OWNER_TRANSFER_TIMELOCK_MS: u64 = 48 * 60 * 60 * 1000;
+const MAX_TIMELOCK_MS: u64 = 7 * 24 * 3600 * 1000;
+const FEE_HISTOGRAM_BUCKETS: usize = 8;
+const MAX_PURGE_BATCH: usize = 10;
+const MAX_RECORDS_PAGE_SIZE: u64 = 100;
+const PREFIX_LEN: usize = 2;
+const MAX_NAMES_PER_PREFIX: u64 = 1000;
+const DEFAULT_MIN_BID_INCREMENT: u128 = 100_000_000_000_000_000_000_000; // 0.1 NEAR
+// A bid this close to the end pushes the end back by the same amount
+const AUCTION_ANTI_SNIPE_MS: u64 = 5 * 60 * 1000;
+// Stop purging once less than this much gas is left so the call still completes
+const GAS_RESERVE_FOR_PURGE: Gas = Gas::from_tgas(10);
+
+// Panics carry a JSON payload so clients can match on the error code instead of
+// parsing free-form text. The detail keeps the human readable message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NominalError {
+    InvalidName,
+    NameAlreadyTaken,
+    InvalidSignature(&'static str),
+    InsufficientFee(&'static str),
+    ExactFeeRequired,
+    TokenNotEnabled,
+    DeadlineExpired,
+    RelayerNotAllowed,
+    InvalidNonce,
+    Unauthorized(&'static str),
+    RegistryPaused,
+    NameNotFound,
+    NameLocked,
+    InvalidRequest(&'static str),
+    SignatureTooOld,
+    PrefixIndexFull,
+}
+
+impl NominalError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            NominalError::InvalidName => "InvalidName",
+            NominalError::NameAlreadyTaken => "NameAlreadyTaken",
+            NominalError::InvalidSignature(_) => "InvalidSignature",
+            NominalError::InsufficientFee(_) => "InsufficientFee",
+            NominalError::ExactFeeRequired => "ExactFeeRequired",
+            NominalError::TokenNotEnabled => "TokenNotEnabled",
+            NominalError::DeadlineExpired => "DeadlineExpired",
+            NominalError::RelayerNotAllowed => "RelayerNotAllowed",
+            NominalError::InvalidNonce => "InvalidNonce",
+            NominalError::Unauthorized(_) => "Unauthorized",
+            NominalError::RegistryPaused => "RegistryPaused",
+            NominalError::NameNotFound => "NameNotFound",
+            NominalError::NameLocked => "NameLocked",
+            NominalError::InvalidRequest(_) => "InvalidRequest",
+            NominalError::SignatureTooOld => "SignatureTooOld",
+            NominalError::PrefixIndexFull => "PrefixIndexFull",
+        }
+    }
+
+    pub fn detail(&self) -> &'static str {
+        match self {
+            NominalError::InvalidName => "Invalid name",
+            NominalError::NameAlreadyTaken => "Name already taken",
+            NominalError::ExactFeeRequired => "Exact fee required",
+            NominalError::TokenNotEnabled => "Token fee disabled",
+            NominalError::DeadlineExpired => "Deadline expired",
+            NominalError::RelayerNotAllowed => "Relayer not allowed",
+            NominalError::InvalidNonce => "Invalid nonce",
+            NominalError::RegistryPaused => "Protocol is paused",
+            NominalError::NameNotFound => "Name not found",
+            NominalError::NameLocked => "Name is locked",
+            NominalError::SignatureTooOld => "Signature deadline exceeds max validity",
+            NominalError::PrefixIndexFull => "Prefix index bucket is full",
+            NominalError::InvalidSignature(detail)
+            | NominalError::InsufficientFee(detail)
+            | NominalError::Unauthorized(detail)
+            | NominalError::InvalidRequest(detail) => detail,
+        }
+    }
+}
+
+impl std::fmt::Display for NominalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{\"error\":\"{}\",\"detail\":\"{}\"}}", self.code(), self.detail())
+    }
+}
+
+macro_rules! ensure {
+    ($cond:expr, $err:expr $(,)?) => {
+        if !$cond {
+            env::panic_str(&$err.to_string())
+        }
+    };
+}
+
+mod bridge;
+mod events;
+pub use bridge::{BridgeProof, GuardianSet, GuardianSignature};
+use events::*;
+
+pub fn normalize_name(name: &str) -> String {
+    name.to_lowercase()
+}
+
+// Same character rules as the Solana program's validate_name; length is checked by the caller
+pub fn validate_name_chars(name: &str, allow_uppercase: bool, allow_underscore: bool) -> Result<(), String> {
+    for c in name.chars() {
+        let allowed = c.is_ascii_lowercase()
+            || c.is_ascii_digit()
+            || c == '-'
+            || (c.is_ascii_uppercase() && allow_uppercase)
+            || (c == '_' && allow_underscore);
+        if !allowed {
+            return Err(format!("Character not allowed: {}", c));
+        }
+    }
+    
+    if name.starts_with('-') || name.ends_with('-') {
+        return Err("Name cannot start or end with a hyphen".to_string());
+    }
+    if name.starts_with('_') || name.ends_with('_') {
+        return Err("Name cannot start or end with an underscore".to_string());
+    }
+    if name.contains("--") {
+        return Err("Name cannot contain consecutive hyphens".to_string());
+    }
+    if name.contains("__") {
+        return Err("Name cannot contain consecutive underscores".to_string());
+    }
+    Ok(())
+}
 
 #[ext_contract(ext_ft)]
 trait FungibleTokenCore {
     fn ft_transfer_from(&mut self, sender_id: AccountId, receiver_id: AccountId, amount: U128, memo: Option<String>);
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
 }
 
 #[ext_contract(ext_self)]
@@ -25,6 +221,39 @@ trait SelfContract {
         token_fee: u128,
         timestamp: u64,
     ) -> bool;
+    fn ft_transfer_referrer_callback(
+        &mut self,
+        name: String,
+        token: AccountId,
+        referrer: AccountId,
+        amount: U128,
+    ) -> bool;
+    fn treasury_deposit_callback(&mut self, amount: U128, memo: String) -> bool;
+    fn near_referrer_callback(&mut self, name: String, referrer: AccountId, amount: U128) -> bool;
+    fn token_treasury_transfer_callback(&mut self, token: AccountId, amount: U128) -> bool;
+    fn ft_transfer_sig_callback(
+        &mut self,
+        name: String,
+        owner: AccountId,
+        token: AccountId,
+        referrer: AccountId,
+        referrer_amount: U128,
+        timestamp: u64,
+    ) -> bool;
+}
+
+// DAO vaults and multisigs that accept fees through a payable deposit method
+#[ext_contract(ext_treasury)]
+trait TreasuryContract {
+    fn deposit(&mut self, memo: String);
+}
+
+// Interface for contracts resolving names through this registry. The resolved
+// account arrives as the promise result, e.g.
+// ext_nominal::ext(registry).resolve(name).then(ext_self::ext(me).on_resolved())
+#[ext_contract(ext_nominal)]
+pub trait NominalResolver {
+    fn resolve(&self, name: String) -> Option<AccountId>;
 }
 
 #[derive(BorshSerialize, BorshStorageKey)]
@@ -35,6 +264,40 @@ enum StorageKey {
     Relayers,
     Nonces,
     AuthorizedKeys,
+    RecordsV2,
+    Reservations,
+    CoinFeesV2,
+    RelayerMetadata,
+    Acl,
+    AdminLog,
+    ResolutionCounts,
+    PendingFtRegistrations,
+    TreasuryTokenAccounts,
+    NameCountByOwner,
+    Bios,
+    ExpiryWarnings,
+    ReferrerStats,
+    History,
+    CoinFeeSchedules,
+    PendingTransfers,
+    Avatars,
+    BridgeClaims,
+    SortedNames,
+    PrefixTrie,
+    DeletedNames,
+    Roles,
+    RoleMembers(String),
+    Auctions,
+    // Record with ttl_ms, locked and created_at; StorageKey::Records holds the 1.x layout
+    RecordsV3,
+    // Per-account key maps; StorageKey::AuthorizedKeys holds the 1.x "account:key" -> bool map
+    AuthorizedKeysV2,
+    AccountKeys(AccountId),
+    PrefixNames(String),
+    UnpaidTokenFees,
+    AccountNonces,
+    ReservationCooldowns,
+    AclEpochs,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
@@ -43,6 +306,48 @@ pub struct Record {
     pub owner: AccountId,
     pub resolved: AccountId,
     pub updated_at: U64,
+    pub ttl_ms: U64,
+    pub locked: bool,
+    pub created_at: U64,
+}
+
+// Record as stored by contract 1.x, kept in legacy_records until migrate_records moves it
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct LegacyRecord {
+    pub owner: AccountId,
+    pub resolved: AccountId,
+    pub updated_at: U64,
+}
+
+impl From<LegacyRecord> for Record {
+    fn from(legacy: LegacyRecord) -> Self {
+        Record {
+            owner: legacy.owner,
+            resolved: legacy.resolved,
+            updated_at: legacy.updated_at,
+            ttl_ms: U64(DEFAULT_TTL_MS),
+            locked: false,
+            // 1.x did not track registration time; the last update is the best bound
+            created_at: legacy.updated_at,
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RecordV2 {
+    pub owner: AccountId,
+    pub resolved: AccountId,
+    pub updated_at: U64,
+    pub expiry_ms: U64, // 0 = no expiry
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub enum SignatureType {
+    #[default]
+    Ed25519,
+    Secp256k1,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
@@ -55,6 +360,251 @@ pub struct RegisterWithSigParams {
     pub amount: U128,
     pub deadline: U64,
     pub nonce: U64,
+    #[serde(default)]
+    pub signature_type: SignatureType,
+}
+
+// Owner-signed request relayed by someone else; nonces are tracked per owner in
+// account_nonces, separately from the per-name registration nonces
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MetaTxParams {
+    pub name: String,
+    pub new_resolved: AccountId,
+    pub owner: AccountId,
+    pub nonce: U64,
+    pub deadline: U64,
+}
+
+// Registration of `name` for `owner`, paid for by `fee_payer`. The signature is only
+// needed when someone other than the fee payer submits it
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MetaRegistrationParams {
+    pub name: String,
+    pub owner: AccountId,
+    pub fee_payer: AccountId,
+    pub fee_amount: U128,
+    pub nonce: U64,
+    pub deadline: U64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Reservation {
+    pub owner: AccountId,
+    pub deposit: U128,
+    pub expires_ms: U64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Auction {
+    pub name: String,
+    // None until the first bid at or above reserve_price
+    pub highest_bidder: Option<AccountId>,
+    pub highest_bid: U128,
+    pub reserve_price: U128,
+    pub start_ms: U64,
+    pub end_ms: U64,
+    pub settled: bool,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct HistoryEntry {
+    pub old_resolved: AccountId,
+    pub new_resolved: AccountId,
+    pub changed_at: U64,
+    pub changed_by: AccountId,
+}
+
+// Promotional discount on a coin fee, active while start_ms <= now < end_ms
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CoinFeeSchedule {
+    pub coin: AccountId,
+    pub base_fee: U128,
+    pub discount_bps: u16,
+    pub discount_start_ms: U64,
+    pub discount_end_ms: U64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AvatarInfo {
+    pub uri: String,
+    pub mime_type: String,
+}
+
+// Lifetime referral payouts; token amounts are summed across all tokens
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReferrerStats {
+    pub total_earned_near: U128,
+    pub total_earned_tokens: U128,
+    pub referral_count: u32,
+}
+
+// `msg` of an ft_transfer_call that registers `name` for the sender
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtRegistrationMsg {
+    pub name: String,
+    pub referrer: Option<AccountId>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingFtReg {
+    pub owner: AccountId,
+    // Account the escrowed fee came from and is refunded to
+    pub payer: AccountId,
+    pub token: AccountId,
+    pub amount: U128,
+    pub created_ms: U64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RelayerMeta {
+    pub name: String,
+    pub endpoint_url: String,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AdminAction {
+    pub action_type: String,
+    pub value: String,
+    pub timestamp_ms: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NameAvailabilityResult {
+    pub available: bool,
+    pub reason: Option<String>,
+    pub fee: U128,
+    pub fee_usd_cents: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PauseStatus {
+    pub paused: bool,
+    pub until_ms: U64,
+    pub remaining_ms: U64,
+    // Earliest time the owner may pause again
+    pub next_pause_ms: U64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AuthorizedKeyInfo {
+    pub key: String,
+    pub expires_ms: Option<U64>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StatsView {
+    pub total_names: U64,
+    pub length_histogram: Vec<U64>,
+    pub fee_histogram: Vec<U64>,
+}
+
+// Social account linked to a name; same shape as the Solana SocialAttestation PDA
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SocialAttestation {
+    pub platform: String,
+    pub handle: String,
+    // Hex SHA256 of the attestation JSON signed by the platform's attester
+    pub attestation_hash: String,
+    pub attested_at: U64,
+}
+
+// Everything known about a name, returned by get_full_profile in one call.
+// text_records, social_attestations and coin_addresses are always empty until
+// those features exist on NEAR; clients can read the fields already.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NameProfile {
+    pub record: Option<Record>,
+    // Set when the name is its owner's primary name
+    pub primary_owner: Option<AccountId>,
+    pub bio: Option<String>,
+    pub avatar: Option<AvatarInfo>,
+    pub text_records: Vec<(String, String)>,
+    pub social_attestations: Vec<SocialAttestation>,
+    pub coin_addresses: Vec<(String, String)>,
+    // None for names without an expiry (not migrated, or expiry_ms == 0)
+    pub expiry_ms: Option<U64>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RecordsPage {
+    pub records: Vec<(String, Record)>,
+    // Pass back as `cursor` for the next page; None once the last record was returned
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RegistryConfigView {
+    pub owner: AccountId,
+    pub pending_owner: Option<AccountId>,
+    pub treasury: AccountId,
+    pub registration_fee: U128,
+    pub referrer_bps: u16,
+    pub min_referrer_bps: u16,
+    pub treasury_splits: Vec<(AccountId, u16)>,
+    pub require_relayer_allowlist: bool,
+    pub allow_admin_override: bool,
+    pub reservation_fee: U128,
+    pub coin_fees_migrated: bool,
+    pub reset_resolved_on_transfer: bool,
+    pub expired_name_premium_bps: u32,
+    pub stale_multiplier_bps: u32,
+    pub staleness_window_ms: U64,
+    pub max_names_per_owner: u32,
+    pub expiry_warning_window_ms: U64,
+    pub owner_transfer_timelock_ms: U64,
+    pub require_exact_fee: bool,
+    pub meta_tx_enabled: bool,
+    pub treasury_is_contract: bool,
+    pub treasury_deposit_method: String,
+    pub name_case_sensitive: bool,
+    pub prefix_search_enabled: bool,
+    pub deletion_cooldown_ms: U64,
+    pub max_sig_validity_ms: U64,
+    pub min_name_len: u8,
+    pub max_name_len: u8,
+    pub allow_underscore: bool,
+    pub allow_uppercase: bool,
+    pub min_bid_increment: U128,
+    pub auction_platform_fee_bps: u16,
+    pub auction_fee_recipient: Option<AccountId>,
+    pub max_ttl_ms: U64,
+    pub registration_period_ms: U64,
+}
+
+// Contract state as deployed by 1.x; read once by migrate
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct OldNameRegistry {
+    pub owner: AccountId,
+    pub treasury: AccountId,
+    pub registration_fee: u128,
+    pub referrer_bps: u16,
+    pub require_relayer_allowlist: bool,
+    pub records: UnorderedMap<String, LegacyRecord>,
+    pub primary_names: LookupMap<AccountId, String>,
+    pub coin_fees: LookupMap<AccountId, u128>,
+    pub relayers: UnorderedSet<AccountId>,
+    pub nonces: LookupMap<String, u64>,
+    pub authorized_keys: LookupMap<String, bool>,
 }
 
 #[near_bindgen]
@@ -64,74 +614,520 @@ pub struct NameRegistry {
     pub treasury: AccountId,
     pub registration_fee: u128,
     pub referrer_bps: u16,
+    pub min_referrer_bps: u16,
     pub require_relayer_allowlist: bool,
+    pub allow_admin_override: bool,
+    pub treasury_splits: Vec<(AccountId, u16)>,
     
     pub records: UnorderedMap<String, Record>,
     pub primary_names: LookupMap<AccountId, String>,
-    pub coin_fees: LookupMap<AccountId, u128>,
+    pub coin_fees: UnorderedMap<AccountId, u128>,
     pub relayers: UnorderedSet<AccountId>,
     pub nonces: LookupMap<String, u64>,
-    pub authorized_keys: LookupMap<String, bool>,
+    // Account -> key_b58 -> expiry in ms, 0 = never expires
+    pub authorized_keys: LookupMap<AccountId, UnorderedMap<String, u64>>,
+    pub records_v2: UnorderedMap<String, RecordV2>,
+    pub migrated: bool,
+    pub reservation_fee: u128,
+    pub reservations: LookupMap<String, Reservation>,
+    pub coin_fees_migrated: bool,
+    pub relayer_metadata: LookupMap<AccountId, RelayerMeta>,
+    pub acl: LookupMap<String, u8>,
+    // Ring buffer of the last MAX_ADMIN_LOG_ENTRIES admin actions; admin_log_head is the oldest slot once full
+    pub admin_log: Vector<AdminAction>,
+    pub admin_log_head: u64,
+    pub reset_resolved_on_transfer: bool,
+    pub pending_owner: Option<AccountId>,
+    // accept_owner opens owner_transfer_timelock_ms after the proposal
+    pub transfer_proposed_at_ms: u64,
+    pub owner_transfer_timelock_ms: u64,
+    pub resolution_counts: LookupMap<String, u64>,
+    pub pending_ft_registrations: LookupMap<String, PendingFtReg>,
+    // Token contract -> account that receives fees paid in that token (defaults to treasury)
+    pub treasury_token_accounts: UnorderedMap<AccountId, AccountId>,
+    pub expired_name_premium_bps: u32,
+    // 0 = unlimited
+    pub max_names_per_owner: u32,
+    pub name_count_by_owner: LookupMap<AccountId, u32>,
+    pub bios: LookupMap<String, String>,
+    pub avatars: LookupMap<String, AvatarInfo>,
+    // Last time an expiry warning was emitted for a name
+    pub expiry_warnings: LookupMap<String, u64>,
+    pub expiry_warning_window_ms: u64,
+    pub referrer_stats: LookupMap<AccountId, ReferrerStats>,
+    // Resolved address changes per name, oldest first, capped at MAX_HISTORY_ENTRIES
+    pub history: UnorderedMap<String, Vec<HistoryEntry>>,
+    // Registrations and name updates are rejected until this time; 0 = not paused
+    pub paused_until_ms: u64,
+    pub coin_fee_schedules: LookupMap<AccountId, CoinFeeSchedule>,
+    // Name -> recipient of a transfer awaiting accept_transfer
+    pub pending_transfers: LookupMap<String, AccountId>,
+    // When false, register and register_with_sig accept overpayment and refund the excess
+    pub require_exact_fee: bool,
+    pub meta_tx_enabled: bool,
+    // When true, fees are sent to the treasury through `treasury_deposit_method` instead of a plain transfer
+    pub treasury_is_contract: bool,
+    pub treasury_deposit_method: String,
+    // Registrations per name length; index is length - 1
+    pub length_histogram: Vec<u64>,
+    // NEAR fee buckets: <0.01, <0.1, <1, <10, <100, <1000, 1000+; last bucket counts token payments
+    pub fee_histogram: Vec<u64>,
+    // Signs merkle roots of Solana BridgeLock messages; claims are rejected while unset
+    pub bridge_guardians: Option<GuardianSet>,
+    // Solana lock message nonce -> name claimed with it
+    pub bridge_claims: LookupMap<u64, String>,
+    // When false, registration names are lowercased before any check or storage
+    pub name_case_sensitive: bool,
+    // Claims within staleness_window_ms of a name's expiry pay this multiple of the registration fee
+    pub stale_multiplier_bps: u32,
+    pub staleness_window_ms: u64,
+    // Every registered name in lexicographic order, for stable cursor pagination
+    pub sorted_names: TreeMap<String, ()>,
+    // First PREFIX_LEN bytes of a name -> names with that prefix, capped at MAX_NAMES_PER_PREFIX
+    pub trie: LookupMap<String, UnorderedSet<String>>,
+    pub prefix_search_enabled: bool,
+    // Name -> when its owner deleted it; re-registration waits deletion_cooldown_ms
+    pub deleted_names: LookupMap<String, u64>,
+    pub deletion_cooldown_ms: u64,
+    // register_with_sig rejects deadlines further than this past the current time
+    pub max_sig_validity_ms: u64,
+    // Names removed through admin_remove_name
+    pub admin_removal_count: u64,
+    // Name validation rules, see validate_name_chars
+    pub allow_underscore: bool,
+    pub allow_uppercase: bool,
+    pub min_name_len: u8,
+    pub max_name_len: u8,
+    // Role -> accounts granted it with grant_role
+    pub roles: LookupMap<String, UnorderedSet<AccountId>>,
+    // Premium names sold by auction; settled auctions are kept until the name is auctioned again
+    pub auctions: UnorderedMap<String, Auction>,
+    pub min_bid_increment: u128,
+    // Share of a winning bid paid to auction_fee_recipient; the rest goes through pay_treasury
+    pub auction_platform_fee_bps: u16,
+    // 1.x records not yet moved into `records`; registrations wait until this is empty
+    pub legacy_records: UnorderedMap<String, LegacyRecord>,
+    // Upper bound for set_ttl
+    pub max_ttl_ms: u64,
+    // Receives auction_platform_fee_bps of each winning bid, e.g. the auction front end
+    pub auction_fee_recipient: Option<AccountId>,
+    // Token fees whose treasury transfer failed, held here until retried
+    pub unpaid_token_fees: LookupMap<AccountId, u128>,
+    // Meta-tx nonces per signing account; `nonces` is keyed by name
+    pub account_nonces: LookupMap<AccountId, u64>,
+    // "name:account" -> earliest time the account may reserve the name again
+    pub reservation_cooldowns: LookupMap<String, u64>,
+    pub pause_cooldown_until_ms: u64,
+    // Bumped whenever a name changes hands; ACL entries from earlier epochs no longer apply
+    pub acl_epochs: LookupMap<String, u32>,
+    // How long a registration or renewal lasts; 0 registers names without an expiry
+    pub registration_period_ms: u64,
 }
 
 #[near_bindgen]
 impl NameRegistry {
     #[init]
-    pub fn new(owner: AccountId, treasury: AccountId, registration_fee: U128) -> Self {
+    pub fn new(
+        owner: AccountId,
+        treasury: AccountId,
+        registration_fee: U128,
+        owner_transfer_timelock_ms: Option<U64>,
+    ) -> Self {
+        let owner_transfer_timelock_ms = owner_transfer_timelock_ms.map_or(DEFAULT_OWNER_TRANSFER_TIMELOCK_MS, |ms| ms.0);
+        ensure!(owner_transfer_timelock_ms <= MAX_TIMELOCK_MS, NominalError::InvalidRequest("Timelock too long"));
         Self {
             owner,
             treasury,
             registration_fee: registration_fee.0,
             referrer_bps: 500,
+            min_referrer_bps: 0,
             require_relayer_allowlist: false,
-            records: UnorderedMap::new(StorageKey::Records),
+            allow_admin_override: true,
+            treasury_splits: Vec::new(),
+            records: UnorderedMap::new(StorageKey::RecordsV3),
             primary_names: LookupMap::new(StorageKey::PrimaryNames),
-            coin_fees: LookupMap::new(StorageKey::CoinFees),
+            coin_fees: UnorderedMap::new(StorageKey::CoinFeesV2),
             relayers: UnorderedSet::new(StorageKey::Relayers),
             nonces: LookupMap::new(StorageKey::Nonces),
-            authorized_keys: LookupMap::new(StorageKey::AuthorizedKeys),
+            authorized_keys: LookupMap::new(StorageKey::AuthorizedKeysV2),
+            records_v2: UnorderedMap::new(StorageKey::RecordsV2),
+            migrated: false,
+            reservation_fee: std::cmp::max(registration_fee.0 / DEFAULT_RESERVATION_FEE_DIVISOR, 1),
+            reservations: LookupMap::new(StorageKey::Reservations),
+            coin_fees_migrated: false,
+            relayer_metadata: LookupMap::new(StorageKey::RelayerMetadata),
+            acl: LookupMap::new(StorageKey::Acl),
+            admin_log: Vector::new(StorageKey::AdminLog),
+            admin_log_head: 0,
+            reset_resolved_on_transfer: false,
+            pending_owner: None,
+            transfer_proposed_at_ms: 0,
+            owner_transfer_timelock_ms,
+            resolution_counts: LookupMap::new(StorageKey::ResolutionCounts),
+            pending_ft_registrations: LookupMap::new(StorageKey::PendingFtRegistrations),
+            treasury_token_accounts: UnorderedMap::new(StorageKey::TreasuryTokenAccounts),
+            expired_name_premium_bps: DEFAULT_EXPIRED_NAME_PREMIUM_BPS,
+            max_names_per_owner: DEFAULT_MAX_NAMES_PER_OWNER,
+            name_count_by_owner: LookupMap::new(StorageKey::NameCountByOwner),
+            bios: LookupMap::new(StorageKey::Bios),
+            avatars: LookupMap::new(StorageKey::Avatars),
+            expiry_warnings: LookupMap::new(StorageKey::ExpiryWarnings),
+            expiry_warning_window_ms: DEFAULT_EXPIRY_WARNING_WINDOW_MS,
+            referrer_stats: LookupMap::new(StorageKey::ReferrerStats),
+            history: UnorderedMap::new(StorageKey::History),
+            paused_until_ms: 0,
+            coin_fee_schedules: LookupMap::new(StorageKey::CoinFeeSchedules),
+            pending_transfers: LookupMap::new(StorageKey::PendingTransfers),
+            require_exact_fee: true,
+            meta_tx_enabled: false,
+            treasury_is_contract: false,
+            treasury_deposit_method: DEFAULT_TREASURY_DEPOSIT_METHOD.to_string(),
+            length_histogram: vec![0; MAX_NAME_LEN],
+            fee_histogram: vec![0; FEE_HISTOGRAM_BUCKETS],
+            bridge_guardians: None,
+            bridge_claims: LookupMap::new(StorageKey::BridgeClaims),
+            name_case_sensitive: false,
+            stale_multiplier_bps: DEFAULT_STALE_MULTIPLIER_BPS,
+            staleness_window_ms: 0,
+            sorted_names: TreeMap::new(StorageKey::SortedNames),
+            trie: LookupMap::new(StorageKey::PrefixTrie),
+            prefix_search_enabled: false,
+            deleted_names: LookupMap::new(StorageKey::DeletedNames),
+            deletion_cooldown_ms: DEFAULT_DELETION_COOLDOWN_MS,
+            max_sig_validity_ms: DEFAULT_MAX_SIG_VALIDITY_MS,
+            admin_removal_count: 0,
+            allow_underscore: true,
+            allow_uppercase: true,
+            min_name_len: DEFAULT_MIN_NAME_LEN,
+            max_name_len: MAX_NAME_LEN as u8,
+            roles: LookupMap::new(StorageKey::Roles),
+            auctions: UnorderedMap::new(StorageKey::Auctions),
+            min_bid_increment: DEFAULT_MIN_BID_INCREMENT,
+            auction_platform_fee_bps: 0,
+            legacy_records: UnorderedMap::new(StorageKey::Records),
+            max_ttl_ms: DEFAULT_MAX_TTL_MS,
+            auction_fee_recipient: None,
+            unpaid_token_fees: LookupMap::new(StorageKey::UnpaidTokenFees),
+            account_nonces: LookupMap::new(StorageKey::AccountNonces),
+            reservation_cooldowns: LookupMap::new(StorageKey::ReservationCooldowns),
+            pause_cooldown_until_ms: 0,
+            acl_epochs: LookupMap::new(StorageKey::AclEpochs),
+            registration_period_ms: 0,
+        }
+    }
+
+    // Upgrades 1.x state in place. Settings missing from 1.x take the same defaults as
+    // `new`; records are moved afterwards in batches with migrate_records, and coin
+    // fees with migrate_coin_fees
+    #[init(ignore_state)]
+    #[private]
+    pub fn migrate() -> Self {
+        let old: OldNameRegistry = env::state_read()
+            .unwrap_or_else(|| env::panic_str(&NominalError::InvalidRequest("No state to migrate").to_string()));
+        
+        let mut registry = Self::new(old.owner, old.treasury, U128(old.registration_fee), None);
+        registry.referrer_bps = old.referrer_bps;
+        registry.require_relayer_allowlist = old.require_relayer_allowlist;
+        registry.primary_names = old.primary_names;
+        registry.relayers = old.relayers;
+        registry.nonces = old.nonces;
+        registry.legacy_records = old.records;
+        // Records are written through save_record, so records_v2 is filled as they move
+        registry.migrated = true;
+        
+        env::log_str(&format!(
+            "Migrated state to contract version {}; {} records pending",
+            CONTRACT_VERSION,
+            registry.legacy_records.len()
+        ));
+        registry
+    }
+
+    // Moves up to `limit` 1.x records into `records` and its indices. Returns how many are left
+    pub fn migrate_records(&mut self, limit: U64) -> U64 {
+        self.assert_owner();
+        self.log_admin_action("migrate_records", limit.0.to_string());
+        
+        let names: Vec<String> = self.legacy_records.keys().take(limit.0 as usize).collect();
+        for name in names.iter() {
+            let record: Record = match self.legacy_records.remove(name) {
+                Some(legacy) => legacy.into(),
+                None => continue,
+            };
+            // 1.x had no per-owner limit, so existing names are counted without enforcing it
+            let count = self.name_count_by_owner.get(&record.owner).unwrap_or(0);
+            self.name_count_by_owner.insert(&record.owner, &(count + 1));
+            self.count_name_length(name);
+            self.save_record(name, &record);
+            // Already registered, so a full bucket is reported rather than refused
+            if self.prefix_search_enabled && !self.add_to_prefix_index(name) {
+                self.emit_prefix_index_full(&name[..PREFIX_LEN], name);
+            }
         }
+        
+        let remaining = self.legacy_records.len();
+        env::log_str(&format!("Migrated {} records, {} remaining", names.len(), remaining));
+        U64(remaining)
     }
 
     #[payable]
     pub fn register(&mut self, name: String) {
+        let name = self.normalize_registration_name(name);
+        self.assert_not_paused();
         let owner = env::predecessor_account_id();
         let amount = env::attached_deposit();
         
-        require!(self.is_valid_name(&name), "Invalid name");
-        require!(!self.records.get(&name).is_some(), "Name already taken");
-        require!(amount.as_yoctonear() == self.registration_fee, "Exact fee required");
+        ensure!(self.is_valid_name(&name), NominalError::InvalidName);
+        ensure!(!self.records.get(&name).is_some(), NominalError::NameAlreadyTaken);
+        self.assert_not_reserved_by_other(&name, &owner);
+        self.assert_not_in_deletion_cooldown(&name);
+        self.assert_not_in_auction(&name);
+        let fee = self.take_registration_fee(&name, &owner, amount.as_yoctonear(), 1);
+        
+        let timestamp = env::block_timestamp_ms();
+        self.register_record_and_primary(&name, &owner, timestamp);
+        
+        self.pay_treasury(fee, &name);
+        
+        self.emit_registered(&name, &owner);
+        self.record_fee_stat(false, fee);
+        self.emit_fee_paid(&name, &owner, None, fee, None);
+    }
+
+    // Registers a name locked on Solana with lock_for_bridge; the caller must be
+    // the lock's near_owner. No fee is taken since the name was paid for on Solana
+    pub fn claim_bridged_name(&mut self, name: String, solana_owner: String, proof: BridgeProof) {
+        let name = self.normalize_registration_name(name);
+        self.assert_not_paused();
+        let claimant = env::predecessor_account_id();
+        let nonce = proof.message_nonce.0;
+        
+        ensure!(self.is_valid_name(&name), NominalError::InvalidName);
+        ensure!(self.records.get(&name).is_none(), NominalError::NameAlreadyTaken);
+        self.assert_not_in_deletion_cooldown(&name);
+        self.assert_not_in_auction(&name);
+        ensure!(self.bridge_claims.get(&nonce).is_none(), NominalError::InvalidNonce);
+        let now_secs = (env::block_timestamp_ms() / 1000) as i64;
+        ensure!(
+            now_secs <= proof.locked_at.0.saturating_add(bridge::BRIDGE_CLAIM_WINDOW_SECONDS),
+            NominalError::InvalidRequest("Bridge claim window closed")
+        );
+        let guardians = self.bridge_guardians.clone()
+            .unwrap_or_else(|| env::panic_str(&NominalError::InvalidRequest("Bridge guardians not set").to_string()));
+        
+        let owner_key = bridge::decode_solana_pubkey(&solana_owner);
+        let leaf = bridge::lock_message_leaf(&name, &owner_key, &claimant, nonce, proof.locked_at.0);
+        bridge::verify_bridge_proof(&guardians, leaf, &proof);
+        
+        self.bridge_claims.insert(&nonce, &name);
+        self.register_record_and_primary(&name, &claimant, env::block_timestamp_ms());
+        
+        self.emit_registered(&name, &claimant);
+        self.emit_bridged_name_claimed(&name, &solana_owner, &claimant, nonce);
+    }
+
+    // Re-registers a name whose grace period has passed; the whole deposit is the bid
+    #[payable]
+    pub fn claim_expired_name(&mut self, name: String) {
+        self.assert_not_paused();
+        let claimant = env::predecessor_account_id();
+        let amount = env::attached_deposit().as_yoctonear();
+        let now = env::block_timestamp_ms();
+        
+        ensure!(self.is_expired_past_grace(&name, now), NominalError::InvalidRequest("Name not expired"));
+        let price = self.expired_name_price(&name, now);
+        ensure!(amount >= price, NominalError::InsufficientFee("Bid below expired name price"));
+        
+        let previous = self.remove_record(&name);
+        self.register_record_and_primary(&name, &claimant, now);
+        self.pay_treasury(amount, &name);
         
+        self.emit_name_transferred(&name, &previous.owner, &claimant);
+        self.emit_registered(&name, &claimant);
+        self.record_fee_stat(false, amount);
+        self.emit_expired_name_fee_paid(&name, &claimant, amount, price);
+    }
+
+    // Extends a name by registration_period_ms from its current expiry, or from now if it
+    // has lapsed. Anyone may pay; names past the grace period go through claim_expired_name
+    #[payable]
+    pub fn renew_name(&mut self, name: String) {
+        self.assert_not_paused();
+        ensure!(self.registration_period_ms > 0, NominalError::InvalidRequest("Names do not expire"));
+        let payer = env::predecessor_account_id();
+        let record = self.get_existing_record(&name);
+        let now = env::block_timestamp_ms();
+        ensure!(!self.is_expired_past_grace(&name, now), NominalError::InvalidRequest("Name past grace period"));
+        
+        let fee = self.take_registration_fee(&name, &payer, env::attached_deposit().as_yoctonear(), 1);
+        let current_expiry = self.records_v2.get(&name).map_or(0, |r| r.expiry_ms.0);
+        let expiry_ms = std::cmp::max(current_expiry, now) + self.registration_period_ms;
+        self.set_expiry(&name, &record, expiry_ms);
+        // Let the next expiry warning fire for the new period
+        self.expiry_warnings.remove(&name);
+        self.pay_treasury(fee, &name);
+        
+        self.record_fee_stat(false, fee);
+        self.emit_fee_paid(&name, &payer, None, fee, None);
+        emit_nep297(&NominalEventStandard::new("nominal_name_renewed", NameRenewedData {
+            name: &name,
+            payer: &payer,
+            expiry_ms: U64(expiry_ms),
+        }));
+    }
+
+    // All-or-nothing: any invalid or taken name panics and rolls back the whole batch
+    #[payable]
+    pub fn batch_register(&mut self, names: Vec<String>) {
+        self.assert_not_paused();
+        let owner = env::predecessor_account_id();
+        let amount = env::attached_deposit().as_yoctonear();
+        
+        ensure!(!names.is_empty(), NominalError::InvalidRequest("Empty batch"));
+        ensure!(names.len() <= MAX_BATCH_SIZE, NominalError::InvalidRequest("Batch too large"));
+        // Normalized like register, so case variants collide as duplicates below
+        let names: Vec<String> = names.into_iter().map(|name| self.normalize_registration_name(name)).collect();
+        let total_fee = self.take_registration_fee(&names.join(","), &owner, amount, names.len() as u128);
+        
+        let timestamp = env::block_timestamp_ms();
+        for name in names.iter() {
+            ensure!(self.is_valid_name(name), NominalError::InvalidName);
+            ensure!(self.records.get(name).is_none(), NominalError::NameAlreadyTaken);
+            self.assert_not_reserved_by_other(name, &owner);
+            self.assert_not_in_deletion_cooldown(name);
+            self.assert_not_in_auction(name);
+            self.register_record_and_primary(name, &owner, timestamp);
+        }
+        
+        self.pay_treasury(total_fee, &names.join(","));
+        
+        for name in names.iter() {
+            self.emit_registered(name, &owner);
+            self.record_fee_stat(false, self.registration_fee);
+            self.emit_fee_paid(name, &owner, None, self.registration_fee, None);
+        }
+    }
+
+    pub fn estimate_batch_cost(&self, names: Vec<String>) -> U128 {
+        U128(self.registration_fee * names.len() as u128)
+    }
+
+    #[payable]
+    pub fn reserve_name(&mut self, name: String) {
+        let name = self.normalize_registration_name(name);
+        self.assert_not_paused();
+        let owner = env::predecessor_account_id();
+        let deposit = env::attached_deposit().as_yoctonear();
+        
+        ensure!(self.is_valid_name(&name), NominalError::InvalidName);
+        ensure!(self.records.get(&name).is_none(), NominalError::NameAlreadyTaken);
+        self.assert_not_in_auction(&name);
+        ensure!(deposit > 0 && deposit >= self.reservation_fee, NominalError::InsufficientFee("Insufficient reservation deposit"));
+        
+        let now = env::block_timestamp_ms();
+        let cooldown_key = format!("{}:{}", name, owner);
+        ensure!(
+            self.reservation_cooldowns.get(&cooldown_key).map_or(true, |until| now >= until),
+            NominalError::InvalidRequest("Reservation cooldown active")
+        );
+        if let Some(existing) = self.reservations.get(&name) {
+            ensure!(existing.expires_ms.0 < now, NominalError::InvalidRequest("Name already reserved"));
+            // Refund the lapsed reservation before replacing it
+            Promise::new(existing.owner).transfer(NearToken::from_yoctonear(existing.deposit.0));
+        }
+        
+        let expires_ms = now + RESERVATION_PERIOD_MS;
+        // Cancelling early doesn't shorten the cooldown
+        self.reservation_cooldowns.insert(&cooldown_key, &(expires_ms + RESERVATION_COOLDOWN_MS));
+        self.reservations.insert(&name, &Reservation {
+            owner: owner.clone(),
+            deposit: U128(deposit),
+            expires_ms: U64(expires_ms),
+        });
+        self.emit_name_reserved(&name, &owner, expires_ms);
+    }
+
+    #[payable]
+    pub fn claim_reservation(&mut self, name: String) {
+        self.assert_not_paused();
+        let owner = env::predecessor_account_id();
+        let reservation = self.reservations.get(&name).unwrap_or_else(|| env::panic_str(&NominalError::InvalidRequest("Reservation not found").to_string()));
+        
+        ensure!(reservation.owner == owner, NominalError::Unauthorized("Only reservation owner"));
+        ensure!(env::block_timestamp_ms() <= reservation.expires_ms.0, NominalError::InvalidRequest("Reservation expired"));
+        ensure!(self.records.get(&name).is_none(), NominalError::NameAlreadyTaken);
+        self.assert_not_in_auction(&name);
+        
+        // The reservation deposit counts towards the registration fee
+        let remaining = self.registration_fee.saturating_sub(reservation.deposit.0);
+        let amount = env::attached_deposit().as_yoctonear();
+        ensure!(amount == remaining, NominalError::ExactFeeRequired);
+        
+        self.reservations.remove(&name);
         let timestamp = env::block_timestamp_ms();
         self.register_record_and_primary(&name, &owner, timestamp);
         
-        Promise::new(self.treasury.clone()).transfer(NearToken::from_yoctonear(amount.as_yoctonear()));
+        let total = reservation.deposit.0 + amount;
+        let excess = total.saturating_sub(self.registration_fee);
+        self.pay_treasury(total - excess, &name);
+        if excess > 0 {
+            Promise::new(owner.clone()).transfer(NearToken::from_yoctonear(excess));
+        }
         
         self.emit_registered(&name, &owner);
-        self.emit_fee_paid(&name, &owner, None, amount.as_yoctonear(), None);
+        self.record_fee_stat(false, total - excess);
+        self.emit_fee_paid(&name, &owner, None, total - excess, None);
+    }
+
+    pub fn cancel_reservation(&mut self, name: String) {
+        let owner = env::predecessor_account_id();
+        let reservation = self.reservations.get(&name).unwrap_or_else(|| env::panic_str(&NominalError::InvalidRequest("Reservation not found").to_string()));
+        
+        ensure!(reservation.owner == owner, NominalError::Unauthorized("Only reservation owner"));
+        ensure!(self.records.get(&name).is_none(), NominalError::NameAlreadyTaken);
+        
+        self.reservations.remove(&name);
+        if reservation.deposit.0 > 0 {
+            Promise::new(owner.clone()).transfer(NearToken::from_yoctonear(reservation.deposit.0));
+        }
+        self.emit_reservation_cancelled(&name, &owner);
     }
 
     #[payable]
     pub fn register_with_ft(&mut self, name: String, token: AccountId) {
-        require!(env::attached_deposit() == NearToken::from_near(0), "No NEAR tokens allowed");
+        let name = self.normalize_registration_name(name);
+        self.assert_not_paused();
+        ensure!(env::attached_deposit() == NearToken::from_near(0), NominalError::InvalidRequest("No NEAR tokens allowed"));
         let owner = env::predecessor_account_id();
         
-        require!(self.is_valid_name(&name), "Invalid name");
-        require!(!self.records.get(&name).is_some(), "Name already taken");
+        ensure!(self.is_valid_name(&name), NominalError::InvalidName);
+        ensure!(self.records.get(&name).is_none(), NominalError::NameAlreadyTaken);
+        self.assert_not_reserved_by_other(&name, &owner);
+        self.assert_not_in_deletion_cooldown(&name);
+        self.assert_not_in_auction(&name);
+        ensure!(!self.owner_at_name_limit(&owner), NominalError::InvalidRequest("Owner name limit reached"));
         
-        let token_fee = self.coin_fees.get(&token).expect("Token not enabled");
+        let token_fee = self.enabled_coin_fee(&token);
         let timestamp = env::block_timestamp_ms();
+        ensure!(self.pending_ft_registrations.get(&name).is_none(), NominalError::InvalidRequest("Registration pending"));
+        self.pending_ft_registrations.insert(&name, &PendingFtReg {
+            owner: owner.clone(),
+            payer: owner.clone(),
+            token: token.clone(),
+            amount: U128(token_fee),
+            created_ms: U64(timestamp),
+        });
         
-        // First attempt the token transfer with callback
+        // Escrow the fee in this contract so it can be refunded if registration fails
         let promise = ext_ft::ext(token.clone())
             .with_attached_deposit(NearToken::from_yoctonear(1))
             .with_static_gas(GAS_FOR_FT_TRANSFER)
-            .ft_transfer_from(owner.clone(), self.treasury.clone(), U128(token_fee), Some(format!("Nominal registration fee for {}", name)));
+            .ft_transfer_from(owner.clone(), env::current_account_id(), U128(token_fee), Some(format!("Nominal registration fee for {}", name)));
             
         // Only register if transfer succeeds
         promise.then(
             ext_self::ext(env::current_account_id())
-                .with_static_gas(Gas::from_tgas(5))
+                .with_static_gas(GAS_FOR_FT_CALLBACK)
                 .ft_transfer_callback(name.clone(), owner.clone(), token.clone(), token_fee, timestamp)
         );
     }
@@ -145,93 +1141,496 @@ impl NameRegistry {
         token_fee: u128,
         timestamp: u64,
     ) -> bool {
+        // registration_timeout already cleared it and refunded the escrow
+        if self.pending_ft_registrations.remove(&name).is_none() {
+            return false;
+        }
         let transfer_success = matches!(env::promise_result(0), PromiseResult::Successful(_));
         
-        if transfer_success {
-            // Only now register the name after successful payment
-            self.register_record_and_primary(&name, &owner, timestamp);
-            self.emit_registered(&name, &owner);
-            self.emit_fee_paid(&name, &owner, Some(&token), token_fee, None);
-            true
-        } else {
-            // Payment failed - emit error event
-            env::panic_str("Token transfer failed - registration cancelled");
+        if !transfer_success {
+            // Nothing was escrowed, so there is nothing to refund
+            self.emit_registration_failed(&name, &owner, "token_transfer_failed");
+            return false;
+        }
+        
+        // The name may have been taken through another path while the transfer was in flight
+        if self.records.get(&name).is_some() {
+            self.refund_token_fee(&name, &owner, &token, token_fee);
+            self.emit_registration_failed(&name, &owner, "name_taken");
+            return false;
+        }
+        if self.owner_at_name_limit(&owner) {
+            self.refund_token_fee(&name, &owner, &token, token_fee);
+            self.emit_registration_failed(&name, &owner, "owner_name_limit");
+            return false;
         }
+        
+        self.register_record_and_primary(&name, &owner, timestamp);
+        self.pay_token_treasury(&token, token_fee, format!("Nominal registration fee for {}", name));
+        self.emit_registered(&name, &owner);
+        self.record_fee_stat(true, token_fee);
+        self.emit_fee_paid(&name, &owner, Some(&token), token_fee, None);
+        true
     }
 
-    #[payable]
-    pub fn register_with_sig(&mut self, params: RegisterWithSigParams, signature: String) {
-        let relayer = env::predecessor_account_id();
-        let current_time = env::block_timestamp_ms();
+    // Clears a pending FT registration whose callback never ran and refunds its escrow.
+    // A failed transfer is handled by the callback's cheap early return, so an entry still
+    // pending after the timeout means the transfer went through and the callback ran out
+    // of gas. Whichever of the two removes the entry first settles the escrow
+    pub fn registration_timeout(&mut self, name: String) {
+        let pending = self.pending_ft_registrations.get(&name).unwrap_or_else(|| env::panic_str(&NominalError::InvalidRequest("No pending registration").to_string()));
+        ensure!(
+            env::block_timestamp_ms() > pending.created_ms.0 + PENDING_FT_TIMEOUT_MS,
+            NominalError::InvalidRequest("Registration still pending")
+        );
         
-        require!(current_time <= params.deadline.0, "Deadline expired");
-        require!(relayer == params.relayer, "Invalid relayer");
-        if self.require_relayer_allowlist {
-            require!(self.relayers.contains(&params.relayer), "Relayer not allowed");
-        }
-        require!(self.is_valid_name(&params.name), "Invalid name");
-        require!(!self.records.get(&params.name).is_some(), "Name already taken");
-        require!(params.owner.to_string() != "", "Invalid owner");
+        self.pending_ft_registrations.remove(&name);
+        self.refund_token_fee(&name, &pending.payer, &pending.token, pending.amount.0);
+        self.emit_registration_failed(&name, &pending.owner, "timeout");
+    }
 
-        self.verify_signature(&params, &signature);
+    // NEP-141 receiver for ft_transfer_call registrations. Panics make the token
+    // contract refund the whole transfer; any amount above the fee is returned
+    pub fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        let token = env::predecessor_account_id();
+        let reg_msg: FtRegistrationMsg = serde_json::from_str(&msg)
+            .unwrap_or_else(|_| env::panic_str(&NominalError::InvalidRequest("Invalid registration message").to_string()));
+        let name = self.normalize_registration_name(reg_msg.name);
+        self.assert_not_paused();
         
-        let timestamp = env::block_timestamp_ms();
+        ensure!(self.is_valid_name(&name), NominalError::InvalidName);
+        ensure!(self.records.get(&name).is_none(), NominalError::NameAlreadyTaken);
+        ensure!(self.pending_ft_registrations.get(&name).is_none(), NominalError::InvalidRequest("Registration pending"));
+        self.assert_not_reserved_by_other(&name, &sender_id);
+        self.assert_not_in_deletion_cooldown(&name);
+        self.assert_not_in_auction(&name);
+        ensure!(!self.owner_at_name_limit(&sender_id), NominalError::InvalidRequest("Owner name limit reached"));
+        if let Some(referrer) = &reg_msg.referrer {
+            ensure!(*referrer != sender_id, NominalError::InvalidRequest("Cannot refer yourself"));
+            ensure!(*referrer != token && *referrer != env::current_account_id(), NominalError::InvalidRequest("Invalid referrer"));
+            if self.require_relayer_allowlist {
+                ensure!(self.relayers.contains(referrer), NominalError::InvalidRequest("Referrer not allowed"));
+            }
+        }
         
-        if params.currency.is_none() {
-            let amount = env::attached_deposit();
-            require!(amount.as_yoctonear() == self.registration_fee, "Exact fee required");
-            
+        let token_fee = self.enabled_coin_fee(&token);
+        ensure!(amount.0 >= token_fee, NominalError::InsufficientFee("Insufficient token amount"));
+        if self.require_exact_fee {
+            ensure!(amount.0 == token_fee, NominalError::ExactFeeRequired);
+        }
+        
+        self.register_record_and_primary(&name, &sender_id, env::block_timestamp_ms());
+        
+        let referrer_share = match &reg_msg.referrer {
+            Some(referrer) => {
+                let share = token_fee * self.referrer_bps as u128 / 10_000;
+                ext_ft::ext(token.clone())
+                    .with_attached_deposit(NearToken::from_yoctonear(1))
+                    .with_static_gas(GAS_FOR_FT_TRANSFER)
+                    .ft_transfer(referrer.clone(), U128(share), Some(format!("Nominal referrer reward for {}", name)))
+                    .then(
+                        ext_self::ext(env::current_account_id())
+                            .with_static_gas(GAS_FOR_FT_CALLBACK)
+                            .ft_transfer_referrer_callback(name.clone(), token.clone(), referrer.clone(), U128(share))
+                    );
+                share
+            }
+            None => 0,
+        };
+        self.pay_token_treasury(&token, token_fee - referrer_share, format!("Nominal treasury fee for {}", name));
+        
+        self.emit_registered(&name, &sender_id);
+        self.record_fee_stat(true, token_fee);
+        self.emit_fee_paid(&name, &sender_id, Some(&token), token_fee, reg_msg.referrer.as_ref());
+        PromiseOrValue::Value(U128(amount.0 - token_fee))
+    }
+
+    // A failed referrer payout goes to the treasury rather than staying in the contract
+    #[private]
+    pub fn ft_transfer_referrer_callback(
+        &mut self,
+        name: String,
+        token: AccountId,
+        referrer: AccountId,
+        amount: U128,
+    ) -> bool {
+        if !matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+            self.pay_token_treasury(&token, amount.0, format!("Nominal unpaid referrer reward for {}", name));
+            return false;
+        }
+        
+        self.record_referral(&referrer, 0, amount.0);
+        self.emit_ft_referrer_paid(&name, &token, &referrer, amount.0);
+        true
+    }
+
+    // A failed transfer returns the share to this contract, so it goes to the treasury
+    #[private]
+    pub fn near_referrer_callback(&mut self, name: String, referrer: AccountId, amount: U128) -> bool {
+        if !matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+            self.deposit_to_treasury(amount.0, &name);
+            return false;
+        }
+        
+        self.record_referral(&referrer, amount.0, 0);
+        true
+    }
+
+    fn refund_token_fee(&self, name: &str, owner: &AccountId, token: &AccountId, amount: u128) {
+        ext_ft::ext(token.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(owner.clone(), U128(amount), Some(format!("Nominal registration refund for {}", name)));
+    }
+
+    #[payable]
+    // The signature must cover the normalized name, since that is what gets stored
+    pub fn register_with_sig(&mut self, mut params: RegisterWithSigParams, signature: String) {
+        params.name = self.normalize_registration_name(params.name);
+        self.assert_not_paused();
+        let relayer = env::predecessor_account_id();
+        let current_time = env::block_timestamp_ms();
+        
+        ensure!(current_time <= params.deadline.0, NominalError::DeadlineExpired);
+        // A signature can't be held back longer than max_sig_validity_ms before it is relayed
+        ensure!(params.deadline.0 - current_time <= self.max_sig_validity_ms, NominalError::SignatureTooOld);
+        ensure!(relayer == params.relayer, NominalError::RelayerNotAllowed);
+        if self.require_relayer_allowlist {
+            ensure!(self.relayers.contains(&params.relayer), NominalError::RelayerNotAllowed);
+        }
+        ensure!(self.is_valid_name(&params.name), NominalError::InvalidName);
+        ensure!(!self.records.get(&params.name).is_some(), NominalError::NameAlreadyTaken);
+        self.assert_not_reserved_by_other(&params.name, &params.owner);
+        self.assert_not_in_deletion_cooldown(&params.name);
+        self.assert_not_in_auction(&params.name);
+        ensure!(params.owner.to_string() != "", NominalError::InvalidRequest("Invalid owner"));
+        ensure!(!self.owner_at_name_limit(&params.owner), NominalError::InvalidRequest("Owner name limit reached"));
+
+        self.verify_signature(&params, &signature);
+        ensure!(self.referrer_bps >= self.min_referrer_bps, NominalError::InvalidRequest("BPS below referrer floor"));
+        
+        let timestamp = env::block_timestamp_ms();
+        
+        if params.currency.is_none() {
+            let fee = self.take_registration_fee(&params.name, &relayer, env::attached_deposit().as_yoctonear(), 1);
+            
             self.register_record_and_primary(&params.name, &params.owner, timestamp);
             
-            let ref_share = (amount.as_yoctonear() * self.referrer_bps as u128) / 10_000;
-            let treasury_share = amount.as_yoctonear() - ref_share;
+            let ref_share = (fee * self.referrer_bps as u128) / 10_000;
+            let treasury_share = fee - ref_share;
             
             self.emit_registered(&params.name, &params.owner);
-            self.emit_fee_paid(&params.name, &relayer, None, amount.as_yoctonear(), Some(&relayer));
+            self.record_fee_stat(false, fee);
+            self.emit_fee_paid(&params.name, &relayer, None, fee, Some(&relayer));
             
             if treasury_share > 0 {
-                Promise::new(self.treasury.clone()).transfer(NearToken::from_yoctonear(treasury_share));
+                self.deposit_to_treasury(treasury_share, &params.name);
             }
             if ref_share > 0 {
-                Promise::new(relayer.clone()).transfer(NearToken::from_yoctonear(ref_share));
+                Promise::new(relayer.clone())
+                    .transfer(NearToken::from_yoctonear(ref_share))
+                    .then(
+                        ext_self::ext(env::current_account_id())
+                            .with_static_gas(GAS_FOR_NEAR_REFERRER_CALLBACK)
+                            .near_referrer_callback(params.name.clone(), relayer.clone(), U128(ref_share))
+                    );
             }
             
         } else {
-            require!(env::attached_deposit() == NearToken::from_near(0), "No NEAR tokens allowed");
+            ensure!(env::attached_deposit() == NearToken::from_near(0), NominalError::InvalidRequest("No NEAR tokens allowed"));
             let token = params.currency.unwrap();
-            let token_fee = self.coin_fees.get(&token).expect("Token not enabled");
-            require!(params.amount.0 == token_fee, "Exact token fee required");
+            let token_fee = self.enabled_coin_fee(&token);
+            ensure!(params.amount.0 == token_fee, NominalError::ExactFeeRequired);
+            ensure!(self.pending_ft_registrations.get(&params.name).is_none(), NominalError::InvalidRequest("Registration pending"));
             
-            self.register_record_and_primary(&params.name, &params.owner, timestamp);
+            // Escrow the whole fee in this contract, like register_with_ft, so it can be
+            // refunded in full if the name can't be registered once it arrives
+            self.pending_ft_registrations.insert(&params.name, &PendingFtReg {
+                owner: params.owner.clone(),
+                payer: relayer.clone(),
+                token: token.clone(),
+                amount: U128(token_fee),
+                created_ms: U64(timestamp),
+            });
             
             let ref_share = (token_fee * self.referrer_bps as u128) / 10_000;
-            let treasury_share = token_fee - ref_share;
-            
-            self.emit_registered(&params.name, &params.owner);
-            self.emit_fee_paid(&params.name, &relayer, Some(&token), token_fee, Some(&relayer));
             
-            if treasury_share > 0 {
-                ext_ft::ext(token.clone())
-                    .with_attached_deposit(NearToken::from_yoctonear(1))
-                    .with_static_gas(GAS_FOR_FT_TRANSFER)
-                    .ft_transfer_from(relayer.clone(), self.treasury.clone(), U128(treasury_share), Some(format!("Nominal treasury fee for {}", params.name)));
-            }
-            if ref_share > 0 {
-                ext_ft::ext(token.clone())
-                    .with_attached_deposit(NearToken::from_yoctonear(1))
-                    .with_static_gas(GAS_FOR_FT_TRANSFER)
-                    .ft_transfer_from(relayer.clone(), relayer.clone(), U128(ref_share), Some(format!("Nominal referrer reward for {}", params.name)));
-            }
+            ext_ft::ext(token.clone())
+                .with_attached_deposit(NearToken::from_yoctonear(1))
+                .with_static_gas(GAS_FOR_FT_TRANSFER)
+                .ft_transfer_from(relayer.clone(), env::current_account_id(), U128(token_fee), Some(format!("Nominal registration fee for {}", params.name)))
+                .then(
+                    ext_self::ext(env::current_account_id())
+                        .with_static_gas(GAS_FOR_SIG_FT_CALLBACK)
+                        .ft_transfer_sig_callback(
+                            params.name.clone(),
+                            params.owner.clone(),
+                            token,
+                            relayer,
+                            U128(ref_share),
+                            timestamp,
+                        )
+                );
+        }
+    }
+
+    // The fee of a token sig registration is escrowed: register the name and forward the
+    // treasury and referrer shares, or refund the relayer
+    #[private]
+    pub fn ft_transfer_sig_callback(
+        &mut self,
+        name: String,
+        owner: AccountId,
+        token: AccountId,
+        referrer: AccountId,
+        referrer_amount: U128,
+        timestamp: u64,
+    ) -> bool {
+        // registration_timeout already cleared it and refunded the escrow
+        let pending = match self.pending_ft_registrations.remove(&name) {
+            Some(pending) => pending,
+            None => return false,
+        };
+        if !matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+            // Nothing was escrowed, so there is nothing to refund
+            self.emit_sig_registration_failed(&name, "fee_transfer");
+            return false;
+        }
+        
+        let token_fee = pending.amount.0;
+        let failure = if self.records.get(&name).is_some() {
+            Some("name_taken")
+        } else if self.owner_at_name_limit(&owner) {
+            Some("owner_name_limit")
+        } else {
+            None
+        };
+        if let Some(step) = failure {
+            self.refund_token_fee(&name, &pending.payer, &token, token_fee);
+            self.emit_sig_registration_failed(&name, step);
+            return false;
+        }
+        
+        self.register_record_and_primary(&name, &owner, timestamp);
+        self.pay_token_treasury(&token, token_fee - referrer_amount.0, format!("Nominal treasury fee for {}", name));
+        if referrer_amount.0 > 0 {
+            // The referral is recorded by the callback once the payout succeeds
+            ext_ft::ext(token.clone())
+                .with_attached_deposit(NearToken::from_yoctonear(1))
+                .with_static_gas(GAS_FOR_FT_TRANSFER)
+                .ft_transfer(referrer.clone(), referrer_amount, Some(format!("Nominal referrer reward for {}", name)))
+                .then(
+                    ext_self::ext(env::current_account_id())
+                        .with_static_gas(GAS_FOR_FT_CALLBACK)
+                        .ft_transfer_referrer_callback(name.clone(), token.clone(), referrer.clone(), referrer_amount)
+                );
+        }
+        
+        self.emit_registered(&name, &owner);
+        self.record_fee_stat(true, token_fee);
+        self.emit_fee_paid(&name, &referrer, Some(&token), token_fee, Some(&referrer));
+        true
+    }
+
+    fn record_history(&mut self, name: &String, old_resolved: &AccountId, new_resolved: &AccountId) {
+        let mut entries = self.history.get(name).unwrap_or_default();
+        if entries.len() == MAX_HISTORY_ENTRIES {
+            entries.remove(0);
+        }
+        entries.push(HistoryEntry {
+            old_resolved: old_resolved.clone(),
+            new_resolved: new_resolved.clone(),
+            changed_at: U64(env::block_timestamp_ms()),
+            changed_by: env::predecessor_account_id(),
+        });
+        self.history.insert(name, &entries);
+    }
+
+    fn record_referral(&mut self, referrer: &AccountId, near_amount: u128, token_amount: u128) {
+        let mut stats = self.referrer_stats.get(referrer).unwrap_or_default();
+        stats.total_earned_near = U128(stats.total_earned_near.0 + near_amount);
+        stats.total_earned_tokens = U128(stats.total_earned_tokens.0 + token_amount);
+        stats.referral_count += 1;
+        self.referrer_stats.insert(referrer, &stats);
+    }
+
+    fn token_treasury(&self, token: &AccountId) -> AccountId {
+        self.treasury_token_accounts.get(token).unwrap_or_else(|| self.treasury.clone())
+    }
+
+    fn pay_token_treasury(&self, token: &AccountId, amount: u128, memo: String) {
+        ext_ft::ext(token.clone())
+            .with_attached_deposit(NearToken::from_yoctonear(1))
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(self.token_treasury(token), U128(amount), Some(memo))
+            .then(
+                ext_self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_TOKEN_TREASURY_CALLBACK)
+                    .token_treasury_transfer_callback(token.clone(), U128(amount))
+            );
+    }
+
+    // A failed transfer leaves the tokens here (e.g. the treasury isn't storage-registered
+    // with the token); they are tracked so the owner can retry once that is fixed
+    #[private]
+    pub fn token_treasury_transfer_callback(&mut self, token: AccountId, amount: U128) -> bool {
+        if matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+            return true;
+        }
+        let unpaid = self.unpaid_token_fees.get(&token).unwrap_or(0);
+        self.unpaid_token_fees.insert(&token, &(unpaid + amount.0));
+        emit_nep297(&NominalEventStandard::new("nominal_token_treasury_transfer_failed", TokenTreasuryTransferFailedData {
+            token: &token,
+            amount,
+        }));
+        false
+    }
+
+    pub fn retry_token_treasury_transfer(&mut self, token: AccountId) {
+        self.assert_owner();
+        let amount = self.unpaid_token_fees.remove(&token).unwrap_or(0);
+        ensure!(amount > 0, NominalError::InvalidRequest("No unpaid token fees"));
+        self.log_admin_action("retry_token_treasury_transfer", format!("{}:{}", token, amount));
+        self.pay_token_treasury(&token, amount, "Nominal unpaid treasury fees".to_string());
+    }
+
+    pub fn get_unpaid_token_fees(&self, token: AccountId) -> U128 {
+        U128(self.unpaid_token_fees.get(&token).unwrap_or(0))
+    }
+
+    fn is_expired_past_grace(&self, name: &String, now: u64) -> bool {
+        self.records_v2
+            .get(name)
+            .map_or(false, |record| record.expiry_ms.0 != 0 && now > record.expiry_ms.0 + EXPIRY_GRACE_PERIOD_MS)
+    }
+
+    // Price of claiming an expired name: the registration fee, with the premium applied
+    // during the first EXPIRED_NAME_PREMIUM_WINDOW_MS after the grace period and the
+    // stale multiplier within staleness_window_ms of expiry, whichever is higher
+    fn expired_name_price(&self, name: &String, now: u64) -> u128 {
+        let expiry_ms = self.records_v2.get(name).map_or(0, |record| record.expiry_ms.0);
+        let claimable_at = expiry_ms + EXPIRY_GRACE_PERIOD_MS;
+        let premium_fee = if now <= claimable_at + EXPIRED_NAME_PREMIUM_WINDOW_MS {
+            self.registration_fee * self.expired_name_premium_bps as u128 / 10_000
+        } else {
+            self.registration_fee
+        };
+        let time_since_expiry = now.saturating_sub(expiry_ms);
+        let stale_fee = if time_since_expiry < self.staleness_window_ms {
+            self.registration_fee * self.stale_multiplier_bps as u128 / 10_000
+        } else {
+            self.registration_fee
+        };
+        premium_fee.max(stale_fee)
+    }
+
+    // Drops a record along with its v2 entry, secondary indices, counters and primary mapping
+    fn remove_record(&mut self, name: &String) -> Record {
+        let record = self.records.remove(name).unwrap_or_else(|| env::panic_str(&NominalError::NameNotFound.to_string()));
+        self.records_v2.remove(name);
+        self.sorted_names.remove(name);
+        self.remove_from_prefix_index(name);
+        self.resolution_counts.remove(name);
+        self.bios.remove(name);
+        self.avatars.remove(name);
+        self.expiry_warnings.remove(name);
+        self.history.remove(name);
+        self.pending_transfers.remove(name);
+        self.clear_acl(name);
+        self.decrement_name_count(&record.owner);
+        if self.primary_names.get(&record.owner).as_ref() == Some(name) {
+            self.primary_names.remove(&record.owner);
+        }
+        record
+    }
+
+    // Epoch 0 keeps the original "name:member" key so grants made before epochs existed
+    // still resolve; names can't contain '#', so later keys can't collide
+    fn acl_key(&self, name: &str, member: &AccountId) -> String {
+        match self.acl_epochs.get(&name.to_string()).unwrap_or(0) {
+            0 => format!("{}:{}", name, member),
+            epoch => format!("{}#{}:{}", name, epoch, member),
+        }
+    }
+
+    fn clear_acl(&mut self, name: &String) {
+        let epoch = self.acl_epochs.get(name).unwrap_or(0);
+        self.acl_epochs.insert(name, &(epoch + 1));
+    }
+
+    // Checks the attached deposit against the registration fee for `count` names,
+    // refunding any overpayment when exact fees are not required. Returns the fee kept.
+    fn take_registration_fee(&self, name: &str, payer: &AccountId, amount: u128, count: u128) -> u128 {
+        let fee = self.registration_fee * count;
+        if self.require_exact_fee {
+            ensure!(amount == fee, NominalError::ExactFeeRequired);
+            return amount;
+        }
+        
+        ensure!(amount >= fee, NominalError::InsufficientFee("Insufficient fee"));
+        let excess = amount - fee;
+        if excess > 0 {
+            Promise::new(payer.clone()).transfer(NearToken::from_yoctonear(excess));
+            self.emit_excess_fee_refunded(name, payer, excess);
         }
+        fee
+    }
+
+    fn owner_at_name_limit(&self, owner: &AccountId) -> bool {
+        self.max_names_per_owner != 0
+            && self.name_count_by_owner.get(owner).unwrap_or(0) >= self.max_names_per_owner
+    }
+
+    fn increment_name_count(&mut self, owner: &AccountId) {
+        ensure!(!self.owner_at_name_limit(owner), NominalError::InvalidRequest("Owner name limit reached"));
+        let count = self.name_count_by_owner.get(owner).unwrap_or(0);
+        self.name_count_by_owner.insert(owner, &(count + 1));
+    }
+
+    fn decrement_name_count(&mut self, owner: &AccountId) {
+        let count = self.name_count_by_owner.get(owner).unwrap_or(0);
+        self.name_count_by_owner.insert(owner, &count.saturating_sub(1));
+    }
+
+    fn record_fee_stat(&mut self, is_token: bool, amount: u128) {
+        let bucket = if is_token {
+            FEE_HISTOGRAM_BUCKETS - 1
+        } else {
+            let mut bucket = 0;
+            let mut bound = NearToken::from_millinear(10).as_yoctonear();
+            while bucket < FEE_HISTOGRAM_BUCKETS - 2 && amount >= bound {
+                bucket += 1;
+                bound = bound.saturating_mul(10);
+            }
+            bucket
+        };
+        self.fee_histogram[bucket] += 1;
+    }
+
+    // The last bucket also counts grandfathered names longer than MAX_NAME_LEN
+    fn count_name_length(&mut self, name: &str) {
+        let bucket = name.len().clamp(1, self.length_histogram.len()) - 1;
+        self.length_histogram[bucket] += 1;
     }
 
     fn register_record_and_primary(&mut self, name: &str, owner: &AccountId, timestamp: u64) {
+        self.increment_name_count(owner);
+        self.count_name_length(name);
         let record = Record {
             owner: owner.clone(),
             resolved: owner.clone(),
             updated_at: U64(timestamp),
+            ttl_ms: U64(DEFAULT_TTL_MS),
+            locked: false,
+            created_at: U64(timestamp),
         };
-        self.records.insert(&name.to_string(), &record);
+        self.save_record(name, &record);
+        if self.registration_period_ms > 0 {
+            self.set_expiry(name, &record, timestamp + self.registration_period_ms);
+        }
+        if self.prefix_search_enabled {
+            ensure!(self.add_to_prefix_index(name), NominalError::PrefixIndexFull);
+        }
         
         if self.primary_names.get(owner).is_none() {
             self.primary_names.insert(owner, &name.to_string());
@@ -239,36 +1638,256 @@ impl NameRegistry {
         }
     }
 
+    fn set_expiry(&mut self, name: &str, record: &Record, expiry_ms: u64) {
+        self.records_v2.insert(&name.to_string(), &RecordV2 {
+            owner: record.owner.clone(),
+            resolved: record.resolved.clone(),
+            updated_at: record.updated_at,
+            expiry_ms: U64(expiry_ms),
+        });
+    }
+
+    // Names shorter than PREFIX_LEN are not indexed. Returns false, leaving the
+    // name out, when its prefix bucket is already full
+    fn add_to_prefix_index(&mut self, name: &str) -> bool {
+        let prefix = match name.get(..PREFIX_LEN) {
+            Some(prefix) => prefix.to_string(),
+            None => return true,
+        };
+        let mut names = self
+            .trie
+            .get(&prefix)
+            .unwrap_or_else(|| UnorderedSet::new(StorageKey::PrefixNames(prefix.clone())));
+        let name = name.to_string();
+        if names.contains(&name) {
+            return true;
+        }
+        if names.len() >= MAX_NAMES_PER_PREFIX {
+            return false;
+        }
+        names.insert(&name);
+        self.trie.insert(&prefix, &names);
+        true
+    }
+
+    fn remove_from_prefix_index(&mut self, name: &str) {
+        let prefix = match name.get(..PREFIX_LEN) {
+            Some(prefix) => prefix.to_string(),
+            None => return,
+        };
+        if let Some(mut names) = self.trie.get(&prefix) {
+            if names.remove(&name.to_string()) {
+                self.trie.insert(&prefix, &names);
+            }
+        }
+    }
+
+    // All record writes go through here so records_v2 stays in sync after migration
+    fn save_record(&mut self, name: &str, record: &Record) {
+        let name = name.to_string();
+        self.records.insert(&name, record);
+        self.sorted_names.insert(&name, &());
+        
+        // Names with an expiry always have a RecordV2, even before migrate_records_v2
+        let existing = self.records_v2.get(&name);
+        if self.migrated || existing.is_some() {
+            let expiry_ms = existing.map(|r| r.expiry_ms).unwrap_or(U64(0));
+            self.records_v2.insert(&name, &RecordV2 {
+                owner: record.owner.clone(),
+                resolved: record.resolved.clone(),
+                updated_at: record.updated_at,
+                expiry_ms,
+            });
+        }
+    }
+
+    fn deposit_to_treasury(&self, amount: u128, memo: &str) -> Promise {
+        if !self.treasury_is_contract {
+            return Promise::new(self.treasury.clone()).transfer(NearToken::from_yoctonear(amount));
+        }
+        let deposit = if self.treasury_deposit_method == DEFAULT_TREASURY_DEPOSIT_METHOD {
+            ext_treasury::ext(self.treasury.clone())
+                .with_attached_deposit(NearToken::from_yoctonear(amount))
+                .with_static_gas(GAS_FOR_TREASURY_DEPOSIT)
+                .deposit(memo.to_string())
+        } else {
+            let args = serde_json::json!({ "memo": memo }).to_string().into_bytes();
+            Promise::new(self.treasury.clone()).function_call(
+                self.treasury_deposit_method.clone(),
+                args,
+                NearToken::from_yoctonear(amount),
+                GAS_FOR_TREASURY_DEPOSIT,
+            )
+        };
+        deposit.then(
+            ext_self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_TREASURY_DEPOSIT_CALLBACK)
+                .treasury_deposit_callback(U128(amount), memo.to_string())
+        )
+    }
+
+    // A failed deposit call refunds the attached amount to this contract; send it on as a
+    // plain transfer so fees never stay stranded here
+    #[private]
+    pub fn treasury_deposit_callback(&mut self, amount: U128, memo: String) -> bool {
+        if matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+            return true;
+        }
+        Promise::new(self.treasury.clone()).transfer(NearToken::from_yoctonear(amount.0));
+        self.emit_treasury_deposit_failed(&memo, amount.0);
+        false
+    }
+
+    fn pay_treasury(&self, amount: u128, memo: &str) {
+        if self.treasury_splits.is_empty() {
+            self.deposit_to_treasury(amount, memo);
+            return;
+        }
+        
+        let mut distributed: u128 = 0;
+        let last = self.treasury_splits.len() - 1;
+        for (i, (recipient, bps)) in self.treasury_splits.iter().enumerate() {
+            // Last recipient absorbs rounding dust
+            let share = if i == last {
+                amount - distributed
+            } else {
+                (amount * *bps as u128) / 10_000
+            };
+            distributed += share;
+            if share > 0 {
+                Promise::new(recipient.clone()).transfer(NearToken::from_yoctonear(share));
+            }
+        }
+    }
+
     fn is_valid_name(&self, name: &str) -> bool {
-        !name.is_empty() && name.len() <= 64 && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        name.len() >= self.min_name_len as usize
+            && name.len() <= self.max_name_len as usize
+            && validate_name_chars(name, self.allow_uppercase, self.allow_underscore).is_ok()
+    }
+
+    fn normalize_registration_name(&self, name: String) -> String {
+        if self.name_case_sensitive {
+            return name;
+        }
+        let normalized = normalize_name(&name);
+        if normalized != name {
+            self.emit_name_normalized(&name, &normalized);
+        }
+        normalized
+    }
+
+    fn assert_not_in_deletion_cooldown(&self, name: &String) {
+        if let Some(deleted_at) = self.deleted_names.get(name) {
+            ensure!(
+                env::block_timestamp_ms() >= deleted_at + self.deletion_cooldown_ms,
+                NominalError::InvalidRequest("Name in cooldown")
+            );
+        }
+    }
+
+    fn assert_not_in_auction(&self, name: &String) {
+        ensure!(
+            self.auctions.get(name).map_or(true, |auction| auction.settled),
+            NominalError::InvalidRequest("Name is being auctioned")
+        );
+    }
+
+    fn assert_not_reserved_by_other(&self, name: &String, owner: &AccountId) {
+        if let Some(reservation) = self.reservations.get(name) {
+            ensure!(
+                reservation.owner == *owner || reservation.expires_ms.0 < env::block_timestamp_ms(),
+                NominalError::InvalidRequest("Name reserved")
+            );
+        }
+    }
+
+    fn enabled_coin_fee(&self, token: &AccountId) -> u128 {
+        let token_fee = self.coin_fees.get(token).unwrap_or_else(|| env::panic_str(&NominalError::TokenNotEnabled.to_string()));
+        ensure!(token_fee != COIN_FEE_DISABLED, NominalError::TokenNotEnabled);
+        self.effective_coin_fee(token, token_fee)
+    }
+
+    fn effective_coin_fee(&self, coin: &AccountId, token_fee: u128) -> u128 {
+        let schedule = match self.coin_fee_schedules.get(coin) {
+            Some(schedule) => schedule,
+            None => return token_fee,
+        };
+        let now = env::block_timestamp_ms();
+        let base_fee = schedule.base_fee.0;
+        if now >= schedule.discount_start_ms.0 && now < schedule.discount_end_ms.0 {
+            base_fee - base_fee * schedule.discount_bps as u128 / 10_000
+        } else {
+            base_fee
+        }
     }
 
     fn assert_owner(&self) {
-        require!(env::predecessor_account_id() == self.owner, "Only owner");
+        ensure!(env::predecessor_account_id() == self.owner, NominalError::Unauthorized("Only owner"));
+    }
+
+    fn assert_role(&self, role: &str) {
+        ensure!(self.account_has_role(role, &env::predecessor_account_id()), NominalError::Unauthorized("Missing role"));
+    }
+
+    fn account_has_role(&self, role: &str, account: &AccountId) -> bool {
+        *account == self.owner || self.roles.get(&role.to_string()).map_or(false, |members| members.contains(account))
+    }
+
+    fn assert_not_paused(&self) {
+        ensure!(env::block_timestamp_ms() >= self.paused_until_ms, NominalError::RegistryPaused);
+        // Unmigrated names would look free to the registration paths
+        ensure!(self.legacy_records.is_empty(), NominalError::InvalidRequest("Record migration in progress"));
+    }
+
+    fn log_admin_action(&mut self, action_type: &str, value: String) {
+        let action = AdminAction {
+            action_type: action_type.to_string(),
+            value,
+            timestamp_ms: env::block_timestamp_ms(),
+        };
+        self.emit_admin_action(&action);
+        
+        if self.admin_log.len() < MAX_ADMIN_LOG_ENTRIES {
+            self.admin_log.push(&action);
+        } else {
+            // Overwrite the oldest entry
+            self.admin_log.replace(self.admin_log_head, &action);
+            self.admin_log_head = (self.admin_log_head + 1) % MAX_ADMIN_LOG_ENTRIES;
+        }
     }
 
     fn verify_signature(&mut self, params: &RegisterWithSigParams, signature: &str) {
         let current_nonce = self.nonces.get(&params.name).unwrap_or(0);
-        require!(params.nonce.0 == current_nonce, "Invalid nonce");
+        ensure!(params.nonce.0 == current_nonce, NominalError::InvalidNonce);
         
         let message = self.create_registration_message(params);
       
-        require!(!signature.is_empty(), "Empty signature");
-       
+        ensure!(!signature.is_empty(), NominalError::InvalidSignature("Empty signature"));
+        
+        match params.signature_type {
+            SignatureType::Ed25519 => self.verify_ed25519_signature(&params.owner, &message, signature),
+            SignatureType::Secp256k1 => self.verify_secp256k1_signature(&params.owner, &message, signature),
+        }
+        
+        self.nonces.insert(&params.name, &(current_nonce + 1));
+    }
+    
+    fn verify_ed25519_signature(&self, owner: &AccountId, message: &[u8], signature: &str) {
         let parts: Vec<&str> = signature.split(':').collect();
-        require!(parts.len() == 2, "Invalid signature format - expected 'signature:public_key'");
+        ensure!(parts.len() == 2, NominalError::InvalidSignature("Invalid signature format - expected 'signature:public_key'"));
         
 
         let signature_bytes = bs58::decode(parts[0])
             .into_vec()
-            .expect("Invalid signature base58");
-        require!(signature_bytes.len() == 64, "Invalid ED25519 signature length");
+            .unwrap_or_else(|_| env::panic_str(&NominalError::InvalidSignature("Invalid signature base58").to_string()));
+        ensure!(signature_bytes.len() == 64, NominalError::InvalidSignature("Invalid ED25519 signature length"));
         
      
         let public_key = PublicKey::from_str(parts[1])
-            .expect("Invalid public key format");
+            .unwrap_or_else(|_| env::panic_str(&NominalError::InvalidSignature("Invalid public key format").to_string()));
         
-        let message_hash = env::sha256(&message);
+        let message_hash = env::sha256(message);
         
     
         let mut sig_array = [0u8; 64];
@@ -276,7 +1895,7 @@ impl NameRegistry {
         
         // For now, assume ED25519 signatures only (most common in NEAR)
         let key_data = public_key.clone().into_bytes();
-        require!(key_data.len() >= 32, "Invalid public key length");
+        ensure!(key_data.len() >= 32, NominalError::InvalidSignature("Invalid public key length"));
         
         // Extract the actual key bytes
         let mut key_array = [0u8; 32];
@@ -285,11 +1904,39 @@ impl NameRegistry {
         // Verify ED25519 signature using NEAR's crypto functions
         let is_valid = env::ed25519_verify(&sig_array, &message_hash, &key_array);
         
-        require!(is_valid, "Invalid signature");
+        ensure!(is_valid, NominalError::InvalidSignature("Invalid signature"));
         
-        self.verify_key_belongs_to_account(&params.owner, &public_key);
+        self.verify_key_belongs_to_account(owner, &public_key);
+    }
+    
+    fn verify_secp256k1_signature(&self, owner: &AccountId, message: &[u8], signature: &str) {
+        let signature_bytes = bs58::decode(signature)
+            .into_vec()
+            .unwrap_or_else(|_| env::panic_str(&NominalError::InvalidSignature("Invalid signature base58").to_string()));
+        ensure!(signature_bytes.len() == 65, NominalError::InvalidSignature("Invalid SECP256K1 signature length"));
         
-        self.nonces.insert(&params.name, &(current_nonce + 1));
+        // Accept both raw (0/1) and EVM-style (27/28) recovery ids
+        let v = match signature_bytes[64] {
+            v @ 0..=1 => v,
+            v @ 27..=28 => v - 27,
+            _ => env::panic_str(&NominalError::InvalidSignature("Invalid SECP256K1 recovery id").to_string()),
+        };
+        
+        let message_hash = env::keccak256_array(message);
+        let recovered = env::ecrecover(&message_hash, &signature_bytes[..64], v, true)
+            .unwrap_or_else(|| env::panic_str(&NominalError::InvalidSignature("Invalid signature").to_string()));
+        
+        // Eth-implicit accounts are 0x + last 20 bytes of keccak256(public key)
+        let account_str = owner.to_string();
+        if account_str.len() == 42 && account_str.starts_with("0x") {
+            let expected_account = format!("0x{}", hex::encode(&env::keccak256_array(recovered)[12..]));
+            ensure!(account_str == expected_account, NominalError::InvalidSignature("Public key does not match implicit account"));
+            return;
+        }
+        
+        let public_key = PublicKey::from_parts(CurveType::SECP256K1, recovered.to_vec())
+            .unwrap_or_else(|_| env::panic_str(&NominalError::InvalidSignature("Invalid public key format").to_string()));
+        self.verify_key_belongs_to_account(owner, &public_key);
     }
     
     fn create_registration_message(&self, params: &RegisterWithSigParams) -> Vec<u8> {
@@ -310,182 +1957,4556 @@ impl NameRegistry {
         env::sha256(&message)
     }
     
+    fn create_meta_tx_message(&self, params: &MetaTxParams) -> Vec<u8> {
+        let mut message = Vec::new();
+        message.extend_from_slice(env::current_account_id().as_bytes());
+        message.extend_from_slice(params.name.as_bytes());
+        message.extend_from_slice(params.new_resolved.as_bytes());
+        message.extend_from_slice(params.owner.as_bytes());
+        message.extend_from_slice(&params.nonce.0.to_le_bytes());
+        message.extend_from_slice(&params.deadline.0.to_le_bytes());
+        
+        env::sha256(&message)
+    }
+    
+    fn create_meta_registration_message(&self, params: &MetaRegistrationParams) -> Vec<u8> {
+        let mut message = Vec::new();
+        message.extend_from_slice(env::current_account_id().as_bytes());
+        message.extend_from_slice(b"meta_register");
+        message.extend_from_slice(params.name.as_bytes());
+        message.extend_from_slice(params.owner.as_bytes());
+        message.extend_from_slice(params.fee_payer.as_bytes());
+        message.extend_from_slice(&params.fee_amount.0.to_le_bytes());
+        message.extend_from_slice(&params.nonce.0.to_le_bytes());
+        message.extend_from_slice(&params.deadline.0.to_le_bytes());
+        
+        env::sha256(&message)
+    }
+    
     fn verify_key_belongs_to_account(&self, account: &AccountId, public_key: &PublicKey) {
         let account_str = account.to_string();
         
         if account_str.len() == 64 && account_str.chars().all(|c| c.is_ascii_hexdigit()) {
             let expected_account = hex::encode(&public_key.clone().into_bytes()[1..33]);
-            require!(account_str == expected_account, "Public key does not match implicit account");
+            ensure!(account_str == expected_account, NominalError::InvalidSignature("Public key does not match implicit account"));
             return;
         }
         
-        let key_bytes = public_key.clone().into_bytes();
-        let key_b58 = bs58::encode(&key_bytes).into_string();
-        
-        if let Some(_) = self.authorized_keys.get(&format!("{}:{}", account, key_b58)) {
-            return; // Key is authorized
-        }
-        
-        require!(false, "Public key not authorized for this account - call authorize_key first");
+        ensure!(
+            self.is_key_authorized(account.clone(), public_key.clone()),
+            NominalError::InvalidSignature("Public key not authorized for this account - call authorize_key first")
+        );
     }
     
-    pub fn authorize_key(&mut self, public_key: PublicKey) {
+    // expires_ms of None keeps the key authorized until revoked
+    pub fn authorize_key(&mut self, public_key: PublicKey, expires_ms: Option<U64>) {
         let caller = env::predecessor_account_id();
-        let key_bytes = public_key.into_bytes();
-        let key_b58 = bs58::encode(&key_bytes).into_string();
-        let auth_key = format!("{}:{}", caller, key_b58);
+        let key_b58 = bs58::encode(&public_key.into_bytes()).into_string();
+        let expires_ms = expires_ms.map_or(0, |expiry| expiry.0);
+        ensure!(
+            expires_ms == 0 || expires_ms > env::block_timestamp_ms(),
+            NominalError::InvalidRequest("Key expiry must be in the future")
+        );
         
-        self.authorized_keys.insert(&auth_key, &true);
+        self.insert_authorized_key(&caller, &key_b58, expires_ms);
         env::log_str(&format!("Key authorized for account {}: {}", caller, key_b58));
     }
     
     pub fn revoke_key(&mut self, public_key: PublicKey) {
         let caller = env::predecessor_account_id();
-        let key_bytes = public_key.into_bytes();
-        let key_b58 = bs58::encode(&key_bytes).into_string();
-        let auth_key = format!("{}:{}", caller, key_b58);
+        let key_b58 = bs58::encode(&public_key.into_bytes()).into_string();
         
-        self.authorized_keys.remove(&auth_key);
+        if let Some(mut keys) = self.authorized_keys.get(&caller) {
+            keys.remove(&key_b58);
+            self.authorized_keys.insert(&caller, &keys);
+        }
         env::log_str(&format!("Key revoked for account {}: {}", caller, key_b58));
     }
 
-    pub fn set_registration_fee(&mut self, amount: U128) {
+    pub fn revoke_all_keys(&mut self, account: AccountId) {
         self.assert_owner();
-        self.registration_fee = amount.0;
+        self.log_admin_action("revoke_all_keys", account.to_string());
+        let count = match self.authorized_keys.remove(&account) {
+            Some(mut keys) => {
+                let count = keys.len();
+                keys.clear();
+                count
+            }
+            None => 0,
+        };
+        env::log_str(&format!("All keys revoked for account {}: {}", account, count));
     }
 
-    pub fn set_treasury(&mut self, treasury: AccountId) {
+    // 1.x keys live in a non-iterable LookupMap under StorageKey::AuthorizedKeys, so the
+    // caller supplies the (account, key) pairs to carry over; they keep never expiring
+    pub fn migrate_authorized_keys(&mut self, keys: Vec<(AccountId, PublicKey)>) -> String {
         self.assert_owner();
-        self.treasury = treasury;
+        self.log_admin_action("migrate_authorized_keys", format!("{} keys", keys.len()));
+        
+        let mut legacy_keys: LookupMap<String, bool> = LookupMap::new(StorageKey::AuthorizedKeys);
+        let mut count: u64 = 0;
+        for (account, public_key) in keys.into_iter() {
+            let key_b58 = bs58::encode(&public_key.into_bytes()).into_string();
+            if legacy_keys.remove(&format!("{}:{}", account, key_b58)).is_some() {
+                self.insert_authorized_key(&account, &key_b58, 0);
+                count += 1;
+            }
+        }
+        
+        env::log_str(&format!("Migrated {} authorized keys", count));
+        format!("migrated {} authorized keys", count)
     }
 
-    pub fn set_referrer_bps(&mut self, bps: u16) {
-        self.assert_owner();
-        require!(bps <= 10000, "BPS must be <= 10000");
-        self.referrer_bps = bps;
+    fn insert_authorized_key(&mut self, account: &AccountId, key_b58: &String, expires_ms: u64) {
+        let mut keys = self
+            .authorized_keys
+            .get(account)
+            .unwrap_or_else(|| UnorderedMap::new(StorageKey::AccountKeys(account.clone())));
+        ensure!(
+            keys.get(key_b58).is_some() || keys.len() < MAX_KEYS_PER_ACCOUNT,
+            NominalError::InvalidRequest("Too many authorized keys")
+        );
+        keys.insert(key_b58, &expires_ms);
+        self.authorized_keys.insert(account, &keys);
     }
 
-    pub fn set_relayer(&mut self, relayer: AccountId, allowed: bool) {
-        self.assert_owner();
-        if allowed {
+    pub fn generate_ownership_proof(&mut self, name: String, challenge: String) -> String {
+        let caller = env::predecessor_account_id();
+        let record = self.get_existing_record(&name);
+        ensure!(record.owner == caller, NominalError::Unauthorized("Only name owner"));
+        
+        let timestamp = env::block_timestamp_ms();
+        let mut message = Vec::new();
+        message.extend_from_slice(env::current_account_id().as_bytes());
+        message.extend_from_slice(name.as_bytes());
+        message.extend_from_slice(caller.as_bytes());
+        message.extend_from_slice(challenge.as_bytes());
+        message.extend_from_slice(&timestamp.to_le_bytes());
+        
+        let proof_hash = hex::encode(env::sha256(&message));
+        self.emit_ownership_proof_generated(&name, &caller, &proof_hash, timestamp);
+        proof_hash
+    }
+
+    // Mirrors records into records_v2 for deployments created with `new`; migrated
+    // deployments fill records_v2 as migrate_records moves each record
+    pub fn migrate_records_v2(&mut self) -> String {
+        self.assert_owner();
+        self.log_admin_action("migrate_records_v2", CONTRACT_VERSION.to_string());
+        ensure!(!self.migrated, NominalError::InvalidRequest("Already migrated"));
+        
+        let mut count: u64 = 0;
+        for (name, record) in self.records.iter() {
+            self.records_v2.insert(&name, &RecordV2 {
+                owner: record.owner,
+                resolved: record.resolved,
+                updated_at: record.updated_at,
+                expiry_ms: U64(0),
+            });
+            count += 1;
+        }
+        self.migrated = true;
+        
+        env::log_str(&format!("Migrated {} records to contract version {}", count, CONTRACT_VERSION));
+        format!("migrated {} records", count)
+    }
+
+    pub fn set_ttl(&mut self, name: String, ttl_ms: U64) {
+        let mut record = self.get_existing_record(&name);
+        ensure!(record.owner == env::predecessor_account_id(), NominalError::Unauthorized("Only name owner"));
+        ensure!(ttl_ms.0 <= self.max_ttl_ms, NominalError::InvalidRequest("TTL too high"));
+        
+        record.ttl_ms = ttl_ms;
+        record.updated_at = U64(env::block_timestamp_ms());
+        self.save_record(&name, &record);
+    }
+
+    pub fn transfer_name(&mut self, name: String, new_owner: AccountId) {
+        self.assert_not_paused();
+        let old_owner = env::predecessor_account_id();
+        let record = self.get_existing_record(&name);
+        ensure!(record.owner == old_owner, NominalError::Unauthorized("Only name owner"));
+        ensure!(!record.locked, NominalError::NameLocked);
+        
+        self.complete_name_transfer(&name, record, new_owner);
+    }
+
+    // Voluntarily gives up a name; nobody can re-register it for deletion_cooldown_ms
+    pub fn delete_name(&mut self, name: String) {
+        self.assert_not_paused();
+        let record = self.get_existing_record(&name);
+        ensure!(record.owner == env::predecessor_account_id(), NominalError::Unauthorized("Only name owner"));
+        ensure!(!record.locked, NominalError::NameLocked);
+        
+        self.remove_record(&name);
+        self.deleted_names.insert(&name, &env::block_timestamp_ms());
+        self.emit_name_deleted(&name, &record.owner);
+    }
+
+    // Anyone can drop a deletion entry once its cooldown has passed
+    pub fn cleanup_deleted_name(&mut self, name: String) {
+        let deleted_at = self.deleted_names.get(&name).unwrap_or_else(|| env::panic_str(&NominalError::InvalidRequest("Name not deleted").to_string()));
+        ensure!(
+            env::block_timestamp_ms() >= deleted_at + self.deletion_cooldown_ms,
+            NominalError::InvalidRequest("Name in cooldown")
+        );
+        
+        self.deleted_names.remove(&name);
+        self.emit_deleted_name_cleaned_up(&name);
+    }
+
+    // Two-step variant of transfer_name: the recipient must accept
+    pub fn initiate_transfer(&mut self, name: String, new_owner: AccountId) {
+        self.assert_not_paused();
+        let record = self.get_existing_record(&name);
+        ensure!(record.owner == env::predecessor_account_id(), NominalError::Unauthorized("Only name owner"));
+        ensure!(new_owner != record.owner, NominalError::InvalidRequest("Invalid owner"));
+        ensure!(!record.locked, NominalError::NameLocked);
+        
+        self.pending_transfers.insert(&name, &new_owner);
+        self.emit_name_transfer_initiated(&name, &record.owner, &new_owner);
+    }
+
+    pub fn accept_transfer(&mut self, name: String) {
+        self.assert_not_paused();
+        let new_owner = self.pending_transfers.get(&name).unwrap_or_else(|| env::panic_str(&NominalError::InvalidRequest("No pending transfer").to_string()));
+        ensure!(env::predecessor_account_id() == new_owner, NominalError::Unauthorized("Only pending owner"));
+        let record = self.get_existing_record(&name);
+        ensure!(!record.locked, NominalError::NameLocked);
+        
+        self.complete_name_transfer(&name, record, new_owner);
+    }
+
+    pub fn cancel_transfer(&mut self, name: String) {
+        let record = self.get_existing_record(&name);
+        ensure!(record.owner == env::predecessor_account_id(), NominalError::Unauthorized("Only name owner"));
+        let cancelled_pending = self.pending_transfers.remove(&name).unwrap_or_else(|| env::panic_str(&NominalError::InvalidRequest("No pending transfer").to_string()));
+        
+        self.emit_name_transfer_cancelled(&name, &record.owner, &cancelled_pending);
+    }
+
+    fn complete_name_transfer(&mut self, name: &String, mut record: Record, new_owner: AccountId) {
+        let old_owner = record.owner.clone();
+        self.increment_name_count(&new_owner);
+        self.decrement_name_count(&old_owner);
+        self.pending_transfers.remove(name);
+        // Grants were made by the previous owner
+        self.clear_acl(name);
+        if self.primary_names.get(&old_owner).as_ref() == Some(name) {
+            self.primary_names.remove(&old_owner);
+        }
+        
+        record.owner = new_owner.clone();
+        record.updated_at = U64(env::block_timestamp_ms());
+        
+        let old_resolved = record.resolved.clone();
+        if self.reset_resolved_on_transfer {
+            record.resolved = new_owner.clone();
+            self.record_history(name, &old_resolved, &new_owner);
+        }
+        self.save_record(name, &record);
+        
+        self.emit_name_transferred(name, &old_owner, &new_owner);
+        if self.reset_resolved_on_transfer {
+            self.emit_resolved_reset(name, &old_resolved, &new_owner);
+        }
+    }
+
+    pub fn set_resolved(&mut self, name: String, resolved: AccountId) {
+        self.assert_not_paused();
+        let caller = env::predecessor_account_id();
+        let mut record = self.get_existing_record(&name);
+        if record.owner != caller {
+            let permissions = self.acl.get(&self.acl_key(&name, &caller)).unwrap_or(0);
+            ensure!(permissions & ACL_CAN_SET_RESOLVED != 0, NominalError::Unauthorized("Only name owner or ACL member"));
+        }
+        ensure!(!record.locked, NominalError::NameLocked);
+        
+        self.record_history(&name, &record.resolved, &resolved);
+        record.resolved = resolved;
+        record.updated_at = U64(env::block_timestamp_ms());
+        self.save_record(&name, &record);
+    }
+
+    // Gas is paid by the calling relayer; the owner only signs params
+    pub fn meta_set_resolved(&mut self, params: MetaTxParams, signature: String) {
+        self.assert_not_paused();
+        ensure!(self.meta_tx_enabled, NominalError::InvalidRequest("Meta transactions disabled"));
+        ensure!(env::block_timestamp_ms() <= params.deadline.0, NominalError::DeadlineExpired);
+        
+        let mut record = self.get_existing_record(&params.name);
+        ensure!(record.owner == params.owner, NominalError::Unauthorized("Only name owner"));
+        ensure!(!record.locked, NominalError::NameLocked);
+        
+        let current_nonce = self.meta_tx_nonce(&params.owner);
+        ensure!(params.nonce.0 == current_nonce, NominalError::InvalidNonce);
+        ensure!(!signature.is_empty(), NominalError::InvalidSignature("Empty signature"));
+        
+        let message = self.create_meta_tx_message(&params);
+        self.verify_ed25519_signature(&params.owner, &message, &signature);
+        self.account_nonces.insert(&params.owner, &(current_nonce + 1));
+        
+        self.record_history(&params.name, &record.resolved, &params.new_resolved);
+        record.resolved = params.new_resolved;
+        record.updated_at = U64(env::block_timestamp_ms());
+        self.save_record(&params.name, &record);
+        
+        self.emit_meta_tx_executed(&params.name, &params.owner, &env::predecessor_account_id(), "set_resolved");
+    }
+
+    // Registers a name to `owner` with fee_amount attached by the caller. When the fee
+    // payer calls this itself (e.g. with a FastAuth function-call key) its transaction
+    // already authenticates it; anyone else must pass the fee payer's signature over
+    // the params, which must cover the normalized name
+    #[payable]
+    pub fn register_via_meta_tx(&mut self, mut params: MetaRegistrationParams, signature: Option<String>) {
+        params.name = self.normalize_registration_name(params.name);
+        self.assert_not_paused();
+        ensure!(self.meta_tx_enabled, NominalError::InvalidRequest("Meta transactions disabled"));
+        ensure!(env::block_timestamp_ms() <= params.deadline.0, NominalError::DeadlineExpired);
+        let amount = env::attached_deposit().as_yoctonear();
+        ensure!(amount == params.fee_amount.0, NominalError::InsufficientFee("Attached deposit must equal fee_amount"));
+        
+        ensure!(self.is_valid_name(&params.name), NominalError::InvalidName);
+        ensure!(self.records.get(&params.name).is_none(), NominalError::NameAlreadyTaken);
+        self.assert_not_reserved_by_other(&params.name, &params.owner);
+        self.assert_not_in_deletion_cooldown(&params.name);
+        self.assert_not_in_auction(&params.name);
+        
+        if env::predecessor_account_id() != params.fee_payer {
+            let signature = signature
+                .filter(|signature| !signature.is_empty())
+                .unwrap_or_else(|| env::panic_str(&NominalError::Unauthorized("Fee payer signature required").to_string()));
+            let current_nonce = self.meta_tx_nonce(&params.fee_payer);
+            ensure!(params.nonce.0 == current_nonce, NominalError::InvalidNonce);
+            
+            let message = self.create_meta_registration_message(&params);
+            self.verify_ed25519_signature(&params.fee_payer, &message, &signature);
+            self.account_nonces.insert(&params.fee_payer, &(current_nonce + 1));
+        }
+        
+        // Any excess goes back to whoever attached the deposit, not the signer
+        let fee = self.take_registration_fee(&params.name, &env::predecessor_account_id(), amount, 1);
+        self.register_record_and_primary(&params.name, &params.owner, env::block_timestamp_ms());
+        self.pay_treasury(fee, &params.name);
+        
+        self.emit_registered(&params.name, &params.owner);
+        self.record_fee_stat(false, fee);
+        self.emit_fee_paid(&params.name, &params.fee_payer, None, fee, None);
+        self.emit_meta_tx_executed(&params.name, &params.owner, &params.fee_payer, "register");
+    }
+
+    pub fn set_bio(&mut self, name: String, bio: String) {
+        let record = self.get_existing_record(&name);
+        ensure!(record.owner == env::predecessor_account_id(), NominalError::Unauthorized("Only name owner"));
+        ensure!(bio.len() <= MAX_BIO_LEN, NominalError::InvalidRequest("Bio too long"));
+        
+        self.emit_bio_set(&name, &record.owner, bio.len());
+        self.bios.insert(&name, &bio);
+    }
+
+    pub fn set_avatar(&mut self, name: String, uri: String, mime_type: String) {
+        let record = self.get_existing_record(&name);
+        ensure!(record.owner == env::predecessor_account_id(), NominalError::Unauthorized("Only name owner"));
+        ensure!(uri.len() <= MAX_AVATAR_URI_LEN, NominalError::InvalidRequest("Avatar URI too long"));
+        ensure!(
+            SUPPORTED_AVATAR_MIME_TYPES.contains(&mime_type.as_str()),
+            NominalError::InvalidRequest("Unsupported MIME type")
+        );
+        
+        self.emit_avatar_set(&name, &record.owner, &mime_type);
+        self.avatars.insert(&name, &AvatarInfo { uri, mime_type });
+    }
+
+    pub fn grant_acl(&mut self, name: String, member: AccountId, permissions: u8) {
+        let record = self.get_existing_record(&name);
+        ensure!(record.owner == env::predecessor_account_id(), NominalError::Unauthorized("Only name owner"));
+        
+        self.acl.insert(&self.acl_key(&name, &member), &permissions);
+        self.emit_acl_granted(&name, &member, permissions);
+    }
+
+    pub fn revoke_acl(&mut self, name: String, member: AccountId) {
+        let record = self.get_existing_record(&name);
+        ensure!(record.owner == env::predecessor_account_id(), NominalError::Unauthorized("Only name owner"));
+        
+        self.acl.remove(&self.acl_key(&name, &member));
+        self.emit_acl_revoked(&name, &member);
+    }
+
+    // Irreversible: there is no unlock method
+    pub fn lock_name(&mut self, name: String) {
+        let owner = env::predecessor_account_id();
+        let mut record = self.get_existing_record(&name);
+        ensure!(record.owner == owner, NominalError::Unauthorized("Only name owner"));
+        ensure!(!record.locked, NominalError::NameLocked);
+        
+        record.locked = true;
+        record.updated_at = U64(env::block_timestamp_ms());
+        self.save_record(&name, &record);
+        self.emit_name_locked(&name, &owner);
+    }
+
+    // Legacy coin fees live in a non-iterable LookupMap under StorageKey::CoinFees,
+    // so the caller supplies the configured token list to carry over.
+    pub fn migrate_coin_fees(&mut self, coins: Vec<AccountId>) -> String {
+        self.assert_owner();
+        self.log_admin_action("migrate_coin_fees", format!("{} coins", coins.len()));
+        ensure!(!self.coin_fees_migrated, NominalError::InvalidRequest("Coin fees already migrated"));
+        
+        let mut legacy_coin_fees: LookupMap<AccountId, u128> = LookupMap::new(StorageKey::CoinFees);
+        let mut count: u64 = 0;
+        for coin in coins.iter() {
+            if let Some(fee) = legacy_coin_fees.remove(coin) {
+                self.coin_fees.insert(coin, &fee);
+                count += 1;
+            }
+        }
+        self.coin_fees_migrated = true;
+        
+        env::log_str(&format!("Migrated {} coin fees", count));
+        format!("migrated {} coin fees", count)
+    }
+
+    pub fn propose_transfer_owner(&mut self, new_owner: AccountId) {
+        self.assert_owner();
+        self.log_admin_action("propose_transfer_owner", new_owner.to_string());
+        self.pending_owner = Some(new_owner.clone());
+        self.transfer_proposed_at_ms = env::block_timestamp_ms();
+        self.emit_owner_transfer_initiated(&new_owner);
+    }
+
+    pub fn accept_owner(&mut self) {
+        let pending_owner = self.pending_owner.clone().unwrap_or_else(|| env::panic_str(&NominalError::InvalidRequest("No pending owner transfer").to_string()));
+        ensure!(env::predecessor_account_id() == pending_owner, NominalError::Unauthorized("Only pending owner"));
+        ensure!(
+            env::block_timestamp_ms() >= self.transfer_proposed_at_ms + self.owner_transfer_timelock_ms,
+            NominalError::InvalidRequest("Owner transfer timelock active")
+        );
+        
+        self.owner = pending_owner;
+        self.pending_owner = None;
+        self.emit_owner_transfer_accepted();
+    }
+
+    pub fn cancel_transfer_owner(&mut self) {
+        self.assert_owner();
+        let cancelled_pending = self.pending_owner.take().unwrap_or_else(|| env::panic_str(&NominalError::InvalidRequest("No pending owner transfer").to_string()));
+        self.log_admin_action("cancel_transfer_owner", cancelled_pending.to_string());
+        self.emit_owner_transfer_cancelled(&cancelled_pending);
+    }
+
+    // Pauses expire on their own so a lost or compromised owner key cannot freeze the registry
+    pub fn pause(&mut self, duration_ms: U64) {
+        self.assert_owner();
+        ensure!(duration_ms.0 <= MAX_PAUSE_DURATION_MS, NominalError::InvalidRequest("Pause duration too long"));
+        // Also rejects extending a pause that is still running
+        ensure!(env::block_timestamp_ms() >= self.pause_cooldown_until_ms, NominalError::InvalidRequest("Pause cooldown active"));
+        self.log_admin_action("pause", duration_ms.0.to_string());
+        self.paused_until_ms = env::block_timestamp_ms() + duration_ms.0;
+        self.pause_cooldown_until_ms = self.paused_until_ms + PAUSE_COOLDOWN_MS;
+        self.emit_protocol_paused(self.paused_until_ms);
+    }
+
+    pub fn force_unpause(&mut self) {
+        self.assert_owner();
+        self.log_admin_action("force_unpause", String::new());
+        self.paused_until_ms = 0;
+        self.pause_cooldown_until_ms = env::block_timestamp_ms() + PAUSE_COOLDOWN_MS;
+        self.emit_protocol_unpaused();
+    }
+
+    pub fn get_pause_status(&self) -> PauseStatus {
+        let remaining_ms = self.paused_until_ms.saturating_sub(env::block_timestamp_ms());
+        PauseStatus {
+            paused: remaining_ms > 0,
+            until_ms: U64(self.paused_until_ms),
+            remaining_ms: U64(remaining_ms),
+            next_pause_ms: U64(self.pause_cooldown_until_ms),
+        }
+    }
+
+    pub fn grant_role(&mut self, role: String, account: AccountId) {
+        self.assert_owner();
+        ensure!(ROLES.contains(&role.as_str()), NominalError::InvalidRequest("Unknown role"));
+        self.log_admin_action("grant_role", format!("{}:{}", role, account));
+        
+        let mut members = self.roles.get(&role).unwrap_or_else(|| UnorderedSet::new(StorageKey::RoleMembers(role.clone())));
+        if members.insert(&account) {
+            self.roles.insert(&role, &members);
+            self.emit_role_granted(&role, &account);
+        }
+    }
+
+    pub fn revoke_role(&mut self, role: String, account: AccountId) {
+        self.assert_owner();
+        self.log_admin_action("revoke_role", format!("{}:{}", role, account));
+        
+        if let Some(mut members) = self.roles.get(&role) {
+            if members.remove(&account) {
+                self.roles.insert(&role, &members);
+                self.emit_role_revoked(&role, &account);
+            }
+        }
+    }
+
+    pub fn has_role(&self, role: String, account: AccountId) -> bool {
+        self.account_has_role(&role, &account)
+    }
+
+    pub fn set_registration_fee(&mut self, amount: U128) {
+        self.assert_role(ROLE_FEE_MANAGER);
+        self.log_admin_action("set_registration_fee", amount.0.to_string());
+        self.registration_fee = amount.0;
+    }
+
+    pub fn set_reservation_fee(&mut self, fee: U128) {
+        self.assert_owner();
+        ensure!(fee.0 > 0, NominalError::InvalidRequest("Reservation fee must be nonzero"));
+        self.log_admin_action("set_reservation_fee", fee.0.to_string());
+        self.reservation_fee = fee.0;
+    }
+
+    pub fn set_treasury(&mut self, treasury: AccountId) {
+        self.assert_role(ROLE_ADMIN);
+        self.log_admin_action("set_treasury", treasury.to_string());
+        self.treasury = treasury;
+    }
+
+    pub fn set_treasury_splits(&mut self, splits: Vec<(AccountId, u16)>) {
+        self.assert_owner();
+        self.log_admin_action("set_treasury_splits", splits.iter().map(|(recipient, bps)| format!("{}:{}", recipient, bps)).collect::<Vec<_>>().join(","));
+        ensure!(splits.len() <= 5, NominalError::InvalidRequest("Too many treasury recipients"));
+        if !splits.is_empty() {
+            // Split payouts are plain transfers, which would bypass the treasury contract's deposit call
+            ensure!(!self.treasury_is_contract, NominalError::InvalidRequest("Treasury splits unavailable in contract mode"));
+            let total_bps: u32 = splits.iter().map(|(_, bps)| *bps as u32).sum();
+            ensure!(total_bps == 10000, NominalError::InvalidRequest("Treasury split BPS must sum to 10000"));
+        }
+        self.treasury_splits = splits;
+    }
+
+    pub fn set_referrer_bps(&mut self, bps: u16) {
+        self.assert_owner();
+        self.log_admin_action("set_referrer_bps", bps.to_string());
+        ensure!(bps <= 10000, NominalError::InvalidRequest("BPS must be <= 10000"));
+        ensure!(bps >= self.min_referrer_bps, NominalError::InvalidRequest("BPS below referrer floor"));
+        self.referrer_bps = bps;
+    }
+
+    pub fn set_min_referrer_bps(&mut self, min_bps: u16) {
+        self.assert_owner();
+        self.log_admin_action("set_min_referrer_bps", min_bps.to_string());
+        ensure!(min_bps <= 10000, NominalError::InvalidRequest("BPS must be <= 10000"));
+        self.min_referrer_bps = min_bps;
+    }
+
+    pub fn set_relayer(&mut self, relayer: AccountId, allowed: bool) {
+        self.assert_role(ROLE_RELAYER_MANAGER);
+        self.log_admin_action("set_relayer", format!("{}:{}", relayer, allowed));
+        if allowed {
             self.relayers.insert(&relayer);
         } else {
             self.relayers.remove(&relayer);
         }
     }
 
-    pub fn set_coin_fee(&mut self, coin: AccountId, fee: U128) -> U128 {
-        self.assert_owner();
-        self.coin_fees.insert(&coin, &fee.0);
-        env::log_str(&format!("Token fee set: {} = {}", coin, fee.0));
-        fee
+    pub fn batch_set_relayers(&mut self, relayers: Vec<(AccountId, bool)>) {
+        self.assert_role(ROLE_RELAYER_MANAGER);
+        ensure!(relayers.len() <= MAX_RELAYER_BATCH_SIZE, NominalError::InvalidRequest("Batch too large"));
+        self.log_admin_action("batch_set_relayers", relayers.len().to_string());
+        
+        let mut added = Vec::new();
+        let mut removed = Vec::new();
+        for (relayer, allowed) in relayers {
+            if allowed {
+                self.relayers.insert(&relayer);
+                added.push(relayer);
+            } else {
+                self.relayers.remove(&relayer);
+                removed.push(relayer);
+            }
+        }
+        self.emit_batch_relayers_updated(&added, &removed);
+    }
+
+    pub fn set_relayer_metadata(&mut self, relayer: AccountId, name: String, url: String) {
+        self.assert_role(ROLE_RELAYER_MANAGER);
+        self.log_admin_action("set_relayer_metadata", format!("{}:{}:{}", relayer, name, url));
+        ensure!(name.len() <= MAX_RELAYER_NAME_LEN, NominalError::InvalidRequest("Relayer name too long"));
+        ensure!(url.len() <= MAX_RELAYER_URL_LEN, NominalError::InvalidRequest("Relayer endpoint URL too long"));
+        self.relayer_metadata.insert(&relayer, &RelayerMeta { name, endpoint_url: url });
+    }
+
+    pub fn set_coin_fee(&mut self, coin: AccountId, fee: U128) -> U128 {
+        self.assert_role(ROLE_FEE_MANAGER);
+        self.log_admin_action("set_coin_fee", format!("{}:{}", coin, fee.0));
+        self.apply_coin_fee(&coin, fee.0);
+        fee
+    }
+
+    pub fn batch_set_coin_fees(&mut self, fees: Vec<(AccountId, U128)>) -> Vec<(AccountId, U128)> {
+        self.assert_role(ROLE_FEE_MANAGER);
+        ensure!(fees.len() <= MAX_BATCH_SIZE, NominalError::InvalidRequest("Batch too large"));
+        self.log_admin_action("batch_set_coin_fees", fees.len().to_string());
+        
+        for (coin, fee) in fees.iter() {
+            self.apply_coin_fee(coin, fee.0);
+        }
+        fees
+    }
+
+    fn apply_coin_fee(&mut self, coin: &AccountId, fee: u128) {
+        self.coin_fees.insert(coin, &fee);
+        // A manually set fee replaces any promotional schedule
+        self.coin_fee_schedules.remove(coin);
+        env::log_str(&format!("Token fee set: {} = {}", coin, fee));
+    }
+
+    pub fn set_treasury_token_account(&mut self, token: AccountId, account: AccountId) {
+        self.assert_owner();
+        self.log_admin_action("set_treasury_token_account", format!("{}:{}", token, account));
+        self.treasury_token_accounts.insert(&token, &account);
+        env::log_str(&format!("Treasury token account set: {} = {}", token, account));
+    }
+
+    // Also sets the coin fee to `base_fee`, enabling the coin if needed
+    pub fn set_coin_fee_schedule(&mut self, coin: AccountId, base_fee: U128, discount_bps: u16, start_ms: U64, end_ms: U64) {
+        self.assert_owner();
+        self.log_admin_action(
+            "set_coin_fee_schedule",
+            format!("{}:{}:{}:{}:{}", coin, base_fee.0, discount_bps, start_ms.0, end_ms.0),
+        );
+        ensure!(discount_bps <= 10_000, NominalError::InvalidRequest("Invalid discount BPS"));
+        ensure!(start_ms.0 <= end_ms.0, NominalError::InvalidRequest("Invalid discount window"));
+        
+        self.coin_fees.insert(&coin, &base_fee.0);
+        self.coin_fee_schedules.insert(&coin, &CoinFeeSchedule {
+            coin: coin.clone(),
+            base_fee,
+            discount_bps,
+            discount_start_ms: start_ms,
+            discount_end_ms: end_ms,
+        });
+        self.emit_coin_fee_schedule_set(&coin, discount_bps, start_ms.0, end_ms.0);
+    }
+
+    pub fn remove_coin_fee(&mut self, coin: AccountId) {
+        self.assert_owner();
+        self.log_admin_action("remove_coin_fee", coin.to_string());
+        ensure!(self.coin_fees.remove(&coin).is_some(), NominalError::InvalidRequest("Token fee not found"));
+        self.coin_fee_schedules.remove(&coin);
+        self.emit_coin_fee_removed(&coin);
+    }
+
+    pub fn disable_coin_fee(&mut self, coin: AccountId) {
+        self.assert_owner();
+        self.log_admin_action("disable_coin_fee", coin.to_string());
+        ensure!(self.coin_fees.get(&coin).is_some(), NominalError::InvalidRequest("Token fee not found"));
+        self.coin_fees.insert(&coin, &COIN_FEE_DISABLED);
+        self.emit_coin_fee_disabled(&coin);
+    }
+
+    pub fn set_allow_admin_override(&mut self, allowed: bool) {
+        self.assert_owner();
+        self.log_admin_action("set_allow_admin_override", allowed.to_string());
+        self.allow_admin_override = allowed;
+    }
+
+    pub fn admin_set_resolved(&mut self, name: String, new_resolved: AccountId) {
+        self.assert_owner();
+        self.log_admin_action("admin_set_resolved", format!("{}:{}", name, new_resolved));
+        ensure!(self.allow_admin_override, NominalError::InvalidRequest("Admin override disabled"));
+        
+        let mut record = self.get_existing_record(&name);
+        let old_resolved = record.resolved.clone();
+        self.record_history(&name, &old_resolved, &new_resolved);
+        record.resolved = new_resolved.clone();
+        record.updated_at = U64(env::block_timestamp_ms());
+        self.save_record(&name, &record);
+        
+        self.emit_admin_forced_resolution_update(&name, &old_resolved, &new_resolved);
+    }
+
+    // Emergency removal of a fraudulent or abusive name; the reason is kept in the admin log
+    pub fn admin_remove_name(&mut self, name: String, removal_reason: String) {
+        self.assert_owner();
+        ensure!(
+            !removal_reason.is_empty() && removal_reason.len() <= MAX_REMOVAL_REASON_LEN,
+            NominalError::InvalidRequest("Removal reason must be 1-128 bytes")
+        );
+        self.log_admin_action("admin_remove_name", format!("{}:{}", name, removal_reason));
+        ensure!(self.allow_admin_override, NominalError::InvalidRequest("Admin override disabled"));
+        ensure!(self.records.get(&name).is_some(), NominalError::NameNotFound);
+        
+        let record = self.remove_record(&name);
+        self.admin_removal_count += 1;
+        self.emit_admin_name_removed(&name, &record.owner, &removal_reason);
+    }
+
+    pub fn get_admin_removal_count(&self) -> U64 {
+        U64(self.admin_removal_count)
+    }
+
+    pub fn set_reset_resolved_on_transfer(&mut self, value: bool) {
+        self.assert_owner();
+        self.log_admin_action("set_reset_resolved_on_transfer", value.to_string());
+        self.reset_resolved_on_transfer = value;
+    }
+
+    pub fn set_treasury_contract_mode(&mut self, is_contract: bool, method: String) {
+        self.assert_owner();
+        ensure!(!method.is_empty(), NominalError::InvalidRequest("Deposit method required"));
+        ensure!(
+            !is_contract || self.treasury_splits.is_empty(),
+            NominalError::InvalidRequest("Treasury splits unavailable in contract mode")
+        );
+        self.log_admin_action("set_treasury_contract_mode", format!("{}:{}", is_contract, method));
+        self.treasury_is_contract = is_contract;
+        self.treasury_deposit_method = method;
+    }
+
+    pub fn set_meta_tx_enabled(&mut self, enabled: bool) {
+        self.assert_owner();
+        self.log_admin_action("set_meta_tx_enabled", enabled.to_string());
+        self.meta_tx_enabled = enabled;
+    }
+
+    pub fn set_name_case_sensitive(&mut self, case_sensitive: bool) {
+        self.assert_owner();
+        self.log_admin_action("set_name_case_sensitive", case_sensitive.to_string());
+        self.name_case_sensitive = case_sensitive;
+    }
+
+    pub fn set_validation_config(&mut self, allow_underscore: bool, allow_uppercase: bool, min_len: u8, max_len: u8) {
+        self.assert_owner();
+        ensure!(
+            min_len >= 1 && min_len <= max_len && max_len as usize <= MAX_NAME_LEN,
+            NominalError::InvalidRequest("Invalid name length bounds")
+        );
+        self.log_admin_action(
+            "set_validation_config",
+            format!("{}:{}:{}:{}", allow_underscore, allow_uppercase, min_len, max_len),
+        );
+        self.allow_underscore = allow_underscore;
+        self.allow_uppercase = allow_uppercase;
+        self.min_name_len = min_len;
+        self.max_name_len = max_len;
+    }
+
+    pub fn set_max_ttl(&mut self, ms: U64) {
+        self.assert_owner();
+        self.log_admin_action("set_max_ttl", ms.0.to_string());
+        self.max_ttl_ms = ms.0;
+    }
+
+    pub fn set_max_sig_validity(&mut self, ms: U64) {
+        self.assert_owner();
+        self.log_admin_action("set_max_sig_validity", ms.0.to_string());
+        ensure!(ms.0 > 0, NominalError::InvalidRequest("Validity must be positive"));
+        self.max_sig_validity_ms = ms.0;
+    }
+
+    pub fn set_auction_config(&mut self, min_increment: U128, platform_fee_bps: u16, fee_recipient: Option<AccountId>) {
+        self.assert_owner();
+        ensure!(platform_fee_bps <= 10_000, NominalError::InvalidRequest("BPS too high"));
+        ensure!(
+            platform_fee_bps == 0 || fee_recipient.is_some(),
+            NominalError::InvalidRequest("Platform fee requires a fee recipient")
+        );
+        self.log_admin_action(
+            "set_auction_config",
+            format!("{}:{}:{}", min_increment.0, platform_fee_bps, fee_recipient.as_ref().map_or("", |r| r.as_str())),
+        );
+        self.min_bid_increment = min_increment.0;
+        self.auction_platform_fee_bps = platform_fee_bps;
+        self.auction_fee_recipient = fee_recipient;
+    }
+
+    // Premium names (short names, dictionary words) are sold by auction instead of first-come registration
+    pub fn start_auction(&mut self, name: String, duration_ms: U64, reserve_price: U128) {
+        self.assert_owner();
+        let name = self.normalize_registration_name(name);
+        // Length bounds are skipped so names below min_name_len can still be auctioned
+        ensure!(
+            !name.is_empty()
+                && name.len() <= self.max_name_len as usize
+                && validate_name_chars(&name, self.allow_uppercase, self.allow_underscore).is_ok(),
+            NominalError::InvalidName
+        );
+        ensure!(self.records.get(&name).is_none(), NominalError::NameAlreadyTaken);
+        ensure!(self.pending_ft_registrations.get(&name).is_none(), NominalError::InvalidRequest("Registration pending"));
+        ensure!(
+            self.reservations.get(&name).map_or(true, |r| r.expires_ms.0 < env::block_timestamp_ms()),
+            NominalError::InvalidRequest("Name reserved")
+        );
+        self.assert_not_in_deletion_cooldown(&name);
+        self.assert_not_in_auction(&name);
+        ensure!(duration_ms.0 > 0, NominalError::InvalidRequest("Invalid auction duration"));
+        self.log_admin_action("start_auction", format!("{}:{}:{}", name, duration_ms.0, reserve_price.0));
+        
+        let now = env::block_timestamp_ms();
+        let auction = Auction {
+            name: name.clone(),
+            highest_bidder: None,
+            highest_bid: U128(0),
+            reserve_price,
+            start_ms: U64(now),
+            end_ms: U64(now + duration_ms.0),
+            settled: false,
+        };
+        self.auctions.insert(&name, &auction);
+        self.emit_auction_started(&auction);
+    }
+
+    #[payable]
+    pub fn place_bid(&mut self, name: String) {
+        let name = self.normalize_registration_name(name);
+        self.assert_not_paused();
+        let bidder = env::predecessor_account_id();
+        let amount = env::attached_deposit().as_yoctonear();
+        let now = env::block_timestamp_ms();
+        let mut auction = self.get_existing_auction(&name);
+        ensure!(!auction.settled && now < auction.end_ms.0, NominalError::InvalidRequest("Auction ended"));
+        ensure!(!self.owner_at_name_limit(&bidder), NominalError::InvalidRequest("Owner name limit reached"));
+        
+        match &auction.highest_bidder {
+            Some(previous) => {
+                ensure!(
+                    amount > auction.highest_bid.0 + self.min_bid_increment,
+                    NominalError::InsufficientFee("Bid below minimum increment")
+                );
+                Promise::new(previous.clone()).transfer(NearToken::from_yoctonear(auction.highest_bid.0));
+            }
+            None => ensure!(amount >= auction.reserve_price.0, NominalError::InsufficientFee("Bid below reserve price")),
+        }
+        
+        auction.highest_bidder = Some(bidder.clone());
+        auction.highest_bid = U128(amount);
+        if auction.end_ms.0 - now < AUCTION_ANTI_SNIPE_MS {
+            auction.end_ms = U64(auction.end_ms.0 + AUCTION_ANTI_SNIPE_MS);
+        }
+        self.auctions.insert(&name, &auction);
+        self.emit_auction_bid(&name, &bidder, amount, auction.end_ms.0);
+    }
+
+    // Anyone can settle once the auction has ended; an auction without bids just closes.
+    // If the winner can no longer take the name, the winning bid is refunded
+    pub fn settle_auction(&mut self, name: String) {
+        let name = self.normalize_registration_name(name);
+        let mut auction = self.get_existing_auction(&name);
+        ensure!(!auction.settled, NominalError::InvalidRequest("Auction already settled"));
+        ensure!(env::block_timestamp_ms() >= auction.end_ms.0, NominalError::InvalidRequest("Auction still running"));
+        
+        auction.settled = true;
+        self.auctions.insert(&name, &auction);
+        
+        let winner = match &auction.highest_bidder {
+            Some(winner) => winner.clone(),
+            None => {
+                self.emit_auction_settled(&name, None, 0);
+                return;
+            }
+        };
+        let bid = auction.highest_bid.0;
+        let failure = if self.records.get(&name).is_some() {
+            Some("name_taken")
+        } else if self.owner_at_name_limit(&winner) {
+            Some("owner_name_limit")
+        } else {
+            None
+        };
+        if let Some(reason) = failure {
+            Promise::new(winner.clone()).transfer(NearToken::from_yoctonear(bid));
+            self.emit_registration_failed(&name, &winner, reason);
+            self.emit_auction_settled(&name, None, 0);
+            return;
+        }
+        self.register_record_and_primary(&name, &winner, env::block_timestamp_ms());
+        
+        let platform_fee = match &self.auction_fee_recipient {
+            Some(recipient) => {
+                let fee = bid * self.auction_platform_fee_bps as u128 / 10_000;
+                if fee > 0 {
+                    Promise::new(recipient.clone()).transfer(NearToken::from_yoctonear(fee));
+                }
+                fee
+            }
+            None => 0,
+        };
+        if bid > platform_fee {
+            self.pay_treasury(bid - platform_fee, &name);
+        }
+        
+        self.emit_registered(&name, &winner);
+        self.record_fee_stat(false, bid);
+        self.emit_fee_paid(&name, &winner, None, bid, None);
+        self.emit_auction_settled(&name, Some(&winner), bid);
+    }
+
+    pub fn get_auction(&self, name: String) -> Option<Auction> {
+        self.auctions.get(&name)
+    }
+
+    fn get_existing_record(&self, name: &String) -> Record {
+        self.records
+            .get(name)
+            .unwrap_or_else(|| env::panic_str(&NominalError::NameNotFound.to_string()))
+    }
+
+    fn get_existing_auction(&self, name: &String) -> Auction {
+        self.auctions
+            .get(name)
+            .unwrap_or_else(|| env::panic_str(&NominalError::InvalidRequest("No auction for name").to_string()))
+    }
+
+    pub fn set_deletion_cooldown(&mut self, ms: U64) {
+        self.assert_owner();
+        self.log_admin_action("set_deletion_cooldown", ms.0.to_string());
+        ensure!(ms.0 <= MAX_DELETION_COOLDOWN_MS, NominalError::InvalidRequest("Cooldown too long"));
+        self.deletion_cooldown_ms = ms.0;
+    }
+
+    // Names registered while disabled are only indexed once backfill_prefix_index reaches them
+    pub fn set_prefix_search_enabled(&mut self, enabled: bool) {
+        self.assert_owner();
+        self.log_admin_action("set_prefix_search_enabled", enabled.to_string());
+        self.prefix_search_enabled = enabled;
+    }
+
+    // Indexes up to `limit` registered names in name order after `cursor`. Returns the
+    // cursor for the next call, or None once every name has been visited
+    pub fn backfill_prefix_index(&mut self, cursor: Option<String>, limit: U64) -> Option<String> {
+        self.assert_owner();
+        ensure!(self.prefix_search_enabled, NominalError::InvalidRequest("Prefix search disabled"));
+        let limit = limit.0.clamp(1, MAX_RECORDS_PAGE_SIZE) as usize;
+        let names: Vec<String> = match cursor {
+            Some(cursor) => self.sorted_names.iter_from(cursor).map(|(name, _)| name).take(limit + 1).collect(),
+            None => self.sorted_names.iter().map(|(name, _)| name).take(limit + 1).collect(),
+        };
+        let has_more = names.len() > limit;
+        
+        for name in names.iter().take(limit) {
+            if !self.add_to_prefix_index(name) {
+                self.emit_prefix_index_full(&name[..PREFIX_LEN], name);
+            }
+        }
+        if has_more { names.get(limit - 1).cloned() } else { None }
+    }
+
+    pub fn set_require_exact_fee(&mut self, require_exact: bool) {
+        self.assert_owner();
+        self.log_admin_action("set_require_exact_fee", require_exact.to_string());
+        self.require_exact_fee = require_exact;
+    }
+
+    pub fn set_max_names_per_owner(&mut self, max: u32) {
+        self.assert_owner();
+        self.log_admin_action("set_max_names_per_owner", max.to_string());
+        self.max_names_per_owner = max;
+    }
+
+    pub fn set_owner_timelock(&mut self, ms: U64) {
+        self.assert_owner();
+        ensure!(ms.0 <= MAX_TIMELOCK_MS, NominalError::InvalidRequest("Timelock too long"));
+        self.log_admin_action("set_owner_timelock", ms.0.to_string());
+        self.owner_transfer_timelock_ms = ms.0;
+    }
+
+    // None disables bridged claims
+    pub fn set_bridge_guardians(&mut self, guardians: Option<GuardianSet>) {
+        self.assert_owner();
+        if let Some(guardians) = &guardians {
+            guardians.validate();
+        }
+        let detail = guardians.as_ref().map_or(String::new(), |g| {
+            let keys: Vec<String> = g.keys.iter().map(String::from).collect();
+            format!("{}/{}:{}", g.threshold, g.keys.len(), keys.join(","))
+        });
+        self.log_admin_action("set_bridge_guardians", detail);
+        self.bridge_guardians = guardians;
+    }
+
+    // Applies to registrations and renewals from now on; existing expiries are unchanged
+    pub fn set_registration_period(&mut self, period_ms: U64) {
+        self.assert_owner();
+        ensure!(period_ms.0 <= MAX_REGISTRATION_PERIOD_MS, NominalError::InvalidRequest("Registration period too long"));
+        self.log_admin_action("set_registration_period", period_ms.0.to_string());
+        self.registration_period_ms = period_ms.0;
+    }
+
+    pub fn set_expiry_warning_window(&mut self, window_ms: U64) {
+        self.assert_owner();
+        self.log_admin_action("set_expiry_warning_window", window_ms.0.to_string());
+        self.expiry_warning_window_ms = window_ms.0;
+    }
+
+    pub fn set_expired_name_premium(&mut self, bps: u32) {
+        self.assert_owner();
+        self.log_admin_action("set_expired_name_premium", bps.to_string());
+        ensure!(bps >= 10_000, NominalError::InvalidRequest("Premium cannot discount the fee"));
+        self.expired_name_premium_bps = bps;
+        self.emit_expired_name_premium_set(bps);
+    }
+
+    pub fn set_stale_multiplier(&mut self, bps: u32, window_ms: U64) {
+        self.assert_owner();
+        self.log_admin_action("set_stale_multiplier", format!("{}:{}", bps, window_ms.0));
+        ensure!(
+            (10_000..=MAX_STALE_MULTIPLIER_BPS).contains(&bps),
+            NominalError::InvalidRequest("Stale multiplier must be between 1x and 5x")
+        );
+        self.stale_multiplier_bps = bps;
+        self.staleness_window_ms = window_ms.0;
+        self.emit_stale_multiplier_set(bps, window_ms.0);
+    }
+
+    pub fn set_require_relayer_allowlist(&mut self, required: bool) {
+        self.assert_owner();
+        self.log_admin_action("set_require_relayer_allowlist", required.to_string());
+        self.require_relayer_allowlist = required;
+    }
+
+    // Anyone may purge up to MAX_PURGE_BATCH names per call; the owner is only bounded by gas
+    pub fn purge_expired_names(&mut self, names: Vec<String>) -> u32 {
+        if env::predecessor_account_id() != self.owner {
+            ensure!(names.len() <= MAX_PURGE_BATCH, NominalError::InvalidRequest("Too many names"));
+        }
+        
+        let now = env::block_timestamp_ms();
+        let mut purged = 0;
+        for name in names {
+            if env::prepaid_gas().saturating_sub(env::used_gas()) < GAS_RESERVE_FOR_PURGE {
+                break;
+            }
+            if !self.is_expired_past_grace(&name, now) {
+                continue;
+            }
+            
+            let record = self.remove_record(&name);
+            self.emit_name_purged(&name, &record.owner);
+            purged += 1;
+        }
+        purged
+    }
+
+    // Permissionless crank; emits at most one warning per name per warning window
+    pub fn emit_expiry_warning(&mut self, name: String) {
+        let record = self.records_v2.get(&name).unwrap_or_else(|| env::panic_str(&NominalError::NameNotFound.to_string()));
+        let expires_at = record.expiry_ms.0;
+        ensure!(expires_at != 0, NominalError::InvalidRequest("Name does not expire"));
+        
+        let now = env::block_timestamp_ms();
+        ensure!(now < expires_at, NominalError::InvalidRequest("Name already expired"));
+        ensure!(expires_at - now <= self.expiry_warning_window_ms, NominalError::InvalidRequest("Name not within warning window"));
+        ensure!(
+            self.expiry_warnings.get(&name).map_or(true, |warned_at| now - warned_at > self.expiry_warning_window_ms),
+            NominalError::InvalidRequest("Expiry warning already emitted")
+        );
+        
+        self.expiry_warnings.insert(&name, &now);
+        self.emit_name_expiring_warning(&name, &record.owner, expires_at, (expires_at - now) / MS_PER_DAY);
+    }
+
+    pub fn get_record(&self, name: String) -> Option<Record> {
+        self.records.get(&name).or_else(|| self.legacy_records.get(&name).map(Record::from))
+    }
+
+    pub fn is_name_available(&self, name: String) -> bool {
+        self.check_name(name).available
+    }
+
+    pub fn check_name(&self, name: String) -> NameAvailabilityResult {
+        // Same normalization as register, so the answer matches what registering would do
+        let name = self.normalize_registration_name(name);
+        let now = env::block_timestamp_ms();
+        // Taken first, so names below a since-raised minimum length still report as taken
+        let reason = if self.records.get(&name).is_some() {
+            Some("taken")
+        } else if !self.is_valid_name(&name) {
+            Some("invalid_name")
+        } else if self.reservations.get(&name).map_or(false, |r| r.expires_ms.0 >= now) {
+            Some("reserved")
+        } else if self.deleted_names.get(&name).map_or(false, |deleted_at| now < deleted_at + self.deletion_cooldown_ms) {
+            Some("deletion_cooldown")
+        } else if self.pending_ft_registrations.get(&name).is_some() {
+            Some("pending_registration")
+        } else if self.auctions.get(&name).map_or(false, |auction| !auction.settled) {
+            Some("in_auction")
+        } else {
+            None
+        };
+        
+        NameAvailabilityResult {
+            available: reason.is_none(),
+            reason: reason.map(str::to_string),
+            fee: U128(self.registration_fee),
+            // No price oracle yet
+            fee_usd_cents: None,
+        }
+    }
+
+    pub fn get_stats(&self) -> StatsView {
+        StatsView {
+            total_names: U64(self.records.len()),
+            length_histogram: self.length_histogram.iter().map(|c| U64(*c)).collect(),
+            fee_histogram: self.fee_histogram.iter().map(|c| U64(*c)).collect(),
+        }
+    }
+
+    // Pages through records in name order; `cursor` is the last name of the previous page.
+    // The page starts right after the cursor in the sorted index, so it is safe to resume
+    // across registrations and removals
+    pub fn get_records_from_cursor(&self, cursor: Option<String>, limit: U64) -> RecordsPage {
+        let limit = limit.0.min(MAX_RECORDS_PAGE_SIZE) as usize;
+        let names: Vec<String> = match cursor {
+            Some(cursor) => self.sorted_names.iter_from(cursor).map(|(name, _)| name).take(limit + 1).collect(),
+            None => self.sorted_names.iter().map(|(name, _)| name).take(limit + 1).collect(),
+        };
+        let has_more = names.len() > limit;
+        
+        let records: Vec<(String, Record)> = names
+            .into_iter()
+            .take(limit)
+            .filter_map(|name| self.records.get(&name).map(|record| (name, record)))
+            .collect();
+        let next_cursor = if has_more { records.last().map(|(name, _)| name.clone()) } else { None };
+        RecordsPage { records, next_cursor }
+    }
+
+    // Kept for clients written against the sorted-index variant; same as get_records_from_cursor
+    pub fn get_records_cursor_stable(&self, cursor: Option<String>, limit: U64) -> RecordsPage {
+        self.get_records_from_cursor(cursor, limit)
+    }
+
+    // Autocomplete lookup; `prefix` must be exactly PREFIX_LEN bytes
+    pub fn search_by_prefix(&self, prefix: String, limit: u64) -> Vec<String> {
+        ensure!(self.prefix_search_enabled, NominalError::InvalidRequest("Prefix search disabled"));
+        ensure!(prefix.len() == PREFIX_LEN, NominalError::InvalidRequest("Prefix must be 2 characters"));
+        match self.trie.get(&prefix) {
+            Some(names) => names.iter().take(limit.min(MAX_RECORDS_PAGE_SIZE) as usize).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn get_name_history(&self, name: String, from: u32, limit: u32) -> Vec<HistoryEntry> {
+        self.history
+            .get(&name)
+            .unwrap_or_default()
+            .into_iter()
+            .skip(from as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    pub fn get_coin_fee_schedule(&self, coin: AccountId) -> Option<CoinFeeSchedule> {
+        self.coin_fee_schedules.get(&coin)
+    }
+
+    // Fee charged right now for `coin`, with any active discount applied
+    pub fn get_effective_coin_fee(&self, coin: AccountId) -> Option<U128> {
+        self.coin_fees
+            .get(&coin)
+            .filter(|fee| *fee != COIN_FEE_DISABLED)
+            .map(|fee| U128(self.effective_coin_fee(&coin, fee)))
+    }
+
+    pub fn get_referrer_stats(&self, referrer: AccountId) -> Option<ReferrerStats> {
+        self.referrer_stats.get(&referrer)
+    }
+
+    pub fn get_bio(&self, name: String) -> Option<String> {
+        self.bios.get(&name)
+    }
+
+    pub fn get_avatar(&self, name: String) -> Option<AvatarInfo> {
+        self.avatars.get(&name)
+    }
+
+    // None when the name isn't registered
+    pub fn get_full_profile(&self, name: String) -> Option<NameProfile> {
+        let record = self.records.get(&name)?;
+        let primary_owner = Some(record.owner.clone())
+            .filter(|owner| self.primary_names.get(owner).as_ref() == Some(&name));
+        let expiry_ms = self.records_v2.get(&name).map(|r| r.expiry_ms).filter(|ms| ms.0 != 0);
+        
+        Some(NameProfile {
+            record: Some(record),
+            primary_owner,
+            bio: self.bios.get(&name),
+            avatar: self.avatars.get(&name),
+            text_records: Vec::new(),
+            social_attestations: Vec::new(),
+            coin_addresses: Vec::new(),
+            expiry_ms,
+        })
+    }
+
+    pub fn get_name_count(&self, owner: AccountId) -> u32 {
+        self.name_count_by_owner.get(&owner).unwrap_or(0)
+    }
+
+    pub fn get_registration_date(&self, name: String) -> Option<U64> {
+        self.records.get(&name).map(|record| record.created_at)
+    }
+
+    pub fn resolve(&self, name: String) -> Option<AccountId> {
+        self.records
+            .get(&name)
+            .map(|record| record.resolved)
+            .or_else(|| self.legacy_records.get(&name).map(|record| record.resolved))
+    }
+
+    pub fn record_resolution(&mut self, name: String) -> U64 {
+        ensure!(self.records.get(&name).is_some(), NominalError::NameNotFound);
+        let count = self.resolution_counts.get(&name).unwrap_or(0) + 1;
+        self.resolution_counts.insert(&name, &count);
+        self.emit_name_resolved(&name, count);
+        U64(count)
+    }
+
+    pub fn get_pending_ft_registration(&self, name: String) -> Option<PendingFtReg> {
+        self.pending_ft_registrations.get(&name)
+    }
+
+    pub fn get_resolution_count(&self, name: String) -> U64 {
+        U64(self.resolution_counts.get(&name).unwrap_or(0))
+    }
+
+    pub fn resolve_batch(&self, names: Vec<String>) -> Vec<Option<Record>> {
+        names.iter().map(|name| self.records.get(name)).collect()
+    }
+
+    pub fn get_record_v2(&self, name: String) -> Option<RecordV2> {
+        self.records_v2.get(&name)
+    }
+
+    pub fn get_contract_version(&self) -> String {
+        CONTRACT_VERSION.to_string()
+    }
+
+    pub fn get_reservation(&self, name: String) -> Option<Reservation> {
+        self.reservations.get(&name)
+    }
+
+    pub fn get_ttl(&self, name: String) -> U64 {
+        self.get_existing_record(&name).ttl_ms
+    }
+
+    pub fn get_pending_transfer(&self, name: String) -> Option<AccountId> {
+        self.pending_transfers.get(&name)
+    }
+
+    // True only for names that register unchanged: valid and already normalized
+    pub fn is_valid_name_normalized(&self, name: String) -> bool {
+        normalize_name(&name) == name && self.is_valid_name(&name)
+    }
+
+    pub fn get_bridge_guardians(&self) -> Option<GuardianSet> {
+        self.bridge_guardians.clone()
+    }
+
+    // Name claimed with the given Solana lock message nonce, if any
+    pub fn get_bridge_claim(&self, message_nonce: U64) -> Option<String> {
+        self.bridge_claims.get(&message_nonce.0)
+    }
+
+    pub fn get_pending_owner(&self) -> Option<AccountId> {
+        self.pending_owner.clone()
+    }
+
+    pub fn get_primary_name(&self, account: AccountId) -> Option<String> {
+        self.primary_names.get(&account)
+    }
+
+    // False when the account has no primary name or no longer owns it (e.g. after a transfer)
+    pub fn validate_primary_name(&self, account: AccountId) -> bool {
+        self.primary_names
+            .get(&account)
+            .and_then(|name| self.records.get(&name))
+            .map_or(false, |record| record.owner == account)
+    }
+
+    pub fn get_coin_fee(&self, coin: AccountId) -> Option<U128> {
+        self.coin_fees.get(&coin).map(U128)
+    }
+
+    pub fn get_treasury_token_account(&self, token: AccountId) -> AccountId {
+        self.token_treasury(&token)
+    }
+
+    pub fn get_all_coin_fees(&self, from_index: U64, limit: U64) -> Vec<(AccountId, U128)> {
+        self.coin_fees
+            .iter()
+            .skip(from_index.0 as usize)
+            .take(limit.0 as usize)
+            .map(|(coin, fee)| (coin, U128(fee)))
+            .collect()
+    }
+
+    pub fn get_relayer_metadata(&self, relayer: AccountId) -> Option<RelayerMeta> {
+        self.relayer_metadata.get(&relayer)
+    }
+
+    pub fn get_acl_permissions(&self, name: String, member: AccountId) -> u8 {
+        self.acl.get(&self.acl_key(&name, &member)).unwrap_or(0)
+    }
+
+    // Oldest first
+    pub fn get_admin_log(&self, from_index: U64, limit: U64) -> Vec<AdminAction> {
+        let len = self.admin_log.len();
+        (from_index.0..len)
+            .take(limit.0 as usize)
+            .filter_map(|i| self.admin_log.get((self.admin_log_head + i) % len))
+            .collect()
+    }
+
+    pub fn is_relayer_allowed(&self, relayer: AccountId) -> bool {
+        !self.require_relayer_allowlist || self.relayers.contains(&relayer)
+    }
+
+    pub fn get_nonce(&self, name: String) -> U64 {
+        U64(self.nonces.get(&name).unwrap_or(0))
+    }
+
+    pub fn get_account_nonce(&self, account: AccountId) -> U64 {
+        U64(self.meta_tx_nonce(&account))
+    }
+
+    // Nonces used before account_nonces existed were stored in `nonces` under the
+    // account id, so they are still honoured until the account's next meta-tx
+    fn meta_tx_nonce(&self, account: &AccountId) -> u64 {
+        self.account_nonces
+            .get(account)
+            .or_else(|| self.nonces.get(&account.to_string()))
+            .unwrap_or(0)
+    }
+    
+    // Unexpired keys only; `from_index` counts expired keys too
+    pub fn get_authorized_keys(&self, account: AccountId, from_index: U64, limit: U64) -> Vec<AuthorizedKeyInfo> {
+        let keys = match self.authorized_keys.get(&account) {
+            Some(keys) => keys,
+            None => return Vec::new(),
+        };
+        let now = env::block_timestamp_ms();
+        keys.iter()
+            .skip(from_index.0 as usize)
+            .take(limit.0 as usize)
+            .filter(|(_, expires_ms)| *expires_ms == 0 || *expires_ms > now)
+            .map(|(key, expires_ms)| AuthorizedKeyInfo {
+                key,
+                expires_ms: if expires_ms == 0 { None } else { Some(U64(expires_ms)) },
+            })
+            .collect()
+    }
+
+    pub fn get_authorized_key_count(&self, account: AccountId) -> U64 {
+        U64(self.get_authorized_keys(account, U64(0), U64(MAX_KEYS_PER_ACCOUNT)).len() as u64)
+    }
+
+    pub fn is_key_authorized(&self, account: AccountId, public_key: PublicKey) -> bool {
+        let key_b58 = bs58::encode(&public_key.into_bytes()).into_string();
+        match self.authorized_keys.get(&account).and_then(|keys| keys.get(&key_b58)) {
+            Some(expires_ms) => expires_ms == 0 || expires_ms > env::block_timestamp_ms(),
+            None => false,
+        }
+    }
+
+    pub fn get_config(&self) -> RegistryConfigView {
+        RegistryConfigView {
+            owner: self.owner.clone(),
+            pending_owner: self.pending_owner.clone(),
+            treasury: self.treasury.clone(),
+            registration_fee: U128(self.registration_fee),
+            referrer_bps: self.referrer_bps,
+            min_referrer_bps: self.min_referrer_bps,
+            treasury_splits: self.treasury_splits.clone(),
+            require_relayer_allowlist: self.require_relayer_allowlist,
+            allow_admin_override: self.allow_admin_override,
+            reservation_fee: U128(self.reservation_fee),
+            coin_fees_migrated: self.coin_fees_migrated,
+            reset_resolved_on_transfer: self.reset_resolved_on_transfer,
+            expired_name_premium_bps: self.expired_name_premium_bps,
+            stale_multiplier_bps: self.stale_multiplier_bps,
+            staleness_window_ms: U64(self.staleness_window_ms),
+            max_names_per_owner: self.max_names_per_owner,
+            expiry_warning_window_ms: U64(self.expiry_warning_window_ms),
+            owner_transfer_timelock_ms: U64(self.owner_transfer_timelock_ms),
+            require_exact_fee: self.require_exact_fee,
+            meta_tx_enabled: self.meta_tx_enabled,
+            treasury_is_contract: self.treasury_is_contract,
+            treasury_deposit_method: self.treasury_deposit_method.clone(),
+            name_case_sensitive: self.name_case_sensitive,
+            prefix_search_enabled: self.prefix_search_enabled,
+            deletion_cooldown_ms: U64(self.deletion_cooldown_ms),
+            max_sig_validity_ms: U64(self.max_sig_validity_ms),
+            min_name_len: self.min_name_len,
+            max_name_len: self.max_name_len,
+            allow_underscore: self.allow_underscore,
+            allow_uppercase: self.allow_uppercase,
+            min_bid_increment: U128(self.min_bid_increment),
+            auction_platform_fee_bps: self.auction_platform_fee_bps,
+            auction_fee_recipient: self.auction_fee_recipient.clone(),
+            max_ttl_ms: U64(self.max_ttl_ms),
+            registration_period_ms: U64(self.registration_period_ms),
+        }
+    }
+
+    fn emit_admin_action(&self, action: &AdminAction) {
+        emit_nep297(&NominalEventStandard::new("nominal_admin_action", AdminActionData {
+            action_type: &action.action_type,
+            value: &action.value,
+            timestamp_ms: U64(action.timestamp_ms),
+            admin: &self.owner,
+        }));
+    }
+
+    fn emit_registered(&self, name: &str, owner: &AccountId) {
+        emit_nep297(&NominalEventStandard::new("nominal_registered", RegisteredData { name, owner }));
+    }
+
+    fn emit_primary_name_set(&self, owner: &AccountId, name: &str) {
+        emit_nep297(&NominalEventStandard::new("nominal_primary_name_set", PrimaryNameSetData { owner, name }));
+    }
+
+    fn emit_coin_fee_schedule_set(&self, coin: &AccountId, discount_bps: u16, start_ms: u64, end_ms: u64) {
+        emit_nep297(&NominalEventStandard::new("nominal_coin_fee_schedule_set", CoinFeeScheduleSetData {
+            coin,
+            discount_bps,
+            start_ms: U64(start_ms),
+            end_ms: U64(end_ms),
+        }));
+    }
+
+    fn emit_batch_relayers_updated(&self, added: &[AccountId], removed: &[AccountId]) {
+        emit_nep297(&NominalEventStandard::new("nominal_batch_relayers_updated", BatchRelayersUpdatedData { added, removed }));
+    }
+
+    fn emit_coin_fee_removed(&self, coin: &AccountId) {
+        emit_nep297(&NominalEventStandard::new("nominal_coin_fee_removed", CoinFeeData { coin }));
+    }
+
+    fn emit_coin_fee_disabled(&self, coin: &AccountId) {
+        emit_nep297(&NominalEventStandard::new("nominal_coin_fee_disabled", CoinFeeData { coin }));
+    }
+
+    fn emit_ownership_proof_generated(&self, name: &str, owner: &AccountId, proof_hash: &str, timestamp: u64) {
+        emit_nep297(&NominalEventStandard::new("nominal_ownership_proof_generated", OwnershipProofGeneratedData {
+            name,
+            owner,
+            proof_hash,
+            timestamp: U64(timestamp),
+        }));
+    }
+
+    fn emit_admin_forced_resolution_update(&self, name: &str, old_resolved: &AccountId, new_resolved: &AccountId) {
+        emit_nep297(&NominalEventStandard::new("nominal_admin_forced_resolution_update", AdminForcedResolutionUpdateData {
+            name,
+            old_resolved,
+            new_resolved,
+            admin: &self.owner,
+        }));
+    }
+
+    fn emit_meta_tx_executed(&self, name: &str, owner: &AccountId, relayer: &AccountId, action: &str) {
+        emit_nep297(&NominalEventStandard::new("nominal_meta_tx_executed", MetaTxExecutedData { name, owner, relayer, action }));
+    }
+
+    fn emit_excess_fee_refunded(&self, name: &str, payer: &AccountId, refund_amount: u128) {
+        emit_nep297(&NominalEventStandard::new("nominal_excess_fee_refunded", ExcessFeeRefundedData {
+            name,
+            payer,
+            refund_amount: U128(refund_amount),
+        }));
+    }
+
+    fn emit_bio_set(&self, name: &str, owner: &AccountId, bio_len: usize) {
+        emit_nep297(&NominalEventStandard::new("nominal_bio_set", BioSetData { name, owner, bio_len }));
+    }
+
+    fn emit_avatar_set(&self, name: &str, owner: &AccountId, mime_type: &str) {
+        emit_nep297(&NominalEventStandard::new("nominal_avatar_set", AvatarSetData { name, owner, mime_type }));
+    }
+
+    fn emit_name_normalized(&self, original: &str, normalized: &str) {
+        emit_nep297(&NominalEventStandard::new("nominal_name_normalized", NameNormalizedData { original, normalized }));
+    }
+
+    fn emit_bridged_name_claimed(&self, name: &str, solana_owner: &str, owner: &AccountId, nonce: u64) {
+        emit_nep297(&NominalEventStandard::new("nominal_bridged_name_claimed", BridgedNameClaimedData {
+            name,
+            solana_owner,
+            owner,
+            nonce: U64(nonce),
+        }));
+    }
+
+    fn emit_prefix_index_full(&self, prefix: &str, name: &str) {
+        emit_nep297(&NominalEventStandard::new("nominal_prefix_index_full", PrefixIndexFullData { prefix, name }));
+    }
+
+    fn emit_auction_started(&self, auction: &Auction) {
+        emit_nep297(&NominalEventStandard::new("nominal_auction_started", AuctionStartedData {
+            name: &auction.name,
+            reserve_price: auction.reserve_price,
+            end_ms: auction.end_ms,
+        }));
+    }
+
+    fn emit_auction_bid(&self, name: &str, bidder: &AccountId, amount: u128, end_ms: u64) {
+        emit_nep297(&NominalEventStandard::new("nominal_auction_bid", AuctionBidData {
+            name,
+            bidder,
+            amount: U128(amount),
+            end_ms: U64(end_ms),
+        }));
+    }
+
+    fn emit_auction_settled(&self, name: &str, winner: Option<&AccountId>, amount: u128) {
+        emit_nep297(&NominalEventStandard::new("nominal_auction_settled", AuctionSettledData {
+            name,
+            winner,
+            amount: U128(amount),
+        }));
+    }
+
+    fn emit_role_granted(&self, role: &str, account: &AccountId) {
+        emit_nep297(&NominalEventStandard::new("nominal_role_granted", RoleData { role, account }));
+    }
+
+    fn emit_role_revoked(&self, role: &str, account: &AccountId) {
+        emit_nep297(&NominalEventStandard::new("nominal_role_revoked", RoleData { role, account }));
+    }
+
+    fn emit_treasury_deposit_failed(&self, memo: &str, amount: u128) {
+        emit_nep297(&NominalEventStandard::new("nominal_treasury_deposit_failed", TreasuryDepositFailedData {
+            memo,
+            amount: U128(amount),
+        }));
+    }
+
+    fn emit_sig_registration_failed(&self, name: &str, step: &str) {
+        emit_nep297(&NominalEventStandard::new("nominal_sig_registration_failed", SigRegistrationFailedData { name, step }));
+    }
+
+    fn emit_admin_name_removed(&self, name: &str, owner: &AccountId, reason: &str) {
+        emit_nep297(&NominalEventStandard::new("nominal_admin_name_removed", AdminNameRemovedData {
+            name,
+            owner,
+            reason,
+            admin: &self.owner,
+        }));
+    }
+
+    fn emit_name_deleted(&self, name: &str, owner: &AccountId) {
+        emit_nep297(&NominalEventStandard::new("nominal_name_deleted", NameOwnerData { name, owner }));
+    }
+
+    fn emit_deleted_name_cleaned_up(&self, name: &str) {
+        emit_nep297(&NominalEventStandard::new("nominal_deleted_name_cleaned_up", DeletedNameCleanedUpData { name }));
+    }
+
+    fn emit_protocol_paused(&self, until_ms: u64) {
+        emit_nep297(&NominalEventStandard::new("nominal_protocol_paused", ProtocolPausedData { until_ms: U64(until_ms) }));
+    }
+
+    fn emit_protocol_unpaused(&self) {
+        emit_nep297(&NominalEventStandard::new("nominal_protocol_unpaused", ProtocolUnpausedData {}));
+    }
+
+    fn emit_name_expiring_warning(&self, name: &str, owner: &AccountId, expires_at: u64, days_left: u64) {
+        emit_nep297(&NominalEventStandard::new("nominal_name_expiring_warning", NameExpiringWarningData {
+            name,
+            owner,
+            expires_at: U64(expires_at),
+            days_left,
+        }));
+    }
+
+    fn emit_expired_name_premium_set(&self, bps: u32) {
+        emit_nep297(&NominalEventStandard::new("nominal_expired_name_premium_set", ExpiredNamePremiumSetData { bps }));
+    }
+
+    fn emit_stale_multiplier_set(&self, bps: u32, window_ms: u64) {
+        emit_nep297(&NominalEventStandard::new("nominal_stale_multiplier_set", StaleMultiplierSetData {
+            bps,
+            window_ms: U64(window_ms),
+        }));
+    }
+
+    fn emit_name_purged(&self, name: &str, owner: &AccountId) {
+        emit_nep297(&NominalEventStandard::new("nominal_name_purged", NameOwnerData { name, owner }));
+    }
+
+    fn emit_ft_referrer_paid(&self, name: &str, token: &AccountId, referrer: &AccountId, amount: u128) {
+        emit_nep297(&NominalEventStandard::new("nominal_ft_referrer_paid", FtReferrerPaidData {
+            name,
+            token,
+            referrer,
+            amount: U128(amount),
+        }));
+    }
+
+    fn emit_registration_failed(&self, name: &str, owner: &AccountId, reason: &str) {
+        emit_nep297(&NominalEventStandard::new("nominal_registration_failed", RegistrationFailedData { name, owner, reason }));
+    }
+
+    fn emit_name_resolved(&self, name: &str, resolution_count: u64) {
+        emit_nep297(&NominalEventStandard::new("nominal_name_resolved", NameResolvedData {
+            name,
+            resolution_count: U64(resolution_count),
+        }));
+    }
+
+    fn emit_owner_transfer_initiated(&self, new_owner: &AccountId) {
+        let accept_after_ms = self.transfer_proposed_at_ms + self.owner_transfer_timelock_ms;
+        emit_nep297(&NominalEventStandard::new("nominal_owner_transfer_initiated", OwnerTransferInitiatedData {
+            owner: &self.owner,
+            new_owner,
+            accept_after_ms: U64(accept_after_ms),
+        }));
+    }
+
+    fn emit_owner_transfer_accepted(&self) {
+        emit_nep297(&NominalEventStandard::new("nominal_owner_transfer_accepted", OwnerTransferAcceptedData {
+            new_owner: &self.owner,
+        }));
+    }
+
+    fn emit_owner_transfer_cancelled(&self, cancelled_pending: &AccountId) {
+        emit_nep297(&NominalEventStandard::new("nominal_owner_transfer_cancelled", OwnerTransferCancelledData {
+            owner: &self.owner,
+            cancelled_pending,
+        }));
+    }
+
+    fn emit_name_transferred(&self, name: &str, old_owner: &AccountId, new_owner: &AccountId) {
+        emit_nep297(&NominalEventStandard::new("nominal_name_transferred", NameTransferredData {
+            name,
+            old_owner,
+            new_owner,
+            timestamp_ms: U64(env::block_timestamp_ms()),
+        }));
+    }
+
+    fn emit_name_transfer_initiated(&self, name: &str, owner: &AccountId, new_owner: &AccountId) {
+        emit_nep297(&NominalEventStandard::new("nominal_name_transfer_initiated", NameTransferInitiatedData { name, owner, new_owner }));
+    }
+
+    fn emit_name_transfer_cancelled(&self, name: &str, owner: &AccountId, cancelled_pending: &AccountId) {
+        emit_nep297(&NominalEventStandard::new("nominal_name_transfer_cancelled", NameTransferCancelledData {
+            name,
+            owner,
+            cancelled_pending,
+        }));
+    }
+
+    fn emit_resolved_reset(&self, name: &str, old_resolved: &AccountId, new_resolved: &AccountId) {
+        emit_nep297(&NominalEventStandard::new("nominal_resolved_reset", ResolvedResetData { name, old_resolved, new_resolved }));
+    }
+
+    fn emit_acl_granted(&self, name: &str, member: &AccountId, permissions: u8) {
+        emit_nep297(&NominalEventStandard::new("nominal_acl_granted", AclGrantedData { name, member, permissions }));
+    }
+
+    fn emit_acl_revoked(&self, name: &str, member: &AccountId) {
+        emit_nep297(&NominalEventStandard::new("nominal_acl_revoked", AclRevokedData { name, member }));
+    }
+
+    fn emit_name_locked(&self, name: &str, owner: &AccountId) {
+        emit_nep297(&NominalEventStandard::new("nominal_name_locked", NameOwnerData { name, owner }));
+    }
+
+    fn emit_name_reserved(&self, name: &str, owner: &AccountId, expires_ms: u64) {
+        emit_nep297(&NominalEventStandard::new("nominal_name_reserved", NameReservedData {
+            name,
+            owner,
+            expires_ms: U64(expires_ms),
+        }));
+    }
+
+    fn emit_reservation_cancelled(&self, name: &str, owner: &AccountId) {
+        emit_nep297(&NominalEventStandard::new("nominal_reservation_cancelled", NameOwnerData { name, owner }));
+    }
+
+    // FeePaid for expired name claims: `amount` is the whole bid, `effective_fee` the price it had to cover
+    fn emit_expired_name_fee_paid(&self, name: &str, payer: &AccountId, amount: u128, effective_fee: u128) {
+        emit_nep297(&NominalEventStandard::new("nominal_fee_paid", FeePaidData {
+            name,
+            payer,
+            currency: "NEAR",
+            amount: U128(amount),
+            effective_fee: Some(U128(effective_fee)),
+            referrer: None,
+        }));
+    }
+
+    fn emit_fee_paid(&self, name: &str, payer: &AccountId, currency: Option<&AccountId>, amount: u128, referrer: Option<&AccountId>) {
+        emit_nep297(&NominalEventStandard::new("nominal_fee_paid", FeePaidData {
+            name,
+            payer,
+            currency: currency.map_or("NEAR", |c| c.as_str()),
+            amount: U128(amount),
+            effective_fee: None,
+            referrer,
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, AccountId};
+
+    fn get_context(predecessor: AccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder.predecessor_account_id(predecessor);
+        builder
+    }
+
+    #[test]
+    fn test_contract_initialization() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000); // 0.1 NEAR
+        
+        testing_env!(get_context(owner.clone()).build());
+        
+        // Test contract creation
+        let contract = NameRegistry::new(owner.clone(), treasury.clone(), registration_fee, None);
+        
+        // Verify initialization
+        assert_eq!(contract.owner, owner);
+        assert_eq!(contract.treasury, treasury);
+        assert_eq!(contract.registration_fee, registration_fee.0);
+        assert_eq!(contract.referrer_bps, 500);
+        assert_eq!(contract.require_relayer_allowlist, false);
+        
+        println!(" Contract initialization test passed!");
+    }
+
+    #[test]
+    fn test_error_json_format() {
+        assert_eq!(
+            NominalError::NameAlreadyTaken.to_string(),
+            r#"{"error":"NameAlreadyTaken","detail":"Name already taken"}"#
+        );
+        assert_eq!(
+            NominalError::Unauthorized("Only owner").to_string(),
+            r#"{"error":"Unauthorized","detail":"Only owner"}"#
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "\"error\":\"NameAlreadyTaken\"")]
+    fn test_duplicate_registration_error_code() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+
+        testing_env!(get_context(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(registration_fee.0))
+            .build());
+        contract.register("alice".to_string());
+        contract.register("alice".to_string());
+    }
+
+    #[test]
+    fn test_get_nonce() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        
+        let contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        
+        // Test nonce for non-existent name
+        let nonce = contract.get_nonce("test".to_string());
+        assert_eq!(nonce.0, 0);
+        
+        println!(" Get nonce test passed!");
+    }
+
+    #[test]
+    fn test_min_referrer_bps_floor() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_min_referrer_bps(300);
+        assert_eq!(contract.min_referrer_bps, 300);
+        
+        // Raising above the floor is allowed
+        contract.set_referrer_bps(300);
+        assert_eq!(contract.referrer_bps, 300);
+        
+        println!(" Min referrer bps test passed!");
+    }
+
+    #[test]
+    fn test_remove_and_disable_coin_fee() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_coin_fee(accounts(2), U128(1_000));
+        contract.set_coin_fee(accounts(3), U128(2_000));
+        assert_eq!(contract.get_all_coin_fees(U64(0), U64(10)).len(), 2);
+        
+        // Disabled fee keeps its slot
+        contract.disable_coin_fee(accounts(2));
+        assert_eq!(contract.get_coin_fee(accounts(2)), Some(U128(COIN_FEE_DISABLED)));
+        assert_eq!(contract.get_all_coin_fees(U64(0), U64(10)).len(), 2);
+        
+        contract.remove_coin_fee(accounts(3));
+        assert_eq!(contract.get_coin_fee(accounts(3)), None);
+        assert_eq!(contract.get_all_coin_fees(U64(0), U64(10)).len(), 1);
+        
+        println!(" Remove/disable coin fee test passed!");
+    }
+
+    #[test]
+    fn test_validate_primary_name() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        assert!(contract.validate_primary_name(accounts(2)));
+        assert!(!contract.validate_primary_name(accounts(3)));
+        
+        contract.transfer_name("alice".to_string(), accounts(3));
+        assert!(!contract.validate_primary_name(accounts(2)));
+    }
+
+    #[test]
+    fn test_coin_fee_schedule() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        contract.set_coin_fee_schedule(accounts(2), U128(1_000), 2_500, U64(1_000), U64(2_000));
+        assert_eq!(contract.get_coin_fee(accounts(2)), Some(U128(1_000)));
+        
+        // Before, during and after the discount window
+        assert_eq!(contract.get_effective_coin_fee(accounts(2)), Some(U128(1_000)));
+        testing_env!(get_context(owner.clone()).block_timestamp(1_500 * 1_000_000).build());
+        assert_eq!(contract.get_effective_coin_fee(accounts(2)), Some(U128(750)));
+        testing_env!(get_context(owner).block_timestamp(2_000 * 1_000_000).build());
+        assert_eq!(contract.get_effective_coin_fee(accounts(2)), Some(U128(1_000)));
+        
+        contract.remove_coin_fee(accounts(2));
+        assert!(contract.get_coin_fee_schedule(accounts(2)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid SECP256K1 signature length")]
+    fn test_secp256k1_signature_length() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        let params = RegisterWithSigParams {
+            name: "alice".to_string(),
+            owner: accounts(3),
+            relayer: accounts(2),
+            currency: None,
+            amount: U128(0),
+            deadline: U64(60_000),
+            nonce: U64(0),
+            signature_type: SignatureType::Secp256k1,
+        };
+        let signature = bs58::encode([1u8; 64]).into_string();
+        contract.register_with_sig(params, signature);
+    }
+
+    #[test]
+    fn test_set_treasury_splits() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_treasury_splits(vec![(accounts(2), 7000), (accounts(3), 2000), (accounts(4), 1000)]);
+        assert_eq!(contract.treasury_splits.len(), 3);
+        
+        // Empty split falls back to the single treasury
+        contract.set_treasury_splits(vec![]);
+        assert!(contract.treasury_splits.is_empty());
+        
+        println!(" Treasury splits test passed!");
+    }
+
+    #[test]
+    #[should_panic(expected = "Treasury split BPS must sum to 10000")]
+    fn test_treasury_splits_must_sum() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_treasury_splits(vec![(accounts(2), 7000), (accounts(3), 2000)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Treasury splits unavailable in contract mode")]
+    fn test_treasury_splits_rejected_in_contract_mode() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_treasury_contract_mode(true, "deposit".to_string());
+        contract.set_treasury_splits(vec![(accounts(2), 7000), (accounts(3), 3000)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Treasury splits unavailable in contract mode")]
+    fn test_contract_mode_rejected_with_treasury_splits() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_treasury_splits(vec![(accounts(2), 7000), (accounts(3), 3000)]);
+        contract.set_treasury_contract_mode(true, "deposit".to_string());
+    }
+
+    #[test]
+    fn test_generate_ownership_proof() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        
+        let proof = contract.generate_ownership_proof("alice".to_string(), "challenge".to_string());
+        assert_eq!(proof.len(), 64);
+        
+        println!(" Ownership proof test passed!");
+    }
+
+    #[test]
+    fn test_migrate_records() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.register("bob".to_string());
+        
+        assert_eq!(contract.migrate_records_v2(), "migrated 2 records");
+        assert_eq!(contract.get_record_v2("alice".to_string()).unwrap().expiry_ms.0, 0);
+        assert_eq!(contract.get_contract_version(), "2.0.0");
+        
+        // New registrations are mirrored after migration
+        contract.register("carol".to_string());
+        assert!(contract.get_record_v2("carol".to_string()).is_some());
+        
+        println!(" Migration test passed!");
+    }
+
+    #[test]
+    fn test_migrate_from_v1_state() {
+        let owner: AccountId = accounts(0);
+        testing_env!(get_context(owner.clone()).current_account_id(owner.clone()).build());
+        
+        // Write the 1.x layout directly, as a deployed contract would have it
+        let mut records: UnorderedMap<String, LegacyRecord> = UnorderedMap::new(StorageKey::Records);
+        records.insert(&"alice".to_string(), &LegacyRecord {
+            owner: accounts(2),
+            resolved: accounts(3),
+            updated_at: U64(1_000),
+        });
+        let mut primary_names: LookupMap<AccountId, String> = LookupMap::new(StorageKey::PrimaryNames);
+        primary_names.insert(&accounts(2), &"alice".to_string());
+        let mut relayers: UnorderedSet<AccountId> = UnorderedSet::new(StorageKey::Relayers);
+        relayers.insert(&accounts(4));
+        env::state_write(&OldNameRegistry {
+            owner: owner.clone(),
+            treasury: accounts(1),
+            registration_fee: 100,
+            referrer_bps: 250,
+            require_relayer_allowlist: true,
+            records,
+            primary_names,
+            coin_fees: LookupMap::new(StorageKey::CoinFees),
+            relayers,
+            nonces: LookupMap::new(StorageKey::Nonces),
+            authorized_keys: LookupMap::new(StorageKey::AuthorizedKeys),
+        });
+        
+        let mut contract = NameRegistry::migrate();
+        assert_eq!(contract.referrer_bps, 250);
+        assert!(contract.is_relayer_allowed(accounts(4)));
+        assert_eq!(contract.max_names_per_owner, DEFAULT_MAX_NAMES_PER_OWNER);
+        // Still readable before the records are moved
+        assert_eq!(contract.resolve("alice".to_string()), Some(accounts(3)));
+        
+        assert_eq!(contract.migrate_records(U64(10)).0, 0);
+        let record = contract.get_record("alice".to_string()).unwrap();
+        assert_eq!(record.resolved, accounts(3));
+        assert_eq!(record.ttl_ms.0, DEFAULT_TTL_MS);
+        assert_eq!(record.created_at.0, 1_000);
+        assert_eq!(contract.get_name_count(accounts(2)), 1);
+        assert_eq!(contract.get_primary_name(accounts(2)), Some("alice".to_string()));
+        assert!(contract.get_record_v2("alice".to_string()).is_some());
+        assert_eq!(contract.get_records_from_cursor(None, U64(10)).records.len(), 1);
+        
+        println!(" V1 state migration test passed!");
+    }
+
+    #[test]
+    #[should_panic(expected = "Record migration in progress")]
+    fn test_registration_blocked_during_record_migration() {
+        let owner: AccountId = accounts(0);
+        testing_env!(get_context(owner.clone()).current_account_id(owner.clone()).build());
+        
+        let mut records: UnorderedMap<String, LegacyRecord> = UnorderedMap::new(StorageKey::Records);
+        records.insert(&"alice".to_string(), &LegacyRecord {
+            owner: accounts(2),
+            resolved: accounts(2),
+            updated_at: U64(0),
+        });
+        env::state_write(&OldNameRegistry {
+            owner: owner.clone(),
+            treasury: accounts(1),
+            registration_fee: 0,
+            referrer_bps: 500,
+            require_relayer_allowlist: false,
+            records,
+            primary_names: LookupMap::new(StorageKey::PrimaryNames),
+            coin_fees: LookupMap::new(StorageKey::CoinFees),
+            relayers: UnorderedSet::new(StorageKey::Relayers),
+            nonces: LookupMap::new(StorageKey::Nonces),
+            authorized_keys: LookupMap::new(StorageKey::AuthorizedKeys),
+        });
+        
+        let mut contract = NameRegistry::migrate();
+        testing_env!(get_context(accounts(3)).build());
+        contract.register("alice".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Admin override disabled")]
+    fn test_admin_set_resolved_disabled() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        
+        contract.admin_set_resolved("alice".to_string(), accounts(2));
+        assert_eq!(contract.get_record("alice".to_string()).unwrap().resolved, accounts(2));
+        
+        contract.set_allow_admin_override(false);
+        contract.admin_set_resolved("alice".to_string(), accounts(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "BPS below referrer floor")]
+    fn test_set_referrer_bps_below_floor() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_min_referrer_bps(300);
+        contract.set_referrer_bps(299);
+    }
+
+    #[test]
+    fn test_reserve_and_claim_name() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        let reservation_fee = 10_000_000_000_000_000_000_000;
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_reservation_fee(U128(reservation_fee));
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(reservation_fee)).build());
+        contract.reserve_name("alice".to_string());
+        assert_eq!(contract.get_reservation("alice".to_string()).unwrap().owner, accounts(2));
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0 - reservation_fee)).build());
+        contract.claim_reservation("alice".to_string());
+        assert_eq!(contract.get_record("alice".to_string()).unwrap().owner, accounts(2));
+        assert!(contract.get_reservation("alice".to_string()).is_none());
+        
+        println!(" Reserve and claim test passed!");
+    }
+
+    #[test]
+    #[should_panic(expected = "Name reserved")]
+    fn test_register_reserved_name() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0 / 10)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.reserve_name("alice".to_string());
+        
+        testing_env!(get_context(accounts(3)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        contract.register("alice".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Reservation cooldown active")]
+    fn test_reserve_again_after_cancel_in_cooldown() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        let reservation_fee = registration_fee.0 / 10;
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(reservation_fee)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.reserve_name("alice".to_string());
+        contract.cancel_reservation("alice".to_string());
+        contract.reserve_name("alice".to_string());
+    }
+
+    #[test]
+    fn test_reserve_again_after_cooldown() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        let reservation_fee = registration_fee.0 / 10;
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(reservation_fee)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.reserve_name("alice".to_string());
+        
+        // Another account may take over the lapsed reservation right away
+        let lapsed_ms = RESERVATION_PERIOD_MS + 1;
+        testing_env!(get_context(accounts(3)).block_timestamp(lapsed_ms * 1_000_000).attached_deposit(NearToken::from_yoctonear(reservation_fee)).build());
+        contract.reserve_name("alice".to_string());
+        assert_eq!(contract.get_reservation("alice".to_string()).unwrap().owner, accounts(3));
+        
+        let cooled_ms = RESERVATION_PERIOD_MS + RESERVATION_COOLDOWN_MS;
+        testing_env!(get_context(accounts(3)).block_timestamp(cooled_ms * 1_000_000).build());
+        contract.cancel_reservation("alice".to_string());
+        testing_env!(get_context(accounts(2)).block_timestamp(cooled_ms * 1_000_000).attached_deposit(NearToken::from_yoctonear(reservation_fee)).build());
+        contract.reserve_name("alice".to_string());
+        assert_eq!(contract.get_reservation("alice".to_string()).unwrap().owner, accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Reservation fee must be nonzero")]
+    fn test_set_reservation_fee_zero() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_reservation_fee(U128(0));
+    }
+
+    #[test]
+    fn test_set_ttl() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        assert_eq!(contract.get_ttl("alice".to_string()).0, 300_000);
+        
+        contract.set_ttl("alice".to_string(), U64(60_000));
+        assert_eq!(contract.get_ttl("alice".to_string()).0, 60_000);
+        
+        println!(" TTL test passed!");
+    }
+
+    #[test]
+    #[should_panic(expected = "TTL too high")]
+    fn test_set_ttl_above_max() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.set_ttl("alice".to_string(), U64(DEFAULT_MAX_TTL_MS + 1));
+    }
+
+    #[test]
+    fn test_migrate_coin_fees() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        
+        // Seed the legacy LookupMap layout
+        let mut legacy: LookupMap<AccountId, u128> = LookupMap::new(StorageKey::CoinFees);
+        legacy.insert(&accounts(2), &1_000);
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        assert_eq!(contract.get_coin_fee(accounts(2)), None);
+        
+        assert_eq!(contract.migrate_coin_fees(vec![accounts(2), accounts(3)]), "migrated 1 coin fees");
+        assert_eq!(contract.get_coin_fee(accounts(2)), Some(U128(1_000)));
+        assert_eq!(contract.get_all_coin_fees(U64(0), U64(10)).len(), 1);
+        
+        println!(" Coin fee migration test passed!");
+    }
+
+    #[test]
+    #[should_panic(expected = "Name is locked")]
+    fn test_locked_name_cannot_be_transferred() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.lock_name("alice".to_string());
+        contract.transfer_name("alice".to_string(), accounts(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "Name is locked")]
+    fn test_lock_name() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        assert!(!contract.get_record("alice".to_string()).unwrap().locked);
+        
+        contract.lock_name("alice".to_string());
+        assert!(contract.get_record("alice".to_string()).unwrap().locked);
+        
+        // Locking is one-way
+        contract.lock_name("alice".to_string());
+    }
+
+    #[test]
+    fn test_set_treasury_token_account() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury.clone(), registration_fee, None);
+        assert_eq!(contract.get_treasury_token_account(accounts(3)), treasury);
+        
+        contract.set_treasury_token_account(accounts(3), accounts(4));
+        assert_eq!(contract.get_treasury_token_account(accounts(3)), accounts(4));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only owner")]
+    fn test_set_treasury_token_account_not_owner() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        
+        testing_env!(get_context(accounts(2)).build());
+        contract.set_treasury_token_account(accounts(3), accounts(4));
+    }
+
+    #[test]
+    #[should_panic(expected = "Owner name limit reached")]
+    fn test_max_names_per_owner() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_max_names_per_owner(2);
+        
+        contract.register("alice".to_string());
+        contract.register("bob".to_string());
+        assert_eq!(contract.get_name_count(accounts(0)), 2);
+        
+        contract.register("carol".to_string());
+    }
+
+    #[test]
+    fn test_transfer_name_moves_name_count() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        
+        testing_env!(get_context(accounts(0)).build());
+        contract.transfer_name("alice".to_string(), accounts(2));
+        assert_eq!(contract.get_name_count(accounts(0)), 0);
+        assert_eq!(contract.get_name_count(accounts(2)), 1);
+    }
+
+    #[test]
+    fn test_two_step_name_transfer() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.initiate_transfer("alice".to_string(), accounts(3));
+        assert_eq!(contract.get_pending_transfer("alice".to_string()), Some(accounts(3)));
+        assert_eq!(contract.get_record("alice".to_string()).unwrap().owner, accounts(2));
+        
+        testing_env!(get_context(accounts(3)).build());
+        contract.accept_transfer("alice".to_string());
+        assert_eq!(contract.get_record("alice".to_string()).unwrap().owner, accounts(3));
+        assert_eq!(contract.get_pending_transfer("alice".to_string()), None);
+        assert_eq!(contract.get_primary_name(accounts(2)), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "No pending transfer")]
+    fn test_cancelled_name_transfer_cannot_be_accepted() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.initiate_transfer("alice".to_string(), accounts(3));
+        contract.cancel_transfer("alice".to_string());
+        
+        testing_env!(get_context(accounts(3)).build());
+        contract.accept_transfer("alice".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Meta transactions disabled")]
+    fn test_meta_set_resolved_disabled() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        
+        testing_env!(get_context(accounts(3)).build());
+        contract.meta_set_resolved(MetaTxParams {
+            name: "alice".to_string(),
+            new_resolved: accounts(4),
+            owner: accounts(2),
+            nonce: U64(0),
+            deadline: U64(u64::MAX),
+        }, "sig:key".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid nonce")]
+    fn test_meta_set_resolved_invalid_nonce() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_meta_tx_enabled(true);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        contract.register("alice".to_string());
+        
+        testing_env!(get_context(accounts(3)).build());
+        contract.meta_set_resolved(MetaTxParams {
+            name: "alice".to_string(),
+            new_resolved: accounts(4),
+            owner: accounts(2),
+            nonce: U64(1),
+            deadline: U64(u64::MAX),
+        }, "sig:key".to_string());
+    }
+
+    #[test]
+    fn test_register_refunds_excess_fee() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_require_exact_fee(false);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0 + 1_000)).build());
+        contract.register("alice".to_string());
+        assert_eq!(contract.get_record("alice".to_string()).unwrap().owner, accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient fee")]
+    fn test_register_below_minimum_fee() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_require_exact_fee(false);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0 - 1)).build());
+        contract.register("alice".to_string());
+    }
+
+    #[test]
+    fn test_get_stats() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.register("bob".to_string());
+        
+        let stats = contract.get_stats();
+        assert_eq!(stats.total_names, U64(2));
+        assert_eq!(stats.length_histogram[4], U64(1));
+        assert_eq!(stats.length_histogram[2], U64(1));
+        // 0.1 NEAR falls in the 0.1-1 bucket
+        assert_eq!(stats.fee_histogram[2], U64(2));
+    }
+
+    #[test]
+    fn test_get_records_cursor_stable() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("carol".to_string());
+        contract.register("alice".to_string());
+        contract.register("bob".to_string());
+        
+        let page = contract.get_records_cursor_stable(None, U64(2));
+        let names: Vec<String> = page.records.into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["alice".to_string(), "bob".to_string()]);
+        assert_eq!(page.next_cursor, Some("bob".to_string()));
+        
+        let page = contract.get_records_cursor_stable(page.next_cursor, U64(2));
+        assert_eq!(page.records.len(), 1);
+        assert_eq!(page.records[0].0, "carol");
+        assert_eq!(page.next_cursor, None);
+        
+        let page = contract.get_records_from_cursor(Some("alice".to_string()), U64(10));
+        let names: Vec<String> = page.records.into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["bob".to_string(), "carol".to_string()]);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn test_search_by_prefix() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_prefix_search_enabled(true);
+        contract.register("alice".to_string());
+        contract.register("alex".to_string());
+        contract.register("bob".to_string());
+        
+        assert_eq!(contract.search_by_prefix("al".to_string(), 10), vec!["alice".to_string(), "alex".to_string()]);
+        assert_eq!(contract.search_by_prefix("al".to_string(), 1), vec!["alice".to_string()]);
+        assert!(contract.search_by_prefix("zz".to_string(), 10).is_empty());
+    }
+
+    #[test]
+    fn test_backfill_prefix_index() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.register("alex".to_string());
+        contract.register("bob".to_string());
+        
+        contract.set_prefix_search_enabled(true);
+        assert!(contract.search_by_prefix("al".to_string(), 10).is_empty());
+        
+        let cursor = contract.backfill_prefix_index(None, U64(2));
+        assert_eq!(cursor, Some("alice".to_string()));
+        assert_eq!(contract.backfill_prefix_index(cursor, U64(2)), None);
+        // Running it again doesn't duplicate entries
+        contract.backfill_prefix_index(None, U64(10));
+        assert_eq!(contract.search_by_prefix("al".to_string(), 10).len(), 2);
+        assert_eq!(contract.search_by_prefix("bo".to_string(), 10), vec!["bob".to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "PrefixIndexFull")]
+    fn test_register_fails_when_prefix_bucket_full() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_prefix_search_enabled(true);
+        
+        let mut bucket = UnorderedSet::new(StorageKey::PrefixNames("al".to_string()));
+        for i in 0..MAX_NAMES_PER_PREFIX {
+            bucket.insert(&format!("al{}", i));
+        }
+        contract.trie.insert(&"al".to_string(), &bucket);
+        
+        contract.register("alice".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Name in cooldown")]
+    fn test_deleted_name_cooldown() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.delete_name("alice".to_string());
+        assert!(contract.get_record("alice".to_string()).is_none());
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        contract.register("alice".to_string());
+    }
+
+    #[test]
+    fn test_cleanup_deleted_name_after_cooldown() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.delete_name("alice".to_string());
+        
+        let after_cooldown_ms = DEFAULT_DELETION_COOLDOWN_MS + 1;
+        testing_env!(get_context(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(registration_fee.0))
+            .block_timestamp(after_cooldown_ms * 1_000_000)
+            .build());
+        contract.cleanup_deleted_name("alice".to_string());
+        assert!(contract.deleted_names.get(&"alice".to_string()).is_none());
+        
+        contract.register("alice".to_string());
+        assert_eq!(contract.get_record("alice".to_string()).unwrap().owner, accounts(2));
+    }
+
+    #[test]
+    fn test_get_full_profile() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        assert!(contract.get_full_profile("alice".to_string()).is_none());
+        
+        contract.register("alice".to_string());
+        contract.register("bob".to_string());
+        contract.set_bio("alice".to_string(), "gm".to_string());
+        
+        let profile = contract.get_full_profile("alice".to_string()).unwrap();
+        assert_eq!(profile.record.unwrap().owner, owner);
+        assert_eq!(profile.primary_owner, Some(owner));
+        assert_eq!(profile.bio, Some("gm".to_string()));
+        assert_eq!(profile.avatar, None);
+        assert_eq!(profile.expiry_ms, None);
+        
+        // bob is registered but isn't the owner's primary name
+        assert_eq!(contract.get_full_profile("bob".to_string()).unwrap().primary_owner, None);
+    }
+
+    #[test]
+    fn test_auction_settles_to_highest_bidder() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        let one_near: u128 = 1_000_000_000_000_000_000_000_000;
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.start_auction("ai".to_string(), U64(60 * 60 * 1000), U128(one_near));
+        
+        testing_env!(get_context(accounts(2)).block_timestamp(1_000 * 1_000_000).attached_deposit(NearToken::from_yoctonear(one_near)).build());
+        contract.place_bid("ai".to_string());
+        
+        // Within the last five minutes, so the auction is extended
+        let late_ms = 60 * 60 * 1000 - 60_000;
+        testing_env!(get_context(accounts(3)).block_timestamp(late_ms * 1_000_000).attached_deposit(NearToken::from_yoctonear(2 * one_near)).build());
+        contract.place_bid("ai".to_string());
+        let auction = contract.get_auction("ai".to_string()).unwrap();
+        assert_eq!(auction.highest_bidder, Some(accounts(3)));
+        assert_eq!(auction.end_ms, U64(60 * 60 * 1000 + AUCTION_ANTI_SNIPE_MS));
+        
+        testing_env!(get_context(accounts(4)).block_timestamp((60 * 60 * 1000 + AUCTION_ANTI_SNIPE_MS) * 1_000_000).build());
+        contract.settle_auction("ai".to_string());
+        assert_eq!(contract.get_record("ai".to_string()).unwrap().owner, accounts(3));
+        assert!(contract.get_auction("ai".to_string()).unwrap().settled);
+        assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains(r#""event":"nominal_auction_settled","data":[{"name":"ai","winner":"danny","amount":"2000000000000000000000000"}]"#)));
+    }
+
+    #[test]
+    fn test_auction_refunds_winner_when_name_taken() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.start_auction("ai".to_string(), U64(1_000), U128(registration_fee.0));
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        contract.place_bid("AI".to_string());
+        
+        // The winner already holds the maximum number of names
+        contract.max_names_per_owner = 1;
+        contract.name_count_by_owner.insert(&accounts(2), &1);
+        
+        // The bid landed inside the anti-snipe window, which pushed the end back
+        testing_env!(get_context(accounts(3)).block_timestamp((1_000 + AUCTION_ANTI_SNIPE_MS) * 1_000_000).build());
+        contract.settle_auction("ai".to_string());
+        assert!(contract.get_record("ai".to_string()).is_none());
+        assert!(contract.get_auction("ai".to_string()).unwrap().settled);
+        assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains(r#""reason":"owner_name_limit""#)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Name reserved")]
+    fn test_start_auction_on_reserved_name() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        contract.set_reservation_fee(U128(1));
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(1)).build());
+        contract.reserve_name("alice".to_string());
+        
+        testing_env!(get_context(owner).build());
+        contract.start_auction("alice".to_string(), U64(1_000), U128(registration_fee.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Name is being auctioned")]
+    fn test_reserve_name_in_auction() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.start_auction("alice".to_string(), U64(1_000), U128(registration_fee.0));
+        
+        testing_env!(get_context(accounts(2)).build());
+        contract.reserve_name("alice".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Bid below minimum increment")]
+    fn test_auction_bid_below_increment() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.start_auction("ai".to_string(), U64(60 * 60 * 1000), U128(registration_fee.0));
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        contract.place_bid("ai".to_string());
+        
+        testing_env!(get_context(accounts(3)).attached_deposit(NearToken::from_yoctonear(registration_fee.0 + DEFAULT_MIN_BID_INCREMENT)).build());
+        contract.place_bid("ai".to_string());
+    }
+
+    #[test]
+    fn test_roles() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        assert!(contract.has_role(ROLE_ADMIN.to_string(), owner.clone()));
+        assert!(!contract.has_role(ROLE_FEE_MANAGER.to_string(), accounts(2)));
+        
+        contract.grant_role(ROLE_FEE_MANAGER.to_string(), accounts(2));
+        assert!(contract.has_role(ROLE_FEE_MANAGER.to_string(), accounts(2)));
+        assert!(!contract.has_role(ROLE_RELAYER_MANAGER.to_string(), accounts(2)));
+        assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains(r#""event":"nominal_role_granted","data":[{"role":"FEE_MANAGER","account":"charlie"}]"#)));
+        
+        testing_env!(get_context(accounts(2)).build());
+        contract.set_registration_fee(U128(1_000));
+        assert_eq!(contract.get_config().registration_fee, U128(1_000));
+        contract.set_coin_fee(accounts(3), U128(500));
+        contract.batch_set_coin_fees(vec![(accounts(4), U128(700))]);
+        assert_eq!(contract.get_coin_fee(accounts(3)), Some(U128(500)));
+        assert_eq!(contract.get_coin_fee(accounts(4)), Some(U128(700)));
+        
+        testing_env!(get_context(owner).build());
+        contract.revoke_role(ROLE_FEE_MANAGER.to_string(), accounts(2));
+        assert!(!contract.has_role(ROLE_FEE_MANAGER.to_string(), accounts(2)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Missing role")]
+    fn test_set_relayer_requires_role() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.grant_role(ROLE_FEE_MANAGER.to_string(), accounts(2));
+        
+        testing_env!(get_context(accounts(2)).build());
+        contract.set_relayer(accounts(3), true);
+    }
+
+    #[test]
+    #[should_panic(expected = "Missing role")]
+    fn test_batch_set_relayers_requires_role() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.grant_role(ROLE_FEE_MANAGER.to_string(), accounts(2));
+        
+        testing_env!(get_context(accounts(2)).build());
+        contract.batch_set_relayers(vec![(accounts(3), true)]);
+    }
+
+    #[test]
+    fn test_set_validation_config() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        assert!(contract.is_valid_name("tg_handle"));
+        assert!(!contract.is_valid_name("ab"));
+        
+        contract.set_validation_config(false, false, 2, 32);
+        assert!(!contract.is_valid_name("tg_handle"));
+        assert!(!contract.is_valid_name("Alice"));
+        assert!(contract.is_valid_name("ab"));
+        assert!(!contract.is_valid_name(&"a".repeat(33)));
+        
+        let config = contract.get_config();
+        assert!(!config.allow_underscore);
+        assert!(!config.allow_uppercase);
+        assert_eq!(config.min_name_len, 2);
+        assert_eq!(config.max_name_len, 32);
+    }
+
+    #[test]
+    fn test_admin_remove_name() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        contract.register("scam".to_string());
+        assert_eq!(contract.get_primary_name(accounts(2)), Some("scam".to_string()));
+        
+        testing_env!(get_context(owner).build());
+        contract.admin_remove_name("scam".to_string(), "phishing".to_string());
+        
+        assert!(contract.get_record("scam".to_string()).is_none());
+        assert_eq!(contract.get_primary_name(accounts(2)), None);
+        assert_eq!(contract.get_admin_removal_count(), U64(1));
+        assert!(contract.get_records_cursor_stable(None, U64(10)).records.is_empty());
+    }
+
+    #[test]
+    fn test_set_bio() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        assert_eq!(contract.get_bio("alice".to_string()), None);
+        
+        contract.set_bio("alice".to_string(), "gm".to_string());
+        assert_eq!(contract.get_bio("alice".to_string()), Some("gm".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Bio too long")]
+    fn test_set_bio_too_long() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.set_bio("alice".to_string(), "a".repeat(MAX_BIO_LEN + 1));
+    }
+
+    #[test]
+    fn test_set_avatar() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        assert_eq!(contract.get_avatar("alice".to_string()), None);
+        
+        contract.set_avatar("alice".to_string(), "ipfs://avatar".to_string(), "image/png".to_string());
+        assert_eq!(
+            contract.get_avatar("alice".to_string()),
+            Some(AvatarInfo { uri: "ipfs://avatar".to_string(), mime_type: "image/png".to_string() })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported MIME type")]
+    fn test_set_avatar_unsupported_mime_type() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.set_avatar("alice".to_string(), "ipfs://avatar".to_string(), "text/html".to_string());
+    }
+
+    #[test]
+    fn test_check_name() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        
+        assert!(contract.is_name_available("bob".to_string()));
+        assert!(!contract.is_name_available("alice".to_string()));
+        assert!(!contract.is_name_available("bad name".to_string()));
+        
+        let result = contract.check_name("alice".to_string());
+        assert!(!result.available);
+        assert_eq!(result.reason, Some("taken".to_string()));
+        assert_eq!(result.fee, registration_fee);
+        
+        let result = contract.check_name("bob".to_string());
+        assert!(result.available);
+        assert_eq!(result.reason, None);
+    }
+
+    #[test]
+    fn test_check_name_normalizes_input() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        
+        assert_eq!(contract.check_name("Alice".to_string()).reason, Some("taken".to_string()));
+        assert!(contract.is_name_available("Bob".to_string()));
+    }
+
+    #[test]
+    fn test_check_name_deletion_cooldown() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.delete_name("alice".to_string());
+        
+        let result = contract.check_name("alice".to_string());
+        assert!(!result.available);
+        assert_eq!(result.reason, Some("deletion_cooldown".to_string()));
+        
+        testing_env!(get_context(accounts(2)).block_timestamp((DEFAULT_DELETION_COOLDOWN_MS + 1) * 1_000_000).build());
+        assert!(contract.is_name_available("alice".to_string()));
+    }
+
+    #[test]
+    fn test_registration_date() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone())
+            .attached_deposit(NearToken::from_yoctonear(registration_fee.0))
+            .block_timestamp(1_000_000_000_000)
+            .build());
+        
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        assert_eq!(contract.get_registration_date("alice".to_string()), Some(U64(1_000_000)));
+        assert_eq!(contract.get_registration_date("bob".to_string()), None);
+        
+        // Later updates leave the registration date untouched
+        testing_env!(get_context(owner).block_timestamp(2_000_000_000_000).build());
+        contract.set_resolved("alice".to_string(), accounts(2));
+        let record = contract.get_record("alice".to_string()).unwrap();
+        assert_eq!(record.created_at, U64(1_000_000));
+        assert_eq!(record.updated_at, U64(2_000_000));
+    }
+
+    #[test]
+    fn test_set_relayer_metadata() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        assert!(contract.get_relayer_metadata(accounts(2)).is_none());
+        
+        contract.set_relayer_metadata(accounts(2), "relayer-one".to_string(), "https://relayer.example.com".to_string());
+        let meta = contract.get_relayer_metadata(accounts(2)).unwrap();
+        assert_eq!(meta.name, "relayer-one");
+        assert_eq!(meta.endpoint_url, "https://relayer.example.com");
+        
+        println!(" Relayer metadata test passed!");
+    }
+
+    #[test]
+    fn test_resolve_and_resolve_batch() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        
+        assert_eq!(contract.resolve("alice".to_string()), Some(owner.clone()));
+        assert_eq!(contract.resolve("bob".to_string()), None);
+        
+        let batch = contract.resolve_batch(vec!["alice".to_string(), "bob".to_string()]);
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch[0].as_ref().unwrap().resolved, owner);
+        assert!(batch[1].is_none());
+        
+        println!(" Resolve batch test passed!");
+    }
+
+    #[test]
+    fn test_get_config() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        
+        let contract = NameRegistry::new(owner.clone(), treasury.clone(), registration_fee, None);
+        let config = contract.get_config();
+        assert_eq!(config.owner, owner);
+        assert_eq!(config.treasury, treasury);
+        assert_eq!(config.registration_fee, registration_fee);
+        assert_eq!(config.referrer_bps, 500);
+        assert_eq!(config.max_name_len, 63);
+        
+        println!(" Get config test passed!");
+    }
+
+    #[test]
+    fn test_pause_expires() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        contract.pause(U64(1_000));
+        let status = contract.get_pause_status();
+        assert!(status.paused);
+        assert_eq!(status.remaining_ms, U64(1_000));
+        
+        testing_env!(get_context(owner).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).block_timestamp(1_000 * 1_000_000).build());
+        assert!(!contract.get_pause_status().paused);
+        contract.register("alice".to_string());
+        assert!(contract.get_record("alice".to_string()).is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "Protocol is paused")]
+    fn test_register_while_paused() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.pause(U64(1_000));
+        contract.register("alice".to_string());
+    }
+
+    #[test]
+    fn test_force_unpause() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.pause(U64(MAX_PAUSE_DURATION_MS));
+        contract.force_unpause();
+        contract.register("alice".to_string());
+        assert!(contract.get_record("alice".to_string()).is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "Pause cooldown active")]
+    fn test_pause_cannot_be_extended() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.pause(U64(MAX_PAUSE_DURATION_MS));
+        contract.pause(U64(MAX_PAUSE_DURATION_MS));
+    }
+
+    #[test]
+    fn test_pause_again_after_cooldown() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        contract.pause(U64(1_000));
+        assert_eq!(contract.get_pause_status().next_pause_ms, U64(1_000 + PAUSE_COOLDOWN_MS));
+        
+        testing_env!(get_context(owner).block_timestamp((1_000 + PAUSE_COOLDOWN_MS) * 1_000_000).build());
+        contract.pause(U64(1_000));
+        assert!(contract.get_pause_status().paused);
+    }
+
+    #[test]
+    #[should_panic(expected = "Pause duration too long")]
+    fn test_pause_duration_limit() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.pause(U64(MAX_PAUSE_DURATION_MS + 1));
+    }
+
+    #[test]
+    fn test_authorized_key_expiry() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        let permanent_key = PublicKey::from_str("ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp").unwrap();
+        let session_key = PublicKey::from_str("ed25519:DcA2MzgpJbrUATQLLceocVckhhAqrkingax4oJ9kZ847").unwrap();
+        
+        testing_env!(get_context(accounts(2)).build());
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        contract.authorize_key(permanent_key.clone(), None);
+        contract.authorize_key(session_key.clone(), Some(U64(1_000)));
+        assert_eq!(contract.get_authorized_key_count(accounts(2)), U64(2));
+        assert_eq!(contract.get_authorized_key_count(accounts(3)), U64(0));
+        
+        testing_env!(get_context(accounts(2)).block_timestamp(1_000 * 1_000_000).build());
+        assert!(contract.is_key_authorized(accounts(2), permanent_key.clone()));
+        assert!(!contract.is_key_authorized(accounts(2), session_key));
+        let keys = contract.get_authorized_keys(accounts(2), U64(0), U64(10));
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].key, bs58::encode(permanent_key.clone().into_bytes()).into_string());
+        assert!(keys[0].expires_ms.is_none());
+        
+        testing_env!(get_context(owner).build());
+        contract.revoke_all_keys(accounts(2));
+        assert!(!contract.is_key_authorized(accounts(2), permanent_key));
+        assert_eq!(contract.get_authorized_key_count(accounts(2)), U64(0));
+    }
+
+    #[test]
+    fn test_migrate_authorized_keys() {
+        let owner: AccountId = accounts(0);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        let public_key = PublicKey::from_str("ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp").unwrap();
+        
+        testing_env!(get_context(owner.clone()).build());
+        // Seed the 1.x "account:key" -> bool layout
+        let key_b58 = bs58::encode(public_key.clone().into_bytes()).into_string();
+        let mut legacy: LookupMap<String, bool> = LookupMap::new(StorageKey::AuthorizedKeys);
+        legacy.insert(&format!("{}:{}", accounts(2), key_b58), &true);
+        
+        let mut contract = NameRegistry::new(owner, accounts(1), registration_fee, None);
+        assert!(!contract.is_key_authorized(accounts(2), public_key.clone()));
+        
+        let result = contract.migrate_authorized_keys(vec![(accounts(2), public_key.clone()), (accounts(3), public_key.clone())]);
+        assert_eq!(result, "migrated 1 authorized keys");
+        assert!(contract.is_key_authorized(accounts(2), public_key.clone()));
+        assert!(!contract.is_key_authorized(accounts(3), public_key));
+        assert_eq!(contract.get_authorized_key_count(accounts(2)), U64(1));
+    }
+
+    #[test]
+    fn test_name_history() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.set_resolved("alice".to_string(), accounts(3));
+        contract.set_resolved("alice".to_string(), accounts(4));
+        
+        let history = contract.get_name_history("alice".to_string(), 0, 10);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].old_resolved, accounts(2));
+        assert_eq!(history[0].new_resolved, accounts(3));
+        assert_eq!(history[1].new_resolved, accounts(4));
+        assert_eq!(history[1].changed_by, accounts(2));
+        assert_eq!(contract.get_name_history("alice".to_string(), 1, 10).len(), 1);
+    }
+
+    #[test]
+    fn test_name_history_is_bounded() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        for _ in 0..MAX_HISTORY_ENTRIES + 5 {
+            contract.set_resolved("alice".to_string(), accounts(3));
+        }
+        
+        assert_eq!(contract.get_name_history("alice".to_string(), 0, 100).len(), MAX_HISTORY_ENTRIES);
+    }
+
+    #[test]
+    fn test_referrer_stats() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        assert!(contract.get_referrer_stats(accounts(2)).is_none());
+        
+        contract.record_referral(&accounts(2), 5_000, 0);
+        contract.record_referral(&accounts(2), 0, 300);
+        let stats = contract.get_referrer_stats(accounts(2)).unwrap();
+        assert_eq!(stats.total_earned_near, U128(5_000));
+        assert_eq!(stats.total_earned_tokens, U128(300));
+        assert_eq!(stats.referral_count, 2);
+    }
+
+    #[test]
+    fn test_set_treasury_contract_mode() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        assert_eq!(contract.get_config().treasury_deposit_method, "deposit");
+        
+        contract.set_treasury_contract_mode(true, "deposit_fee".to_string());
+        let config = contract.get_config();
+        assert!(config.treasury_is_contract);
+        assert_eq!(config.treasury_deposit_method, "deposit_fee");
+        
+        contract.register("alice".to_string());
+        assert_eq!(contract.get_record("alice".to_string()).unwrap().owner, accounts(0));
+    }
+
+    #[test]
+    fn test_treasury_deposit_callback_falls_back_to_transfer() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        
+        testing_env!(
+            get_context(owner).current_account_id(accounts(0)).build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        assert!(!contract.treasury_deposit_callback(registration_fee, "alice".to_string()));
+        assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains(r#""event":"nominal_treasury_deposit_failed","data":[{"memo":"alice","amount":"100000000000000000000000"}]"#)));
+    }
+
+    #[test]
+    fn test_near_referrer_callback_records_only_on_success() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        
+        testing_env!(
+            get_context(owner.clone()).current_account_id(accounts(0)).build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        assert!(!contract.near_referrer_callback("alice".to_string(), accounts(3), U128(5_000)));
+        assert!(contract.get_referrer_stats(accounts(3)).is_none());
+        
+        testing_env!(
+            get_context(owner).current_account_id(accounts(0)).build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])],
+        );
+        assert!(contract.near_referrer_callback("alice".to_string(), accounts(3), U128(5_000)));
+        let stats = contract.get_referrer_stats(accounts(3)).unwrap();
+        assert_eq!(stats.total_earned_near, U128(5_000));
+        assert_eq!(stats.referral_count, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only owner")]
+    fn test_set_treasury_contract_mode_non_owner() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        
+        testing_env!(get_context(accounts(2)).build());
+        contract.set_treasury_contract_mode(true, "deposit".to_string());
+    }
+
+    #[test]
+    fn test_acl_member_sets_resolved() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.grant_acl("alice".to_string(), accounts(2), ACL_CAN_SET_RESOLVED);
+        assert_eq!(contract.get_acl_permissions("alice".to_string(), accounts(2)), ACL_CAN_SET_RESOLVED);
+        
+        testing_env!(get_context(accounts(2)).build());
+        contract.set_resolved("alice".to_string(), accounts(3));
+        assert_eq!(contract.resolve("alice".to_string()), Some(accounts(3)));
+        
+        println!(" ACL test passed!");
+    }
+
+    #[test]
+    fn test_transfer_clears_acl() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.grant_acl("alice".to_string(), accounts(2), ACL_CAN_SET_RESOLVED);
+        contract.transfer_name("alice".to_string(), accounts(3));
+        assert_eq!(contract.get_acl_permissions("alice".to_string(), accounts(2)), 0);
+        
+        testing_env!(get_context(accounts(3)).build());
+        contract.grant_acl("alice".to_string(), accounts(4), ACL_CAN_SET_RESOLVED);
+        assert_eq!(contract.get_acl_permissions("alice".to_string(), accounts(4)), ACL_CAN_SET_RESOLVED);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only name owner or ACL member")]
+    fn test_revoked_acl_member_cannot_set_resolved() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.grant_acl("alice".to_string(), accounts(2), ACL_CAN_SET_RESOLVED);
+        contract.revoke_acl("alice".to_string(), accounts(2));
+        
+        testing_env!(get_context(accounts(2)).build());
+        contract.set_resolved("alice".to_string(), accounts(3));
+    }
+
+    #[test]
+    fn test_admin_log() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_registration_fee(U128(1_000));
+        contract.set_treasury(accounts(2));
+        
+        let log = contract.get_admin_log(U64(0), U64(10));
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0].action_type, "set_registration_fee");
+        assert_eq!(log[0].value, "1000");
+        assert_eq!(log[1].action_type, "set_treasury");
+        assert_eq!(contract.get_admin_log(U64(1), U64(10)).len(), 1);
+        
+        println!(" Admin log test passed!");
+    }
+
+    #[test]
+    fn test_transfer_name_resets_resolved() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.register("bob".to_string());
+        
+        // Resolution is kept by default
+        contract.transfer_name("alice".to_string(), accounts(2));
+        let record = contract.get_record("alice".to_string()).unwrap();
+        assert_eq!(record.owner, accounts(2));
+        assert_eq!(record.resolved, owner);
+        
+        contract.set_reset_resolved_on_transfer(true);
+        contract.transfer_name("bob".to_string(), accounts(3));
+        assert_eq!(contract.resolve("bob".to_string()), Some(accounts(3)));
+        
+        println!(" Transfer name test passed!");
+    }
+
+    #[test]
+    fn test_batch_register() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0 * 3)).build());
+        
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        let names = vec!["alice".to_string(), "bob".to_string()];
+        assert_eq!(contract.estimate_batch_cost(names.clone()).0, registration_fee.0 * 2);
+        
+        // Excess deposit is refunded
+        contract.batch_register(names);
+        assert_eq!(contract.get_record("alice".to_string()).unwrap().owner, owner);
+        assert_eq!(contract.get_record("bob".to_string()).unwrap().owner, owner);
+        assert_eq!(contract.get_primary_name(owner), Some("alice".to_string()));
+        
+        println!(" Batch register test passed!");
+    }
+
+    #[test]
+    #[should_panic(expected = "Name already taken")]
+    fn test_batch_register_duplicate_name() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0 * 2)).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.batch_register(vec!["alice".to_string(), "alice".to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Name already taken")]
+    fn test_batch_register_mixed_case_duplicate() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0 * 2)).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.batch_register(vec!["Alice".to_string(), "alice".to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Exact fee required")]
+    fn test_batch_register_requires_exact_fee() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        contract.set_require_exact_fee(true);
+        
+        testing_env!(get_context(owner).attached_deposit(NearToken::from_yoctonear(registration_fee.0 * 3)).build());
+        contract.batch_register(vec!["alice".to_string(), "bob".to_string()]);
+    }
+
+    #[test]
+    fn test_two_step_owner_transfer() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        contract.propose_transfer_owner(accounts(3));
+        contract.cancel_transfer_owner();
+        assert_eq!(contract.get_pending_owner(), None);
+        
+        contract.propose_transfer_owner(accounts(2));
+        testing_env!(get_context(accounts(2)).block_timestamp(DEFAULT_OWNER_TRANSFER_TIMELOCK_MS * 1_000_000).build());
+        contract.accept_owner();
+        assert_eq!(contract.owner, accounts(2));
+        assert_eq!(contract.get_pending_owner(), None);
+        
+        println!(" Owner transfer test passed!");
+    }
+
+    #[test]
+    #[should_panic(expected = "Owner transfer timelock active")]
+    fn test_accept_owner_before_timelock() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, Some(U64(1_000)));
+        contract.propose_transfer_owner(accounts(2));
+        
+        testing_env!(get_context(accounts(2)).block_timestamp(999 * 1_000_000).build());
+        contract.accept_owner();
+    }
+
+    #[test]
+    #[should_panic(expected = "Timelock too long")]
+    fn test_owner_timelock_limit() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_owner_timelock(U64(MAX_TIMELOCK_MS + 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "No pending owner transfer")]
+    fn test_accept_owner_without_pending() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.accept_owner();
+    }
+
+    #[test]
+    fn test_record_resolution() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        assert_eq!(contract.get_resolution_count("alice".to_string()).0, 0);
+        
+        testing_env!(get_context(accounts(2)).build());
+        contract.record_resolution("alice".to_string());
+        assert_eq!(contract.record_resolution("alice".to_string()).0, 2);
+        assert_eq!(contract.get_resolution_count("alice".to_string()).0, 2);
+        
+        println!(" Resolution counter test passed!");
+    }
+
+    #[test]
+    fn test_registration_sets_expiry_and_renewal_extends_it() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        let period_ms = 365 * MS_PER_DAY;
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        contract.set_registration_period(U64(period_ms));
+        contract.register("alice".to_string());
+        assert_eq!(contract.get_record_v2("alice".to_string()).unwrap().expiry_ms, U64(period_ms));
+        
+        // Renewing early extends from the current expiry
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).block_timestamp(MS_PER_DAY * 1_000_000).build());
+        contract.renew_name("alice".to_string());
+        assert_eq!(contract.get_record_v2("alice".to_string()).unwrap().expiry_ms, U64(2 * period_ms));
+        assert_eq!(contract.get_record("alice".to_string()).unwrap().owner, owner);
+        
+        // Renewing within the grace period extends from now
+        let lapsed_ms = 2 * period_ms + MS_PER_DAY;
+        testing_env!(get_context(owner).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).block_timestamp(lapsed_ms * 1_000_000).build());
+        contract.renew_name("alice".to_string());
+        assert_eq!(contract.get_record_v2("alice".to_string()).unwrap().expiry_ms, U64(lapsed_ms + period_ms));
+    }
+
+    #[test]
+    #[should_panic(expected = "Names do not expire")]
+    fn test_renew_without_registration_period() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.renew_name("alice".to_string());
+    }
+
+    #[test]
+    fn test_purge_expired_names() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.register("bob".to_string());
+        contract.records_v2.insert(&"alice".to_string(), &RecordV2 {
+            owner: accounts(2),
+            resolved: accounts(2),
+            updated_at: U64(0),
+            expiry_ms: U64(1_000),
+        });
+        
+        // Still inside the grace period
+        testing_env!(get_context(accounts(3)).block_timestamp(1_000 * 1_000_000).build());
+        assert_eq!(contract.purge_expired_names(vec!["alice".to_string(), "bob".to_string()]), 0);
+        
+        testing_env!(get_context(accounts(3)).block_timestamp((EXPIRY_GRACE_PERIOD_MS + 1_001) * 1_000_000).build());
+        assert_eq!(contract.purge_expired_names(vec!["alice".to_string(), "bob".to_string()]), 1);
+        assert!(contract.get_record("alice".to_string()).is_none());
+        assert!(contract.get_record("bob".to_string()).is_some());
+        
+        println!(" Purge expired names test passed!");
+    }
+
+    #[test]
+    fn test_emit_expiry_warning() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        let expires_at = 10 * MS_PER_DAY;
+        contract.records_v2.insert(&"alice".to_string(), &RecordV2 {
+            owner: accounts(2),
+            resolved: accounts(2),
+            updated_at: U64(0),
+            expiry_ms: U64(expires_at),
+        });
+        
+        testing_env!(get_context(accounts(3)).block_timestamp((expires_at - 2 * MS_PER_DAY) * 1_000_000).build());
+        contract.emit_expiry_warning("alice".to_string());
+        assert_eq!(contract.expiry_warnings.get(&"alice".to_string()), Some(expires_at - 2 * MS_PER_DAY));
+    }
+
+    #[test]
+    #[should_panic(expected = "Name not within warning window")]
+    fn test_emit_expiry_warning_too_early() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.records_v2.insert(&"alice".to_string(), &RecordV2 {
+            owner: accounts(2),
+            resolved: accounts(2),
+            updated_at: U64(0),
+            expiry_ms: U64(30 * MS_PER_DAY),
+        });
+        
+        contract.emit_expiry_warning("alice".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Expiry warning already emitted")]
+    fn test_emit_expiry_warning_twice() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.records_v2.insert(&"alice".to_string(), &RecordV2 {
+            owner: accounts(2),
+            resolved: accounts(2),
+            updated_at: U64(0),
+            expiry_ms: U64(10 * MS_PER_DAY),
+        });
+        
+        testing_env!(get_context(accounts(3)).block_timestamp(5 * MS_PER_DAY * 1_000_000).build());
+        contract.emit_expiry_warning("alice".to_string());
+        contract.emit_expiry_warning("alice".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many names")]
+    fn test_purge_expired_names_batch_limit() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        
+        testing_env!(get_context(accounts(3)).build());
+        let names = (0..=MAX_PURGE_BATCH).map(|i| format!("name{}", i)).collect();
+        contract.purge_expired_names(names);
+    }
+
+    #[test]
+    fn test_claim_expired_name_premium() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.records_v2.insert(&"alice".to_string(), &RecordV2 {
+            owner: accounts(2),
+            resolved: accounts(2),
+            updated_at: U64(0),
+            expiry_ms: U64(1_000),
+        });
+        
+        // Just past the grace period the premium applies
+        let claimable_ms = 1_000 + EXPIRY_GRACE_PERIOD_MS + 1;
+        testing_env!(get_context(accounts(3))
+            .attached_deposit(NearToken::from_yoctonear(registration_fee.0 * 5))
+            .block_timestamp(claimable_ms * 1_000_000)
+            .build());
+        contract.claim_expired_name("alice".to_string());
+        
+        let record = contract.get_record("alice".to_string()).unwrap();
+        assert_eq!(record.owner, accounts(3));
+        assert_eq!(contract.get_primary_name(accounts(3)), Some("alice".to_string()));
+        assert_eq!(contract.get_primary_name(accounts(2)), None);
+        
+        println!(" Claim expired name test passed!");
+    }
+
+    #[test]
+    #[should_panic(expected = "Bid below expired name price")]
+    fn test_claim_expired_name_below_premium() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.records_v2.insert(&"alice".to_string(), &RecordV2 {
+            owner: accounts(2),
+            resolved: accounts(2),
+            updated_at: U64(0),
+            expiry_ms: U64(1_000),
+        });
+        
+        testing_env!(get_context(accounts(3))
+            .attached_deposit(NearToken::from_yoctonear(registration_fee.0))
+            .block_timestamp((1_000 + EXPIRY_GRACE_PERIOD_MS + 1) * 1_000_000)
+            .build());
+        contract.claim_expired_name("alice".to_string());
+    }
+
+    #[test]
+    fn test_register_with_ft_timeout() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_coin_fee(accounts(3), U128(1_000));
+        
+        testing_env!(get_context(accounts(2)).build());
+        contract.register_with_ft("alice".to_string(), accounts(3));
+        let pending = contract.get_pending_ft_registration("alice".to_string()).unwrap();
+        assert_eq!(pending.owner, accounts(2));
+        assert_eq!(pending.amount, U128(1_000));
+        
+        testing_env!(get_context(accounts(4)).block_timestamp((PENDING_FT_TIMEOUT_MS + 1) * 1_000_000).build());
+        contract.registration_timeout("alice".to_string());
+        assert!(contract.get_pending_ft_registration("alice".to_string()).is_none());
+        
+        // A callback arriving after the timeout must not register or refund a second time
+        testing_env!(
+            get_context(accounts(0)).current_account_id(accounts(0)).build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])],
+        );
+        assert!(!contract.ft_transfer_callback("alice".to_string(), accounts(2), accounts(3), 1_000, 0));
+        assert!(contract.get_record("alice".to_string()).is_none());
+        
+        println!(" FT registration timeout test passed!");
+    }
+
+    fn sig_params(name: &str, owner: AccountId, relayer: AccountId, nonce: u64, deadline: u64) -> RegisterWithSigParams {
+        RegisterWithSigParams {
+            name: name.to_string(),
+            owner,
+            relayer,
+            currency: None,
+            amount: U128(100_000_000_000_000_000_000_000),
+            deadline: U64(deadline),
+            nonce: U64(nonce),
+            signature_type: SignatureType::Ed25519,
+        }
+    }
+
+    #[test]
+    fn test_register_basic() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2))
+            .attached_deposit(NearToken::from_yoctonear(registration_fee.0))
+            .block_timestamp(5_000 * 1_000_000)
+            .build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        
+        let record = contract.get_record("alice".to_string()).unwrap();
+        assert_eq!(record.owner, accounts(2));
+        assert_eq!(record.resolved, accounts(2));
+        assert_eq!(record.created_at, U64(5_000));
+        assert!(!contract.is_name_available("alice".to_string()));
+        assert_eq!(contract.get_name_count(accounts(2)), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Name already taken")]
+    fn test_register_double_registration_fails() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        
+        testing_env!(get_context(accounts(3)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        contract.register("alice".to_string());
+    }
+
+    #[test]
+    fn test_short_name_grandfathered_after_min_len_raised() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        contract.set_validation_config(true, true, 2, MAX_NAME_LEN as u8);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        contract.register("ab".to_string());
+        
+        testing_env!(get_context(owner).build());
+        contract.set_validation_config(true, true, 3, MAX_NAME_LEN as u8);
+        
+        let check = contract.check_name("ab".to_string());
+        assert_eq!(check.reason, Some("taken".to_string()));
+        testing_env!(get_context(accounts(2)).build());
+        contract.set_resolved("ab".to_string(), accounts(3));
+        assert_eq!(contract.resolve("ab".to_string()), Some(accounts(3)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid name")]
+    fn test_register_invalid_name() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        assert!(!contract.is_valid_name(""));
+        assert!(!contract.is_valid_name(&"a".repeat(MAX_NAME_LEN + 1)));
+        assert!(!contract.is_valid_name("a b"));
+        assert!(!contract.is_valid_name("bad.name"));
+        assert!(!contract.is_valid_name("ab"));
+        assert!(!contract.is_valid_name("-alice"));
+        assert!(!contract.is_valid_name("alice_"));
+        assert!(!contract.is_valid_name("al__ice"));
+        assert!(!contract.is_valid_name("al--ice"));
+        assert!(!contract.is_valid_name("ålice"));
+        
+        contract.register("bad!name".to_string());
+    }
+
+    #[test]
+    fn test_set_primary_name_on_first_registration() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        assert_eq!(contract.get_primary_name(accounts(2)), None);
+        
+        contract.register("alice".to_string());
+        contract.register("alice-two".to_string());
+        assert_eq!(contract.get_primary_name(accounts(2)), Some("alice".to_string()));
+    }
+
+    #[test]
+    fn test_transfer_name() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        
+        testing_env!(get_context(accounts(2)).block_timestamp(7_000 * 1_000_000).build());
+        contract.transfer_name("alice".to_string(), accounts(3));
+        
+        let record = contract.get_record("alice".to_string()).unwrap();
+        assert_eq!(record.owner, accounts(3));
+        // Resolution is kept unless reset_resolved_on_transfer is set
+        assert_eq!(record.resolved, accounts(2));
+        assert_eq!(record.updated_at, U64(7_000));
+        assert_eq!(contract.get_primary_name(accounts(2)), None);
+    }
+
+    #[test]
+    fn test_set_resolved() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        
+        contract.set_resolved("alice".to_string(), accounts(4));
+        assert_eq!(contract.resolve("alice".to_string()), Some(accounts(4)));
+        assert_eq!(contract.get_record("alice".to_string()).unwrap().owner, accounts(2));
+    }
+
+    #[test]
+    fn test_coin_fee_registration() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        contract.set_coin_fee(accounts(3), U128(1_000));
+        
+        testing_env!(get_context(accounts(2)).block_timestamp(3_000 * 1_000_000).build());
+        contract.register_with_ft("alice".to_string(), accounts(3));
+        assert!(contract.get_record("alice".to_string()).is_none());
+        
+        // The callback runs as the contract itself once ft_transfer_from resolves
+        testing_env!(
+            get_context(owner).current_account_id(accounts(0)).build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])],
+        );
+        assert!(contract.ft_transfer_callback("alice".to_string(), accounts(2), accounts(3), 1_000, 3_000));
+        
+        let record = contract.get_record("alice".to_string()).unwrap();
+        assert_eq!(record.owner, accounts(2));
+        assert_eq!(record.created_at, U64(3_000));
+        assert!(contract.get_pending_ft_registration("alice".to_string()).is_none());
+    }
+
+    #[test]
+    fn test_sig_token_registration_callbacks() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        for name in ["alice", "bob", "carol"] {
+            contract.pending_ft_registrations.insert(&name.to_string(), &PendingFtReg {
+                owner: accounts(2),
+                payer: accounts(3),
+                token: accounts(4),
+                amount: U128(1_000),
+                created_ms: U64(3_000),
+            });
+        }
+        
+        // A failed fee transfer leaves nothing escrowed
+        testing_env!(
+            get_context(owner.clone()).current_account_id(accounts(0)).build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        assert!(!contract.ft_transfer_sig_callback("alice".to_string(), accounts(2), accounts(4), accounts(3), U128(50), 3_000));
+        assert!(contract.get_record("alice".to_string()).is_none());
+        assert!(contract.get_pending_ft_registration("alice".to_string()).is_none());
+        assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains(r#""event":"nominal_sig_registration_failed","data":[{"name":"alice","step":"fee_transfer"}]"#)));
+        
+        // Escrowed: the name is registered and the shares are forwarded
+        testing_env!(
+            get_context(owner.clone()).current_account_id(accounts(0)).build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])],
+        );
+        assert!(contract.ft_transfer_sig_callback("bob".to_string(), accounts(2), accounts(4), accounts(3), U128(50), 3_000));
+        let record = contract.get_record("bob".to_string()).unwrap();
+        assert_eq!(record.owner, accounts(2));
+        assert_eq!(record.created_at, U64(3_000));
+        assert!(contract.get_pending_ft_registration("bob".to_string()).is_none());
+        // Not recorded until the referrer payout resolves
+        assert!(contract.get_referrer_stats(accounts(3)).is_none());
+        
+        // The owner reached the name limit while the transfer was in flight: refund
+        contract.max_names_per_owner = 1;
+        assert!(!contract.ft_transfer_sig_callback("carol".to_string(), accounts(2), accounts(4), accounts(3), U128(50), 3_000));
+        assert!(contract.get_record("carol".to_string()).is_none());
+        assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains(r#""step":"owner_name_limit""#)));
+        
+        // Already cleared by registration_timeout, which refunded the escrow
+        assert!(!contract.ft_transfer_sig_callback("dave".to_string(), accounts(2), accounts(4), accounts(3), U128(50), 3_000));
+        assert!(contract.get_record("dave".to_string()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Relayer not allowed")]
+    fn test_relayer_allowlist_enforcement() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_require_relayer_allowlist(true);
+        contract.set_relayer(accounts(4), true);
+        assert!(contract.is_relayer_allowed(accounts(4)));
+        assert!(!contract.is_relayer_allowed(accounts(3)));
+        
+        testing_env!(get_context(accounts(3)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        contract.register_with_sig(sig_params("alice", accounts(2), accounts(3), 0, 60_000), "sig:key".to_string());
     }
 
-    pub fn set_require_relayer_allowlist(&mut self, required: bool) {
-        self.assert_owner();
-        self.require_relayer_allowlist = required;
+    #[test]
+    #[should_panic(expected = "Deadline expired")]
+    fn test_deadline_expired_sig() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(3))
+            .attached_deposit(NearToken::from_yoctonear(registration_fee.0))
+            .block_timestamp(10_001 * 1_000_000)
+            .build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register_with_sig(sig_params("alice", accounts(2), accounts(3), 0, 10_000), "sig:key".to_string());
     }
 
-    pub fn get_record(&self, name: String) -> Option<Record> {
-        self.records.get(&name)
+    #[test]
+    #[should_panic(expected = "SignatureTooOld")]
+    fn test_sig_deadline_beyond_max_validity() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(3)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        let deadline = DEFAULT_MAX_SIG_VALIDITY_MS + 1;
+        contract.register_with_sig(sig_params("alice", accounts(2), accounts(3), 0, deadline), "sig:key".to_string());
     }
 
-    pub fn get_primary_name(&self, account: AccountId) -> Option<String> {
-        self.primary_names.get(&account)
+    #[test]
+    #[should_panic(expected = "Invalid nonce")]
+    fn test_nonce_replay_rejected() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(3)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        // As if a request signed with nonce 0 had already been relayed
+        contract.nonces.insert(&"alice".to_string(), &1);
+        assert_eq!(contract.get_nonce("alice".to_string()), U64(1));
+        
+        contract.register_with_sig(sig_params("alice", accounts(2), accounts(3), 0, 60_000), "sig:key".to_string());
     }
 
-    pub fn get_coin_fee(&self, coin: AccountId) -> Option<U128> {
-        self.coin_fees.get(&coin).map(U128)
+    #[test]
+    #[should_panic(expected = "Missing role")]
+    fn test_admin_only_operations() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_registration_fee(U128(1));
+        assert_eq!(contract.registration_fee, 1);
+        
+        testing_env!(get_context(accounts(2)).build());
+        contract.set_registration_fee(U128(2));
     }
 
-    pub fn is_relayer_allowed(&self, relayer: AccountId) -> bool {
-        !self.require_relayer_allowlist || self.relayers.contains(&relayer)
+    #[test]
+    #[should_panic(expected = "Protocol is paused")]
+    fn test_pause_blocks_registration() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_coin_fee(accounts(3), U128(1_000));
+        contract.pause(U64(1_000));
+        
+        testing_env!(get_context(accounts(2)).build());
+        contract.register_with_ft("alice".to_string(), accounts(3));
     }
 
-    pub fn get_nonce(&self, name: String) -> U64 {
-        U64(self.nonces.get(&name).unwrap_or(0))
+    fn bridge_proof(root: [u8; 32]) -> BridgeProof {
+        BridgeProof {
+            message_nonce: U64(0),
+            locked_at: near_sdk::json_types::I64(1_700_000_000),
+            merkle_proof: vec![],
+            root: hex::encode(root),
+            signatures: vec![GuardianSignature { guardian_index: 0, signature: bs58::encode([1u8; 64]).into_string() }],
+        }
     }
-    
-    pub fn get_authorized_keys(&self, _account: AccountId) -> Vec<String> {
-        // TO Do
-        // This is a simplified implementation for the new storage pattern
-        // In mainnet, we  want to iterate through all keys with the account prefix
-        vec![] // Simplified for now
-    }
-
-    pub fn get_config(&self) -> serde_json::Value {
-        serde_json::json!({
-            "owner": self.owner,
-            "treasury": self.treasury,
-            "registration_fee": U128(self.registration_fee),
-            "referrer_bps": self.referrer_bps,
-            "require_relayer_allowlist": self.require_relayer_allowlist
-        })
+
+    #[test]
+    #[should_panic(expected = "Bridge guardians not set")]
+    fn test_claim_bridged_name_requires_verifier() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        let solana_owner = bs58::encode([7u8; 32]).into_string();
+        contract.claim_bridged_name("alice".to_string(), solana_owner, bridge_proof([0u8; 32]));
     }
 
-    fn emit_registered(&self, name: &str, owner: &AccountId) {
-        env::log_str(&format!("EVENT_JSON:{{\"event\":\"Registered\",\"name\":\"{}\",\"owner\":\"{}\"}}", name, owner));
+    #[test]
+    #[should_panic(expected = "Lock message not in bridge root")]
+    fn test_claim_bridged_name_rejects_foreign_root() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        let guardians = GuardianSet {
+            keys: vec![PublicKey::from_str("ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp").unwrap()],
+            threshold: 1,
+        };
+        contract.set_bridge_guardians(Some(guardians.clone()));
+        assert_eq!(contract.get_bridge_guardians(), Some(guardians));
+        
+        // Lock message for accounts(3), claimed by accounts(2)
+        testing_env!(get_context(accounts(2)).build());
+        let owner_key = [7u8; 32];
+        let leaf = bridge::lock_message_leaf("alice", &owner_key, &accounts(3), 0, 1_700_000_000);
+        let solana_owner = bs58::encode(owner_key).into_string();
+        contract.claim_bridged_name("alice".to_string(), solana_owner, bridge_proof(leaf));
     }
 
-    fn emit_primary_name_set(&self, owner: &AccountId, name: &str) {
-        env::log_str(&format!("EVENT_JSON:{{\"event\":\"PrimaryNameSet\",\"owner\":\"{}\",\"name\":\"{}\"}}", owner, name));
+    #[test]
+    #[should_panic(expected = "Guardian threshold must be a majority")]
+    fn test_bridge_guardians_need_majority_threshold() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_bridge_guardians(Some(GuardianSet {
+            keys: vec![
+                PublicKey::from_str("ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp").unwrap(),
+                PublicKey::from_str("ed25519:DcA2MzgpJbrUATQLLceocVckhhAqrkingax4oJ9kZ847").unwrap(),
+            ],
+            threshold: 1,
+        }));
     }
 
-    fn emit_fee_paid(&self, name: &str, payer: &AccountId, currency: Option<&AccountId>, amount: u128, referrer: Option<&AccountId>) {
-        let currency_str = currency.map(|c| c.to_string()).unwrap_or_else(|| "NEAR".to_string());
-        let referrer_str = referrer.map(|r| r.to_string()).unwrap_or_else(|| "null".to_string());
-        env::log_str(&format!("EVENT_JSON:{{\"event\":\"FeePaid\",\"name\":\"{}\",\"payer\":\"{}\",\"currency\":\"{}\",\"amount\":\"{}\",\"referrer\":\"{}\"}}", 
-            name, payer, currency_str, amount, referrer_str));
+    #[test]
+    #[should_panic(expected = "Not enough guardian signatures")]
+    fn test_claim_bridged_name_needs_threshold_signatures() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_bridge_guardians(Some(GuardianSet {
+            keys: vec![
+                PublicKey::from_str("ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp").unwrap(),
+                PublicKey::from_str("ed25519:DcA2MzgpJbrUATQLLceocVckhhAqrkingax4oJ9kZ847").unwrap(),
+                PublicKey::from_str("ed25519:3tysLvy7KGoE8pznUgXvSHa4vYyGvrDZFcT8jgb8PEQ6").unwrap(),
+            ],
+            threshold: 2,
+        }));
+        
+        testing_env!(get_context(accounts(2)).build());
+        let owner_key = [7u8; 32];
+        let leaf = bridge::lock_message_leaf("alice", &owner_key, &accounts(2), 0, 1_700_000_000);
+        let solana_owner = bs58::encode(owner_key).into_string();
+        contract.claim_bridged_name("alice".to_string(), solana_owner, bridge_proof(leaf));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
-    use near_sdk::{testing_env, AccountId};
+    #[test]
+    #[should_panic(expected = "Name in cooldown")]
+    fn test_claim_bridged_name_respects_deletion_cooldown() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("alice".to_string());
+        contract.delete_name("alice".to_string());
+        
+        let solana_owner = bs58::encode([7u8; 32]).into_string();
+        contract.claim_bridged_name("Alice".to_string(), solana_owner, bridge_proof([0u8; 32]));
+    }
 
-    fn get_context(predecessor: AccountId) -> VMContextBuilder {
-        let mut builder = VMContextBuilder::new();
-        builder.predecessor_account_id(predecessor);
-        builder
+    #[test]
+    #[should_panic(expected = "Bridge claim window closed")]
+    fn test_claim_bridged_name_after_window_fails() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_bridge_guardians(Some(GuardianSet {
+            keys: vec![PublicKey::from_str("ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp").unwrap()],
+            threshold: 1,
+        }));
+        
+        let closed_secs = 1_700_000_000 + bridge::BRIDGE_CLAIM_WINDOW_SECONDS as u64 + 1;
+        testing_env!(get_context(accounts(2)).block_timestamp(closed_secs * 1_000_000_000).build());
+        let owner_key = [7u8; 32];
+        let leaf = bridge::lock_message_leaf("alice", &owner_key, &accounts(2), 0, 1_700_000_000);
+        let solana_owner = bs58::encode(owner_key).into_string();
+        contract.claim_bridged_name("alice".to_string(), solana_owner, bridge_proof(leaf));
     }
 
     #[test]
-    fn test_contract_initialization() {
+    fn test_bridge_merkle_root_is_order_independent() {
+        testing_env!(get_context(accounts(0)).build());
+        let leaf = bridge::lock_message_leaf("alice", &[7u8; 32], &accounts(2), 0, 1_700_000_000);
+        let other = bridge::lock_message_leaf("bob", &[7u8; 32], &accounts(2), 1, 1_700_000_000);
+        assert_ne!(leaf, other);
+        assert_eq!(bridge::merkle_root(leaf, &[other]), bridge::merkle_root(other, &[leaf]));
+        assert_eq!(bridge::merkle_root(leaf, &[]), leaf);
+    }
+
+    #[test]
+    fn test_register_normalizes_case() {
         let owner: AccountId = accounts(0);
         let treasury: AccountId = accounts(1);
-        let registration_fee = U128(100_000_000_000_000_000_000_000); // 0.1 NEAR
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.register("Alice".to_string());
+        
+        assert!(contract.records.get(&"alice".to_string()).is_some());
+        assert!(contract.records.get(&"Alice".to_string()).is_none());
+        assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains(r#""event":"nominal_name_normalized","data":[{"original":"Alice","normalized":"alice"}]"#)));
+        assert!(contract.is_valid_name_normalized("alice".to_string()));
+        assert!(!contract.is_valid_name_normalized("Alice".to_string()));
+    }
+
+    #[test]
+    fn test_register_case_sensitive_preserves_name() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
         
         testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_name_case_sensitive(true);
         
-        // Test contract creation
-        let contract = NameRegistry::new(owner.clone(), treasury.clone(), registration_fee);
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        contract.register("Alice".to_string());
+        assert!(contract.records.get(&"Alice".to_string()).is_some());
+        assert!(contract.records.get(&"alice".to_string()).is_none());
+    }
+
+    #[test]
+    fn test_ft_on_transfer_with_referrer() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
         
-        // Verify initialization
-        assert_eq!(contract.owner, owner);
-        assert_eq!(contract.treasury, treasury);
-        assert_eq!(contract.registration_fee, registration_fee.0);
-        assert_eq!(contract.referrer_bps, 500);
-        assert_eq!(contract.require_relayer_allowlist, false);
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        contract.set_coin_fee(accounts(3), U128(1_000));
+        contract.set_require_exact_fee(false);
         
-        println!(" Contract initialization test passed!");
+        // ft_transfer_call from accounts(2) on token accounts(3)
+        testing_env!(get_context(accounts(3)).build());
+        let msg = r#"{"name":"alice","referrer":"eugene.near"}"#.to_string();
+        match contract.ft_on_transfer(accounts(2), U128(1_200), msg) {
+            PromiseOrValue::Value(unused) => assert_eq!(unused, U128(200)),
+            PromiseOrValue::Promise(_) => panic!("Expected unused amount"),
+        }
+        assert_eq!(contract.get_record("alice".to_string()).unwrap().owner, accounts(2));
+        
+        testing_env!(
+            get_context(owner).current_account_id(accounts(0)).build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])],
+        );
+        // referrer_bps defaults to 500
+        assert!(contract.ft_transfer_referrer_callback("alice".to_string(), accounts(3), accounts(4), U128(50)));
+        let stats = contract.get_referrer_stats(accounts(4)).unwrap();
+        assert_eq!(stats.total_earned_tokens, U128(50));
+        assert_eq!(stats.referral_count, 1);
     }
 
     #[test]
-    fn test_get_nonce() {
+    #[should_panic(expected = "Invalid referrer")]
+    fn test_ft_on_transfer_rejects_token_as_referrer() {
         let owner: AccountId = accounts(0);
         let treasury: AccountId = accounts(1);
         let registration_fee = U128(100_000_000_000_000_000_000_000);
         
         testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_coin_fee(accounts(3), U128(1_000));
         
-        let contract = NameRegistry::new(owner, treasury, registration_fee);
+        testing_env!(get_context(accounts(3)).build());
+        let msg = format!(r#"{{"name":"alice","referrer":"{}"}}"#, accounts(3));
+        contract.ft_on_transfer(accounts(2), U128(1_000), msg);
+    }
+
+    #[test]
+    #[should_panic(expected = "Referrer not allowed")]
+    fn test_ft_on_transfer_referrer_must_be_relayer_when_allowlisted() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
         
-        // Test nonce for non-existent name
-        let nonce = contract.get_nonce("test".to_string());
-        assert_eq!(nonce.0, 0);
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_coin_fee(accounts(3), U128(1_000));
+        contract.set_require_relayer_allowlist(true);
         
-        println!(" Get nonce test passed!");
+        testing_env!(get_context(accounts(3)).build());
+        let msg = r#"{"name":"alice","referrer":"eugene.near"}"#.to_string();
+        contract.ft_on_transfer(accounts(2), U128(1_000), msg);
+    }
+
+    #[test]
+    fn test_failed_token_treasury_transfer_is_retried() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner.clone(), treasury, registration_fee, None);
+        
+        testing_env!(
+            get_context(owner.clone()).current_account_id(accounts(0)).build(),
+            near_sdk::test_vm_config(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        assert!(!contract.token_treasury_transfer_callback(accounts(3), U128(950)));
+        assert!(!contract.token_treasury_transfer_callback(accounts(3), U128(50)));
+        assert_eq!(contract.get_unpaid_token_fees(accounts(3)), U128(1_000));
+        assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains(r#""event":"nominal_token_treasury_transfer_failed","data":[{"token":"danny","amount":"50"}]"#)));
+        
+        testing_env!(get_context(owner).build());
+        contract.retry_token_treasury_transfer(accounts(3));
+        assert_eq!(contract.get_unpaid_token_fees(accounts(3)), U128(0));
+    }
+
+    #[test]
+    fn test_batch_set_relayers_and_coin_fees() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_relayer(accounts(4), true);
+        
+        contract.batch_set_relayers(vec![(accounts(2), true), (accounts(3), true), (accounts(4), false)]);
+        assert!(contract.relayers.contains(&accounts(2)));
+        assert!(contract.relayers.contains(&accounts(3)));
+        assert!(!contract.relayers.contains(&accounts(4)));
+        assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains(
+            r#""event":"nominal_batch_relayers_updated","data":[{"added":["charlie.near","danny.near"],"removed":["eugene.near"]}]"#
+        )));
+        
+        let fees = vec![(accounts(3), U128(1_000)), (accounts(4), U128(2_000))];
+        assert_eq!(contract.batch_set_coin_fees(fees.clone()), fees);
+        assert_eq!(contract.coin_fees.get(&accounts(4)), Some(2_000));
+    }
+
+    #[test]
+    #[should_panic(expected = "Batch too large")]
+    fn test_batch_set_relayers_cap() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.batch_set_relayers(vec![(accounts(2), true); MAX_RELAYER_BATCH_SIZE + 1]);
+    }
+
+    #[test]
+    fn test_claim_expired_name_stale_multiplier() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_stale_multiplier(30_000, U64(EXPIRY_GRACE_PERIOD_MS + 7 * 24 * 60 * 60 * 1000));
+        
+        testing_env!(get_context(accounts(2)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        contract.register("alice".to_string());
+        contract.records_v2.insert(&"alice".to_string(), &RecordV2 {
+            owner: accounts(2),
+            resolved: accounts(2),
+            updated_at: U64(0),
+            expiry_ms: U64(1_000),
+        });
+        
+        // Past the premium window but still within the staleness window
+        let claim_ms = 1_000 + EXPIRY_GRACE_PERIOD_MS + EXPIRED_NAME_PREMIUM_WINDOW_MS + 1;
+        assert_eq!(contract.expired_name_price(&"alice".to_string(), claim_ms), registration_fee.0 * 3);
+        testing_env!(get_context(accounts(3))
+            .attached_deposit(NearToken::from_yoctonear(registration_fee.0 * 3))
+            .block_timestamp(claim_ms * 1_000_000)
+            .build());
+        contract.claim_expired_name("alice".to_string());
+        
+        assert_eq!(contract.get_record("alice".to_string()).unwrap().owner, accounts(3));
+        let effective_fee = format!(r#""effective_fee":"{}""#, registration_fee.0 * 3);
+        assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains(&effective_fee)));
+        
+        // Once the staleness window has passed the base fee applies
+        let late_ms = 1_000 + EXPIRY_GRACE_PERIOD_MS + 7 * 24 * 60 * 60 * 1000;
+        assert_eq!(contract.expired_name_price(&"alice".to_string(), late_ms), registration_fee.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Fee payer signature required")]
+    fn test_register_via_meta_tx_relayed_requires_signature() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_meta_tx_enabled(true);
+        
+        // accounts(3) submits params naming accounts(4) as the fee payer
+        testing_env!(get_context(accounts(3)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let params = MetaRegistrationParams {
+            name: "alice".to_string(),
+            owner: accounts(2),
+            fee_payer: accounts(4),
+            fee_amount: registration_fee,
+            nonce: U64(0),
+            deadline: U64(u64::MAX),
+        };
+        contract.register_via_meta_tx(params, None);
+    }
+
+    #[test]
+    fn test_register_via_meta_tx_by_fee_payer() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_meta_tx_enabled(true);
+        
+        testing_env!(get_context(accounts(4)).attached_deposit(NearToken::from_yoctonear(registration_fee.0)).build());
+        let params = MetaRegistrationParams {
+            name: "alice".to_string(),
+            owner: accounts(2),
+            fee_payer: accounts(4),
+            fee_amount: registration_fee,
+            nonce: U64(0),
+            deadline: U64(u64::MAX),
+        };
+        contract.register_via_meta_tx(params, None);
+        assert_eq!(contract.get_record("alice".to_string()).unwrap().owner, accounts(2));
+        assert_eq!(contract.get_account_nonce(accounts(4)), U64(0));
+    }
+
+    #[test]
+    fn test_register_via_meta_tx_refunds_relayer_overpayment() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.set_meta_tx_enabled(true);
+        
+        // Implicit account of the ed25519 key with secret [7; 32], which signed these params
+        let fee_payer: AccountId = "ea4a6c63e29c520abef5507b132ec5f9954776aebebe7b92421eea691446d22c".parse().unwrap();
+        let signature = "5niR4VKRdZgpLVvY26tjZirNXnXcHi4ntmFUVFYQKCTApqMXZfjSESQi2drXfuduNRLEX9VXYULygiSMoXdqyTRj:ed25519:GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB";
+        
+        // accounts(3) relays and attaches twice the registration fee
+        testing_env!(get_context(accounts(3)).attached_deposit(NearToken::from_yoctonear(registration_fee.0 * 2)).build());
+        let params = MetaRegistrationParams {
+            name: "alice".to_string(),
+            owner: accounts(2),
+            fee_payer: fee_payer.clone(),
+            fee_amount: U128(registration_fee.0 * 2),
+            nonce: U64(0),
+            deadline: U64(u64::MAX),
+        };
+        contract.register_via_meta_tx(params, Some(signature.to_string()));
+        
+        assert_eq!(contract.get_record("alice".to_string()).unwrap().owner, accounts(2));
+        assert_eq!(contract.get_account_nonce(fee_payer), U64(1));
+        assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains(
+            r#""event":"nominal_excess_fee_refunded","data":[{"name":"alice","payer":"danny","refund_amount":"100000000000000000000000"}]"#
+        )));
+    }
+
+    #[test]
+    fn test_meta_tx_nonce_falls_back_to_legacy_key() {
+        let owner: AccountId = accounts(0);
+        let treasury: AccountId = accounts(1);
+        let registration_fee = U128(100_000_000_000_000_000_000_000);
+        
+        testing_env!(get_context(owner.clone()).build());
+        let mut contract = NameRegistry::new(owner, treasury, registration_fee, None);
+        contract.nonces.insert(&accounts(2).to_string(), &3);
+        assert_eq!(contract.get_account_nonce(accounts(2)), U64(3));
+        
+        contract.account_nonces.insert(&accounts(2), &4);
+        assert_eq!(contract.get_account_nonce(accounts(2)), U64(4));
+        assert_eq!(contract.get_nonce(accounts(2).to_string()), U64(3));
     }
 }
@@ -1,12 +1,29 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Mint, Transfer};
+use anchor_spl::token_2022::{self, TransferChecked};
+use anchor_spl::token_interface::{TokenInterface, TokenAccount as InterfaceTokenAccount, Mint as InterfaceMint};
+use anchor_spl::metadata::MetadataAccount;
 use anchor_lang::solana_program::{
+    bpf_loader_upgradeable,
     clock::Clock,
+    compute_units::sol_remaining_compute_units,
+    ed25519_program,
+    hash::hashv,
+    instruction::{AccountMeta, Instruction},
+    native_token::LAMPORTS_PER_SOL,
+    program::{get_return_data, invoke},
+    secp256k1_recover::secp256k1_recover,
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
 };
 use anchor_lang::system_program;
 
 declare_id!("6TVpb5Ga5c8mfgiFRddf8T1sGFJbgtzcj2WFQBB1gFMq");
 
+pub mod pda;
+pub use pda::*;
+pub mod signing;
+use signing::{build_registration_message, build_set_resolved_message, DOMAIN_SEPARATOR};
+
 // ========================================
 // CONSTANTS
 // ========================================
@@ -17,8 +34,117 @@ declare_id!("6TVpb5Ga5c8mfgiFRddf8T1sGFJbgtzcj2WFQBB1gFMq");
 // space against the existing account size). Without this, a user who first
 // registers a short name (allocating a small account) and later registers a
 // longer name would cause a mismatch (e.g. Left: 53 Right: 50) and fail.
+pub const MIN_NAME_LEN: usize = 3;
 pub const MAX_NAME_LEN: usize = 63;
+// Matches the crate version in Cargo.toml
+pub const PROGRAM_VERSION: [u8; 3] = [0, 1, 0];
+pub const PROGRAM_META_ACCOUNT_SPACE: usize = 8 + 3 + 32 + 8 + 1 + 32; // discriminator + version + authority + deployed_at + bump + domain separator
+pub const LEGACY_PROGRAM_META_ACCOUNT_SPACE: usize = 8 + 3 + 32 + 8 + 1; // ProgramMeta before domain_separator was appended
+pub const MULTISIG_CONFIG_ACCOUNT_SPACE: usize = 8 + 32 + 1 + 1 + 1; // discriminator + multisig + threshold + enabled + bump
+// Squads v3 (squads-mpl) program. Approved proposals execute with the
+// multisig's authority PDA as signer.
+pub const SQUADS_PROGRAM_ID: Pubkey = pubkey!("SMPLecH534NA9acpos4G6x7uf3LWbCAwZQE9e8ZekMu");
+pub const DEFAULT_SQUADS_AUTHORITY_INDEX: u32 = 1;
+// SPL Governance (Realms) program; gated names need a Completed proposal there
+pub const SPL_GOVERNANCE_PROGRAM_ID: Pubkey = pubkey!("GovER5Lthms3bLBqWub97yVrMmEogzX7xNjdXpPPCVZw");
+pub const COMPUTE_BUDGET_PROGRAM_ID: Pubkey = pubkey!("ComputeBudget111111111111111111111111111111");
+// ComputeBudgetInstruction::SetComputeUnitPrice tag, followed by the u64 price in micro-lamports
+pub const SET_COMPUTE_UNIT_PRICE_TAG: u8 = 3;
+pub const GOVERNANCE_GATE_ACCOUNT_SPACE: usize = 8 + 32 + 32 + 1 + 1; // discriminator + realm + mint + proposal type + bump
 pub const PRIMARY_NAME_ACCOUNT_SPACE: usize = 8 + 37 + MAX_NAME_LEN; // discriminator + base + max name
+// NameRecord without the name bytes. Records created at this size can later be
+// grown to NAME_RECORD_V2_SPACE via migrate_name_record to make room for new fields.
+pub const NAME_RECORD_BASE_SPACE: usize = 92;
+pub const NAME_RECORD_EXTRA_FIELDS_BYTES: usize = 128;
+pub const NAME_RECORD_V2_SPACE: usize = 8 + NAME_RECORD_BASE_SPACE + MAX_NAME_LEN + NAME_RECORD_EXTRA_FIELDS_BYTES;
+pub const MAX_TREASURY_RECIPIENTS: usize = 5;
+pub const MAX_SUPPORTED_MINTS: usize = 32;
+pub const MAX_PROPOSAL_VALUE_LEN: usize = 32;
+pub const PROPOSAL_ACCOUNT_SPACE: usize = 8 + 39 + MAX_PROPOSAL_VALUE_LEN; // discriminator + base + max value
+pub const VOTE_RECORD_ACCOUNT_SPACE: usize = 8 + 32 + 8 + 1; // discriminator + voter + amount + bump
+pub const MINT_REGISTRY_ACCOUNT_SPACE: usize = 8 + 4 + 32 * MAX_SUPPORTED_MINTS + 1; // discriminator + vec of mints + bump
+pub const TREASURY_TOKEN_ACCOUNT_SPACE: usize = 8 + 32 + 32 + 1; // discriminator + mint + token account + bump
+pub const NFT_DISCOUNT_ACCOUNT_SPACE: usize = 8 + 32 + 2 + 1 + 1; // discriminator + collection + bps + enabled + bump
+pub const CHARACTER_SET_ACCOUNT_SPACE: usize = 8 + 1 + 1 + 1 + 1; // discriminator + flags + bump
+pub const MAX_BIO_LEN: usize = 280;
+pub const NAME_BIO_ACCOUNT_SPACE: usize = 8 + 32 + 4 + MAX_BIO_LEN + 1; // discriminator + name hash + max bio + bump
+pub const MAX_AVATAR_URI_LEN: usize = 200;
+pub const MAX_AVATAR_MIME_TYPE_LEN: usize = 50;
+pub const SUPPORTED_AVATAR_MIME_TYPES: [&str; 5] = ["image/jpeg", "image/png", "image/gif", "image/webp", "image/svg+xml"];
+pub const AVATAR_RECORD_ACCOUNT_SPACE: usize =
+    8 + 32 + 4 + MAX_AVATAR_URI_LEN + 4 + MAX_AVATAR_MIME_TYPE_LEN + 1; // discriminator + name hash + max uri + max mime type + bump
+// Platform names are PDA seeds, so they are capped at the 32-byte seed limit
+pub const MAX_SOCIAL_PLATFORM_LEN: usize = 32;
+pub const MAX_SOCIAL_HANDLE_LEN: usize = 64;
+pub const SOCIAL_ATTESTATION_ACCOUNT_SPACE: usize =
+    8 + 32 + 4 + MAX_SOCIAL_PLATFORM_LEN + 4 + MAX_SOCIAL_HANDLE_LEN + 32 + 8 + 1 + 32 + 32; // discriminator + name hash + max platform + max handle + attestation hash + attested_at + bump + owner + attester
+pub const ATTESTER_CONFIG_ACCOUNT_SPACE: usize = 8 + 4 + MAX_SOCIAL_PLATFORM_LEN + 32 + 1; // discriminator + max platform + public key + bump
+pub const NAME_HISTORY_HEAD_ACCOUNT_SPACE: usize = 8 + 32 + 4 + 1; // discriminator + name hash + next index + bump
+pub const NAME_HISTORY_ENTRY_ACCOUNT_SPACE: usize = 8 + 32 + 4 + 32 + 32 + 8 + 32 + 1; // discriminator + base + bump
+pub const BENCHMARK_COUNTER_ACCOUNT_SPACE: usize = 8 + 8 + 1; // discriminator + count + bump
+pub const BENCHMARK_RESULT_ACCOUNT_SPACE: usize = 8 + 1 + 8 + 8 + 1; // discriminator + name len + cu used + timestamp + bump
+pub const FEE_HISTOGRAM_BUCKETS: usize = 8;
+pub const REGISTRY_STATS_ACCOUNT_SPACE: usize =
+    8 + 8 + 8 * FEE_HISTOGRAM_BUCKETS + 8 * (MAX_NAME_LEN + 1) + 8 + 1; // discriminator + counters + histograms + bump
+pub const OWNER_NONCE_ACCOUNT_SPACE: usize = 8 + 32 + 8 + 1; // discriminator + owner + nonce + bump
+pub const OWNER_NAME_COUNT_ACCOUNT_SPACE: usize = 8 + 32 + 4 + 1; // discriminator + owner + count + bump
+pub const REFERRER_ACCOUNT_SPACE: usize = 8 + 32 + 8 + 8 + 4 + 1; // discriminator + referrer + earnings + count + bump
+pub const MAX_LEADERBOARD_ENTRIES: usize = 20;
+pub const LEADERBOARD_ACCOUNT_SPACE: usize = 8 + 4 + (32 + 4) * MAX_LEADERBOARD_ENTRIES + 1; // discriminator + entries + bump
+pub const DEFAULT_MAX_NAMES_PER_OWNER: u32 = 100;
+pub const DEFAULT_MAX_DEADLINE_WINDOW_SECONDS: i64 = 60;
+pub const DEFAULT_MAX_SIGNATURE_VALIDITY_SECONDS: i64 = 24 * 60 * 60;
+pub const LEASE_RECORD_ACCOUNT_SPACE: usize = 8 + 32 + 32 + 8 + 8 + 1; // discriminator + owners + timestamps + bump
+pub const SUB_REGISTRY_ACCOUNT_SPACE: usize = 8 + 4 + MAX_NAME_LEN + 32 + 32 + 1 + 1; // discriminator + max name + program + owner + enabled + bump
+pub const APPROVED_SUB_REGISTRY_PROGRAM_ACCOUNT_SPACE: usize = 8 + 32 + 1; // discriminator + program + bump
+pub const ESCROW_ACCOUNT_SPACE: usize = 8 + 4 + MAX_NAME_LEN + 32 + 33 + 8 + 8 + 8 + 1; // discriminator + max name + parties + price + timestamps + bump
+pub const MAX_NEAR_ACCOUNT_LEN: usize = 64;
+pub const LEGACY_REGISTRY_CONFIG_SPACE: usize = 8 + 109; // discriminator + original RegistryConfig fields
+pub const NAME_EXPIRY_ACCOUNT_SPACE: usize = 8 + 4 + MAX_NAME_LEN + 8 + 1; // discriminator + max name + expires_at + bump
+pub const NOTIFY_EXPIRING_ACCOUNT_SPACE: usize = 8 + 4 + MAX_NAME_LEN + 32 + 8 + 8 + 1; // discriminator + max name + owner + timestamps + bump
+pub const MAX_REGISTRATION_PERIOD_SECONDS: i64 = 10 * 365 * 24 * 60 * 60; // 10 years
+pub const DEFAULT_EXPIRY_WARNING_WINDOW_SECONDS: i64 = 7 * 24 * 60 * 60; // 7 days
+pub const EXPIRY_WARNING_REWARD_LAMPORTS: u64 = 10_000;
+pub const COMMITMENT_CONFIG_ACCOUNT_SPACE: usize = 8 + 8 + 8 + 1 + 8 + 8; // discriminator + reveal bounds + bump + window + legacy cutoff
+pub const LEGACY_COMMITMENT_CONFIG_SPACE: usize = 8 + 8 + 8 + 1; // CommitmentConfig before the slot window was appended
+pub const DEFAULT_SLOT_COMMITMENT_WINDOW: u64 = 150; // about a minute of slots
+pub const BRIDGE_LOCK_ACCOUNT_SPACE: usize =
+    8 + 4 + MAX_NAME_LEN + 32 + 4 + MAX_NEAR_ACCOUNT_LEN + 8 + 8 + 1 + 1; // discriminator + max name + owner + max near owner + locked_at + nonce + bump + claimed
+// Longer than the NEAR contract's BRIDGE_CLAIM_WINDOW_SECONDS, so an unclaimed
+// lock can no longer be claimed on NEAR by the time its owner takes it back
+pub const BRIDGE_UNLOCK_TIMEOUT_SECONDS: i64 = 8 * 24 * 60 * 60; // 8 days
+pub const BRIDGE_STATE_ACCOUNT_SPACE: usize = 8 + 8 + 1; // discriminator + next nonce + bump
+pub const RELAYER_BOND_LOCK_SECONDS: i64 = 7 * 24 * 60 * 60; // 7 days
+pub const OWNERSHIP_PROOF_ACCOUNT_SPACE: usize = 8 + 109 + MAX_NAME_LEN; // discriminator + base + max name
+pub const PROOF_VALIDITY_SECONDS: i64 = 600; // 10 minutes
+pub const SECP256K1_OWNER_ACCOUNT_SPACE: usize = 8 + 25 + MAX_NAME_LEN; // discriminator + base + max name
+pub const AUCTION_ACCOUNT_SPACE: usize = 8 + 78 + MAX_NAME_LEN; // discriminator + base + max name
+// Slots a winning bidder has after end_slot to settle before the auction reopens
+pub const AUCTION_SETTLE_WINDOW_SLOTS: u64 = 216_000; // about a day
+pub const NAME_STATS_ACCOUNT_SPACE: usize = 8 + 21 + MAX_NAME_LEN; // discriminator + base + max name
+pub const DEFAULT_TTL_SECONDS: u32 = 300;
+// Indices into RegistryConfig.relayer_allowlist_per_instruction
+pub const RELAYER_GATE_REGISTER_NAME: usize = 0;
+pub const RELAYER_GATE_REGISTER_NAME_WITH_TOKEN: usize = 1;
+pub const RELAYER_GATE_REGISTER_NAME_WITH_SIG: usize = 2;
+pub const RELAYER_GATE_REGISTER_NAME_WITH_SIG_TOKEN: usize = 3;
+// Compute units register_name_with_signature_token needs left when its handler starts
+pub const MIN_REQUIRED_CU: u64 = 100_000;
+pub const DEFAULT_MAX_TTL_SECONDS: u32 = 86_400;
+pub const MAX_PROXY_DEPTH: u8 = 5;
+pub const MAX_RELAYER_NAME_LEN: usize = 64;
+pub const MAX_RELAYER_URL_LEN: usize = 128;
+pub const DEFAULT_MAX_RELAYER_VIOLATIONS: u8 = 5;
+// A relayer's violation count starts over once this long passes without a new one
+pub const RELAYER_VIOLATION_DECAY_SECONDS: i64 = 7 * 24 * 60 * 60;
+pub const RELAYER_VIOLATION_ACCOUNT_SPACE: usize = 8 + 32 + 4 + 9 + 1 + 8;
+pub const LEGACY_RELAYER_VIOLATION_SPACE: usize = 8 + 32 + 4 + 9 + 1; // RelayerViolation before last_violation_at was appended
+// NameAcl permission bits
+pub const ACL_CAN_SET_RESOLVED: u8 = 0x01;
+pub const ACL_CAN_SET_TEXT_RECORD: u8 = 0x02;
+pub const ACL_CAN_SET_CONTENT_HASH: u8 = 0x04;
+pub const RELAYER_ENTRY_ACCOUNT_SPACE: usize = 8 + 42 + MAX_RELAYER_NAME_LEN + MAX_RELAYER_URL_LEN; // discriminator + base + max name + max url
+pub const LEGACY_RELAYER_ENTRY_SPACE: usize = 8 + 32 + 1; // RelayerEntry before the metadata fields were appended
 
 #[allow(deprecated)]
 #[program]
@@ -42,8 +168,16 @@ pub mod nominal_registry {
         config.treasury = ctx.accounts.treasury.key();
         config.registration_fee = registration_fee;
         config.referrer_bps = referrer_bps;
-        config.require_allowlisted_relayer = false;
+        config.legacy_relayer_allowlist = false;
         config.bump = ctx.bumps.config;
+        config.set_appended_defaults();
+
+        let program_meta = &mut ctx.accounts.program_meta;
+        program_meta.version = PROGRAM_VERSION;
+        program_meta.upgrade_authority = ctx.accounts.program_data.upgrade_authority_address.unwrap_or_default();
+        program_meta.deployed_at = Clock::get()?.unix_timestamp;
+        program_meta.bump = ctx.bumps.program_meta;
+        program_meta.domain_separator = DOMAIN_SEPARATOR;
 
         msg!("RegistryInitialized: admin={}, treasury={}, fee={}, referrer_bps={}",
              config.admin, config.treasury, registration_fee, referrer_bps);
@@ -56,6 +190,7 @@ pub mod nominal_registry {
         new_fee: u64,
     ) -> Result<()> {
         let config = &mut ctx.accounts.config;
+        require!(new_fee >= config.min_sol_fee && new_fee <= config.max_sol_fee, ErrorCode::SolFeeOutOfBounds);
         config.registration_fee = new_fee;
 
         msg!("RegistrationFeeSet: new_fee={}", new_fee);
@@ -67,8 +202,19 @@ pub mod nominal_registry {
         amount: u64,
         enabled: bool,
     ) -> Result<()> {
+        let config = &ctx.accounts.config;
+        require!(amount >= config.min_token_fee && amount <= config.max_token_fee, ErrorCode::TokenFeeOutOfBounds);
+
+        let mint = ctx.accounts.mint.key();
+        let mint_registry = &mut ctx.accounts.mint_registry;
+        if !mint_registry.mints.contains(&mint) {
+            require!(mint_registry.mints.len() < MAX_SUPPORTED_MINTS, ErrorCode::MintRegistryFull);
+            mint_registry.mints.push(mint);
+        }
+        mint_registry.bump = ctx.bumps.mint_registry;
+
         let token_fee = &mut ctx.accounts.token_fee;
-        token_fee.mint = ctx.accounts.mint.key();
+        token_fee.mint = mint;
         token_fee.amount = amount;
         token_fee.enabled = enabled;
         token_fee.bump = ctx.bumps.token_fee;
@@ -78,6 +224,197 @@ pub mod nominal_registry {
         Ok(())
     }
 
+    pub fn set_token22_fee(
+        ctx: Context<SetToken22Fee>,
+        amount: u64,
+        enabled: bool,
+    ) -> Result<()> {
+        let config = &ctx.accounts.config;
+        require!(amount >= config.min_token_fee && amount <= config.max_token_fee, ErrorCode::TokenFeeOutOfBounds);
+
+        let mint = ctx.accounts.mint.key();
+        let mint_registry = &mut ctx.accounts.mint_registry;
+        if !mint_registry.mints.contains(&mint) {
+            require!(mint_registry.mints.len() < MAX_SUPPORTED_MINTS, ErrorCode::MintRegistryFull);
+            mint_registry.mints.push(mint);
+        }
+        mint_registry.bump = ctx.bumps.mint_registry;
+
+        let token22_fee = &mut ctx.accounts.token22_fee;
+        token22_fee.mint = mint;
+        token22_fee.amount = amount;
+        token22_fee.enabled = enabled;
+        token22_fee.bump = ctx.bumps.token22_fee;
+
+        msg!("Token22FeeSet: mint={}, amount={}, enabled={}",
+             token22_fee.mint, amount, enabled);
+        Ok(())
+    }
+
+    pub fn remove_token_fee(
+        ctx: Context<RemoveTokenFee>,
+        mint: Pubkey,
+    ) -> Result<()> {
+        require!(ctx.accounts.token_fee.mint == mint, ErrorCode::TokenNotEnabled);
+        ctx.accounts.mint_registry.mints.retain(|m| *m != mint);
+
+        msg!("TokenFeeRemoved: mint={}", mint);
+        Ok(())
+    }
+
+    pub fn remove_token22_fee(
+        ctx: Context<RemoveToken22Fee>,
+        mint: Pubkey,
+    ) -> Result<()> {
+        require!(ctx.accounts.token22_fee.mint == mint, ErrorCode::TokenNotEnabled);
+        ctx.accounts.mint_registry.mints.retain(|m| *m != mint);
+
+        msg!("TokenFeeRemoved: mint={}", mint);
+        Ok(())
+    }
+
+    pub fn set_treasury_token_account(
+        ctx: Context<SetTreasuryTokenAccount>,
+        mint: Pubkey,
+        token_account: Pubkey,
+    ) -> Result<()> {
+        require!(token_account != Pubkey::default(), ErrorCode::InvalidTreasuryTokenAccount);
+
+        let treasury_ta = &mut ctx.accounts.treasury_ta;
+        treasury_ta.mint = mint;
+        treasury_ta.token_account = token_account;
+        treasury_ta.bump = ctx.bumps.treasury_ta;
+
+        msg!("TreasuryTokenAccountSet: mint={}, token_account={}", mint, token_account);
+        Ok(())
+    }
+
+    pub fn set_character_set(
+        ctx: Context<SetCharacterSet>,
+        allow_uppercase: bool,
+        allow_underscore: bool,
+        allow_dot: bool,
+    ) -> Result<()> {
+        let charset = &mut ctx.accounts.charset;
+        charset.allow_uppercase = allow_uppercase;
+        charset.allow_underscore = allow_underscore;
+        charset.allow_dot = allow_dot;
+        charset.bump = ctx.bumps.charset;
+
+        msg!("CharacterSetUpdated: allow_uppercase={}, allow_underscore={}, allow_dot={}",
+             allow_uppercase, allow_underscore, allow_dot);
+        Ok(())
+    }
+
+    pub fn set_nft_discount(
+        ctx: Context<SetNftDiscount>,
+        collection_mint: Pubkey,
+        discount_bps: u16,
+        enabled: bool,
+    ) -> Result<()> {
+        require!(discount_bps <= 10000, ErrorCode::InvalidDiscountBps);
+
+        let nft_discount = &mut ctx.accounts.nft_discount;
+        nft_discount.collection_mint = collection_mint;
+        nft_discount.discount_bps = discount_bps;
+        nft_discount.enabled = enabled;
+        nft_discount.bump = ctx.bumps.nft_discount;
+
+        msg!("NftDiscountUpdated: collection={}, discount_bps={}, enabled={}",
+             collection_mint, discount_bps, enabled);
+        Ok(())
+    }
+
+    // Hands all registration to an SPL Governance realm. While the gate is set,
+    // names can only be registered through register_name_via_governance. Calling
+    // it again points the gate at another realm or mint.
+    pub fn set_governance_gate(
+        ctx: Context<SetGovernanceGate>,
+        realm: Pubkey,
+        governing_token_mint: Pubkey,
+    ) -> Result<()> {
+        let gate = &mut ctx.accounts.gov_gate;
+        gate.realm = realm;
+        gate.governing_token_mint = governing_token_mint;
+        gate.proposal_type = SplVoteType::SingleChoice;
+        gate.bump = ctx.bumps.gov_gate;
+        ctx.accounts.config.governance_gated = true;
+
+        msg!("GovernanceGateSet: realm={}, governing_token_mint={}", realm, governing_token_mint);
+        Ok(())
+    }
+
+    // Reopens the other registration paths and closes the gate account
+    pub fn clear_governance_gate(ctx: Context<ClearGovernanceGate>) -> Result<()> {
+        ctx.accounts.config.governance_gated = false;
+
+        msg!("GovernanceGateCleared: realm={}", ctx.accounts.gov_gate.realm);
+        Ok(())
+    }
+
+    // Permissionless: creates the shared stats account registrations may write to
+    pub fn initialize_stats(ctx: Context<InitializeStats>) -> Result<()> {
+        ctx.accounts.stats.bump = ctx.bumps.stats;
+        Ok(())
+    }
+
+    pub fn get_stats_detailed(ctx: Context<GetStatsDetailed>) -> Result<RegistryStatsView> {
+        let stats = &ctx.accounts.stats;
+        Ok(RegistryStatsView {
+            total_registrations: stats.total_registrations,
+            fee_histogram: stats.fee_histogram,
+            name_length_histogram: stats.name_length_histogram.to_vec(),
+            total_fees_collected_usd_cents: stats.total_fees_collected_usd_cents,
+        })
+    }
+
+    pub fn create_referrer_account(ctx: Context<CreateReferrerAccount>) -> Result<()> {
+        let referrer_account = &mut ctx.accounts.referrer_account;
+        referrer_account.referrer = ctx.accounts.referrer.key();
+        referrer_account.total_earned_sol = 0;
+        referrer_account.total_earned_tokens = 0;
+        referrer_account.referral_count = 0;
+        referrer_account.bump = ctx.bumps.referrer_account;
+
+        msg!("ReferrerAccountCreated: referrer={}", referrer_account.referrer);
+        Ok(())
+    }
+
+    pub fn get_referrer_stats(ctx: Context<GetReferrerStats>, _referrer: Pubkey) -> Result<ReferrerAccount> {
+        Ok((*ctx.accounts.referrer_account).clone())
+    }
+
+    // Merges the ReferrerAccounts passed as remaining accounts into the
+    // leaderboard and emits the top `top_n` entries
+    pub fn referrer_leaderboard<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ReferrerLeaderboard<'info>>,
+        top_n: u8,
+    ) -> Result<()> {
+        let leaderboard = &mut ctx.accounts.leaderboard;
+        leaderboard.bump = ctx.bumps.leaderboard;
+
+        for info in ctx.remaining_accounts.iter() {
+            let referrer_account = Account::<ReferrerAccount>::try_from(info)?;
+            leaderboard.entries.retain(|entry| entry.referrer != referrer_account.referrer);
+            leaderboard.entries.push(LeaderboardEntry {
+                referrer: referrer_account.referrer,
+                referral_count: referrer_account.referral_count,
+            });
+        }
+        leaderboard.entries.sort_by(|a, b| b.referral_count.cmp(&a.referral_count));
+        leaderboard.entries.truncate(MAX_LEADERBOARD_ENTRIES);
+
+        for (rank, entry) in leaderboard.entries.iter().take(top_n as usize).enumerate() {
+            msg!("ReferrerLeaderboard: rank={}, referrer={}, referral_count={}",
+                 rank + 1, entry.referrer, entry.referral_count);
+        }
+        Ok(())
+    }
+
+    pub fn get_supported_mints(ctx: Context<GetSupportedMints>) -> Result<Vec<Pubkey>> {
+        Ok(ctx.accounts.mint_registry.mints.clone())
+    }
+
     pub fn set_treasury(
         ctx: Context<SetTreasury>,
         new_treasury: Pubkey,
@@ -98,763 +435,7013 @@ pub mod nominal_registry {
         require!(bps <= 10_000, ErrorCode::InvalidReferrerBps);
 
         let config = &mut ctx.accounts.config;
+        require!(bps >= config.min_referrer_bps, ErrorCode::ReferrerBpsBelowFloor);
         config.referrer_bps = bps;
 
         msg!("ReferrerBpsSet: bps={}", bps);
         Ok(())
     }
 
+    pub fn set_min_referrer_bps(
+        ctx: Context<SetMinReferrerBps>,
+        min_bps: u16,
+    ) -> Result<()> {
+        require!(min_bps <= 10_000, ErrorCode::InvalidReferrerBps);
+
+        let config = &mut ctx.accounts.config;
+        config.min_referrer_bps = min_bps;
+
+        msg!("MinReferrerBpsSet: min_bps={}", min_bps);
+        Ok(())
+    }
+
+    pub fn set_treasury_split(
+        ctx: Context<SetTreasurySplit>,
+        recipients: Vec<TreasuryRecipient>,
+    ) -> Result<()> {
+        require!(
+            !recipients.is_empty() && recipients.len() <= MAX_TREASURY_RECIPIENTS,
+            ErrorCode::InvalidTreasuryRecipients
+        );
+        let total_bps: u32 = recipients.iter().map(|r| r.bps as u32).sum();
+        require!(total_bps == 10_000, ErrorCode::TreasurySplitMismatch);
+        for entry in recipients.iter() {
+            require!(entry.recipient != Pubkey::default(), ErrorCode::InvalidTreasuryAddress);
+        }
+
+        let split = &mut ctx.accounts.treasury_split;
+        split.recipients = [TreasuryRecipient::default(); MAX_TREASURY_RECIPIENTS];
+        split.recipients[..recipients.len()].copy_from_slice(&recipients);
+        split.count = recipients.len() as u8;
+        split.bump = ctx.bumps.treasury_split;
+
+        msg!("TreasurySplitSet: count={}", split.count);
+        Ok(())
+    }
+
     pub fn add_relayer(
         ctx: Context<AddRelayer>,
         relayer: Pubkey,
+        name: String,
+        endpoint_url: String,
     ) -> Result<()> {
+        require!(
+            ctx.accounts.relayer_bond.amount >= ctx.accounts.config.min_relayer_bond,
+            ErrorCode::InsufficientRelayerBond
+        );
+        validate_relayer_metadata(&name, &endpoint_url)?;
+
         let entry = &mut ctx.accounts.relayer_entry;
         entry.relayer = relayer;
+        entry.name = name;
+        entry.endpoint_url = endpoint_url;
+        entry.active = true;
         entry.bump = ctx.bumps.relayer_entry;
-        msg!("RelayerAdded: relayer={}", relayer);
+        msg!("RelayerAdded: relayer={}, bond={}, name={}, endpoint_url={}",
+             relayer, ctx.accounts.relayer_bond.amount, entry.name, entry.endpoint_url);
         Ok(())
     }
 
-    pub fn remove_relayer(
-        ctx: Context<RemoveRelayer>,
-        relayer: Pubkey,
+    pub fn update_relayer_metadata(
+        ctx: Context<UpdateRelayerMetadata>,
+        name: String,
+        endpoint_url: String,
     ) -> Result<()> {
-        require!(ctx.accounts.relayer_entry.relayer == relayer, ErrorCode::Unauthorized);
-        msg!("RelayerRemoved: relayer={}", relayer);
+        validate_relayer_metadata(&name, &endpoint_url)?;
+
+        let entry = &mut ctx.accounts.relayer_entry;
+        entry.name = name;
+        entry.endpoint_url = endpoint_url;
+
+        msg!("RelayerMetadataUpdated: relayer={}, name={}, endpoint_url={}",
+             entry.relayer, entry.name, entry.endpoint_url);
         Ok(())
     }
 
-    pub fn transfer_admin(
-        ctx: Context<TransferAdmin>,
-        new_admin: Pubkey,
+    pub fn set_relayer_active(
+        ctx: Context<SetRelayerActive>,
+        relayer: Pubkey,
+        active: bool,
     ) -> Result<()> {
-        let config = &mut ctx.accounts.config;
-        config.pending_admin = Some(new_admin);
+        let entry = &mut ctx.accounts.relayer_entry;
+        require!(entry.relayer == relayer, ErrorCode::Unauthorized);
+        entry.active = active;
 
-        msg!("AdminTransferInitiated: new_admin={}", new_admin);
+        msg!("RelayerActiveSet: relayer={}, active={}", relayer, active);
         Ok(())
     }
 
-    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
-        let config = &mut ctx.accounts.config;
-        let new_admin = config.pending_admin.unwrap();
-        require!(ctx.accounts.new_admin.key() == new_admin, ErrorCode::Unauthorized);
+    // Grows a RelayerEntry created before name, endpoint_url and active were
+    // appended. The relayer was already allowlisted, so it comes back active
+    // with empty metadata for update_relayer_metadata to fill in.
+    pub fn migrate_relayer_entry(
+        ctx: Context<MigrateRelayerEntry>,
+        relayer: Pubkey,
+    ) -> Result<()> {
+        let entry_info = ctx.accounts.relayer_entry.to_account_info();
+        require!(entry_info.data_len() == LEGACY_RELAYER_ENTRY_SPACE, ErrorCode::AlreadyMigrated);
 
-        config.admin = new_admin;
-        config.pending_admin = None;
+        let legacy = {
+            let data = entry_info.try_borrow_data()?;
+            require!(data[..8] == *RelayerEntry::DISCRIMINATOR, ErrorCode::InvalidConfigAccount);
+            LegacyRelayerEntry::deserialize(&mut &data[8..])
+                .map_err(|_| error!(ErrorCode::InvalidConfigAccount))?
+        };
+        require!(legacy.relayer == relayer, ErrorCode::Unauthorized);
 
-        msg!("AdminTransferAccepted: new_admin={}", new_admin);
+        let rent_due = Rent::get()?
+            .minimum_balance(RELAYER_ENTRY_ACCOUNT_SPACE)
+            .saturating_sub(entry_info.lamports());
+        if rent_due > 0 {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.admin.to_account_info(),
+                to: entry_info.clone(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            system_program::transfer(cpi_ctx, rent_due)?;
+        }
+        entry_info.resize(RELAYER_ENTRY_ACCOUNT_SPACE)?;
+
+        let entry = RelayerEntry {
+            relayer: legacy.relayer,
+            bump: legacy.bump,
+            name: String::new(),
+            endpoint_url: String::new(),
+            active: true,
+        };
+        entry.try_serialize(&mut &mut entry_info.try_borrow_mut_data()?[..])?;
+
+        msg!("RelayerEntryMigrated: relayer={}", relayer);
         Ok(())
     }
 
-    // ========================================
-    // USER INSTRUCTIONS
-    // ========================================
-
-    pub fn register_name(
-        ctx: Context<RegisterName>,
-        name: String,
+    pub fn set_min_relayer_bond(
+        ctx: Context<SetMinRelayerBond>,
+        min_bond: u64,
     ) -> Result<()> {
-        validate_name(&name)?;
+        let config = &mut ctx.accounts.config;
+        config.min_relayer_bond = min_bond;
 
-        let config = &ctx.accounts.config;
-        let name_record = &mut ctx.accounts.name_record;
+        msg!("MinRelayerBondSet: min_bond={}", min_bond);
+        Ok(())
+    }
 
-        // Set record data
-        name_record.name = name.clone();
-        name_record.owner = ctx.accounts.user.key();
-        name_record.resolved = ctx.accounts.user.key();
-        name_record.updated_at = Clock::get()?.unix_timestamp;
-        name_record.bump = ctx.bumps.name_record;
+    pub fn stake_relayer(
+        ctx: Context<StakeRelayer>,
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidBondAmount);
 
-        // Transfer SOL to treasury (CPI)
         {
             let cpi_accounts = system_program::Transfer {
-                from: ctx.accounts.user.to_account_info(),
-                to: ctx.accounts.treasury.to_account_info(),
+                from: ctx.accounts.relayer.to_account_info(),
+                to: ctx.accounts.relayer_bond.to_account_info(),
             };
             let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
-            system_program::transfer(cpi_ctx, config.registration_fee)?;
+            system_program::transfer(cpi_ctx, amount)?;
         }
 
-        // Set as primary name if user doesn't have one
-        if ctx.accounts.primary_name.owner == Pubkey::default() {
-            let primary = &mut ctx.accounts.primary_name;
-            primary.owner = ctx.accounts.user.key();
-            primary.name = name.clone();
-            primary.bump = ctx.bumps.primary_name;
+        let bond = &mut ctx.accounts.relayer_bond;
+        bond.relayer = ctx.accounts.relayer.key();
+        bond.amount = bond.amount.checked_add(amount).ok_or(ErrorCode::InvalidBondAmount)?;
+        bond.locked_until = Clock::get()?.unix_timestamp + RELAYER_BOND_LOCK_SECONDS;
+        bond.bump = ctx.bumps.relayer_bond;
 
-            msg!("PrimaryNameSet: owner={}, name={}", primary.owner, name);
-        }
+        msg!("RelayerStaked: relayer={}, amount={}, total={}, locked_until={}",
+             bond.relayer, amount, bond.amount, bond.locked_until);
+        Ok(())
+    }
 
-        msg!("NameRegistered: name={}, owner={}, resolved={}",
-             name, name_record.owner, name_record.resolved);
-        msg!("FeePaid: name={}, payer={}, amount={}, currency=SOL, referrer=None",
-             name, ctx.accounts.user.key(), config.registration_fee);
+    pub fn unstake_relayer(ctx: Context<UnstakeRelayer>) -> Result<()> {
+        let bond = &ctx.accounts.relayer_bond;
+        require!(bond.locked_until < Clock::get()?.unix_timestamp, ErrorCode::BondLocked);
+        // Relayer must leave the allowlist before withdrawing its bond
+        require!(ctx.accounts.relayer_entry.data_is_empty(), ErrorCode::RelayerStillActive);
 
+        msg!("RelayerUnstaked: relayer={}, amount={}", bond.relayer, bond.amount);
         Ok(())
     }
 
-    pub fn register_name_with_token(
-        ctx: Context<RegisterNameWithToken>,
-        name: String,
+    pub fn slash_relayer(
+        ctx: Context<SlashRelayer>,
+        relayer: Pubkey,
+        reason: String,
     ) -> Result<()> {
-        validate_name(&name)?;
-
-        let token_fee = &ctx.accounts.token_fee;
-        require!(token_fee.enabled, ErrorCode::TokenNotEnabled);
-
-        let name_record = &mut ctx.accounts.name_record;
-        name_record.name = name.clone();
-        name_record.owner = ctx.accounts.user.key();
-        name_record.resolved = ctx.accounts.user.key();
-        name_record.updated_at = Clock::get()?.unix_timestamp;
-        name_record.bump = ctx.bumps.name_record;
-
-        // Transfer tokens to treasury
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.user_token_account.to_account_info(),
-            to: ctx.accounts.treasury_token_account.to_account_info(),
-            authority: ctx.accounts.user.to_account_info(),
-        };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        token::transfer(cpi_ctx, token_fee.amount)?;
+        require!(ctx.accounts.relayer_bond.relayer == relayer, ErrorCode::Unauthorized);
 
-        // Set as primary name if user doesn't have one
-        if ctx.accounts.primary_name.owner == Pubkey::default() {
-            let primary = &mut ctx.accounts.primary_name;
-            primary.owner = ctx.accounts.user.key();
-            primary.name = name.clone();
-            primary.bump = ctx.bumps.primary_name;
+        msg!("RelayerSlashed: relayer={}, amount={}, reason={}",
+             relayer, ctx.accounts.relayer_bond.amount, reason);
+        msg!("RelayerRemoved: relayer={}", relayer);
+        Ok(())
+    }
 
-            msg!("PrimaryNameSet: owner={}, name={}", primary.owner, name);
-        }
+    pub fn set_max_relayer_violations(
+        ctx: Context<SetMaxRelayerViolations>,
+        max_violations: u8,
+    ) -> Result<()> {
+        require!(max_violations > 0, ErrorCode::InvalidViolationLimit);
 
-        msg!("NameRegistered: name={}, owner={}, resolved={}",
-             name, name_record.owner, name_record.resolved);
-        msg!("FeePaid: name={}, payer={}, amount={}, currency={}, referrer=None",
-             name, ctx.accounts.user.key(), token_fee.amount, token_fee.mint);
+        let config = &mut ctx.accounts.config;
+        config.max_relayer_violations = max_violations;
 
+        msg!("MaxRelayerViolationsSet: max_violations={}", max_violations);
         Ok(())
     }
 
-    pub fn register_name_with_signature(
-        ctx: Context<RegisterNameWithSignature>,
-        params: RegisterWithSigParams,
-        signature: Vec<u8>,
+    pub fn reset_relayer_violations(
+        ctx: Context<ResetRelayerViolations>,
+        relayer: Pubkey,
     ) -> Result<()> {
-        validate_name(&params.name)?;
-
-        // Verify deadline
-        require!(Clock::get()?.unix_timestamp <= params.deadline, ErrorCode::DeadlineExpired);
-
-        // Verify relayer
-        require!(ctx.accounts.relayer.key() == params.relayer, ErrorCode::Unauthorized);
+        // Seeds already bind the account to `relayer`
+        let violation = &mut ctx.accounts.relayer_violation;
+        violation.violations = 0;
+        violation.slashed_at = None;
 
-    // Enforce relayer allowlist (must have relayer_entry account present)
-    let config = &ctx.accounts.config; // currently unused for gating beyond referrer bps / fee
-    let relayer_entry = &ctx.accounts.relayer_entry;
-    require!(relayer_entry.relayer == ctx.accounts.relayer.key(), ErrorCode::RelayerNotAllowed);
+        msg!("RelayerViolationsReset: relayer={}", relayer);
+        Ok(())
+    }
 
-        // Verify signature (simplified - in production use proper Ed25519 verification)
-        // For now, we'll trust the transaction signature mechanism
-        require!(signature.len() == 64, ErrorCode::InvalidSignature);
+    // Registrations with a bad signature fail and roll back, so violations are
+    // reported by the admin from the failed transactions it observes. Reaching
+    // max_relayer_violations within the decay window removes the relayer from
+    // the allowlist.
+    pub fn report_relayer_violation(
+        ctx: Context<ReportRelayerViolation>,
+        relayer: Pubkey,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let violation = &mut ctx.accounts.relayer_violation;
+        violation.relayer = relayer;
+        violation.bump = ctx.bumps.relayer_violation;
+        if now.saturating_sub(violation.last_violation_at) >= RELAYER_VIOLATION_DECAY_SECONDS {
+            violation.violations = 0;
+        }
+        violation.violations = violation.violations.saturating_add(1);
+        violation.last_violation_at = now;
 
-        let name_record = &mut ctx.accounts.name_record;
-        name_record.name = params.name.clone();
-        name_record.owner = params.owner;
-        name_record.resolved = params.owner;
-        name_record.updated_at = Clock::get()?.unix_timestamp;
-        name_record.bump = ctx.bumps.name_record;
+        msg!("RelayerViolation: relayer={}, violations={}", relayer, violation.violations);
 
-        // Enforce SOL-only path here
-        require!(params.currency.is_none(), ErrorCode::TokenNotEnabled);
+        if violation.violations >= ctx.accounts.config.max_relayer_violations as u32 {
+            if let Some(relayer_entry) = ctx.accounts.relayer_entry.as_ref() {
+                relayer_entry.close(ctx.accounts.treasury.to_account_info())?;
+                msg!("RelayerBlacklisted: relayer={}, violations={}", relayer, violation.violations);
+            }
+        }
+        Ok(())
+    }
 
-        // SOL payment
-        require!(ctx.accounts.relayer.lamports() >= config.registration_fee, ErrorCode::InsufficientTokenBalance);
+    // Appends last_violation_at to a RelayerViolation created before it existed.
+    // Existing violations start decaying from the migration.
+    pub fn migrate_relayer_violation(
+        ctx: Context<MigrateRelayerViolation>,
+        relayer: Pubkey,
+    ) -> Result<()> {
+        let violation_info = ctx.accounts.relayer_violation.to_account_info();
+        require!(violation_info.data_len() == LEGACY_RELAYER_VIOLATION_SPACE, ErrorCode::AlreadyMigrated);
 
-        let referrer_amount = (config.registration_fee as u128)
-            .checked_mul(config.referrer_bps as u128)
-            .unwrap()
-            .checked_div(10_000)
-            .unwrap() as u64;
-        let treasury_amount = config.registration_fee - referrer_amount;
+        let legacy = {
+            let data = violation_info.try_borrow_data()?;
+            require!(data[..8] == *RelayerViolation::DISCRIMINATOR, ErrorCode::InvalidConfigAccount);
+            LegacyRelayerViolation::deserialize(&mut &data[8..])
+                .map_err(|_| error!(ErrorCode::InvalidConfigAccount))?
+        };
 
-        // Transfer to treasury (CPI)
-        {
+        let rent_due = Rent::get()?
+            .minimum_balance(RELAYER_VIOLATION_ACCOUNT_SPACE)
+            .saturating_sub(violation_info.lamports());
+        if rent_due > 0 {
             let cpi_accounts = system_program::Transfer {
-                from: ctx.accounts.relayer.to_account_info(),
-                to: ctx.accounts.treasury.to_account_info(),
+                from: ctx.accounts.admin.to_account_info(),
+                to: violation_info.clone(),
             };
             let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
-            system_program::transfer(cpi_ctx, treasury_amount)?;
+            system_program::transfer(cpi_ctx, rent_due)?;
         }
+        violation_info.resize(RELAYER_VIOLATION_ACCOUNT_SPACE)?;
 
-        msg!("FeePaid: name={}, payer={}, amount={}, currency=SOL, referrer={}, ref_amount={}",
-             params.name, ctx.accounts.relayer.key(), config.registration_fee,
-             ctx.accounts.relayer.key(), referrer_amount);
+        let violation = RelayerViolation {
+            relayer,
+            violations: legacy.violations,
+            slashed_at: legacy.slashed_at,
+            bump: legacy.bump,
+            last_violation_at: Clock::get()?.unix_timestamp,
+        };
+        violation.try_serialize(&mut &mut violation_info.try_borrow_mut_data()?[..])?;
 
-        // Set as primary name if owner doesn't have one
-        if ctx.accounts.primary_name.owner == Pubkey::default() {
-            let primary = &mut ctx.accounts.primary_name;
-            primary.owner = params.owner;
-            primary.name = params.name.clone();
-            primary.bump = ctx.bumps.primary_name;
+        msg!("RelayerViolationMigrated: relayer={}, violations={}", relayer, violation.violations);
+        Ok(())
+    }
 
-            msg!("PrimaryNameSet: owner={}, name={}", params.owner, params.name);
-        }
+    pub fn slash_relayer_manually(
+        ctx: Context<SlashRelayerManually>,
+        relayer: Pubkey,
+    ) -> Result<()> {
+        require!(ctx.accounts.relayer_bond.relayer == relayer, ErrorCode::Unauthorized);
 
-        msg!("NameRegistered: name={}, owner={}, resolved={}",
-             params.name, params.owner, params.owner);
+        let violation = &mut ctx.accounts.relayer_violation;
+        violation.relayer = relayer;
+        violation.slashed_at = Some(Clock::get()?.unix_timestamp);
 
+        msg!("RelayerSlashed: relayer={}, amount={}, violations={}",
+             relayer, ctx.accounts.relayer_bond.amount, violation.violations);
         Ok(())
     }
 
-    // Token signature registration kept in second function below (see after SOL version)
-
-    pub fn transfer_name(
-        ctx: Context<TransferName>,
-        name: String,
-        new_owner: Pubkey,
+    pub fn remove_relayer(
+        ctx: Context<RemoveRelayer>,
+        relayer: Pubkey,
     ) -> Result<()> {
-        let name_record = &mut ctx.accounts.name_record;
-        let old_owner = name_record.owner;
+        require!(ctx.accounts.relayer_entry.relayer == relayer, ErrorCode::Unauthorized);
+        msg!("RelayerRemoved: relayer={}", relayer);
+        Ok(())
+    }
 
-        name_record.owner = new_owner;
-        name_record.updated_at = Clock::get()?.unix_timestamp;
+    pub fn set_max_ttl_seconds(
+        ctx: Context<SetMaxTtlSeconds>,
+        max_ttl_seconds: u32,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.max_ttl_seconds = max_ttl_seconds;
 
-        msg!("NameTransferred: name={}, old_owner={}, new_owner={}",
-             name, old_owner, new_owner);
+        msg!("MaxTtlSet: max_ttl_seconds={}", max_ttl_seconds);
+        Ok(())
+    }
+
+    pub fn set_whitelist_phase(
+        ctx: Context<SetWhitelistPhase>,
+        active: bool,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.whitelist_phase = active;
+
+        msg!("WhitelistPhaseSet: active={}", active);
+        Ok(())
+    }
+
+    pub fn add_to_whitelist(
+        ctx: Context<AddToWhitelist>,
+        user: Pubkey,
+        max_registrations: u8,
+    ) -> Result<()> {
+        let whitelist = &mut ctx.accounts.whitelist;
+        whitelist.user = user;
+        whitelist.max_registrations = max_registrations;
+        whitelist.bump = ctx.bumps.whitelist;
+
+        msg!("WhitelistAdded: user={}, max_registrations={}", user, max_registrations);
+        Ok(())
+    }
+
+    pub fn remove_from_whitelist(
+        ctx: Context<RemoveFromWhitelist>,
+        user: Pubkey,
+    ) -> Result<()> {
+        require!(ctx.accounts.whitelist.user == user, ErrorCode::Unauthorized);
+        msg!("WhitelistRemoved: user={}", user);
+        Ok(())
+    }
+
+    pub fn set_locked_names_transferable(
+        ctx: Context<SetLockedNamesTransferable>,
+        transferable: bool,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.locked_names_transferable = transferable;
+
+        msg!("LockedNamesTransferableSet: transferable={}", transferable);
+        Ok(())
+    }
+
+    // When required, signature registrations must pass the relayer's active
+    // RelayerEntry as the first remaining account, and direct registrations
+    // must pass the signer's own RelayerEntry as `relayer_entry`.
+    // instruction_index is one of the RELAYER_GATE_* constants.
+    pub fn set_instruction_relayer_gate(
+        ctx: Context<SetInstructionRelayerGate>,
+        instruction_index: u8,
+        required: bool,
+    ) -> Result<()> {
+        let index = instruction_index as usize;
+        require!(index < 4, ErrorCode::InvalidInstructionIndex);
+
+        let config = &mut ctx.accounts.config;
+        config.relayer_allowlist_per_instruction[index] = required;
+
+        msg!("InstructionRelayerGateChanged: instruction_index={}, required={}", instruction_index, required);
+        Ok(())
+    }
+
+    pub fn get_instruction_relayer_gates(ctx: Context<GetConfig>) -> Result<[bool; 4]> {
+        Ok(ctx.accounts.config.relayer_allowlist_per_instruction)
+    }
+
+    pub fn set_token_fee_bounds(
+        ctx: Context<SetTokenFeeBounds>,
+        min: u64,
+        max: u64,
+    ) -> Result<()> {
+        require!(min <= max, ErrorCode::TokenFeeOutOfBounds);
+        let config = &mut ctx.accounts.config;
+        config.min_token_fee = min;
+        config.max_token_fee = max;
+
+        msg!("TokenFeeBoundsSet: min={}, max={}", min, max);
+        Ok(())
+    }
+
+    pub fn set_sol_fee_bounds(
+        ctx: Context<SetSolFeeBounds>,
+        min: u64,
+        max: u64,
+    ) -> Result<()> {
+        require!(min <= max, ErrorCode::SolFeeOutOfBounds);
+        let config = &mut ctx.accounts.config;
+        config.min_sol_fee = min;
+        config.max_sol_fee = max;
+
+        msg!("SolFeeBoundsSet: min={}, max={}", min, max);
+        Ok(())
+    }
+
+    // Signed messages may not carry a deadline more than `seconds` ahead of the
+    // current time, so a leaked signature stays usable only briefly
+    pub fn set_max_deadline_window(
+        ctx: Context<SetMaxDeadlineWindow>,
+        seconds: i64,
+    ) -> Result<()> {
+        require!(seconds > 0, ErrorCode::InvalidDeadlineWindow);
+
+        let config = &mut ctx.accounts.config;
+        config.max_deadline_window_seconds = seconds;
+
+        msg!("MaxDeadlineWindowSet: seconds={}", seconds);
+        Ok(())
+    }
+
+    // Priority fee floor for register_name; 0 turns the check off
+    pub fn set_min_cu_price(
+        ctx: Context<SetMinCuPrice>,
+        price: u64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.min_compute_unit_price = price;
+
+        msg!("MinComputeUnitPriceSet: price={}", price);
+        Ok(())
+    }
+
+    // Upper bound on how long a signed message stays usable, independent of the
+    // deadline window, so widening that window can't revive months-old signatures
+    pub fn set_max_signature_validity(
+        ctx: Context<SetMaxSignatureValidity>,
+        seconds: i64,
+    ) -> Result<()> {
+        require!(seconds > 0, ErrorCode::InvalidDeadlineWindow);
+
+        let config = &mut ctx.accounts.config;
+        config.max_signature_validity_seconds = seconds;
+
+        msg!("MaxSignatureValiditySet: seconds={}", seconds);
+        Ok(())
+    }
+
+    pub fn set_max_names_per_owner(
+        ctx: Context<SetMaxNamesPerOwner>,
+        max: u32,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.max_names_per_owner = max;
+
+        msg!("MaxNamesPerOwnerSet: max={}", max);
+        Ok(())
+    }
+
+    pub fn set_reset_resolved_on_transfer(
+        ctx: Context<SetResetResolvedOnTransfer>,
+        value: bool,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.reset_resolved_on_transfer = value;
+
+        msg!("ResetResolvedOnTransferSet: value={}", value);
+        Ok(())
+    }
+
+    pub fn set_allow_admin_override(
+        ctx: Context<SetAllowAdminOverride>,
+        allowed: bool,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.allow_admin_override = allowed;
 
+        msg!("AllowAdminOverrideSet: allowed={}", allowed);
         Ok(())
     }
 
-    pub fn set_resolved_address(
-        ctx: Context<SetResolvedAddress>,
+    pub fn admin_set_resolved(
+        ctx: Context<AdminSetResolved>,
         name: String,
         new_resolved: Pubkey,
     ) -> Result<()> {
+        require!(ctx.accounts.config.allow_admin_override, ErrorCode::AdminOverrideDisabled);
+
         let name_record = &mut ctx.accounts.name_record;
+        let old_resolved = name_record.resolved;
         name_record.resolved = new_resolved;
         name_record.updated_at = Clock::get()?.unix_timestamp;
 
-        msg!("ResolvedUpdated: name={}, owner={}, new_resolved={}",
-             name, name_record.owner, new_resolved);
+        msg!("AdminForcedResolutionUpdate: name={}, old_resolved={}, new_resolved={}, admin={}",
+             name, old_resolved, new_resolved, ctx.accounts.admin.key());
 
         Ok(())
     }
 
-    pub fn set_primary_name(
-        ctx: Context<SetPrimaryName>,
-        name: String,
+    pub fn withdraw_treasury_surplus(
+        ctx: Context<WithdrawTreasurySurplus>,
+        amount: u64,
     ) -> Result<()> {
-        // Verify user owns the name
-        let name_record = &ctx.accounts.name_record;
-        require!(name_record.owner == ctx.accounts.user.key(), ErrorCode::Unauthorized);
+        let config_info = ctx.accounts.config.to_account_info();
+        let rent_exempt_minimum = Rent::get()?.minimum_balance(config_info.data_len());
+        let surplus = config_info.lamports().saturating_sub(rent_exempt_minimum);
+        require!(amount > 0 && amount <= surplus, ErrorCode::InsufficientSurplus);
 
-        let primary = &mut ctx.accounts.primary_name;
-        primary.owner = ctx.accounts.user.key();
-        primary.name = name.clone();
-        primary.bump = ctx.bumps.primary_name;
+        // Config is owned by this program, so lamports can be moved directly
+        **config_info.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += amount;
 
-        msg!("PrimaryNameSet: owner={}, name={}", primary.owner, name);
+        msg!("TreasurySurplusWithdrawn: amount={}, treasury={}", amount, ctx.accounts.treasury.key());
+        Ok(())
+    }
+
+    pub fn get_config(ctx: Context<GetConfig>) -> Result<RegistryConfigView> {
+        let config = &ctx.accounts.config;
+        Ok(RegistryConfigView {
+            admin: config.admin,
+            pending_admin: config.pending_admin,
+            treasury: config.treasury,
+            registration_fee: config.registration_fee,
+            referrer_bps: config.referrer_bps,
+            min_referrer_bps: config.min_referrer_bps,
+            min_relayer_bond: config.min_relayer_bond,
+            relayer_allowlist_per_instruction: config.relayer_allowlist_per_instruction,
+            allow_admin_override: config.allow_admin_override,
+            whitelist_phase: config.whitelist_phase,
+            max_ttl_seconds: config.max_ttl_seconds,
+            locked_names_transferable: config.locked_names_transferable,
+            max_relayer_violations: config.max_relayer_violations,
+            reset_resolved_on_transfer: config.reset_resolved_on_transfer,
+            max_names_per_owner: config.max_names_per_owner,
+            min_token_fee: config.min_token_fee,
+            max_token_fee: config.max_token_fee,
+            min_sol_fee: config.min_sol_fee,
+            max_sol_fee: config.max_sol_fee,
+            max_deadline_window_seconds: config.max_deadline_window_seconds,
+            max_signature_validity_seconds: config.max_signature_validity_seconds,
+            min_compute_unit_price: config.min_compute_unit_price,
+            registration_period_seconds: config.registration_period_seconds,
+            expiry_warning_window_seconds: config.expiry_warning_window_seconds,
+            governance_gated: config.governance_gated,
+            min_name_len: MIN_NAME_LEN as u8,
+            max_name_len: MAX_NAME_LEN as u8,
+        })
+    }
 
+    pub fn update_program_meta(
+        ctx: Context<UpdateProgramMeta>,
+        new_version: [u8; 3],
+    ) -> Result<()> {
+        let program_meta = &mut ctx.accounts.program_meta;
+        if program_meta.deployed_at == 0 {
+            program_meta.deployed_at = Clock::get()?.unix_timestamp;
+            program_meta.bump = ctx.bumps.program_meta;
+        }
+        // Read from the loader so the stored value can't drift from the real authority
+        program_meta.upgrade_authority = ctx.accounts.program_data.upgrade_authority_address.unwrap_or_default();
+        program_meta.version = new_version;
+        program_meta.domain_separator = DOMAIN_SEPARATOR;
+
+        msg!("ProgramMetaUpdated: version={}.{}.{}, upgrade_authority={}",
+             new_version[0], new_version[1], new_version[2], program_meta.upgrade_authority);
         Ok(())
     }
 
-    pub fn register_name_with_signature_token(
-        ctx: Context<RegisterNameWithSignatureToken>,
-        params: RegisterWithSigParams,
-        signature: Vec<u8>,
+    // Grows a ProgramMeta account created before domain_separator was appended
+    pub fn migrate_program_meta(ctx: Context<MigrateProgramMeta>) -> Result<()> {
+        let meta_info = ctx.accounts.program_meta.to_account_info();
+        require!(meta_info.data_len() == LEGACY_PROGRAM_META_ACCOUNT_SPACE, ErrorCode::AlreadyMigrated);
+
+        let legacy = {
+            let data = meta_info.try_borrow_data()?;
+            require!(data[..8] == *ProgramMeta::DISCRIMINATOR, ErrorCode::InvalidConfigAccount);
+            LegacyProgramMeta::deserialize(&mut &data[8..])
+                .map_err(|_| error!(ErrorCode::InvalidConfigAccount))?
+        };
+
+        let rent_due = Rent::get()?
+            .minimum_balance(PROGRAM_META_ACCOUNT_SPACE)
+            .saturating_sub(meta_info.lamports());
+        if rent_due > 0 {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.admin.to_account_info(),
+                to: meta_info.clone(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            system_program::transfer(cpi_ctx, rent_due)?;
+        }
+        meta_info.resize(PROGRAM_META_ACCOUNT_SPACE)?;
+
+        let program_meta = ProgramMeta {
+            version: legacy.version,
+            upgrade_authority: legacy.upgrade_authority,
+            deployed_at: legacy.deployed_at,
+            bump: legacy.bump,
+            domain_separator: DOMAIN_SEPARATOR,
+        };
+        program_meta.try_serialize(&mut &mut meta_info.try_borrow_mut_data()?[..])?;
+
+        msg!("ProgramMetaMigrated: version={}.{}.{}",
+             legacy.version[0], legacy.version[1], legacy.version[2]);
+        Ok(())
+    }
+
+    // Rotates the program's upgrade authority through the upgradeable loader.
+    // The admin must currently hold the upgrade authority.
+    pub fn transfer_upgrade_authority(
+        ctx: Context<TransferUpgradeAuthority>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let ix = bpf_loader_upgradeable::set_upgrade_authority(
+            &crate::ID,
+            &ctx.accounts.admin.key(),
+            Some(&new_authority),
+        );
+        invoke(
+            &ix,
+            &[
+                ctx.accounts.program_data.to_account_info(),
+                ctx.accounts.admin.to_account_info(),
+                ctx.accounts.new_authority.to_account_info(),
+            ],
+        )?;
+
+        let program_meta = &mut ctx.accounts.program_meta;
+        program_meta.upgrade_authority = new_authority;
+
+        msg!("ProgramMetaUpdated: version={}.{}.{}, upgrade_authority={}",
+             program_meta.version[0], program_meta.version[1], program_meta.version[2], new_authority);
+        Ok(())
+    }
+
+    pub fn transfer_admin(
+        ctx: Context<TransferAdmin>,
+        new_admin: Pubkey,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.pending_admin = Some(new_admin);
+
+        msg!("AdminTransferInitiated: new_admin={}", new_admin);
+        Ok(())
+    }
+
+    pub fn cancel_admin_transfer(ctx: Context<CancelAdminTransfer>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let cancelled_pending = config.pending_admin.take().ok_or(ErrorCode::NoPendingAdminTransfer)?;
+
+        msg!("AdminTransferCancelled: admin={}, cancelled_pending={}", config.admin, cancelled_pending);
+        Ok(())
+    }
+
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let new_admin = config.pending_admin.ok_or(ErrorCode::NoPendingAdminTransfer)?;
+        require!(ctx.accounts.new_admin.key() == new_admin, ErrorCode::Unauthorized);
+
+        config.admin = new_admin;
+        config.pending_admin = None;
+
+        msg!("AdminTransferAccepted: new_admin={}", new_admin);
+        Ok(())
+    }
+
+    // Hands admin rights to a Squads multisig. From here on every admin
+    // instruction must be signed by the multisig's executor PDA, i.e. go
+    // through an approved Squads proposal.
+    pub fn enable_multisig(
+        ctx: Context<EnableMultiSig>,
+        multisig_address: Pubkey,
+        threshold: u8,
+    ) -> Result<()> {
+        require!(threshold > 0, ErrorCode::InvalidMultisigThreshold);
+
+        let executor = ctx.accounts.executor.key();
+
+        let multisig_config = &mut ctx.accounts.multisig_config;
+        multisig_config.multisig_address = multisig_address;
+        multisig_config.threshold = threshold;
+        multisig_config.enabled = true;
+        multisig_config.bump = ctx.bumps.multisig_config;
+
+        let config = &mut ctx.accounts.config;
+        config.admin = executor;
+        config.pending_admin = None;
+
+        msg!("MultisigEnabled: multisig={}, threshold={}, executor={}",
+             multisig_address, threshold, executor);
+        Ok(())
+    }
+
+    // Executed through a Squads proposal to return admin rights to a single key
+    pub fn disable_multisig(
+        ctx: Context<DisableMultiSig>,
+        new_admin: Pubkey,
     ) -> Result<()> {
-        validate_name(&params.name)?;
-        require!(signature.len() == 64, ErrorCode::InvalidSignature);
+        let multisig_config = &mut ctx.accounts.multisig_config;
+        require!(multisig_config.enabled, ErrorCode::MultisigNotEnabled);
+        multisig_config.enabled = false;
+
+        let config = &mut ctx.accounts.config;
+        config.admin = new_admin;
+        config.pending_admin = None;
+
+        msg!("MultisigDisabled: multisig={}, new_admin={}",
+             multisig_config.multisig_address, new_admin);
+        Ok(())
+    }
+
+    // ========================================
+    // USER INSTRUCTIONS
+    // ========================================
+
+    pub fn register_name<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RegisterName<'info>>,
+        name: String,
+    ) -> Result<RegistrationResult> {
+        validate_name(&name, ctx.accounts.charset.as_deref())?;
+        let name = name.to_ascii_lowercase();
+
         let config = &ctx.accounts.config;
-        // Enforce relayer allowlist
-        require!(ctx.accounts.relayer_entry.relayer == ctx.accounts.relayer.key(), ErrorCode::RelayerNotAllowed);
-        let token_fee = &ctx.accounts.token_fee;
-        require!(token_fee.enabled, ErrorCode::TokenNotEnabled);
-        require!(token_fee.amount == params.amount, ErrorCode::TokenFeeMismatch);
+        require!(!config.governance_gated, ErrorCode::GovernanceGateActive);
+        assert_direct_relayer_gate(
+            config,
+            RELAYER_GATE_REGISTER_NAME,
+            ctx.accounts.relayer.as_ref(),
+            ctx.accounts.relayer_entry.as_deref(),
+        )?;
+        assert_min_compute_unit_price(config, ctx.accounts.instructions_sysvar.as_deref())?;
+
+        consume_whitelist_slot(config, ctx.accounts.whitelist.as_deref_mut(), ctx.accounts.user.key())?;
+
+        let registration_fee = nft_discounted_fee(
+            &name,
+            config.registration_fee,
+            &ctx.accounts.user.key(),
+            ctx.accounts.nft_discount.as_deref(),
+            ctx.accounts.nft_token_account.as_deref(),
+            ctx.accounts.nft_metadata.as_deref(),
+        )?;
 
-        // Populate name record
         let name_record = &mut ctx.accounts.name_record;
-        name_record.name = params.name.clone();
-        name_record.owner = params.owner;
-        name_record.resolved = params.owner;
+        assert_fresh_name_record(name_record)?;
+
+        // Set record data
+        name_record.name = name.clone();
+        name_record.owner = ctx.accounts.user.key();
+        name_record.resolved = ctx.accounts.user.key();
         name_record.updated_at = Clock::get()?.unix_timestamp;
+        name_record.program_version = 1;
+        name_record.ttl_seconds = DEFAULT_TTL_SECONDS;
+        name_record.locked = false;
+        name_record.proxy_name = None;
+        name_record.created_at = Clock::get()?.unix_timestamp;
+        record_owner_name(
+            &mut ctx.accounts.owner_count,
+            name_record.owner,
+            ctx.bumps.owner_count,
+            ctx.accounts.config.max_names_per_owner,
+        )?;
+        record_registration_stats(
+            ctx.accounts.stats.as_deref_mut(),
+            name_record.name.len(),
+            Some(registration_fee),
+        );
         name_record.bump = ctx.bumps.name_record;
 
-        // Compute referrer split (referrer remains relayer for now)
-        let referrer_amount = (token_fee.amount as u128)
-            .checked_mul(config.referrer_bps as u128)
-            .unwrap()
-            .checked_div(10_000)
-            .unwrap() as u64;
-        let treasury_amount = token_fee.amount - referrer_amount;
-
-        // Transfer tokens to treasury
-        let cpi_accounts = anchor_spl::token::Transfer {
-            from: ctx.accounts.relayer_token_account.to_account_info(),
-            to: ctx.accounts.treasury_token_account.to_account_info(),
-            authority: ctx.accounts.relayer.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
-        token::transfer(cpi_ctx, treasury_amount)?;
+        // Transfer SOL to treasury, or split across recipients when a
+        // treasury_split PDA is passed as the first remaining account (CPI)
+        if ctx.remaining_accounts.is_empty() {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.user.to_account_info(),
+                to: ctx.accounts.treasury.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            system_program::transfer(cpi_ctx, registration_fee)?;
+        } else {
+            distribute_treasury_split(
+                &ctx.accounts.user.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                ctx.remaining_accounts,
+                registration_fee,
+            )?;
+        }
 
-        // Set primary name if empty
+        // Set as primary name if user doesn't have one
         if ctx.accounts.primary_name.owner == Pubkey::default() {
             let primary = &mut ctx.accounts.primary_name;
-            primary.owner = params.owner;
-            primary.name = params.name.clone();
+            primary.owner = ctx.accounts.user.key();
+            primary.name = name.clone();
             primary.bump = ctx.bumps.primary_name;
-            msg!("PrimaryNameSet: owner={}, name={}", params.owner, params.name);
+
+            msg!("PrimaryNameSet: owner={}, name={}", primary.owner, name);
         }
 
-        msg!("NameRegistered: name={}, owner={}, resolved={}", params.name, params.owner, params.owner);
-        msg!("FeePaid: name={}, payer={}, amount={}, currency={}, referrer={}, ref_amount={}",
-             params.name, ctx.accounts.relayer.key(), token_fee.amount, ctx.accounts.mint.key(),
-             ctx.accounts.relayer.key(), referrer_amount);
-        Ok(())
-    }
-}
+        msg!("NameRegistered: name={}, owner={}, resolved={}",
+             name, name_record.owner, name_record.resolved);
+        msg!("FeePaid: name={}, payer={}, amount={}, currency=SOL, referrer=None",
+             name, ctx.accounts.user.key(), registration_fee);
 
-// ========================================
-// DATA STRUCTURES
-// ========================================
+        Ok(registration_result(&ctx.accounts.name_record))
+    }
 
-#[account]
-pub struct RegistryConfig {
-    pub admin: Pubkey,                    // 32
-    pub pending_admin: Option<Pubkey>,    // 33
-    pub treasury: Pubkey,                 // 32
-    pub registration_fee: u64,            // 8
-    pub referrer_bps: u16,                // 2
-    pub require_allowlisted_relayer: bool, // 1
-    pub bump: u8,                         // 1
-    // Total: ~109 bytes + discriminator
-}
+    // Registers a name approved by a Completed SPL Governance proposal of the gated
+    // realm. The proposal's first option label must read "<name>:<recipient>", so
+    // only the recipient the realm voted for can use it. The registration fee is
+    // charged as on register_name.
+    pub fn register_name_via_governance(
+        ctx: Context<RegisterNameViaGovernance>,
+        name: String,
+        governance_proposal: Pubkey,
+    ) -> Result<RegistrationResult> {
+        validate_name(&name, ctx.accounts.charset.as_deref())?;
+        let name = name.to_ascii_lowercase();
+        require!(ctx.accounts.config.governance_gated, ErrorCode::GovernanceGateInactive);
 
-#[account]
-pub struct NameRecord {
-    pub name: String,         // 4 + len (up to 63)
-    pub owner: Pubkey,        // 32
-    pub resolved: Pubkey,     // 32
-    pub updated_at: i64,      // 8
-    pub bump: u8,             // 1
-    // Total: ~80 bytes + name length + discriminator
-}
+        let user = ctx.accounts.user.key();
+        let approved_label = governance_approved_name(
+            &ctx.accounts.gov_gate,
+            &ctx.accounts.governance.to_account_info(),
+            &ctx.accounts.proposal.to_account_info(),
+        )?;
+        require!(approved_label == governance_proposal_label(&name, &user), ErrorCode::ProposalNotApproved);
 
-#[account]
-pub struct TokenFeeConfig {
+        let registration_fee = ctx.accounts.config.registration_fee;
+        {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.user.to_account_info(),
+                to: ctx.accounts.treasury.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            system_program::transfer(cpi_ctx, registration_fee)?;
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        consume_whitelist_slot(&ctx.accounts.config, ctx.accounts.whitelist.as_deref_mut(), user)?;
+        let name_record = &mut ctx.accounts.name_record;
+        assert_fresh_name_record(name_record)?;
+        name_record.name = name.clone();
+        name_record.owner = user;
+        name_record.resolved = user;
+        name_record.updated_at = now;
+        name_record.program_version = 1;
+        name_record.ttl_seconds = DEFAULT_TTL_SECONDS;
+        name_record.locked = false;
+        name_record.proxy_name = None;
+        name_record.created_at = now;
+        record_owner_name(
+            &mut ctx.accounts.owner_count,
+            user,
+            ctx.bumps.owner_count,
+            ctx.accounts.config.max_names_per_owner,
+        )?;
+        record_registration_stats(ctx.accounts.stats.as_deref_mut(), name.len(), Some(registration_fee));
+        name_record.bump = ctx.bumps.name_record;
+
+        if ctx.accounts.primary_name.owner == Pubkey::default() {
+            let primary = &mut ctx.accounts.primary_name;
+            primary.owner = user;
+            primary.name = name.clone();
+            primary.bump = ctx.bumps.primary_name;
+
+            msg!("PrimaryNameSet: owner={}, name={}", primary.owner, name);
+        }
+
+        msg!("NameRegistered: name={}, owner={}, resolved={}", name, user, user);
+        msg!("GovernanceRegistration: name={}, proposal={}, realm={}",
+             name, governance_proposal, ctx.accounts.gov_gate.realm);
+        msg!("FeePaid: name={}, payer={}, amount={}, currency=SOL, referrer=None",
+             name, user, registration_fee);
+
+        Ok(registration_result(&ctx.accounts.name_record))
+    }
+
+    pub fn register_name_with_token(
+        ctx: Context<RegisterNameWithToken>,
+        name: String,
+    ) -> Result<RegistrationResult> {
+        validate_name(&name, ctx.accounts.charset.as_deref())?;
+        let name = name.to_ascii_lowercase();
+        require!(!ctx.accounts.config.governance_gated, ErrorCode::GovernanceGateActive);
+
+        assert_direct_relayer_gate(
+            &ctx.accounts.config,
+            RELAYER_GATE_REGISTER_NAME_WITH_TOKEN,
+            ctx.accounts.relayer.as_ref(),
+            ctx.accounts.relayer_entry.as_deref(),
+        )?;
+
+        let token_fee = &ctx.accounts.token_fee;
+        require!(token_fee.enabled, ErrorCode::TokenNotEnabled);
+        let fee_amount = nft_discounted_fee(
+            &name,
+            token_fee.amount,
+            &ctx.accounts.user.key(),
+            ctx.accounts.nft_discount.as_deref(),
+            ctx.accounts.nft_token_account.as_deref(),
+            ctx.accounts.nft_metadata.as_deref(),
+        )?;
+
+        consume_whitelist_slot(&ctx.accounts.config, ctx.accounts.whitelist.as_deref_mut(), ctx.accounts.user.key())?;
+        let name_record = &mut ctx.accounts.name_record;
+        assert_fresh_name_record(name_record)?;
+        name_record.name = name.clone();
+        name_record.owner = ctx.accounts.user.key();
+        name_record.resolved = ctx.accounts.user.key();
+        name_record.updated_at = Clock::get()?.unix_timestamp;
+        name_record.program_version = 1;
+        name_record.ttl_seconds = DEFAULT_TTL_SECONDS;
+        name_record.locked = false;
+        name_record.proxy_name = None;
+        name_record.created_at = Clock::get()?.unix_timestamp;
+        record_owner_name(
+            &mut ctx.accounts.owner_count,
+            name_record.owner,
+            ctx.bumps.owner_count,
+            ctx.accounts.config.max_names_per_owner,
+        )?;
+        record_registration_stats(
+            ctx.accounts.stats.as_deref_mut(),
+            name_record.name.len(),
+            None,
+        );
+        name_record.bump = ctx.bumps.name_record;
+
+        // Transfer tokens to treasury
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.user_token_account.to_account_info(),
+            to: ctx.accounts.treasury_token_account.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, fee_amount)?;
+
+        // Set as primary name if user doesn't have one
+        if ctx.accounts.primary_name.owner == Pubkey::default() {
+            let primary = &mut ctx.accounts.primary_name;
+            primary.owner = ctx.accounts.user.key();
+            primary.name = name.clone();
+            primary.bump = ctx.bumps.primary_name;
+
+            msg!("PrimaryNameSet: owner={}, name={}", primary.owner, name);
+        }
+
+        msg!("NameRegistered: name={}, owner={}, resolved={}",
+             name, name_record.owner, name_record.resolved);
+        msg!("FeePaid: name={}, payer={}, amount={}, currency={}, referrer=None",
+             name, ctx.accounts.user.key(), fee_amount, token_fee.mint);
+
+        Ok(registration_result(&ctx.accounts.name_record))
+    }
+
+    // Token-2022 mints need transfer_checked so transfer-fee and other extensions apply
+    pub fn register_name_with_token22(
+        ctx: Context<RegisterNameWithToken22>,
+        name: String,
+    ) -> Result<RegistrationResult> {
+        validate_name(&name, ctx.accounts.charset.as_deref())?;
+        let name = name.to_ascii_lowercase();
+        require!(!ctx.accounts.config.governance_gated, ErrorCode::GovernanceGateActive);
+
+        let token22_fee = &ctx.accounts.token22_fee;
+        require!(token22_fee.enabled, ErrorCode::TokenNotEnabled);
+        let fee_amount = nft_discounted_fee(
+            &name,
+            token22_fee.amount,
+            &ctx.accounts.user.key(),
+            ctx.accounts.nft_discount.as_deref(),
+            ctx.accounts.nft_token_account.as_deref(),
+            ctx.accounts.nft_metadata.as_deref(),
+        )?;
+
+        consume_whitelist_slot(&ctx.accounts.config, ctx.accounts.whitelist.as_deref_mut(), ctx.accounts.user.key())?;
+        let name_record = &mut ctx.accounts.name_record;
+        assert_fresh_name_record(name_record)?;
+        name_record.name = name.clone();
+        name_record.owner = ctx.accounts.user.key();
+        name_record.resolved = ctx.accounts.user.key();
+        name_record.updated_at = Clock::get()?.unix_timestamp;
+        name_record.program_version = 1;
+        name_record.ttl_seconds = DEFAULT_TTL_SECONDS;
+        name_record.locked = false;
+        name_record.proxy_name = None;
+        name_record.created_at = Clock::get()?.unix_timestamp;
+        record_owner_name(
+            &mut ctx.accounts.owner_count,
+            name_record.owner,
+            ctx.bumps.owner_count,
+            ctx.accounts.config.max_names_per_owner,
+        )?;
+        record_registration_stats(
+            ctx.accounts.stats.as_deref_mut(),
+            name_record.name.len(),
+            None,
+        );
+        name_record.bump = ctx.bumps.name_record;
+
+        // Transfer tokens to treasury
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.user_token_account.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to: ctx.accounts.treasury_token_account.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token_2022::transfer_checked(cpi_ctx, fee_amount, ctx.accounts.mint.decimals)?;
+
+        // Set as primary name if user doesn't have one
+        if ctx.accounts.primary_name.owner == Pubkey::default() {
+            let primary = &mut ctx.accounts.primary_name;
+            primary.owner = ctx.accounts.user.key();
+            primary.name = name.clone();
+            primary.bump = ctx.bumps.primary_name;
+
+            msg!("PrimaryNameSet: owner={}, name={}", primary.owner, name);
+        }
+
+        msg!("NameRegistered: name={}, owner={}, resolved={}",
+             name, name_record.owner, name_record.resolved);
+        msg!("FeePaid: name={}, payer={}, amount={}, currency={}, referrer=None",
+             name, ctx.accounts.user.key(), fee_amount, token22_fee.mint);
+
+        Ok(registration_result(&ctx.accounts.name_record))
+    }
+
+    // In allowlist mode the relayer's RelayerEntry is passed as the first remaining account
+    pub fn register_name_with_signature<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RegisterNameWithSignature<'info>>,
+        params: RegisterWithSigParams,
+        signature: Vec<u8>,
+    ) -> Result<RegistrationResult> {
+        validate_name(&params.name, ctx.accounts.charset.as_deref())?;
+
+        // Verify relayer
+        require!(ctx.accounts.relayer.key() == params.relayer, ErrorCode::Unauthorized);
+
+        let config = &ctx.accounts.config;
+        require!(!config.governance_gated, ErrorCode::GovernanceGateActive);
+        check_deadline(config, params.deadline)?;
+        load_allowlisted_relayer(
+            config,
+            RELAYER_GATE_REGISTER_NAME_WITH_SIG,
+            &ctx.accounts.relayer.key(),
+            ctx.remaining_accounts,
+        )?;
+        verify_signature(&params, &signature, &ctx.accounts.instructions_sysvar)?;
+        // The nonce is part of the signed message, so each signature is usable once
+        let owner_nonce = &mut ctx.accounts.owner_nonce;
+        require!(owner_nonce.nonce == params.nonce, ErrorCode::InvalidNonce);
+        owner_nonce.owner = params.owner;
+        owner_nonce.nonce += 1;
+        owner_nonce.bump = ctx.bumps.owner_nonce;
+
+        // The owner signed the name as submitted; records use the normalized form
+        let name = params.name.to_ascii_lowercase();
+        let registration_fee = nft_discounted_fee(
+            &name,
+            config.registration_fee,
+            &params.owner,
+            ctx.accounts.nft_discount.as_deref(),
+            ctx.accounts.nft_token_account.as_deref(),
+            ctx.accounts.nft_metadata.as_deref(),
+        )?;
+        consume_whitelist_slot(&ctx.accounts.config, ctx.accounts.whitelist.as_deref_mut(), params.owner)?;
+        let name_record = &mut ctx.accounts.name_record;
+        assert_fresh_name_record(name_record)?;
+        name_record.name = name.clone();
+        name_record.owner = params.owner;
+        name_record.resolved = params.owner;
+        name_record.updated_at = Clock::get()?.unix_timestamp;
+        name_record.program_version = 1;
+        name_record.ttl_seconds = DEFAULT_TTL_SECONDS;
+        name_record.locked = false;
+        name_record.proxy_name = None;
+        name_record.created_at = Clock::get()?.unix_timestamp;
+        record_owner_name(
+            &mut ctx.accounts.owner_count,
+            name_record.owner,
+            ctx.bumps.owner_count,
+            ctx.accounts.config.max_names_per_owner,
+        )?;
+        record_registration_stats(
+            ctx.accounts.stats.as_deref_mut(),
+            name_record.name.len(),
+            Some(registration_fee),
+        );
+        name_record.bump = ctx.bumps.name_record;
+
+        // Map the signing EVM address to its name when registering via secp256k1
+        if let Some(evm_owner) = ctx.accounts.evm_owner.as_mut() {
+            require!(params.signature_type == SignatureType::Secp256k1, ErrorCode::InvalidSignatureType);
+            if evm_owner.name.is_empty() {
+                evm_owner.name = name.clone();
+                evm_owner.evm_address = params.evm_address.ok_or(ErrorCode::InvalidSignatureType)?;
+                evm_owner.bump = ctx.bumps.evm_owner.ok_or(ErrorCode::InvalidSignatureType)?;
+            }
+        }
+
+        // Enforce SOL-only path here
+        require!(params.currency.is_none(), ErrorCode::TokenNotEnabled);
+
+        // SOL payment
+        require!(ctx.accounts.relayer.lamports() >= registration_fee, ErrorCode::InsufficientTokenBalance);
+
+        let effective_bps = config.referrer_bps;
+        require!(effective_bps >= config.min_referrer_bps, ErrorCode::ReferrerBpsBelowFloor);
+        let referrer_amount = (registration_fee as u128)
+            .checked_mul(effective_bps as u128)
+            .unwrap()
+            .checked_div(10_000)
+            .unwrap() as u64;
+        let treasury_amount = registration_fee - referrer_amount;
+
+        // Transfer to treasury (CPI)
+        {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.relayer.to_account_info(),
+                to: ctx.accounts.treasury.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            system_program::transfer(cpi_ctx, treasury_amount)?;
+        }
+
+        if referrer_amount > 0 {
+            record_referral(ctx.accounts.referrer_account.as_mut(), referrer_amount, 0);
+        }
+
+        msg!("FeePaid: name={}, payer={}, amount={}, currency=SOL, referrer={}, ref_amount={}",
+             name, ctx.accounts.relayer.key(), registration_fee,
+             ctx.accounts.relayer.key(), referrer_amount);
+
+        // Set as primary name if owner doesn't have one
+        if ctx.accounts.primary_name.owner == Pubkey::default() {
+            let primary = &mut ctx.accounts.primary_name;
+            primary.owner = params.owner;
+            primary.name = name.clone();
+            primary.bump = ctx.bumps.primary_name;
+
+            msg!("PrimaryNameSet: owner={}, name={}", params.owner, name);
+        }
+
+        msg!("NameRegistered: name={}, owner={}, resolved={}",
+             name, params.owner, params.owner);
+
+        Ok(registration_result(&ctx.accounts.name_record))
+    }
+
+    // Token signature registration kept in second function below (see after SOL version)
+
+    pub fn transfer_name(
+        ctx: Context<TransferName>,
+        name: String,
+        new_owner: Pubkey,
+    ) -> Result<()> {
+        // A lessee only holds the name temporarily and cannot hand it on
+        let lease_info = ctx.accounts.lease_record.to_account_info();
+        if !lease_info.data_is_empty() {
+            let lease = LeaseRecord::try_deserialize(&mut &lease_info.try_borrow_data()?[..])?;
+            require!(Clock::get()?.unix_timestamp < lease.expires_at, ErrorCode::LeaseExpired);
+            return err!(ErrorCode::LeaseActive);
+        }
+        require!(ctx.accounts.escrow.data_is_empty(), ErrorCode::NameInEscrow);
+
+        let name_record = &mut ctx.accounts.name_record;
+        let old_owner = name_record.owner;
+
+        // The lock is permanent, so it moves with the name
+        if name_record.locked {
+            require!(ctx.accounts.config.locked_names_transferable, ErrorCode::NameIsLocked);
+        }
+
+        name_record.owner = new_owner;
+        name_record.updated_at = Clock::get()?.unix_timestamp;
+
+        msg!("NameTransferred: name={}, old_owner={}, new_owner={}",
+             name, old_owner, new_owner);
+
+        // A locked name's resolution is frozen
+        if ctx.accounts.config.reset_resolved_on_transfer && !name_record.locked {
+            let old_resolved = name_record.resolved;
+            name_record.resolved = new_owner;
+
+            msg!("ResolvedReset: name={}, old_resolved={}, new_resolved={}",
+                 name, old_resolved, new_owner);
+        }
+
+        Ok(())
+    }
+
+    // Non-owners must pass their NameAcl entry as the first remaining account
+    // Remaining accounts: [acl_entry] when the authority is not the owner,
+    // followed by the name's NameHistoryHead and the next NameHistoryEntry
+    pub fn set_resolved_address<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SetResolvedAddress<'info>>,
+        name: String,
+        new_resolved: Pubkey,
+    ) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        let mut history_accounts = ctx.remaining_accounts;
+        if ctx.accounts.name_record.owner != authority {
+            let acl_entry = ctx.remaining_accounts.first().ok_or(ErrorCode::Unauthorized)?;
+            require_acl_permission(&name, &ctx.accounts.name_record.owner, &authority, acl_entry, ACL_CAN_SET_RESOLVED)?;
+            history_accounts = &ctx.remaining_accounts[1..];
+        }
+
+        let name_record = &mut ctx.accounts.name_record;
+        require!(!name_record.locked, ErrorCode::NameIsLocked);
+        let old_resolved = name_record.resolved;
+        name_record.resolved = new_resolved;
+        name_record.updated_at = Clock::get()?.unix_timestamp;
+
+        record_history_entry(
+            history_accounts,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &name,
+            old_resolved,
+            new_resolved,
+            name_record.updated_at,
+        )?;
+
+        msg!("ResolvedUpdated: name={}, owner={}, new_resolved={}, updated_by={}",
+             name, name_record.owner, new_resolved, authority);
+
+        Ok(())
+    }
+
+    // Relayed by a fee payer; the owner's signature must be checked by an
+    // Ed25519 program instruction placed directly before this one
+    pub fn set_resolved_with_signature(
+        ctx: Context<SetResolvedWithSignature>,
+        name: String,
+        new_resolved: Pubkey,
+        deadline: i64,
+        nonce: u64,
+        signature: [u8; 64],
+    ) -> Result<()> {
+        check_deadline(&ctx.accounts.config, deadline)?;
+
+        let owner = ctx.accounts.name_record.owner;
+        let owner_nonce = &mut ctx.accounts.owner_nonce;
+        require!(owner_nonce.nonce == nonce, ErrorCode::InvalidNonce);
+
+        let message = build_set_resolved_message(&name, &new_resolved, &owner, deadline, nonce, &DOMAIN_SEPARATOR);
+        verify_ed25519_instruction(
+            &ctx.accounts.instructions_sysvar.to_account_info(),
+            &owner,
+            &message,
+            &signature,
+        )?;
+
+        owner_nonce.owner = owner;
+        owner_nonce.nonce += 1;
+        owner_nonce.bump = ctx.bumps.owner_nonce;
+
+        let name_record = &mut ctx.accounts.name_record;
+        require!(!name_record.locked, ErrorCode::NameIsLocked);
+        name_record.resolved = new_resolved;
+        name_record.updated_at = Clock::get()?.unix_timestamp;
+
+        msg!("ResolvedUpdated: name={}, owner={}, new_resolved={}, updated_by={}",
+             name, owner, new_resolved, ctx.accounts.relayer.key());
+
+        Ok(())
+    }
+
+    pub fn grant_acl(
+        ctx: Context<GrantAcl>,
+        name: String,
+        member: Pubkey,
+        permissions: u8,
+    ) -> Result<()> {
+        let acl = &mut ctx.accounts.acl_entry;
+        acl.name_hash = hashv(&[name.as_bytes()]).to_bytes();
+        acl.member = member;
+        acl.permissions = permissions;
+        acl.bump = ctx.bumps.acl_entry;
+
+        msg!("AclGranted: name={}, member={}, permissions={}", name, member, permissions);
+        Ok(())
+    }
+
+    pub fn revoke_acl(
+        ctx: Context<RevokeAcl>,
+        name: String,
+        member: Pubkey,
+    ) -> Result<()> {
+        require!(ctx.accounts.acl_entry.member == member, ErrorCode::Unauthorized);
+
+        msg!("AclRevoked: name={}, member={}", name, member);
+        Ok(())
+    }
+
+    pub fn set_bio(
+        ctx: Context<SetBio>,
+        name: String,
+        bio: String,
+    ) -> Result<()> {
+        require!(bio.len() <= MAX_BIO_LEN, ErrorCode::BioTooLong);
+
+        let name_bio = &mut ctx.accounts.name_bio;
+        name_bio.name_hash = hashv(&[name.as_bytes()]).to_bytes();
+        name_bio.bio = bio;
+        name_bio.bump = ctx.bumps.name_bio;
+
+        // Only the length is logged to keep compute down
+        msg!("BioSet: name={}, owner={}, bio_len={}", name, ctx.accounts.owner.key(), name_bio.bio.len());
+        Ok(())
+    }
+
+    pub fn delete_bio(
+        ctx: Context<DeleteBio>,
+        name: String,
+    ) -> Result<()> {
+        msg!("BioDeleted: name={}, owner={}", name, ctx.accounts.owner.key());
+        Ok(())
+    }
+
+    pub fn set_avatar(
+        ctx: Context<SetAvatar>,
+        name: String,
+        uri: String,
+        mime_type: String,
+    ) -> Result<()> {
+        require!(uri.len() <= MAX_AVATAR_URI_LEN, ErrorCode::AvatarUriTooLong);
+        require!(SUPPORTED_AVATAR_MIME_TYPES.contains(&mime_type.as_str()), ErrorCode::UnsupportedMimeType);
+
+        let avatar = &mut ctx.accounts.avatar;
+        avatar.name_hash = hashv(&[name.as_bytes()]).to_bytes();
+        avatar.uri = uri;
+        avatar.mime_type = mime_type;
+        avatar.bump = ctx.bumps.avatar;
+
+        msg!("AvatarSet: name={}, owner={}, mime_type={}", name, ctx.accounts.owner.key(), avatar.mime_type);
+        Ok(())
+    }
+
+    pub fn delete_avatar(
+        ctx: Context<DeleteAvatar>,
+        name: String,
+    ) -> Result<()> {
+        msg!("AvatarDeleted: name={}, owner={}", name, ctx.accounts.owner.key());
+        Ok(())
+    }
+
+    // Registers the off-chain verifier whose signed attestations clients accept for `platform`
+    pub fn set_attester(
+        ctx: Context<SetAttester>,
+        platform: String,
+        public_key: Pubkey,
+    ) -> Result<()> {
+        require!(
+            !platform.is_empty() && platform.len() <= MAX_SOCIAL_PLATFORM_LEN,
+            ErrorCode::InvalidSocialPlatform
+        );
+
+        let attester = &mut ctx.accounts.attester;
+        attester.platform = platform.clone();
+        attester.public_key = public_key;
+        attester.bump = ctx.bumps.attester;
+
+        msg!("AttesterSet: platform={}, public_key={}", platform, public_key);
+        Ok(())
+    }
+
+    // Links `name` to `handle` on `platform`. attestation_hash is the SHA256 of
+    // the canonical attestation JSON signed by the platform's attester; clients
+    // check that signature off-chain against the stored attester key. An
+    // attestation only counts while its `owner` still owns the name.
+    pub fn set_social_attestation(
+        ctx: Context<SetSocialAttestation>,
+        name: String,
+        platform: String,
+        handle: String,
+        attestation_hash: [u8; 32],
+    ) -> Result<()> {
+        require!(!handle.is_empty(), ErrorCode::SocialHandleEmpty);
+        require!(handle.len() <= MAX_SOCIAL_HANDLE_LEN, ErrorCode::SocialHandleTooLong);
+
+        let attestation = &mut ctx.accounts.social_attestation;
+        attestation.name_hash = hashv(&[name.as_bytes()]).to_bytes();
+        attestation.platform = platform.clone();
+        attestation.handle = handle.clone();
+        attestation.attestation_hash = attestation_hash;
+        attestation.attested_at = Clock::get()?.unix_timestamp;
+        attestation.bump = ctx.bumps.social_attestation;
+        attestation.owner = ctx.accounts.owner.key();
+        attestation.attester = ctx.accounts.attester.public_key;
+
+        msg!("SocialAttestationSet: name={}, platform={}, handle={}, owner={}, attester={}",
+             name, platform, handle, attestation.owner, attestation.attester);
+        Ok(())
+    }
+
+    // Closed by hand so attestations written before owner/attester were
+    // appended can be removed as well
+    pub fn delete_social_attestation(
+        ctx: Context<DeleteSocialAttestation>,
+        name: String,
+        platform: String,
+    ) -> Result<()> {
+        let attestation_info = ctx.accounts.social_attestation.to_account_info();
+        let owner_info = ctx.accounts.owner.to_account_info();
+        let lamports = attestation_info.lamports();
+        **attestation_info.try_borrow_mut_lamports()? = 0;
+        **owner_info.try_borrow_mut_lamports()? += lamports;
+        attestation_info.assign(&system_program::ID);
+        attestation_info.resize(0)?;
+
+        msg!("SocialAttestationDeleted: name={}, platform={}, owner={}", name, platform, ctx.accounts.owner.key());
+        Ok(())
+    }
+
+    // Permissionless crank closing an attestation made by a previous owner of
+    // `name`; the rent goes back to that owner
+    pub fn invalidate_social_attestation(
+        ctx: Context<InvalidateSocialAttestation>,
+        name: String,
+        platform: String,
+    ) -> Result<()> {
+        let attestation = &ctx.accounts.social_attestation;
+        require!(attestation.owner != ctx.accounts.name_record.owner, ErrorCode::SocialAttestationNotStale);
+
+        msg!("SocialAttestationInvalidated: name={}, platform={}, previous_owner={}",
+             name, platform, attestation.owner);
+        Ok(())
+    }
+
+    // Locks the name on Solana so `near_owner` can claim it on NEAR. The lock
+    // PDA takes ownership of the record; the BridgeLockEvent log line is what
+    // the NEAR verifier proves inclusion of. An unclaimed lock can be undone
+    // with unlock_bridge_lock once BRIDGE_UNLOCK_TIMEOUT_SECONDS have passed
+    pub fn lock_for_bridge(
+        ctx: Context<LockForBridge>,
+        name: String,
+        near_owner: String,
+    ) -> Result<()> {
+        require!(is_valid_near_account_id(&near_owner), ErrorCode::InvalidNearAccount);
+        require!(ctx.accounts.lease_record.data_is_empty(), ErrorCode::LeaseActive);
+
+        let name_record = &mut ctx.accounts.name_record;
+        require!(!name_record.locked, ErrorCode::NameIsLocked);
+
+        let bridge_state = &mut ctx.accounts.bridge_state;
+        let nonce = bridge_state.next_nonce;
+        bridge_state.next_nonce = nonce + 1;
+        bridge_state.bump = ctx.bumps.bridge_state;
+
+        let now = Clock::get()?.unix_timestamp;
+        let bridge_lock = &mut ctx.accounts.bridge_lock;
+        bridge_lock.name = name.clone();
+        bridge_lock.owner = ctx.accounts.owner.key();
+        bridge_lock.near_owner = near_owner.clone();
+        bridge_lock.locked_at = now;
+        bridge_lock.message_nonce = nonce;
+        bridge_lock.bump = ctx.bumps.bridge_lock;
+        bridge_lock.claimed = false;
+
+        name_record.owner = bridge_lock.key();
+        name_record.resolved = bridge_lock.key();
+        name_record.updated_at = now;
+
+        release_owner_name(&mut ctx.accounts.owner_count, ctx.accounts.owner.key(), ctx.bumps.owner_count);
+        let primary = &mut ctx.accounts.primary_name;
+        primary.owner = ctx.accounts.owner.key();
+        primary.bump = ctx.bumps.primary_name;
+        if primary.name == name {
+            primary.name.clear();
+        }
+
+        msg!("BridgeLockEvent: name={}, owner={}, near_owner={}, nonce={}, timestamp={}",
+             name, bridge_lock.owner, near_owner, nonce, now);
+
+        Ok(())
+    }
+
+    // Admin records that the guardians observed a NEAR claim for this lock, after
+    // which the lock can never be undone
+    pub fn mark_bridge_claimed(ctx: Context<MarkBridgeClaimed>, name: String) -> Result<()> {
+        let bridge_lock = &mut ctx.accounts.bridge_lock;
+        bridge_lock.claimed = true;
+
+        msg!("BridgeClaimMarked: name={}, nonce={}", name, bridge_lock.message_nonce);
+        Ok(())
+    }
+
+    // Hands a lock that was never claimed on NEAR back to its Solana owner
+    pub fn unlock_bridge_lock(ctx: Context<UnlockBridgeLock>, name: String) -> Result<()> {
+        let bridge_lock = &ctx.accounts.bridge_lock;
+        require!(!bridge_lock.claimed, ErrorCode::BridgeLockClaimed);
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= bridge_lock.locked_at.saturating_add(BRIDGE_UNLOCK_TIMEOUT_SECONDS),
+            ErrorCode::BridgeLockActive
+        );
+
+        let owner = ctx.accounts.owner.key();
+        let name_record = &mut ctx.accounts.name_record;
+        name_record.owner = owner;
+        name_record.resolved = owner;
+        name_record.updated_at = now;
+
+        let owner_count = &mut ctx.accounts.owner_count;
+        owner_count.owner = owner;
+        owner_count.count = owner_count.count.saturating_add(1);
+        owner_count.bump = ctx.bumps.owner_count;
+
+        msg!("BridgeUnlockEvent: name={}, owner={}, nonce={}, timestamp={}",
+             name, owner, bridge_lock.message_nonce, now);
+        Ok(())
+    }
+
+    // Hands the name to `lessee` until the lease ends; both parties sign. The
+    // lessee can resolve the name and update its records, but every
+    // instruction that hands on or commits the name requires no active lease
+    pub fn lease_name(
+        ctx: Context<LeaseName>,
+        name: String,
+        lessee: Pubkey,
+        lease_duration_seconds: i64,
+        price: u64,
+    ) -> Result<()> {
+        require!(lease_duration_seconds > 0 && price > 0, ErrorCode::LeasePriced);
+
+        let cpi_accounts = system_program::Transfer {
+            from: ctx.accounts.lessee.to_account_info(),
+            to: ctx.accounts.owner.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+        system_program::transfer(cpi_ctx, price)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let lease_record = &mut ctx.accounts.lease_record;
+        lease_record.original_owner = ctx.accounts.owner.key();
+        lease_record.lessee = lessee;
+        lease_record.leased_at = now;
+        lease_record.expires_at = now.checked_add(lease_duration_seconds).ok_or(ErrorCode::LeasePriced)?;
+        lease_record.bump = ctx.bumps.lease_record;
+
+        let name_record = &mut ctx.accounts.name_record;
+        name_record.owner = lessee;
+        name_record.updated_at = now;
+
+        msg!("NameLeased: name={}, original_owner={}, lessee={}, expires_at={}, price={}",
+             name, lease_record.original_owner, lessee, lease_record.expires_at, price);
+
+        Ok(())
+    }
+
+    // Permissionless once expired; the original owner may end it early
+    pub fn end_lease(
+        ctx: Context<EndLease>,
+        name: String,
+    ) -> Result<()> {
+        let lease_record = &ctx.accounts.lease_record;
+        require!(
+            Clock::get()?.unix_timestamp >= lease_record.expires_at
+                || ctx.accounts.caller.key() == lease_record.original_owner,
+            ErrorCode::LeaseActive
+        );
+
+        let name_record = &mut ctx.accounts.name_record;
+        name_record.owner = lease_record.original_owner;
+        name_record.updated_at = Clock::get()?.unix_timestamp;
+
+        msg!("LeaseEnded: name={}, original_owner={}, lessee={}",
+             name, lease_record.original_owner, lease_record.lessee);
+
+        Ok(())
+    }
+
+    // Parks the name with the escrow PDA during a private sale. `buyer` of None
+    // lets anyone complete the sale at `price`.
+    pub fn escrow_name(
+        ctx: Context<EscrowName>,
+        name: String,
+        buyer: Option<Pubkey>,
+        price: u64,
+        timeout_seconds: i64,
+    ) -> Result<()> {
+        require!(price > 0 && timeout_seconds > 0, ErrorCode::InvalidEscrowParams);
+        require!(ctx.accounts.lease_record.data_is_empty(), ErrorCode::LeaseActive);
+
+        let name_record = &mut ctx.accounts.name_record;
+        if name_record.locked {
+            require!(ctx.accounts.config.locked_names_transferable, ErrorCode::NameIsLocked);
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.name = name.clone();
+        escrow.seller = ctx.accounts.owner.key();
+        escrow.buyer = buyer;
+        escrow.price = price;
+        escrow.locked_at = now;
+        escrow.timeout_at = now.checked_add(timeout_seconds).ok_or(ErrorCode::InvalidEscrowParams)?;
+        escrow.bump = ctx.bumps.escrow;
+
+        name_record.owner = escrow.key();
+        name_record.updated_at = now;
+
+        msg!("NameEscrowed: name={}, seller={}, buyer={:?}, price={}, timeout_at={}",
+             name, escrow.seller, escrow.buyer, price, escrow.timeout_at);
+
+        Ok(())
+    }
+
+    pub fn complete_escrow(
+        ctx: Context<CompleteEscrow>,
+        name: String,
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        let buyer = ctx.accounts.buyer.key();
+        require!(Clock::get()?.unix_timestamp < escrow.timeout_at, ErrorCode::EscrowExpired);
+        if let Some(expected_buyer) = escrow.buyer {
+            require!(buyer == expected_buyer, ErrorCode::Unauthorized);
+        }
+
+        let cpi_accounts = system_program::Transfer {
+            from: ctx.accounts.buyer.to_account_info(),
+            to: ctx.accounts.seller.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+        system_program::transfer(cpi_ctx, escrow.price)?;
+
+        // Same rules as transfer_name: the lock moves with the name and freezes its resolution
+        let name_record = &mut ctx.accounts.name_record;
+        name_record.owner = buyer;
+        name_record.updated_at = Clock::get()?.unix_timestamp;
+        if ctx.accounts.config.reset_resolved_on_transfer && !name_record.locked {
+            name_record.resolved = buyer;
+        }
+
+        release_owner_name(&mut ctx.accounts.seller_count, escrow.seller, ctx.bumps.seller_count);
+        let seller_primary = &mut ctx.accounts.seller_primary;
+        seller_primary.owner = escrow.seller;
+        seller_primary.bump = ctx.bumps.seller_primary;
+        if seller_primary.name == name {
+            seller_primary.name.clear();
+        }
+
+        record_owner_name(
+            &mut ctx.accounts.buyer_count,
+            buyer,
+            ctx.bumps.buyer_count,
+            ctx.accounts.config.max_names_per_owner,
+        )?;
+        if ctx.accounts.buyer_primary.name.is_empty() {
+            let buyer_primary = &mut ctx.accounts.buyer_primary;
+            buyer_primary.owner = buyer;
+            buyer_primary.name = name.clone();
+            buyer_primary.bump = ctx.bumps.buyer_primary;
+
+            msg!("PrimaryNameSet: owner={}, name={}", buyer, name);
+        }
+
+        msg!("EscrowCompleted: name={}, seller={}, buyer={}, price={}",
+             name, escrow.seller, buyer, escrow.price);
+
+        Ok(())
+    }
+
+    // The seller may cancel at any time; anyone may once the escrow times out
+    pub fn cancel_escrow(
+        ctx: Context<CancelEscrow>,
+        name: String,
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        require!(
+            Clock::get()?.unix_timestamp >= escrow.timeout_at
+                || ctx.accounts.caller.key() == escrow.seller,
+            ErrorCode::EscrowActive
+        );
+
+        let name_record = &mut ctx.accounts.name_record;
+        name_record.owner = escrow.seller;
+        name_record.updated_at = Clock::get()?.unix_timestamp;
+
+        msg!("EscrowCancelled: name={}, seller={}", name, escrow.seller);
+
+        Ok(())
+    }
+
+    // Irreversible: there is no unlock instruction
+    pub fn lock_name(
+        ctx: Context<LockName>,
+        name: String,
+    ) -> Result<()> {
+        let name_record = &mut ctx.accounts.name_record;
+        name_record.locked = true;
+        name_record.updated_at = Clock::get()?.unix_timestamp;
+
+        msg!("NameLocked: name={}, owner={}", name, name_record.owner);
+
+        Ok(())
+    }
+
+    pub fn set_name_ttl(
+        ctx: Context<SetNameTtl>,
+        name: String,
+        ttl: u32,
+    ) -> Result<()> {
+        require!(ttl <= ctx.accounts.config.max_ttl_seconds, ErrorCode::TtlTooHigh);
+
+        let name_record = &mut ctx.accounts.name_record;
+        name_record.ttl_seconds = ttl;
+        name_record.updated_at = Clock::get()?.unix_timestamp;
+
+        msg!("TtlUpdated: name={}, owner={}, ttl_seconds={}", name, name_record.owner, ttl);
+
+        Ok(())
+    }
+
+    pub fn set_proxy(
+        ctx: Context<SetProxy>,
+        name: String,
+        proxy_name: Option<String>,
+    ) -> Result<()> {
+        let proxy_name = proxy_name.map(|proxy| proxy.to_ascii_lowercase());
+        if let Some(proxy) = proxy_name.as_ref() {
+            validate_name(proxy, ctx.accounts.charset.as_deref())?;
+            require!(*proxy != name.to_ascii_lowercase(), ErrorCode::CircularProxyDetected);
+        }
+
+        let name_record = &mut ctx.accounts.name_record;
+        require!(!name_record.locked, ErrorCode::NameIsLocked);
+        name_record.proxy_name = proxy_name.clone();
+        name_record.updated_at = Clock::get()?.unix_timestamp;
+
+        msg!("ProxySet: name={}, proxy_name={}", name, proxy_name.unwrap_or_default());
+
+        Ok(())
+    }
+
+    // Follows proxy_name links starting at `name`. Each hop's name record must be
+    // passed as a remaining account, in chain order.
+    pub fn resolve_chain<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ResolveChain<'info>>,
+        name: String,
+        max_depth: u8,
+    ) -> Result<Pubkey> {
+        require!(max_depth <= MAX_PROXY_DEPTH, ErrorCode::ProxyDepthExceeded);
+
+        let mut visited: Vec<String> = vec![name.clone()];
+        let mut resolved = ctx.accounts.name_record.resolved;
+        let mut next = ctx.accounts.name_record.proxy_name.clone();
+        let mut hops = ctx.remaining_accounts.iter();
+
+        while let Some(proxy_name) = next {
+            require!(!visited.contains(&proxy_name), ErrorCode::CircularProxyDetected);
+            require!(visited.len() <= max_depth as usize, ErrorCode::ProxyDepthExceeded);
+
+            let record_info = hops.next().ok_or(ErrorCode::NameNotFound)?;
+            let (expected_record, _) = Pubkey::find_program_address(&[b"name", proxy_name.as_bytes()], &crate::ID);
+            require_keys_eq!(record_info.key(), expected_record, ErrorCode::NameNotFound);
+            let record: Account<'info, NameRecord> = Account::try_from(record_info)?;
+
+            resolved = record.resolved;
+            next = record.proxy_name.clone();
+            visited.push(proxy_name);
+        }
+
+        msg!("ChainResolved: name={}, resolved={}, hops={}", name, resolved, visited.len() - 1);
+        Ok(resolved)
+    }
+
+    // Read-only: returns the resolved address via return data for CPI callers
+    pub fn resolve_name(
+        ctx: Context<ResolveName>,
+        name: String,
+    ) -> Result<Pubkey> {
+        let resolved = ctx.accounts.name_record.resolved;
+        msg!("NameResolved: name={}, resolved={}", name, resolved);
+        Ok(resolved)
+    }
+
+    // Cheap availability check: reads only the discriminator of the name_record PDA
+    pub fn name_exists(
+        ctx: Context<NameExists>,
+        _name: String,
+    ) -> Result<bool> {
+        let data = ctx.accounts.name_record.try_borrow_data()?;
+        Ok(data.starts_with(NameRecord::DISCRIMINATOR))
+    }
+
+    // Admin allowlist of programs a name owner may use as a sub-registry
+    pub fn approve_sub_registry_program(
+        ctx: Context<ApproveSubRegistryProgram>,
+        program: Pubkey,
+    ) -> Result<()> {
+        require_keys_neq!(program, crate::ID, ErrorCode::InvalidSubRegistryProgram);
+
+        let approved = &mut ctx.accounts.approved_program;
+        approved.program = program;
+        approved.bump = ctx.bumps.approved_program;
+
+        msg!("SubRegistryProgramApproved: program={}", program);
+        Ok(())
+    }
+
+    // Existing sub-registries on `program` stop resolving until it is approved again
+    pub fn revoke_sub_registry_program(
+        _ctx: Context<RevokeSubRegistryProgram>,
+        program: Pubkey,
+    ) -> Result<()> {
+        msg!("SubRegistryProgramRevoked: program={}", program);
+        Ok(())
+    }
+
+    // Points `*.parent_name` at a child registry program. Calling it again
+    // re-enables the sub-registry or switches it to another program.
+    pub fn create_sub_registry(
+        ctx: Context<CreateSubRegistry>,
+        parent_name: String,
+        sub_registry_program: Pubkey,
+    ) -> Result<()> {
+        require_keys_neq!(sub_registry_program, crate::ID, ErrorCode::InvalidSubRegistryProgram);
+
+        let sub_registry = &mut ctx.accounts.sub_registry;
+        sub_registry.parent_name = parent_name.clone();
+        sub_registry.sub_registry_program = sub_registry_program;
+        sub_registry.owner = ctx.accounts.owner.key();
+        sub_registry.enabled = true;
+        sub_registry.bump = ctx.bumps.sub_registry;
+
+        msg!("SubRegistryCreated: parent={}, program={}", parent_name, sub_registry_program);
+        Ok(())
+    }
+
+    pub fn disable_sub_registry(
+        ctx: Context<DisableSubRegistry>,
+        parent_name: String,
+    ) -> Result<()> {
+        ctx.accounts.sub_registry.enabled = false;
+
+        msg!("SubRegistryDisabled: parent={}", parent_name);
+        Ok(())
+    }
+
+    // CPIs into the parent's sub-registry, which must expose the Anchor
+    // instruction `resolve(label: String) -> Pubkey`. Remaining accounts are
+    // forwarded read-only and never as signers, so the child program can't
+    // spend or sign with anything the caller passed in.
+    pub fn resolve_subdomain<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ResolveSubdomain<'info>>,
+        parent_name: String,
+        label: String,
+    ) -> Result<Pubkey> {
+        let sub_registry_program = ctx.accounts.sub_registry.sub_registry_program;
+
+        let mut data = hashv(&[b"global:resolve"]).to_bytes()[..8].to_vec();
+        label.serialize(&mut data)?;
+        let accounts = ctx.remaining_accounts.iter()
+            .map(|account| AccountMeta::new_readonly(account.key(), false))
+            .collect();
+        let ix = Instruction { program_id: sub_registry_program, accounts, data };
+
+        let mut account_infos = ctx.remaining_accounts.to_vec();
+        account_infos.push(ctx.accounts.sub_registry_program.to_account_info());
+        invoke(&ix, &account_infos)?;
+
+        let (program_id, return_data) = get_return_data().ok_or(ErrorCode::InvalidSubRegistryResponse)?;
+        require_keys_eq!(program_id, sub_registry_program, ErrorCode::InvalidSubRegistryResponse);
+        let resolved = Pubkey::try_from_slice(&return_data)
+            .map_err(|_| error!(ErrorCode::InvalidSubRegistryResponse))?;
+
+        msg!("SubdomainResolved: parent={}, label={}, resolved={}", parent_name, label, resolved);
+        Ok(resolved)
+    }
+
+    pub fn record_resolution(
+        ctx: Context<RecordResolution>,
+        name: String,
+    ) -> Result<()> {
+        let stats = &mut ctx.accounts.name_stats;
+        if stats.name.is_empty() {
+            stats.name = name.clone();
+            stats.bump = ctx.bumps.name_stats;
+        }
+        stats.resolution_count = stats.resolution_count.saturating_add(1);
+        stats.last_resolved_at = Clock::get()?.unix_timestamp;
+
+        msg!("NameResolved: name={}, resolution_count={}", name, stats.resolution_count);
+        Ok(())
+    }
+
+    // Measures the compute units spent deriving and reading a name's PDA and
+    // stores the result for CU budget checks in integration tests
+    pub fn benchmark_resolve(
+        ctx: Context<BenchmarkResolve>,
+        name: String,
+    ) -> Result<u64> {
+        let before = sol_remaining_compute_units();
+        let (expected, _) = Pubkey::find_program_address(&[b"name", name.as_bytes()], ctx.program_id);
+        require_keys_eq!(ctx.accounts.name_record.key(), expected, ErrorCode::NameNotFound);
+        let resolved = {
+            let data = ctx.accounts.name_record.try_borrow_data()?;
+            NameRecord::try_deserialize(&mut &data[..])?.resolved
+        };
+        let cu_used = before.saturating_sub(sol_remaining_compute_units());
+
+        let counter = &mut ctx.accounts.counter;
+        let index = counter.count;
+        counter.count += 1;
+        counter.bump = ctx.bumps.counter;
+
+        let result = &mut ctx.accounts.result;
+        result.name_len = name.len() as u8;
+        result.cu_used = cu_used;
+        result.timestamp = Clock::get()?.unix_timestamp;
+        result.bump = ctx.bumps.result;
+
+        msg!("BenchmarkRecorded: index={}, name={}, resolved={}, cu_used={}", index, name, resolved, cu_used);
+        Ok(cu_used)
+    }
+
+    // Emits stored benchmark results; pass the BenchmarkResult PDAs for
+    // indices from..from + limit as remaining accounts
+    pub fn get_benchmark_results<'info>(
+        ctx: Context<'_, '_, 'info, 'info, GetBenchmarkResults>,
+        from: u64,
+        limit: u64,
+    ) -> Result<()> {
+        for (offset, info) in ctx.remaining_accounts.iter().take(limit as usize).enumerate() {
+            let index = from + offset as u64;
+            let (expected, _) = Pubkey::find_program_address(&[b"benchmark", &index.to_le_bytes()], ctx.program_id);
+            require_keys_eq!(info.key(), expected, ErrorCode::InvalidBenchmarkAccount);
+
+            let result = Account::<BenchmarkResult>::try_from(info)?;
+            msg!("BenchmarkResult: index={}, name_len={}, cu_used={}, timestamp={}",
+                 index, result.name_len, result.cu_used, result.timestamp);
+        }
+        Ok(())
+    }
+
+    pub fn set_primary_name(
+        ctx: Context<SetPrimaryName>,
+        name: String,
+    ) -> Result<()> {
+        // Verify user owns the name
+        let name_record = &ctx.accounts.name_record;
+        require!(name_record.owner == ctx.accounts.user.key(), ErrorCode::Unauthorized);
+
+        let primary = &mut ctx.accounts.primary_name;
+        primary.owner = ctx.accounts.user.key();
+        primary.name = name.clone();
+        primary.bump = ctx.bumps.primary_name;
+
+        msg!("PrimaryNameSet: owner={}, name={}", primary.owner, name);
+
+        Ok(())
+    }
+
+    // Called by the primary name's owner after transferring it away. Switches
+    // to `replacement` when given, otherwise clears the primary name.
+    pub fn validate_primary_name(ctx: Context<ValidatePrimaryName>) -> Result<()> {
+        let user = ctx.accounts.user.key();
+        if ctx.accounts.name_record.owner == user {
+            msg!("PrimaryNameValid: owner={}, name={}", user, ctx.accounts.primary_name.name);
+            return Ok(());
+        }
+
+        let stale = ctx.accounts.primary_name.name.clone();
+        let replacement = ctx.accounts.replacement.as_ref().map(|record| record.name.clone());
+        let primary = &mut ctx.accounts.primary_name;
+        primary.name = replacement.unwrap_or_default();
+
+        msg!("StalePrimaryCleared: owner={}, name={}", user, stale);
+        if !primary.name.is_empty() {
+            msg!("PrimaryNameSet: owner={}, name={}", user, primary.name);
+        }
+        Ok(())
+    }
+
+    pub fn set_expiry_config(
+        ctx: Context<SetExpiryConfig>,
+        registration_period_seconds: i64,
+        expiry_warning_window_seconds: i64,
+    ) -> Result<()> {
+        require!(
+            (0..=MAX_REGISTRATION_PERIOD_SECONDS).contains(&registration_period_seconds),
+            ErrorCode::InvalidRegistrationPeriod
+        );
+        require!(expiry_warning_window_seconds > 0, ErrorCode::InvalidRegistrationPeriod);
+
+        let config = &mut ctx.accounts.config;
+        config.registration_period_seconds = registration_period_seconds;
+        config.expiry_warning_window_seconds = expiry_warning_window_seconds;
+
+        msg!("ExpiryConfigSet: registration_period_seconds={}, expiry_warning_window_seconds={}",
+             registration_period_seconds, expiry_warning_window_seconds);
+        Ok(())
+    }
+
+    // Buys one more registration period for `name`, from its current expiry or
+    // from now if it has lapsed. Anyone may pay; a name without a NameExpiry
+    // account has never been renewed and does not expire
+    pub fn renew_name(ctx: Context<RenewName>, name: String) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let period = config.registration_period_seconds;
+        require!(period > 0, ErrorCode::NamesDoNotExpire);
+
+        let cpi_accounts = system_program::Transfer {
+            from: ctx.accounts.payer.to_account_info(),
+            to: ctx.accounts.treasury.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+        system_program::transfer(cpi_ctx, config.registration_fee)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let name_expiry = &mut ctx.accounts.name_expiry;
+        name_expiry.name = name.clone();
+        name_expiry.expires_at = name_expiry.expires_at.max(now).saturating_add(period);
+        name_expiry.bump = ctx.bumps.name_expiry;
+
+        msg!("NameRenewed: name={}, payer={}, expires_at={}",
+             name, ctx.accounts.payer.key(), name_expiry.expires_at);
+        Ok(())
+    }
+
+    // Owner asks to be warned before `name` expires; the rent comes back when
+    // the warning is emitted
+    pub fn schedule_expiry_warning(ctx: Context<ScheduleExpiryWarning>, name: String) -> Result<()> {
+        let notify = &mut ctx.accounts.notify_expiring;
+        notify.name = name.clone();
+        notify.owner = ctx.accounts.owner.key();
+        notify.expires_at = ctx.accounts.name_expiry.expires_at;
+        notify.warned_at = 0;
+        notify.bump = ctx.bumps.notify_expiring;
+
+        msg!("ExpiryWarningScheduled: name={}, owner={}, expires_at={}", name, notify.owner, notify.expires_at);
+        Ok(())
+    }
+
+    // Permissionless crank: once `name` is inside the warning window, logs the
+    // warning, closes the NotifyExpiring account and pays the caller up to
+    // EXPIRY_WARNING_REWARD_LAMPORTS out of lamports sent to the stats account
+    pub fn emit_expiry_warning(ctx: Context<EmitExpiryWarning>, name: String) -> Result<()> {
+        let window = ctx.accounts.config.expiry_warning_window_seconds;
+        let now = Clock::get()?.unix_timestamp;
+        // A renewal since scheduling moves the expiry, so read the live value
+        let expires_at = ctx.accounts.name_expiry.expires_at;
+        let remaining = expires_at.saturating_sub(now);
+        require!(remaining > 0 && remaining <= window, ErrorCode::ExpiryWarningNotDue);
+
+        let notify = &mut ctx.accounts.notify_expiring;
+        require!(
+            notify.warned_at == 0 || now.saturating_sub(notify.warned_at) >= window,
+            ErrorCode::ExpiryWarningTooRecent
+        );
+        notify.expires_at = expires_at;
+        notify.warned_at = now;
+
+        let stats_info = ctx.accounts.stats.to_account_info();
+        let rent_exempt_minimum = Rent::get()?.minimum_balance(stats_info.data_len());
+        let reward = stats_info
+            .lamports()
+            .saturating_sub(rent_exempt_minimum)
+            .min(EXPIRY_WARNING_REWARD_LAMPORTS);
+        if reward > 0 {
+            // Stats is owned by this program, so lamports can be moved directly
+            **stats_info.try_borrow_mut_lamports()? -= reward;
+            **ctx.accounts.caller.to_account_info().try_borrow_mut_lamports()? += reward;
+        }
+
+        msg!("NameExpiringWarning: name={}, owner={}, expires_at={}, days_left={}",
+             name, notify.owner, expires_at, remaining / (24 * 60 * 60));
+        msg!("ExpiryWarningCranked: name={}, caller={}, reward={}", name, ctx.accounts.caller.key(), reward);
+        Ok(())
+    }
+
+    // Permissionless crank clearing a primary name its owner no longer holds
+    pub fn invalidate_stale_primary(
+        ctx: Context<InvalidateStalePrimary>,
+        user: Pubkey,
+    ) -> Result<()> {
+        require!(ctx.accounts.name_record.owner != user, ErrorCode::PrimaryNameNotStale);
+
+        let primary = &mut ctx.accounts.primary_name;
+        let stale = std::mem::take(&mut primary.name);
+
+        msg!("StalePrimaryCleared: owner={}, name={}", user, stale);
+        Ok(())
+    }
+
+    // Grows a config account created with the original layout to
+    // RegistryConfig::LEN. Fields keep their offsets; the appended ones get the
+    // same defaults initialize uses and the old relayer allowlist flag is
+    // copied to every per-instruction gate
+    pub fn migrate_config(ctx: Context<MigrateConfig>) -> Result<()> {
+        let config_info = ctx.accounts.config.to_account_info();
+        require!(config_info.data_len() == LEGACY_REGISTRY_CONFIG_SPACE, ErrorCode::AlreadyMigrated);
+
+        let legacy = {
+            let data = config_info.try_borrow_data()?;
+            require!(
+                data[..8] == *RegistryConfig::DISCRIMINATOR,
+                ErrorCode::InvalidConfigAccount
+            );
+            LegacyRegistryConfig::deserialize(&mut &data[8..])
+                .map_err(|_| error!(ErrorCode::InvalidConfigAccount))?
+        };
+        require!(legacy.admin == ctx.accounts.admin.key(), ErrorCode::Unauthorized);
+
+        let rent_due = Rent::get()?
+            .minimum_balance(RegistryConfig::LEN)
+            .saturating_sub(config_info.lamports());
+        if rent_due > 0 {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.admin.to_account_info(),
+                to: config_info.clone(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            system_program::transfer(cpi_ctx, rent_due)?;
+        }
+        config_info.resize(RegistryConfig::LEN)?;
+
+        let mut config = RegistryConfig {
+            admin: legacy.admin,
+            pending_admin: legacy.pending_admin,
+            treasury: legacy.treasury,
+            registration_fee: legacy.registration_fee,
+            referrer_bps: legacy.referrer_bps,
+            legacy_relayer_allowlist: legacy.require_allowlisted_relayer,
+            bump: legacy.bump,
+            ..Default::default()
+        };
+        config.set_appended_defaults();
+        config.relayer_allowlist_per_instruction = [legacy.require_allowlisted_relayer; 4];
+        config.try_serialize(&mut &mut config_info.try_borrow_mut_data()?[..])?;
+
+        msg!("ConfigMigrated: admin={}, len={}", config.admin, RegistryConfig::LEN);
+        Ok(())
+    }
+
+    // Grows a NameRecord to NAME_RECORD_V2_SPACE. Records from before
+    // program_version was added no longer deserialize as NameRecord, so both
+    // layouts are read by hand; fields the legacy layout lacks get their
+    // registration defaults.
+    pub fn migrate_name_record(
+        ctx: Context<MigrateNameRecord>,
+        name: String,
+    ) -> Result<()> {
+        let record_info = ctx.accounts.name_record.to_account_info();
+        let mut name_record = {
+            let data = record_info.try_borrow_data()?;
+            require!(data[..8] == *NameRecord::DISCRIMINATOR, ErrorCode::InvalidNameRecordAccount);
+            match NameRecord::deserialize(&mut &data[8..]) {
+                Ok(record) => record,
+                Err(_) => {
+                    let legacy = LegacyNameRecord::deserialize(&mut &data[8..])
+                        .map_err(|_| error!(ErrorCode::InvalidNameRecordAccount))?;
+                    NameRecord {
+                        name: legacy.name,
+                        owner: legacy.owner,
+                        resolved: legacy.resolved,
+                        updated_at: legacy.updated_at,
+                        bump: legacy.bump,
+                        program_version: 1,
+                        ttl_seconds: DEFAULT_TTL_SECONDS,
+                        locked: false,
+                        proxy_name: None,
+                        created_at: 0,
+                    }
+                }
+            }
+        };
+        require!(!name_record.has_migrated(), ErrorCode::AlreadyMigrated);
+
+        let payer = ctx.accounts.payer.key();
+        require!(
+            payer == name_record.owner || payer == ctx.accounts.config.admin,
+            ErrorCode::Unauthorized
+        );
+
+        let rent_due = Rent::get()?
+            .minimum_balance(NAME_RECORD_V2_SPACE)
+            .saturating_sub(record_info.lamports());
+        if rent_due > 0 {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.payer.to_account_info(),
+                to: record_info.clone(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            system_program::transfer(cpi_ctx, rent_due)?;
+        }
+        record_info.resize(NAME_RECORD_V2_SPACE)?;
+
+        name_record.program_version = 2;
+        name_record.try_serialize(&mut &mut record_info.try_borrow_mut_data()?[..])?;
+
+        msg!("NameRecordMigrated: name={}, program_version={}, payer={}",
+             name, name_record.program_version, payer);
+
+        Ok(())
+    }
+
+    pub fn generate_ownership_proof(
+        ctx: Context<GenerateOwnershipProof>,
+        name: String,
+        challenge: [u8; 32],
+    ) -> Result<[u8; 32]> {
+        let owner = ctx.accounts.owner.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        let proof_hash = hashv(&[
+            crate::ID.as_ref(),
+            name.as_bytes(),
+            owner.as_ref(),
+            &challenge,
+            &now.to_le_bytes(),
+        ]);
+        let valid_until = now + PROOF_VALIDITY_SECONDS;
+
+        let proof = &mut ctx.accounts.ownership_proof;
+        proof.name = name.clone();
+        proof.owner = owner;
+        proof.challenge = challenge;
+        proof.proof_hash = proof_hash.to_bytes();
+        proof.valid_until = valid_until;
+        proof.bump = ctx.bumps.ownership_proof;
+
+        msg!("OwnershipProofGenerated: name={}, owner={}, proof_hash={}, valid_until={}",
+             name, owner, proof_hash, valid_until);
+
+        Ok(proof_hash.to_bytes())
+    }
+
+    /// Registers a relayed, token-paid name. In allowlist mode the relayer's
+    /// RelayerEntry is passed as the first remaining account.
+    ///
+    /// Request a compute unit limit of at least 200_000 with
+    /// ComputeBudgetInstruction::set_compute_unit_limit. The handler fails with
+    /// InsufficientComputeUnits if fewer than MIN_REQUIRED_CU remain when it starts.
+    pub fn register_name_with_signature_token<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RegisterNameWithSignatureToken<'info>>,
+        params: RegisterWithSigParams,
+        signature: Vec<u8>,
+    ) -> Result<RegistrationResult> {
+        assert_compute_budget(MIN_REQUIRED_CU)?;
+        validate_name(&params.name, ctx.accounts.charset.as_deref())?;
+        let config = &ctx.accounts.config;
+        require!(!config.governance_gated, ErrorCode::GovernanceGateActive);
+        check_deadline(config, params.deadline)?;
+        load_allowlisted_relayer(
+            config,
+            RELAYER_GATE_REGISTER_NAME_WITH_SIG_TOKEN,
+            &ctx.accounts.relayer.key(),
+            ctx.remaining_accounts,
+        )?;
+        require!(ctx.accounts.relayer.key() == params.relayer, ErrorCode::Unauthorized);
+        verify_signature(&params, &signature, &ctx.accounts.instructions_sysvar)?;
+        // The nonce is part of the signed message, so each signature is usable once
+        let owner_nonce = &mut ctx.accounts.owner_nonce;
+        require!(owner_nonce.nonce == params.nonce, ErrorCode::InvalidNonce);
+        owner_nonce.owner = params.owner;
+        owner_nonce.nonce += 1;
+        owner_nonce.bump = ctx.bumps.owner_nonce;
+        let token_fee = &ctx.accounts.token_fee;
+        require!(token_fee.enabled, ErrorCode::TokenNotEnabled);
+        require!(token_fee.amount == params.amount, ErrorCode::TokenFeeMismatch);
+
+        // Populate name record
+        // The owner signed the name as submitted; records use the normalized form
+        let name = params.name.to_ascii_lowercase();
+        let fee_amount = nft_discounted_fee(
+            &name,
+            token_fee.amount,
+            &params.owner,
+            ctx.accounts.nft_discount.as_deref(),
+            ctx.accounts.nft_token_account.as_deref(),
+            ctx.accounts.nft_metadata.as_deref(),
+        )?;
+        consume_whitelist_slot(&ctx.accounts.config, ctx.accounts.whitelist.as_deref_mut(), params.owner)?;
+        let name_record = &mut ctx.accounts.name_record;
+        assert_fresh_name_record(name_record)?;
+        name_record.name = name.clone();
+        name_record.owner = params.owner;
+        name_record.resolved = params.owner;
+        name_record.updated_at = Clock::get()?.unix_timestamp;
+        name_record.program_version = 1;
+        name_record.ttl_seconds = DEFAULT_TTL_SECONDS;
+        name_record.locked = false;
+        name_record.proxy_name = None;
+        name_record.created_at = Clock::get()?.unix_timestamp;
+        record_owner_name(
+            &mut ctx.accounts.owner_count,
+            name_record.owner,
+            ctx.bumps.owner_count,
+            ctx.accounts.config.max_names_per_owner,
+        )?;
+        record_registration_stats(
+            ctx.accounts.stats.as_deref_mut(),
+            name_record.name.len(),
+            None,
+        );
+        name_record.bump = ctx.bumps.name_record;
+
+        if let Some(evm_owner) = ctx.accounts.evm_owner.as_mut() {
+            require!(params.signature_type == SignatureType::Secp256k1, ErrorCode::InvalidSignatureType);
+            if evm_owner.name.is_empty() {
+                evm_owner.name = name.clone();
+                evm_owner.evm_address = params.evm_address.ok_or(ErrorCode::InvalidSignatureType)?;
+                evm_owner.bump = ctx.bumps.evm_owner.ok_or(ErrorCode::InvalidSignatureType)?;
+            }
+        }
+
+        // Compute referrer split (referrer remains relayer for now)
+        let effective_bps = config.referrer_bps;
+        require!(effective_bps >= config.min_referrer_bps, ErrorCode::ReferrerBpsBelowFloor);
+        let referrer_amount = (fee_amount as u128)
+            .checked_mul(effective_bps as u128)
+            .unwrap()
+            .checked_div(10_000)
+            .unwrap() as u64;
+        let treasury_amount = fee_amount - referrer_amount;
+
+        // Transfer tokens to treasury
+        let cpi_accounts = anchor_spl::token::Transfer {
+            from: ctx.accounts.relayer_token_account.to_account_info(),
+            to: ctx.accounts.treasury_token_account.to_account_info(),
+            authority: ctx.accounts.relayer.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        token::transfer(cpi_ctx, treasury_amount)?;
+
+        if referrer_amount > 0 {
+            record_referral(ctx.accounts.referrer_account.as_mut(), 0, referrer_amount);
+        }
+
+        // Set primary name if empty
+        if ctx.accounts.primary_name.owner == Pubkey::default() {
+            let primary = &mut ctx.accounts.primary_name;
+            primary.owner = params.owner;
+            primary.name = name.clone();
+            primary.bump = ctx.bumps.primary_name;
+            msg!("PrimaryNameSet: owner={}, name={}", params.owner, name);
+        }
+
+        msg!("NameRegistered: name={}, owner={}, resolved={}", name, params.owner, params.owner);
+        msg!("FeePaid: name={}, payer={}, amount={}, currency={}, referrer={}, ref_amount={}",
+             name, ctx.accounts.relayer.key(), fee_amount, ctx.accounts.mint.key(),
+             ctx.accounts.relayer.key(), referrer_amount);
+        Ok(registration_result(&ctx.accounts.name_record))
+    }
+
+    // ========================================
+    // GOVERNANCE
+    // ========================================
+
+    pub fn set_governance_config(
+        ctx: Context<SetGovernanceConfig>,
+        voting_token_mint: Pubkey,
+        voting_duration_slots: u64,
+        quorum: u64,
+    ) -> Result<()> {
+        require!(voting_duration_slots > 0, ErrorCode::InvalidProposal);
+
+        let governance = &mut ctx.accounts.governance;
+        governance.voting_token_mint = voting_token_mint;
+        governance.voting_duration_slots = voting_duration_slots;
+        governance.quorum = quorum;
+        governance.bump = ctx.bumps.governance;
+
+        msg!("GovernanceConfigSet: voting_token_mint={}, voting_duration_slots={}, quorum={}",
+             voting_token_mint, voting_duration_slots, quorum);
+        Ok(())
+    }
+
+    // voting_duration = 0 uses the governance default
+    pub fn create_proposal(
+        ctx: Context<CreateProposal>,
+        proposal_type: ProposalType,
+        value: Vec<u8>,
+        voting_duration: u64,
+    ) -> Result<()> {
+        require!(value.len() == proposal_type.value_len(), ErrorCode::InvalidProposal);
+
+        let governance = &mut ctx.accounts.governance;
+        let id = governance.proposal_count;
+        governance.proposal_count += 1;
+        let duration = if voting_duration == 0 { governance.voting_duration_slots } else { voting_duration };
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.id = id;
+        proposal.proposal_type = proposal_type;
+        proposal.value = value;
+        proposal.votes_for = 0;
+        proposal.votes_against = 0;
+        proposal.end_slot = Clock::get()?.slot + duration;
+        proposal.executed = false;
+        proposal.bump = ctx.bumps.proposal;
+
+        msg!("ProposalCreated: id={}, proposal_type={:?}, end_slot={}", id, proposal_type, proposal.end_slot);
+        Ok(())
+    }
+
+    pub fn vote(
+        ctx: Context<Vote>,
+        proposal_id: u64,
+        support: bool,
+    ) -> Result<()> {
+        require!(Clock::get()?.slot <= ctx.accounts.proposal.end_slot, ErrorCode::VotingClosed);
+
+        // Vote weight is the voter's whole voting token balance, locked in the
+        // proposal's escrow until voting ends so it can't be moved and voted again
+        let weight = ctx.accounts.voter_token_account.amount;
+        require!(weight > 0, ErrorCode::InsufficientTokenBalance);
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.voter_token_account.to_account_info(),
+            to: ctx.accounts.vote_escrow.to_account_info(),
+            authority: ctx.accounts.voter.to_account_info(),
+        };
+        token::transfer(CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts), weight)?;
+
+        let proposal = &mut ctx.accounts.proposal;
+        if support {
+            proposal.votes_for = proposal.votes_for.checked_add(weight).ok_or(ErrorCode::InvalidProposal)?;
+        } else {
+            proposal.votes_against = proposal.votes_against.checked_add(weight).ok_or(ErrorCode::InvalidProposal)?;
+        }
+
+        let vote_record = &mut ctx.accounts.vote_record;
+        vote_record.voter = ctx.accounts.voter.key();
+        vote_record.amount = weight;
+        vote_record.bump = ctx.bumps.vote_record;
+
+        msg!("VoteCast: proposal_id={}, voter={}, support={}, weight={}",
+             proposal_id, ctx.accounts.voter.key(), support, weight);
+        Ok(())
+    }
+
+    pub fn execute_proposal(
+        ctx: Context<ExecuteProposal>,
+        proposal_id: u64,
+    ) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        require!(!proposal.executed, ErrorCode::ProposalAlreadyExecuted);
+        require!(Clock::get()?.slot > proposal.end_slot, ErrorCode::VotingStillOpen);
+        proposal.executed = true;
+
+        let total_votes = proposal.votes_for.saturating_add(proposal.votes_against);
+        let passed = proposal.votes_for > proposal.votes_against && total_votes >= ctx.accounts.governance.quorum;
+        if !passed {
+            msg!("ProposalDefeated: id={}, votes_for={}, votes_against={}",
+                 proposal_id, proposal.votes_for, proposal.votes_against);
+            return Ok(());
+        }
+
+        apply_proposal(&mut ctx.accounts.config, proposal.proposal_type, &proposal.value)?;
+
+        msg!("ProposalExecuted: id={}, proposal_type={:?}, votes_for={}, votes_against={}",
+             proposal_id, proposal.proposal_type, proposal.votes_for, proposal.votes_against);
+        Ok(())
+    }
+
+    // Returns the tokens locked by `vote` once voting has ended and closes the vote record
+    pub fn withdraw_vote_tokens(
+        ctx: Context<WithdrawVoteTokens>,
+        proposal_id: u64,
+    ) -> Result<()> {
+        require!(Clock::get()?.slot > ctx.accounts.proposal.end_slot, ErrorCode::VotingStillOpen);
+
+        let amount = ctx.accounts.vote_record.amount;
+        let id_bytes = proposal_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[b"proposal", id_bytes.as_ref(), &[ctx.accounts.proposal.bump]];
+        let signer_seeds = [seeds];
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.vote_escrow.to_account_info(),
+            to: ctx.accounts.voter_token_account.to_account_info(),
+            authority: ctx.accounts.proposal.to_account_info(),
+        };
+        token::transfer(
+            CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, &signer_seeds),
+            amount,
+        )?;
+
+        msg!("VoteTokensWithdrawn: proposal_id={}, voter={}, amount={}",
+             proposal_id, ctx.accounts.voter.key(), amount);
+        Ok(())
+    }
+
+    // ========================================
+    // COMMIT-REVEAL
+    // ========================================
+
+    // A slot_commitment_window of 0 selects DEFAULT_SLOT_COMMITMENT_WINDOW
+    pub fn set_commitment_config(
+        ctx: Context<SetCommitmentConfig>,
+        min_reveal_slots: u64,
+        max_reveal_slots: u64,
+        slot_commitment_window: u64,
+    ) -> Result<()> {
+        require!(min_reveal_slots <= max_reveal_slots, ErrorCode::InvalidCommitmentConfig);
+        let slot_commitment_window = if slot_commitment_window == 0 {
+            DEFAULT_SLOT_COMMITMENT_WINDOW
+        } else {
+            slot_commitment_window
+        };
+
+        let commit_config = &mut ctx.accounts.commit_config;
+        commit_config.min_reveal_slots = min_reveal_slots;
+        commit_config.max_reveal_slots = max_reveal_slots;
+        commit_config.slot_commitment_window = slot_commitment_window;
+        commit_config.bump = ctx.bumps.commit_config;
+
+        msg!("CommitmentConfigSet: min_reveal_slots={}, max_reveal_slots={}, slot_commitment_window={}",
+             min_reveal_slots, max_reveal_slots, slot_commitment_window);
+        Ok(())
+    }
+
+    // Grows a CommitmentConfig created before slot_commitment_window was appended.
+    // Commitments already on chain were hashed without a slot; the current slot
+    // becomes the cutoff below which reveal_name still accepts that hash.
+    pub fn migrate_commitment_config(ctx: Context<MigrateCommitmentConfig>) -> Result<()> {
+        let commit_info = ctx.accounts.commit_config.to_account_info();
+        require!(commit_info.data_len() == LEGACY_COMMITMENT_CONFIG_SPACE, ErrorCode::AlreadyMigrated);
+
+        let legacy = {
+            let data = commit_info.try_borrow_data()?;
+            require!(data[..8] == *CommitmentConfig::DISCRIMINATOR, ErrorCode::InvalidConfigAccount);
+            LegacyCommitmentConfig::deserialize(&mut &data[8..])
+                .map_err(|_| error!(ErrorCode::InvalidConfigAccount))?
+        };
+
+        let rent_due = Rent::get()?
+            .minimum_balance(COMMITMENT_CONFIG_ACCOUNT_SPACE)
+            .saturating_sub(commit_info.lamports());
+        if rent_due > 0 {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.admin.to_account_info(),
+                to: commit_info.clone(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            system_program::transfer(cpi_ctx, rent_due)?;
+        }
+        commit_info.resize(COMMITMENT_CONFIG_ACCOUNT_SPACE)?;
+
+        let commit_config = CommitmentConfig {
+            min_reveal_slots: legacy.min_reveal_slots,
+            max_reveal_slots: legacy.max_reveal_slots,
+            bump: legacy.bump,
+            slot_commitment_window: DEFAULT_SLOT_COMMITMENT_WINDOW,
+            legacy_commitment_cutoff_slot: Clock::get()?.slot,
+        };
+        commit_config.try_serialize(&mut &mut commit_info.try_borrow_mut_data()?[..])?;
+
+        msg!("CommitmentConfigMigrated: slot_commitment_window={}, legacy_commitment_cutoff_slot={}",
+             commit_config.slot_commitment_window, commit_config.legacy_commitment_cutoff_slot);
+        Ok(())
+    }
+
+    // commitment = sha256(name || salt || owner || slot), hiding the name until reveal.
+    // `slot` is the recent slot the client hashed in; it must be within
+    // slot_commitment_window of the landing slot, so a table of precomputed
+    // commitments for common names goes stale after a few blocks
+    pub fn commit_name(
+        ctx: Context<CommitName>,
+        commitment: [u8; 32],
+        slot: u64,
+    ) -> Result<()> {
+        let current_slot = Clock::get()?.slot;
+        let commit_config = &ctx.accounts.commit_config;
+        require!(
+            current_slot.abs_diff(slot) <= commit_config.slot_commitment_window,
+            ErrorCode::CommitmentSlotOutOfWindow
+        );
+        // Keeps new commitments out of the range where reveal_name accepts the legacy hash
+        require!(slot >= commit_config.legacy_commitment_cutoff_slot, ErrorCode::CommitmentSlotOutOfWindow);
+
+        let record = &mut ctx.accounts.commitment;
+        record.committer = ctx.accounts.user.key();
+        record.committed_slot = slot;
+        record.bump = ctx.bumps.commitment;
+
+        msg!("NameCommitted: committer={}, committed_slot={}", record.committer, record.committed_slot);
+        Ok(())
+    }
+
+    pub fn reveal_name(
+        ctx: Context<RevealName>,
+        name: String,
+        salt: [u8; 32],
+    ) -> Result<()> {
+        validate_name(&name, ctx.accounts.charset.as_deref())?;
+        let config = &ctx.accounts.config;
+        require!(!config.whitelist_phase, ErrorCode::WhitelistPhaseActive);
+        require!(!config.governance_gated, ErrorCode::GovernanceGateActive);
+
+        let user = ctx.accounts.user.key();
+        let committed_slot = ctx.accounts.commitment.committed_slot;
+        let commit_config = &ctx.accounts.commit_config;
+        let expected = hashv(&[name.as_bytes(), &salt, user.as_ref(), &committed_slot.to_le_bytes()]);
+        let matches = ctx.accounts.commitment.key() == commitment_pda(&expected.to_bytes())
+            || (committed_slot < commit_config.legacy_commitment_cutoff_slot
+                && ctx.accounts.commitment.key()
+                    == commitment_pda(&hashv(&[name.as_bytes(), user.as_ref(), &salt]).to_bytes()));
+        require!(matches, ErrorCode::CommitmentMismatch);
+
+        let slot = Clock::get()?.slot;
+        require!(slot >= committed_slot + commit_config.min_reveal_slots, ErrorCode::CommitmentTooFresh);
+        require!(slot <= committed_slot + commit_config.max_reveal_slots, ErrorCode::CommitmentExpired);
+        let name = name.to_ascii_lowercase();
+
+        let name_record = &mut ctx.accounts.name_record;
+        assert_fresh_name_record(name_record)?;
+        name_record.name = name.clone();
+        name_record.owner = user;
+        name_record.resolved = user;
+        name_record.updated_at = Clock::get()?.unix_timestamp;
+        name_record.program_version = 1;
+        name_record.ttl_seconds = DEFAULT_TTL_SECONDS;
+        name_record.locked = false;
+        name_record.proxy_name = None;
+        name_record.created_at = Clock::get()?.unix_timestamp;
+        record_owner_name(
+            &mut ctx.accounts.owner_count,
+            name_record.owner,
+            ctx.bumps.owner_count,
+            ctx.accounts.config.max_names_per_owner,
+        )?;
+        record_registration_stats(
+            ctx.accounts.stats.as_deref_mut(),
+            name_record.name.len(),
+            Some(config.registration_fee),
+        );
+        name_record.bump = ctx.bumps.name_record;
+
+        {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.user.to_account_info(),
+                to: ctx.accounts.treasury.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            system_program::transfer(cpi_ctx, config.registration_fee)?;
+        }
+
+        if ctx.accounts.primary_name.owner == Pubkey::default() {
+            let primary = &mut ctx.accounts.primary_name;
+            primary.owner = user;
+            primary.name = name.clone();
+            primary.bump = ctx.bumps.primary_name;
+
+            msg!("PrimaryNameSet: owner={}, name={}", user, name);
+        }
+
+        msg!("NameRegistered: name={}, owner={}, resolved={}", name, user, user);
+        msg!("FeePaid: name={}, payer={}, amount={}, currency=SOL, referrer=None",
+             name, user, config.registration_fee);
+        Ok(())
+    }
+
+    pub fn cleanup_expired_commitment(
+        ctx: Context<CleanupExpiredCommitment>,
+        commitment_bytes: [u8; 32],
+    ) -> Result<()> {
+        let commitment = &ctx.accounts.commitment;
+        let expires_after = commitment.committed_slot + ctx.accounts.commit_config.max_reveal_slots;
+        require!(Clock::get()?.slot > expires_after, ErrorCode::CommitmentNotExpired);
+
+        msg!("CommitmentCleanedUp: commitment={:?}, committer={}, cleaned_by={}",
+             commitment_bytes, commitment.committer, ctx.accounts.caller.key());
+        Ok(())
+    }
+
+    // ========================================
+    // AUCTIONS
+    // ========================================
+
+    pub fn start_auction(
+        ctx: Context<StartAuction>,
+        name: String,
+        start_price: u64,
+        end_price: u64,
+        duration_slots: u64,
+    ) -> Result<()> {
+        validate_name(&name, ctx.accounts.charset.as_deref())?;
+        let name = name.to_ascii_lowercase();
+        require!(start_price >= end_price && duration_slots > 0, ErrorCode::InvalidAuctionParams);
+        // Auctions already running when the gate is set still settle, so no bid is stranded
+        require!(!ctx.accounts.config.governance_gated, ErrorCode::GovernanceGateActive);
+
+        let start_slot = Clock::get()?.slot;
+        let auction = &mut ctx.accounts.auction;
+        auction.name = name.clone();
+        auction.start_price = start_price;
+        auction.end_price = end_price;
+        auction.start_slot = start_slot;
+        auction.end_slot = start_slot + duration_slots;
+        auction.winner = None;
+        auction.winning_bid = 0;
+        auction.bump = ctx.bumps.auction;
+
+        // The record is created here, held by the auction PDA, so no other
+        // registration path can take the name while the auction runs
+        let now = Clock::get()?.unix_timestamp;
+        let name_record = &mut ctx.accounts.name_record;
+        name_record.name = name.clone();
+        name_record.owner = auction.key();
+        name_record.resolved = auction.key();
+        name_record.updated_at = now;
+        name_record.program_version = 1;
+        name_record.ttl_seconds = DEFAULT_TTL_SECONDS;
+        name_record.locked = false;
+        name_record.proxy_name = None;
+        name_record.created_at = now;
+        name_record.bump = ctx.bumps.name_record;
+
+        msg!("AuctionStarted: name={}, start_price={}, end_price={}, start_slot={}, end_slot={}",
+             name, start_price, end_price, auction.start_slot, auction.end_slot);
+        Ok(())
+    }
+
+    pub fn place_bid(
+        ctx: Context<PlaceBid>,
+        name: String,
+    ) -> Result<()> {
+        let auction = &ctx.accounts.auction;
+        let slot = Clock::get()?.slot;
+        let price = auction.current_price(slot);
+
+        // A winner who lets the settle window lapse is refunded in place and the
+        // name goes to the new bidder at the current price
+        if let Some(previous_bidder) = auction.winner {
+            require!(
+                slot > auction.end_slot.saturating_add(AUCTION_SETTLE_WINDOW_SLOTS),
+                ErrorCode::AuctionClosed
+            );
+            let refund_to = ctx.accounts.previous_bidder.as_ref().ok_or(ErrorCode::PreviousBidderRequired)?;
+            require_keys_eq!(refund_to.key(), previous_bidder, ErrorCode::PreviousBidderRequired);
+
+            let refund = auction.winning_bid;
+            **ctx.accounts.auction.to_account_info().try_borrow_mut_lamports()? -= refund;
+            **refund_to.to_account_info().try_borrow_mut_lamports()? += refund;
+
+            msg!("AuctionBidRefunded: name={}, bidder={}, amount={}", name, previous_bidder, refund);
+        }
+
+        // Escrow the bid in the auction PDA until settlement
+        {
+            let cpi_accounts = system_program::Transfer {
+                from: ctx.accounts.bidder.to_account_info(),
+                to: ctx.accounts.auction.to_account_info(),
+            };
+            let cpi_ctx = CpiContext::new(ctx.accounts.system_program.to_account_info(), cpi_accounts);
+            system_program::transfer(cpi_ctx, price)?;
+        }
+
+        // Dutch auction: first bid at the current price wins
+        let auction = &mut ctx.accounts.auction;
+        auction.winner = Some(ctx.accounts.bidder.key());
+        auction.winning_bid = price;
+        // Restarts the settle window for a bid on a reopened auction
+        auction.end_slot = auction.end_slot.max(slot);
+
+        msg!("AuctionBidPlaced: name={}, bidder={}, amount={}", name, ctx.accounts.bidder.key(), price);
+        Ok(())
+    }
+
+    // Ends an auction nobody bid on and releases the held name record
+    pub fn cancel_auction(
+        ctx: Context<CancelAuction>,
+        name: String,
+    ) -> Result<()> {
+        require!(ctx.accounts.auction.winner.is_none(), ErrorCode::AuctionClosed);
+
+        msg!("AuctionCancelled: name={}", name.to_ascii_lowercase());
+        Ok(())
+    }
+
+    pub fn settle_auction(
+        ctx: Context<SettleAuction>,
+        name: String,
+    ) -> Result<()> {
+        let winning_bid = ctx.accounts.auction.winning_bid;
+        let name = name.to_ascii_lowercase();
+
+        consume_whitelist_slot(&ctx.accounts.config, ctx.accounts.whitelist.as_deref_mut(), ctx.accounts.winner.key())?;
+
+        // Move escrowed bid from the auction PDA to treasury
+        **ctx.accounts.auction.to_account_info().try_borrow_mut_lamports()? -= winning_bid;
+        **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += winning_bid;
+
+        // The record has been held by the auction PDA since start_auction
+        let winner = ctx.accounts.winner.key();
+        let now = Clock::get()?.unix_timestamp;
+        let name_record = &mut ctx.accounts.name_record;
+        name_record.owner = winner;
+        name_record.resolved = winner;
+        name_record.updated_at = now;
+        name_record.created_at = now;
+        record_owner_name(
+            &mut ctx.accounts.owner_count,
+            name_record.owner,
+            ctx.bumps.owner_count,
+            ctx.accounts.config.max_names_per_owner,
+        )?;
+        record_registration_stats(
+            ctx.accounts.stats.as_deref_mut(),
+            name_record.name.len(),
+            Some(winning_bid),
+        );
+
+        if ctx.accounts.primary_name.owner == Pubkey::default() {
+            let primary = &mut ctx.accounts.primary_name;
+            primary.owner = winner;
+            primary.name = name.clone();
+            primary.bump = ctx.bumps.primary_name;
+
+            msg!("PrimaryNameSet: owner={}, name={}", winner, name);
+        }
+
+        msg!("AuctionSettled: name={}, winner={}, amount={}", name, winner, winning_bid);
+        msg!("NameRegistered: name={}, owner={}, resolved={}", name, winner, winner);
+        Ok(())
+    }
+}
+
+// ========================================
+// DATA STRUCTURES
+// ========================================
+
+// Fields up to `bump` are the original layout; everything after it is appended
+// in the order it was added. Never insert a field before the end
+#[account]
+#[derive(Default)]
+pub struct RegistryConfig {
+    pub admin: Pubkey,                    // 32
+    pub pending_admin: Option<Pubkey>,    // 33
+    pub treasury: Pubkey,                 // 32
+    pub registration_fee: u64,            // 8
+    pub referrer_bps: u16,                // 2
+    // Superseded by relayer_allowlist_per_instruction; kept so the fields
+    // after it stay at their original offsets
+    pub legacy_relayer_allowlist: bool,   // 1
+    pub bump: u8,                         // 1
+    pub min_referrer_bps: u16,            // 2
+    pub allow_admin_override: bool,       // 1
+    pub min_relayer_bond: u64,            // 8
+    pub whitelist_phase: bool,            // 1
+    pub max_ttl_seconds: u32,             // 4
+    pub locked_names_transferable: bool,  // 1
+    pub max_relayer_violations: u8,       // 1
+    pub reset_resolved_on_transfer: bool, // 1
+    pub max_names_per_owner: u32,         // 4 (0 = unlimited)
+    pub min_token_fee: u64,               // 8
+    pub max_token_fee: u64,               // 8
+    pub min_sol_fee: u64,                 // 8
+    pub max_sol_fee: u64,                 // 8
+    // Relayer allowlist per registration path, indexed by RELAYER_GATE_*
+    pub relayer_allowlist_per_instruction: [bool; 4], // 4
+    pub max_deadline_window_seconds: i64, // 8
+    pub max_signature_validity_seconds: i64, // 8
+    pub min_compute_unit_price: u64,      // 8 (micro-lamports, 0 = off)
+    pub registration_period_seconds: i64, // 8 (0 = names never expire)
+    pub expiry_warning_window_seconds: i64, // 8
+    // Set while a GovernanceGate is configured; every other registration path refuses
+    pub governance_gated: bool,           // 1
+    // Total: 209 bytes + discriminator
+}
+
+impl RegistryConfig {
+    /// Discriminator + current fields + padding. New fields are appended at the
+    /// end and taken out of the padding, so accounts created at this LEN never
+    /// need a realloc. Accounts from the original program are grown by
+    /// `migrate_config`.
+    pub const LEN: usize = 8 + 209 + 23;
+
+    /// Defaults for every field appended after `bump`.
+    pub fn set_appended_defaults(&mut self) {
+        self.min_referrer_bps = 0;
+        self.allow_admin_override = true;
+        self.min_relayer_bond = 0;
+        self.whitelist_phase = false;
+        self.max_ttl_seconds = DEFAULT_MAX_TTL_SECONDS;
+        self.locked_names_transferable = true;
+        self.max_relayer_violations = DEFAULT_MAX_RELAYER_VIOLATIONS;
+        self.reset_resolved_on_transfer = false;
+        self.max_names_per_owner = DEFAULT_MAX_NAMES_PER_OWNER;
+        // A zero token fee would make registration free
+        self.min_token_fee = 1;
+        self.max_token_fee = u64::MAX;
+        self.min_sol_fee = 0;
+        self.max_sol_fee = u64::MAX;
+        self.relayer_allowlist_per_instruction = [false; 4];
+        self.max_deadline_window_seconds = DEFAULT_MAX_DEADLINE_WINDOW_SECONDS;
+        self.max_signature_validity_seconds = DEFAULT_MAX_SIGNATURE_VALIDITY_SECONDS;
+        self.min_compute_unit_price = 0;
+        self.registration_period_seconds = 0;
+        self.expiry_warning_window_seconds = DEFAULT_EXPIRY_WARNING_WINDOW_SECONDS;
+        self.governance_gated = false;
+    }
+}
+
+// RegistryConfig as the original program laid it out, read by migrate_config
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyRegistryConfig {
+    pub admin: Pubkey,                     // 32
+    pub pending_admin: Option<Pubkey>,     // 33
+    pub treasury: Pubkey,                  // 32
+    pub registration_fee: u64,             // 8
+    pub referrer_bps: u16,                 // 2
+    pub require_allowlisted_relayer: bool, // 1
+    pub bump: u8,                          // 1
+}
+
+// On-chain record of the deployed version and who can upgrade the program
+#[account]
+pub struct ProgramMeta {
+    pub version: [u8; 3],          // 3
+    pub upgrade_authority: Pubkey, // 32
+    pub deployed_at: i64,          // 8
+    pub bump: u8,                  // 1
+    // signing::DOMAIN_SEPARATOR, prefixed to every signed message
+    pub domain_separator: [u8; 32], // 32
+}
+
+// ProgramMeta before domain_separator was appended, read by migrate_program_meta
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyProgramMeta {
+    pub version: [u8; 3],          // 3
+    pub upgrade_authority: Pubkey, // 32
+    pub deployed_at: i64,          // 8
+    pub bump: u8,                  // 1
+}
+
+// Squads multisig that holds admin rights while enabled
+#[account]
+pub struct MultiSigConfig {
+    pub multisig_address: Pubkey, // 32
+    pub threshold: u8,            // 1
+    pub enabled: bool,            // 1
+    pub bump: u8,                 // 1
+}
+
+// Returned by the register_name* instructions so integrators get the new record
+// without fetching it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RegistrationResult {
+    pub name: String,
+    pub owner: Pubkey,
+    pub resolved: Pubkey,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+// Stable, typed snapshot of RegistryConfig returned by get_config
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RegistryConfigView {
+    pub admin: Pubkey,
+    pub pending_admin: Option<Pubkey>,
+    pub treasury: Pubkey,
+    pub registration_fee: u64,
+    pub referrer_bps: u16,
+    pub min_referrer_bps: u16,
+    pub min_relayer_bond: u64,
+    pub relayer_allowlist_per_instruction: [bool; 4],
+    pub allow_admin_override: bool,
+    pub whitelist_phase: bool,
+    pub max_ttl_seconds: u32,
+    pub locked_names_transferable: bool,
+    pub max_relayer_violations: u8,
+    pub reset_resolved_on_transfer: bool,
+    pub max_names_per_owner: u32,
+    pub min_token_fee: u64,
+    pub max_token_fee: u64,
+    pub min_sol_fee: u64,
+    pub max_sol_fee: u64,
+    pub max_deadline_window_seconds: i64,
+    pub max_signature_validity_seconds: i64,
+    pub min_compute_unit_price: u64,
+    pub registration_period_seconds: i64,
+    pub expiry_warning_window_seconds: i64,
+    pub governance_gated: bool,
+    pub min_name_len: u8,
+    pub max_name_len: u8,
+}
+
+#[account]
+pub struct NameRecord {
+    pub name: String,         // 4 + len (up to 63)
+    pub owner: Pubkey,        // 32
+    pub resolved: Pubkey,     // 32
+    pub updated_at: i64,      // 8
+    pub bump: u8,             // 1
+    pub program_version: u8,  // 1
+    pub ttl_seconds: u32,     // 4 (cache hint for resolvers)
+    pub locked: bool,         // 1 (resolution frozen, see lock_name)
+    pub proxy_name: Option<String>, // 1 (+ 4 + len when set, see set_proxy)
+    pub created_at: i64,      // 8 (set once at registration, never updated)
+    // Total: 92 bytes + name length + proxy name + discriminator
+}
+
+// NameRecord before program_version and the later fields, read by migrate_name_record
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyNameRecord {
+    pub name: String,     // 4 + len (up to 63)
+    pub owner: Pubkey,    // 32
+    pub resolved: Pubkey, // 32
+    pub updated_at: i64,  // 8
+    pub bump: u8,         // 1
+}
+
+impl NameRecord {
+    /// True once the account has been grown by `migrate_name_record`.
+    pub fn has_migrated(&self) -> bool {
+        self.program_version >= 2
+    }
+
+    /// Account size needed to hold `name` with the given proxy name.
+    pub fn space_with_proxy(name: &str, proxy_name: Option<&String>) -> usize {
+        8 + NAME_RECORD_BASE_SPACE + name.len() + proxy_name.map_or(0, |p| 4 + p.len())
+    }
+}
+
+#[account]
+pub struct BenchmarkCounter {
+    pub count: u64, // 8
+    pub bump: u8,   // 1
+}
+
+// Compute units spent resolving a name, seeded [b"benchmark", index]
+#[account]
+pub struct BenchmarkResult {
+    pub name_len: u8,   // 1
+    pub cu_used: u64,   // 8
+    pub timestamp: i64, // 8
+    pub bump: u8,       // 1
+}
+
+#[account]
+pub struct NameStats {
+    pub name: String,           // 4 + len (up to 63)
+    pub resolution_count: u64,  // 8
+    pub last_resolved_at: i64,  // 8
+    pub bump: u8,               // 1
+    // Total: 21 bytes + name length; allocated at max name length
+}
+
+#[account]
+pub struct TokenFeeConfig {
     pub mint: Pubkey,         // 32
     pub amount: u64,          // 8
     pub enabled: bool,        // 1
     pub bump: u8,             // 1
-    // Total: ~50 bytes + discriminator
+    // Total: ~50 bytes + discriminator
+}
+
+// Kept apart from NameRecord so existing records don't need a realloc
+#[account]
+pub struct NameBio {
+    pub name_hash: [u8; 32], // 32
+    pub bio: String,         // 4 + len (up to 280)
+    pub bump: u8,            // 1
+}
+
+// Name locked on Solana for claiming on NEAR; owns the name record until it is
+// unlocked, or for good once the NEAR claim is marked
+#[account]
+pub struct BridgeLock {
+    pub name: String,       // 4 + len (up to 63)
+    pub owner: Pubkey,      // 32
+    pub near_owner: String, // 4 + len (up to 64)
+    pub locked_at: i64,     // 8
+    pub message_nonce: u64, // 8
+    pub bump: u8,           // 1
+    pub claimed: bool,      // 1
+}
+
+// Paid-up expiry of a name, written by renew_name
+#[account]
+pub struct NameExpiry {
+    pub name: String,    // 4 + len (up to 63)
+    pub expires_at: i64, // 8
+    pub bump: u8,        // 1
+}
+
+// Owner's request to be warned before a name expires, closed by emit_expiry_warning
+#[account]
+pub struct NotifyExpiring {
+    pub name: String,    // 4 + len (up to 63)
+    pub owner: Pubkey,   // 32
+    pub expires_at: i64, // 8
+    pub warned_at: i64,  // 8
+    pub bump: u8,        // 1
+}
+
+// Source of BridgeLock message nonces so every lock message is unique
+#[account]
+pub struct BridgeState {
+    pub next_nonce: u64, // 8
+    pub bump: u8,        // 1
+}
+
+// Social account linked to a name, one per platform
+#[account]
+pub struct SocialAttestation {
+    pub name_hash: [u8; 32],        // 32
+    pub platform: String,           // 4 + len (up to 32)
+    pub handle: String,             // 4 + len (up to 64)
+    pub attestation_hash: [u8; 32], // 32
+    pub attested_at: i64,           // 8
+    pub bump: u8,                   // 1
+    // Owner of the name when the attestation was set; stale once the name moves
+    pub owner: Pubkey,              // 32
+    // AttesterConfig.public_key at the time, which signed the attestation
+    pub attester: Pubkey,           // 32
+}
+
+// Off-chain verifier trusted to attest accounts on `platform`
+#[account]
+pub struct AttesterConfig {
+    pub platform: String,   // 4 + len (up to 32)
+    pub public_key: Pubkey, // 32
+    pub bump: u8,           // 1
+}
+
+// Profile picture for a name; mime_type is a hint for clients rendering it
+#[account]
+pub struct AvatarRecord {
+    pub name_hash: [u8; 32], // 32
+    pub uri: String,         // 4 + len (up to 200)
+    pub mime_type: String,   // 4 + len (up to 50)
+    pub bump: u8,            // 1
+}
+
+// Tracks the next free NameHistoryEntry index for a name
+#[account]
+pub struct NameHistoryHead {
+    pub name_hash: [u8; 32], // 32
+    pub next_index: u32,     // 4
+    pub bump: u8,            // 1
+}
+
+// One resolved address change, seeded [b"history", name_hash, entry_index]
+#[account]
+pub struct NameHistoryEntry {
+    pub name_hash: [u8; 32],  // 32
+    pub entry_index: u32,     // 4
+    pub old_resolved: Pubkey, // 32
+    pub new_resolved: Pubkey, // 32
+    pub changed_at: i64,      // 8
+    pub changed_by: Pubkey,   // 32
+    pub bump: u8,             // 1
+}
+
+// Registry-wide registration statistics, created by initialize_stats. Registration
+// paths take it as an optional account, so high-volume callers can skip it.
+#[account]
+pub struct RegistryStats {
+    pub total_registrations: u64,                          // 8
+    // SOL fee buckets: <0.01, <0.1, <1, <10, <100, <1000, 1000+; last bucket counts token payments
+    pub fee_histogram: [u64; FEE_HISTOGRAM_BUCKETS],       // 64
+    // Indexed by name length (MIN_NAME_LEN..=MAX_NAME_LEN)
+    pub name_length_histogram: [u64; MAX_NAME_LEN + 1],    // 512
+    // Stays zero until fees can be priced through an oracle
+    pub total_fees_collected_usd_cents: u64,               // 8
+    pub bump: u8,                                          // 1
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RegistryStatsView {
+    pub total_registrations: u64,
+    pub fee_histogram: [u64; FEE_HISTOGRAM_BUCKETS],
+    pub name_length_histogram: Vec<u64>,
+    pub total_fees_collected_usd_cents: u64,
+}
+
+// Replay protection for owner-signed, relayed instructions
+#[account]
+pub struct OwnerNonce {
+    pub owner: Pubkey, // 32
+    pub nonce: u64,    // 8
+    pub bump: u8,      // 1
+}
+
+// Lifetime referral payouts; token earnings are summed across mints
+#[account]
+pub struct ReferrerAccount {
+    pub referrer: Pubkey,         // 32
+    pub total_earned_sol: u64,    // 8
+    pub total_earned_tokens: u64, // 8
+    pub referral_count: u32,      // 4
+    pub bump: u8,                 // 1
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LeaderboardEntry {
+    pub referrer: Pubkey,    // 32
+    pub referral_count: u32, // 4
+}
+
+// Top referrers by referral_count, capped at MAX_LEADERBOARD_ENTRIES
+#[account]
+pub struct Leaderboard {
+    pub entries: Vec<LeaderboardEntry>,
+    pub bump: u8,
+}
+
+#[account]
+pub struct OwnerNameCount {
+    pub owner: Pubkey, // 32
+    pub count: u32,    // 4
+    pub bump: u8,      // 1
+}
+
+#[account]
+pub struct LeaseRecord {
+    pub original_owner: Pubkey, // 32
+    pub lessee: Pubkey,         // 32
+    pub leased_at: i64,         // 8
+    pub expires_at: i64,        // 8
+    pub bump: u8,               // 1
+}
+
+// Child registry resolving `*.parent_name` subnames
+#[account]
+pub struct SubRegistry {
+    pub parent_name: String,          // 4 + len (up to 63)
+    pub sub_registry_program: Pubkey, // 32
+    pub owner: Pubkey,                // 32
+    pub enabled: bool,                // 1
+    pub bump: u8,                     // 1
+}
+
+// Marks `program` as allowed behind a SubRegistry
+#[account]
+pub struct ApprovedSubRegistryProgram {
+    pub program: Pubkey, // 32
+    pub bump: u8,        // 1
+}
+
+// Private sale in progress; the escrow PDA owns the name until it settles
+#[account]
+pub struct Escrow {
+    pub name: String,          // 4 + len (up to 63)
+    pub seller: Pubkey,        // 32
+    pub buyer: Option<Pubkey>, // 33
+    pub price: u64,            // 8
+    pub locked_at: i64,        // 8
+    pub timeout_at: i64,       // 8
+    pub bump: u8,              // 1
+}
+
+// Extra characters accepted by validate_name on top of a-z, 0-9 and '-'
+#[account]
+pub struct CharacterSetConfig {
+    pub allow_uppercase: bool,  // 1
+    pub allow_underscore: bool, // 1
+    pub allow_dot: bool,        // 1
+    pub bump: u8,               // 1
+}
+
+// Fee discount for payers holding a verified NFT from `collection_mint`
+#[account]
+pub struct NftDiscount {
+    pub collection_mint: Pubkey, // 32
+    pub discount_bps: u16,       // 2
+    pub enabled: bool,           // 1
+    pub bump: u8,                // 1
+}
+
+// Token account that must receive fees paid in `mint`
+#[account]
+pub struct TreasuryTokenAccount {
+    pub mint: Pubkey,          // 32
+    pub token_account: Pubkey, // 32
+    pub bump: u8,              // 1
+}
+
+#[account]
+pub struct MintRegistry {
+    pub mints: Vec<Pubkey>, // 4 + 32 * len (up to 32)
+    pub bump: u8,           // 1
+}
+
+#[account]
+pub struct PrimaryNameRegistry {
+    pub owner: Pubkey,        // 32
+    pub name: String,         // 4 + len
+    pub bump: u8,             // 1
+    // Total: 37 bytes + name length; with 8-byte discriminator allocate 8 + 37 + name.len()
+}
+
+#[account]
+pub struct RelayerEntry {
+    pub relayer: Pubkey,      // 32
+    pub bump: u8,             // 1
+    pub name: String,         // 4 + len (up to 64)
+    pub endpoint_url: String, // 4 + len (up to 128)
+    pub active: bool,         // 1
+    // Total: 42 bytes + name + url; allocated at max lengths
+}
+
+// RelayerEntry before the metadata fields were appended, read by migrate_relayer_entry
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyRelayerEntry {
+    pub relayer: Pubkey, // 32
+    pub bump: u8,        // 1
+}
+
+#[account]
+pub struct RelayerBond {
+    pub relayer: Pubkey,   // 32
+    pub amount: u64,       // 8
+    pub locked_until: i64, // 8
+    pub bump: u8,          // 1
+}
+
+#[account]
+pub struct RelayerViolation {
+    pub relayer: Pubkey,           // 32
+    pub violations: u32,           // 4
+    pub slashed_at: Option<i64>,   // 9
+    pub bump: u8,                  // 1
+    pub last_violation_at: i64,    // 8
+}
+
+// RelayerViolation before last_violation_at was appended, read by migrate_relayer_violation
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyRelayerViolation {
+    pub relayer: Pubkey,         // 32
+    pub violations: u32,         // 4
+    pub slashed_at: Option<i64>, // 9
+    pub bump: u8,                // 1
+}
+
+// Seeded by the granting owner as well as the member, so a transfer leaves the
+// previous owner's grants unreachable instead of handing them to the new owner
+#[account]
+pub struct NameAcl {
+    pub name_hash: [u8; 32], // 32
+    pub member: Pubkey,      // 32
+    pub permissions: u8,     // 1 (ACL_CAN_* bitmask)
+    pub bump: u8,            // 1
+}
+
+#[account]
+pub struct OwnershipProof {
+    pub name: String,         // 4 + len (up to 63)
+    pub owner: Pubkey,        // 32
+    pub challenge: [u8; 32],  // 32
+    pub proof_hash: [u8; 32], // 32
+    pub valid_until: i64,     // 8
+    pub bump: u8,             // 1
+    // Total: 109 bytes + name length; allocated at max name length
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct TreasuryRecipient {
+    pub recipient: Pubkey, // 32
+    pub bps: u16,          // 2
+}
+
+#[account]
+pub struct TreasurySplit {
+    pub recipients: [TreasuryRecipient; MAX_TREASURY_RECIPIENTS], // 34 * 5
+    pub count: u8,                                               // 1
+    pub bump: u8,                                                // 1
+    // Total: 172 bytes + discriminator
+}
+
+#[account]
+pub struct Secp256k1OwnerAddress {
+    pub name: String,          // 4 + len (up to 63)
+    pub evm_address: [u8; 20], // 20
+    pub bump: u8,              // 1
+    // Total: 25 bytes + name length; allocated at max name length
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureType {
+    Ed25519,   // Solana native keys
+    Secp256k1, // EVM wallets
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RegisterWithSigParams {
+    pub name: String,
+    pub owner: Pubkey,
+    pub relayer: Pubkey,
+    pub currency: Option<Pubkey>, // None = SOL, Some = token mint
+    pub amount: u64,
+    pub deadline: i64,
+    pub nonce: u64,
+    pub signature_type: SignatureType,
+    pub evm_address: Option<[u8; 20]>, // Required for Secp256k1 signatures; owner must be evm_owner_pda(evm_address)
+}
+
+#[account]
+pub struct Auction {
+    pub name: String,           // 4 + len (up to 63)
+    pub start_price: u64,       // 8
+    pub end_price: u64,         // 8
+    pub start_slot: u64,        // 8
+    pub end_slot: u64,          // 8
+    pub winner: Option<Pubkey>, // 33
+    pub winning_bid: u64,       // 8
+    pub bump: u8,               // 1
+    // Total: 78 bytes + name length; allocated at max name length
+}
+
+impl Auction {
+    // Linear decay from start_price to end_price over [start_slot, end_slot]
+    pub fn current_price(&self, slot: u64) -> u64 {
+        if slot >= self.end_slot {
+            return self.end_price;
+        }
+        let elapsed = slot.saturating_sub(self.start_slot) as u128;
+        let total = (self.end_slot - self.start_slot) as u128;
+        let decay = (self.start_price - self.end_price) as u128 * elapsed / total;
+        self.start_price - decay as u64
+    }
+}
+
+// SPL Governance VoteType of the proposals a GovernanceGate accepts
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SplVoteType {
+    SingleChoice,
+    MultiChoice,
+}
+
+// Names registered through register_name_via_governance need a Completed
+// proposal from `realm` voted with `governing_token_mint`
+#[account]
+pub struct GovernanceGate {
+    pub realm: Pubkey,                // 32
+    pub governing_token_mint: Pubkey, // 32
+    pub proposal_type: SplVoteType,   // 1
+    pub bump: u8,                     // 1
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ProposalType {
+    RegistrationFee, // value: u64 LE
+    ReferrerBps,     // value: u16 LE
+    MinReferrerBps,  // value: u16 LE
+    Treasury,        // value: Pubkey bytes
+}
+
+impl ProposalType {
+    pub fn value_len(&self) -> usize {
+        match self {
+            ProposalType::RegistrationFee => 8,
+            ProposalType::ReferrerBps | ProposalType::MinReferrerBps => 2,
+            ProposalType::Treasury => 32,
+        }
+    }
+}
+
+#[account]
+pub struct GovernanceConfig {
+    pub voting_token_mint: Pubkey,  // 32
+    pub voting_duration_slots: u64, // 8
+    pub quorum: u64,                // 8
+    pub proposal_count: u64,        // 8
+    pub bump: u8,                   // 1
+}
+
+#[account]
+pub struct Proposal {
+    pub id: u64,                    // 8
+    pub proposal_type: ProposalType, // 1
+    pub value: Vec<u8>,             // 4 + len (up to 32)
+    pub votes_for: u64,             // 8
+    pub votes_against: u64,         // 8
+    pub end_slot: u64,              // 8
+    pub executed: bool,             // 1
+    pub bump: u8,                   // 1
+    // Total: 39 bytes + value length; allocated at max value length
+}
+
+// Marks that a voter has voted on a proposal and holds what they locked
+#[account]
+pub struct VoteRecord {
+    pub voter: Pubkey, // 32
+    pub amount: u64,   // 8
+    pub bump: u8,      // 1
+}
+
+#[account]
+pub struct CommitmentConfig {
+    pub min_reveal_slots: u64,              // 8
+    pub max_reveal_slots: u64,              // 8
+    pub bump: u8,                           // 1
+    // Max distance between the slot hashed into a commitment and the slot it lands in
+    pub slot_commitment_window: u64,        // 8
+    // Commitments from before this slot were hashed as sha256(name || owner || salt)
+    pub legacy_commitment_cutoff_slot: u64, // 8
+}
+
+// CommitmentConfig before slot_commitment_window was appended, read by migrate_commitment_config
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyCommitmentConfig {
+    pub min_reveal_slots: u64, // 8
+    pub max_reveal_slots: u64, // 8
+    pub bump: u8,              // 1
+}
+
+#[account]
+pub struct Commitment {
+    pub committer: Pubkey,   // 32
+    pub committed_slot: u64, // 8
+    pub bump: u8,            // 1
+}
+
+#[account]
+pub struct Whitelist {
+    pub user: Pubkey,          // 32
+    pub max_registrations: u8, // 1
+    pub used: u8,              // 1
+    pub bump: u8,              // 1
+}
+
+// ========================================
+// INSTRUCTION CONTEXTS
+// ========================================
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    /// CHECK: Treasury can be any account
+    pub treasury: UncheckedAccount<'info>,
+    
+    #[account(
+        init,
+        payer = admin,
+        space = RegistryConfig::LEN,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        init,
+        payer = admin,
+        space = PROGRAM_META_ACCOUNT_SPACE,
+        seeds = [b"program_meta"],
+        bump
+    )]
+    pub program_meta: Account<'info, ProgramMeta>,
+    
+    // Source of the upgrade authority recorded in program_meta
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = bpf_loader_upgradeable::ID
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProgramMeta<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    // Created here for registries initialized before ProgramMeta existed
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = PROGRAM_META_ACCOUNT_SPACE,
+        seeds = [b"program_meta"],
+        bump
+    )]
+    pub program_meta: Account<'info, ProgramMeta>,
+    
+    // Source of the upgrade authority recorded in program_meta
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = bpf_loader_upgradeable::ID
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateProgramMeta<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    /// CHECK: Still in the legacy layout, so it is read by hand in migrate_program_meta
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"program_meta"],
+        bump
+    )]
+    pub program_meta: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(new_authority: Pubkey)]
+pub struct TransferUpgradeAuthority<'info> {
+    pub admin: Signer<'info>,
+    
+    #[account(
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"program_meta"],
+        bump = program_meta.bump
+    )]
+    pub program_meta: Account<'info, ProgramMeta>,
+    
+    /// CHECK: ProgramData account of this program, checked by address
+    #[account(
+        mut,
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = bpf_loader_upgradeable::ID
+    )]
+    pub program_data: UncheckedAccount<'info>,
+    
+    /// CHECK: only its key is used, as the loader's new authority
+    #[account(address = new_authority)]
+    pub new_authority: UncheckedAccount<'info>,
+    
+    /// CHECK: the BPF upgradeable loader
+    #[account(address = bpf_loader_upgradeable::ID)]
+    pub upgradeable_loader: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRegistrationFee<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetTokenFee<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + 50,
+        seeds = [b"token_fee", mint.key().as_ref()],
+        bump
+    )]
+    pub token_fee: Account<'info, TokenFeeConfig>,
+    
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = MINT_REGISTRY_ACCOUNT_SPACE,
+        seeds = [b"mint_registry"],
+        bump
+    )]
+    pub mint_registry: Account<'info, MintRegistry>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetToken22Fee<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(constraint = *mint.to_account_info().owner == token_2022::ID @ ErrorCode::InvalidTokenProgram)]
+    pub mint: InterfaceAccount<'info, InterfaceMint>,
+    
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + 50,
+        seeds = [b"token22_fee", mint.key().as_ref()],
+        bump
+    )]
+    pub token22_fee: Account<'info, TokenFeeConfig>,
+    
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = MINT_REGISTRY_ACCOUNT_SPACE,
+        seeds = [b"mint_registry"],
+        bump
+    )]
+    pub mint_registry: Account<'info, MintRegistry>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct SetTreasuryTokenAccount<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = TREASURY_TOKEN_ACCOUNT_SPACE,
+        seeds = [b"treasury_ta", mint.as_ref()],
+        bump
+    )]
+    pub treasury_ta: Account<'info, TreasuryTokenAccount>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetCharacterSet<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = CHARACTER_SET_ACCOUNT_SPACE,
+        seeds = [b"charset"],
+        bump
+    )]
+    pub charset: Account<'info, CharacterSetConfig>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetGovernanceGate<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = GOVERNANCE_GATE_ACCOUNT_SPACE,
+        seeds = [b"gov_gate"],
+        bump
+    )]
+    pub gov_gate: Account<'info, GovernanceGate>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClearGovernanceGate<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"gov_gate"],
+        bump = gov_gate.bump
+    )]
+    pub gov_gate: Account<'info, GovernanceGate>,
+}
+
+#[derive(Accounts)]
+#[instruction(collection_mint: Pubkey)]
+pub struct SetNftDiscount<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = NFT_DISCOUNT_ACCOUNT_SPACE,
+        seeds = [b"nft_discount", collection_mint.as_ref()],
+        bump
+    )]
+    pub nft_discount: Account<'info, NftDiscount>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct RemoveTokenFee<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"token_fee", mint.as_ref()],
+        bump = token_fee.bump
+    )]
+    pub token_fee: Account<'info, TokenFeeConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"mint_registry"],
+        bump = mint_registry.bump
+    )]
+    pub mint_registry: Account<'info, MintRegistry>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct RemoveToken22Fee<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"token22_fee", mint.as_ref()],
+        bump = token22_fee.bump
+    )]
+    pub token22_fee: Account<'info, TokenFeeConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"mint_registry"],
+        bump = mint_registry.bump
+    )]
+    pub mint_registry: Account<'info, MintRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeStats<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = REGISTRY_STATS_ACCOUNT_SPACE,
+        seeds = [b"stats"],
+        bump
+    )]
+    pub stats: Account<'info, RegistryStats>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GetStatsDetailed<'info> {
+    #[account(
+        seeds = [b"stats"],
+        bump = stats.bump
+    )]
+    pub stats: Account<'info, RegistryStats>,
+}
+
+#[derive(Accounts)]
+pub struct CreateReferrerAccount<'info> {
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+    
+    #[account(
+        init,
+        payer = referrer,
+        space = REFERRER_ACCOUNT_SPACE,
+        seeds = [b"referrer", referrer.key().as_ref()],
+        bump
+    )]
+    pub referrer_account: Account<'info, ReferrerAccount>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(referrer: Pubkey)]
+pub struct GetReferrerStats<'info> {
+    #[account(
+        seeds = [b"referrer", referrer.as_ref()],
+        bump = referrer_account.bump
+    )]
+    pub referrer_account: Account<'info, ReferrerAccount>,
+}
+
+// Permissionless: anyone may pay to refresh the leaderboard
+#[derive(Accounts)]
+pub struct ReferrerLeaderboard<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = LEADERBOARD_ACCOUNT_SPACE,
+        seeds = [b"referrer_leaderboard"],
+        bump
+    )]
+    pub leaderboard: Account<'info, Leaderboard>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GetSupportedMints<'info> {
+    #[account(
+        seeds = [b"mint_registry"],
+        bump = mint_registry.bump
+    )]
+    pub mint_registry: Account<'info, MintRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct SetTreasury<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetReferrerBps<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetMinReferrerBps<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetAllowAdminOverride<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetResetResolvedOnTransfer<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetTokenFeeBounds<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetSolFeeBounds<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxNamesPerOwner<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxDeadlineWindow<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxSignatureValidity<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetMinCuPrice<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetInstructionRelayerGate<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetLockedNamesTransferable<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct AdminSetResolved<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTreasurySurplus<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    /// CHECK: Treasury receives the surplus lamports
+    #[account(
+        mut,
+        constraint = treasury.key() == config.treasury @ ErrorCode::InvalidTreasuryAddress
+    )]
+    pub treasury: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct GetConfig<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+pub struct TransferAdmin<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+pub struct CancelAdminTransfer<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(mut)]
+    pub new_admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(multisig_address: Pubkey)]
+pub struct EnableMultiSig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    /// CHECK: Must be a multisig account created by the Squads program
+    #[account(
+        address = multisig_address,
+        owner = SQUADS_PROGRAM_ID @ ErrorCode::InvalidMultisigAccount
+    )]
+    pub multisig: UncheckedAccount<'info>,
+    
+    /// CHECK: Squads authority PDA of `multisig`; becomes the registry admin
+    #[account(
+        address = squads_executor_pda(multisig_address, DEFAULT_SQUADS_AUTHORITY_INDEX)
+            @ ErrorCode::InvalidMultisigAccount
+    )]
+    pub executor: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = MULTISIG_CONFIG_ACCOUNT_SPACE,
+        seeds = [b"multisig_config"],
+        bump
+    )]
+    pub multisig_config: Account<'info, MultiSigConfig>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DisableMultiSig<'info> {
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"multisig_config"],
+        bump = multisig_config.bump
+    )]
+    pub multisig_config: Account<'info, MultiSigConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct RegisterName<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    // Falls back to lowercase, digits and hyphens until the admin configures a character set
+    #[account(seeds = [b"charset"], bump = charset.bump)]
+    pub charset: Option<Account<'info, CharacterSetConfig>>,
+    
+    #[account(
+        init,
+        payer = user,
+        space = 8 + NAME_RECORD_BASE_SPACE + name.len(),
+        seeds = [b"name", name.to_ascii_lowercase().as_bytes()],
+        bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+    // Allocate max to allow future longer primary names without resize
+    space = PRIMARY_NAME_ACCOUNT_SPACE,
+        seeds = [b"primary", user.key().as_ref()],
+        bump
+    )]
+    pub primary_name: Account<'info, PrimaryNameRegistry>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = OWNER_NAME_COUNT_ACCOUNT_SPACE,
+        seeds = [b"owner_count", user.key().as_ref()],
+        bump
+    )]
+    pub owner_count: Account<'info, OwnerNameCount>,
+    
+    #[account(
+        mut,
+        seeds = [b"stats"],
+        bump = stats.bump
+    )]
+    pub stats: Option<Box<Account<'info, RegistryStats>>>,
+    
+    /// CHECK: Treasury can be any account to receive SOL fees
+    #[account(mut)]
+    pub treasury: UncheckedAccount<'info>,
+    
+    // Relayer submitting the transaction for the user. Only required, together
+    // with its allowlist entry, while the instruction's relayer gate is on
+    pub relayer: Option<Signer<'info>>,
+    
+    #[account(
+        seeds = [b"relayer", relayer_entry.relayer.as_ref()],
+        bump = relayer_entry.bump
+    )]
+    pub relayer_entry: Option<Account<'info, RelayerEntry>>,
+    
+    // Only required while config.min_compute_unit_price is set
+    /// CHECK: address is the instructions sysvar
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+    
+    // Only required while config.whitelist_phase is active
+    #[account(
+        mut,
+        seeds = [b"whitelist", user.key().as_ref()],
+        bump = whitelist.bump
+    )]
+    pub whitelist: Option<Account<'info, Whitelist>>,
+    
+    // Optional NFT holder discount: the NftDiscount PDA for the collection,
+    // the payer's token account for the NFT and the NFT's Metaplex metadata
+    #[account(
+        seeds = [b"nft_discount", nft_discount.collection_mint.as_ref()],
+        bump = nft_discount.bump
+    )]
+    pub nft_discount: Option<Box<Account<'info, NftDiscount>>>,
+    
+    pub nft_token_account: Option<Box<Account<'info, TokenAccount>>>,
+    
+    pub nft_metadata: Option<Box<Account<'info, MetadataAccount>>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String, governance_proposal: Pubkey)]
+pub struct RegisterNameViaGovernance<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    // Falls back to lowercase, digits and hyphens until the admin configures a character set
+    #[account(seeds = [b"charset"], bump = charset.bump)]
+    pub charset: Option<Account<'info, CharacterSetConfig>>,
+    
+    #[account(
+        seeds = [b"gov_gate"],
+        bump = gov_gate.bump
+    )]
+    pub gov_gate: Account<'info, GovernanceGate>,
+    
+    /// CHECK: SPL Governance account of the proposal; parsed in governance_approved_name
+    #[account(owner = SPL_GOVERNANCE_PROGRAM_ID @ ErrorCode::ProposalNotApproved)]
+    pub governance: UncheckedAccount<'info>,
+    
+    /// CHECK: SPL Governance proposal; parsed in governance_approved_name
+    #[account(
+        address = governance_proposal @ ErrorCode::ProposalNotApproved,
+        owner = SPL_GOVERNANCE_PROGRAM_ID @ ErrorCode::ProposalNotApproved
+    )]
+    pub proposal: UncheckedAccount<'info>,
+    
+    #[account(
+        init,
+        payer = user,
+        space = 8 + NAME_RECORD_BASE_SPACE + name.len(),
+        seeds = [b"name", name.to_ascii_lowercase().as_bytes()],
+        bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = PRIMARY_NAME_ACCOUNT_SPACE,
+        seeds = [b"primary", user.key().as_ref()],
+        bump
+    )]
+    pub primary_name: Account<'info, PrimaryNameRegistry>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = OWNER_NAME_COUNT_ACCOUNT_SPACE,
+        seeds = [b"owner_count", user.key().as_ref()],
+        bump
+    )]
+    pub owner_count: Account<'info, OwnerNameCount>,
+    
+    #[account(
+        mut,
+        seeds = [b"stats"],
+        bump = stats.bump
+    )]
+    pub stats: Option<Box<Account<'info, RegistryStats>>>,
+    
+    /// CHECK: Treasury receives the registration fee
+    #[account(
+        mut,
+        constraint = treasury.key() == config.treasury @ ErrorCode::InvalidTreasuryAddress
+    )]
+    pub treasury: UncheckedAccount<'info>,
+    
+    // Only required while config.whitelist_phase is active
+    #[account(
+        mut,
+        seeds = [b"whitelist", user.key().as_ref()],
+        bump = whitelist.bump
+    )]
+    pub whitelist: Option<Account<'info, Whitelist>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct RegisterNameWithToken<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    // Falls back to lowercase, digits and hyphens until the admin configures a character set
+    #[account(seeds = [b"charset"], bump = charset.bump)]
+    pub charset: Option<Account<'info, CharacterSetConfig>>,
+    
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        constraint = token_fee.mint == mint.key() @ ErrorCode::TokenNotEnabled,
+        seeds = [b"token_fee", mint.key().as_ref()],
+        bump = token_fee.bump
+    )]
+    pub token_fee: Account<'info, TokenFeeConfig>,
+    
+    #[account(
+        init,
+        payer = user,
+        space = 8 + NAME_RECORD_BASE_SPACE + name.len(),
+        seeds = [b"name", name.to_ascii_lowercase().as_bytes()],
+        bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+    // Allocate max to allow future longer primary names without resize
+    space = PRIMARY_NAME_ACCOUNT_SPACE,
+        seeds = [b"primary", user.key().as_ref()],
+        bump
+    )]
+    pub primary_name: Account<'info, PrimaryNameRegistry>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = OWNER_NAME_COUNT_ACCOUNT_SPACE,
+        seeds = [b"owner_count", user.key().as_ref()],
+        bump
+    )]
+    pub owner_count: Account<'info, OwnerNameCount>,
+    
+    #[account(
+        mut,
+        seeds = [b"stats"],
+        bump = stats.bump
+    )]
+    pub stats: Option<Box<Account<'info, RegistryStats>>>,
+    
+    #[account(
+        mut,
+        constraint = user_token_account.owner == user.key(),
+        constraint = user_token_account.mint == mint.key()
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    
+    #[account(
+        seeds = [b"treasury_ta", mint.key().as_ref()],
+        bump = treasury_ta.bump
+    )]
+    pub treasury_ta: Option<Account<'info, TreasuryTokenAccount>>,
+    
+    #[account(
+        mut,
+        constraint = treasury_token_account.mint == mint.key(),
+        constraint = treasury_token_account_allowed(treasury_ta.as_deref(), treasury_token_account.key(), treasury_token_account.owner, config.treasury) @ ErrorCode::InvalidTreasuryTokenAccount
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+    
+    // Relayer submitting the transaction for the user. Only required, together
+    // with its allowlist entry, while the instruction's relayer gate is on
+    pub relayer: Option<Signer<'info>>,
+    
+    #[account(
+        seeds = [b"relayer", relayer_entry.relayer.as_ref()],
+        bump = relayer_entry.bump
+    )]
+    pub relayer_entry: Option<Account<'info, RelayerEntry>>,
+    
+    // Optional NFT holder discount: the NftDiscount PDA for the collection,
+    // the payer's token account for the NFT and the NFT's Metaplex metadata
+    #[account(
+        seeds = [b"nft_discount", nft_discount.collection_mint.as_ref()],
+        bump = nft_discount.bump
+    )]
+    pub nft_discount: Option<Box<Account<'info, NftDiscount>>>,
+    
+    pub nft_token_account: Option<Box<Account<'info, TokenAccount>>>,
+    
+    pub nft_metadata: Option<Box<Account<'info, MetadataAccount>>>,
+    
+    pub token_program: Program<'info, Token>,
+    // Only required while config.whitelist_phase is active
+    #[account(
+        mut,
+        seeds = [b"whitelist", user.key().as_ref()],
+        bump = whitelist.bump
+    )]
+    pub whitelist: Option<Account<'info, Whitelist>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct RegisterNameWithToken22<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    // Falls back to lowercase, digits and hyphens until the admin configures a character set
+    #[account(seeds = [b"charset"], bump = charset.bump)]
+    pub charset: Option<Account<'info, CharacterSetConfig>>,
+    
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, InterfaceMint>,
+    
+    #[account(
+        constraint = token22_fee.mint == mint.key() @ ErrorCode::TokenNotEnabled,
+        seeds = [b"token22_fee", mint.key().as_ref()],
+        bump = token22_fee.bump
+    )]
+    pub token22_fee: Account<'info, TokenFeeConfig>,
+    
+    #[account(
+        init,
+        payer = user,
+        space = 8 + NAME_RECORD_BASE_SPACE + name.len(),
+        seeds = [b"name", name.to_ascii_lowercase().as_bytes()],
+        bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = PRIMARY_NAME_ACCOUNT_SPACE,
+        seeds = [b"primary", user.key().as_ref()],
+        bump
+    )]
+    pub primary_name: Account<'info, PrimaryNameRegistry>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = OWNER_NAME_COUNT_ACCOUNT_SPACE,
+        seeds = [b"owner_count", user.key().as_ref()],
+        bump
+    )]
+    pub owner_count: Account<'info, OwnerNameCount>,
+    
+    #[account(
+        mut,
+        seeds = [b"stats"],
+        bump = stats.bump
+    )]
+    pub stats: Option<Box<Account<'info, RegistryStats>>>,
+    
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = user,
+        token::token_program = token_program
+    )]
+    pub user_token_account: InterfaceAccount<'info, InterfaceTokenAccount>,
+    
+    #[account(
+        seeds = [b"treasury_ta", mint.key().as_ref()],
+        bump = treasury_ta.bump
+    )]
+    pub treasury_ta: Option<Account<'info, TreasuryTokenAccount>>,
+    
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program,
+        constraint = treasury_token_account_allowed(treasury_ta.as_deref(), treasury_token_account.key(), treasury_token_account.owner, config.treasury) @ ErrorCode::InvalidTreasuryTokenAccount
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, InterfaceTokenAccount>,
+    
+    #[account(constraint = token_program.key() == token_2022::ID @ ErrorCode::InvalidTokenProgram)]
+    pub token_program: Interface<'info, TokenInterface>,
+    // Only required while config.whitelist_phase is active
+    #[account(
+        mut,
+        seeds = [b"whitelist", user.key().as_ref()],
+        bump = whitelist.bump
+    )]
+    pub whitelist: Option<Account<'info, Whitelist>>,
+    
+    // Optional NFT holder discount: the NftDiscount PDA for the collection,
+    // the payer's token account for the NFT and the NFT's Metaplex metadata
+    #[account(
+        seeds = [b"nft_discount", nft_discount.collection_mint.as_ref()],
+        bump = nft_discount.bump
+    )]
+    pub nft_discount: Option<Box<Account<'info, NftDiscount>>>,
+    
+    pub nft_token_account: Option<Box<Account<'info, TokenAccount>>>,
+    
+    pub nft_metadata: Option<Box<Account<'info, MetadataAccount>>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: RegisterWithSigParams)]
+pub struct RegisterNameWithSignature<'info> {
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    // Falls back to lowercase, digits and hyphens until the admin configures a character set
+    #[account(seeds = [b"charset"], bump = charset.bump)]
+    pub charset: Option<Account<'info, CharacterSetConfig>>,
+    
+    #[account(
+        init,
+        payer = relayer,
+        space = 8 + NAME_RECORD_BASE_SPACE + params.name.len(),
+        seeds = [b"name", params.name.to_ascii_lowercase().as_bytes()],
+        bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        init_if_needed,
+        payer = relayer,
+    // Allocate max to allow future longer primary names without resize
+    space = PRIMARY_NAME_ACCOUNT_SPACE,
+        seeds = [b"primary", params.owner.as_ref()],
+        bump
+    )]
+    pub primary_name: Account<'info, PrimaryNameRegistry>,
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = OWNER_NAME_COUNT_ACCOUNT_SPACE,
+        seeds = [b"owner_count", params.owner.as_ref()],
+        bump
+    )]
+    pub owner_count: Account<'info, OwnerNameCount>,
+
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = OWNER_NONCE_ACCOUNT_SPACE,
+        seeds = [b"owner_nonce", params.owner.as_ref()],
+        bump
+    )]
+    pub owner_nonce: Box<Account<'info, OwnerNonce>>,
+    
+    #[account(
+        mut,
+        seeds = [b"stats"],
+        bump = stats.bump
+    )]
+    pub stats: Option<Box<Account<'info, RegistryStats>>>,
+
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = SECP256K1_OWNER_ACCOUNT_SPACE,
+        seeds = [b"evm_owner", params.evm_address.unwrap_or_default().as_ref()],
+        bump
+    )]
+    pub evm_owner: Option<Account<'info, Secp256k1OwnerAddress>>,
+    
+    // Credited with the referrer payout when the relayer has a referrer account
+    #[account(
+        mut,
+        seeds = [b"referrer", relayer.key().as_ref()],
+        bump = referrer_account.bump
+    )]
+    pub referrer_account: Option<Account<'info, ReferrerAccount>>,
+    
+    /// CHECK: Treasury receives the payment
+    #[account(mut)]
+    pub treasury: UncheckedAccount<'info>,
+    
+    /// CHECK: Instructions sysvar, used to read the Ed25519 verification instruction
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+    
+    // Only required while config.whitelist_phase is active
+    #[account(
+        mut,
+        seeds = [b"whitelist", params.owner.as_ref()],
+        bump = whitelist.bump
+    )]
+    pub whitelist: Option<Account<'info, Whitelist>>,
+    
+    // Optional NFT holder discount: the NftDiscount PDA for the collection,
+    // the owner's token account for the NFT and the NFT's Metaplex metadata
+    #[account(
+        seeds = [b"nft_discount", nft_discount.collection_mint.as_ref()],
+        bump = nft_discount.bump
+    )]
+    pub nft_discount: Option<Box<Account<'info, NftDiscount>>>,
+    
+    pub nft_token_account: Option<Box<Account<'info, TokenAccount>>>,
+    
+    pub nft_metadata: Option<Box<Account<'info, MetadataAccount>>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: RegisterWithSigParams)]
+pub struct RegisterNameWithSignatureToken<'info> {
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    // Falls back to lowercase, digits and hyphens until the admin configures a character set
+    #[account(seeds = [b"charset"], bump = charset.bump)]
+    pub charset: Option<Account<'info, CharacterSetConfig>>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        constraint = token_fee.mint == mint.key() @ ErrorCode::TokenNotEnabled,
+        seeds = [b"token_fee", mint.key().as_ref()],
+        bump = token_fee.bump
+    )]
+    pub token_fee: Account<'info, TokenFeeConfig>,
+
+    #[account(
+        init,
+        payer = relayer,
+        space = 8 + NAME_RECORD_BASE_SPACE + params.name.len(),
+        seeds = [b"name", params.name.to_ascii_lowercase().as_bytes()],
+        bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = PRIMARY_NAME_ACCOUNT_SPACE,
+        seeds = [b"primary", params.owner.as_ref()],
+        bump
+    )]
+    pub primary_name: Account<'info, PrimaryNameRegistry>,
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = OWNER_NAME_COUNT_ACCOUNT_SPACE,
+        seeds = [b"owner_count", params.owner.as_ref()],
+        bump
+    )]
+    pub owner_count: Account<'info, OwnerNameCount>,
+
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = OWNER_NONCE_ACCOUNT_SPACE,
+        seeds = [b"owner_nonce", params.owner.as_ref()],
+        bump
+    )]
+    pub owner_nonce: Box<Account<'info, OwnerNonce>>,
+    
+    #[account(
+        mut,
+        seeds = [b"stats"],
+        bump = stats.bump
+    )]
+    pub stats: Option<Box<Account<'info, RegistryStats>>>,
+
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = SECP256K1_OWNER_ACCOUNT_SPACE,
+        seeds = [b"evm_owner", params.evm_address.unwrap_or_default().as_ref()],
+        bump
+    )]
+    pub evm_owner: Option<Account<'info, Secp256k1OwnerAddress>>,
+    
+    // Credited with the referrer payout when the relayer has a referrer account
+    #[account(
+        mut,
+        seeds = [b"referrer", relayer.key().as_ref()],
+        bump = referrer_account.bump
+    )]
+    pub referrer_account: Option<Account<'info, ReferrerAccount>>,
+
+    #[account(mut,
+        constraint = relayer_token_account.owner == relayer.key(),
+        constraint = relayer_token_account.mint == mint.key()
+    )]
+    pub relayer_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"treasury_ta", mint.key().as_ref()],
+        bump = treasury_ta.bump
+    )]
+    pub treasury_ta: Option<Account<'info, TreasuryTokenAccount>>,
+
+    #[account(mut,
+        constraint = treasury_token_account.mint == mint.key(),
+        constraint = treasury_token_account_allowed(treasury_ta.as_deref(), treasury_token_account.key(), treasury_token_account.owner, config.treasury) @ ErrorCode::InvalidTreasuryTokenAccount
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Treasury for logging / potential SOL fallback
+    #[account(mut)]
+    pub treasury: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: Instructions sysvar, used to read the Ed25519 verification instruction
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+
+    // Only required while config.whitelist_phase is active
+    #[account(
+        mut,
+        seeds = [b"whitelist", params.owner.as_ref()],
+        bump = whitelist.bump
+    )]
+    pub whitelist: Option<Account<'info, Whitelist>>,
+
+    // Optional NFT holder discount: the NftDiscount PDA for the collection,
+    // the owner's token account for the NFT and the NFT's Metaplex metadata
+    #[account(
+        seeds = [b"nft_discount", nft_discount.collection_mint.as_ref()],
+        bump = nft_discount.bump
+    )]
+    pub nft_discount: Option<Box<Account<'info, NftDiscount>>>,
+
+    pub nft_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    pub nft_metadata: Option<Box<Account<'info, MetadataAccount>>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct TransferName<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        mut,
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+
+    /// CHECK: Must be empty unless the name is leased; read manually in transfer_name
+    #[account(
+        seeds = [b"lease", name.as_bytes()],
+        bump
+    )]
+    pub lease_record: UncheckedAccount<'info>,
+
+    /// CHECK: Must be empty; an escrowed name cannot be transferred
+    #[account(
+        seeds = [b"escrow", name.as_bytes()],
+        bump
+    )]
+    pub escrow: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct SetResolvedAddress<'info> {
+    // Name owner, or an ACL member with ACL_CAN_SET_RESOLVED
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct SetResolvedWithSignature<'info> {
+    // Pays the transaction fee and any rent for the nonce account
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = OWNER_NONCE_ACCOUNT_SPACE,
+        seeds = [b"owner_nonce", name_record.owner.as_ref()],
+        bump
+    )]
+    pub owner_nonce: Account<'info, OwnerNonce>,
+    
+    /// CHECK: Instructions sysvar, used to read the Ed25519 verification instruction
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String, member: Pubkey)]
+pub struct GrantAcl<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    /// CHECK: Must be empty; a lessee cannot delegate the name
+    #[account(
+        constraint = lease_record.data_is_empty() @ ErrorCode::LeaseActive,
+        seeds = [b"lease", name.as_bytes()],
+        bump
+    )]
+    pub lease_record: UncheckedAccount<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + 32 + 32 + 1 + 1,
+        seeds = [b"acl", hashv(&[name.as_bytes()]).as_ref(), owner.key().as_ref(), member.as_ref()],
+        bump
+    )]
+    pub acl_entry: Account<'info, NameAcl>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String, member: Pubkey)]
+pub struct RevokeAcl<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"acl", hashv(&[name.as_bytes()]).as_ref(), owner.key().as_ref(), member.as_ref()],
+        bump = acl_entry.bump
+    )]
+    pub acl_entry: Account<'info, NameAcl>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct SetBio<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    // Sized for the longest bio so later edits never need a realloc
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = NAME_BIO_ACCOUNT_SPACE,
+        seeds = [b"bio", hashv(&[name.as_bytes()]).as_ref()],
+        bump
+    )]
+    pub name_bio: Account<'info, NameBio>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct DeleteBio<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"bio", hashv(&[name.as_bytes()]).as_ref()],
+        bump = name_bio.bump
+    )]
+    pub name_bio: Account<'info, NameBio>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct SetAvatar<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = AVATAR_RECORD_ACCOUNT_SPACE,
+        seeds = [b"avatar", hashv(&[name.as_bytes()]).as_ref()],
+        bump
+    )]
+    pub avatar: Account<'info, AvatarRecord>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct DeleteAvatar<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        mut,
+        close = owner,
+        seeds = [b"avatar", hashv(&[name.as_bytes()]).as_ref()],
+        bump = avatar.bump
+    )]
+    pub avatar: Account<'info, AvatarRecord>,
+}
+
+#[derive(Accounts)]
+#[instruction(platform: String)]
+pub struct SetAttester<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = ATTESTER_CONFIG_ACCOUNT_SPACE,
+        seeds = [b"attester", platform.as_bytes()],
+        bump
+    )]
+    pub attester: Account<'info, AttesterConfig>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String, platform: String)]
+pub struct SetSocialAttestation<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    // Only platforms with a registered attester can be linked
+    #[account(
+        seeds = [b"attester", platform.as_bytes()],
+        bump = attester.bump
+    )]
+    pub attester: Account<'info, AttesterConfig>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = SOCIAL_ATTESTATION_ACCOUNT_SPACE,
+        seeds = [b"social", hashv(&[name.as_bytes()]).as_ref(), platform.as_bytes()],
+        bump
+    )]
+    pub social_attestation: Account<'info, SocialAttestation>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String, platform: String)]
+pub struct DeleteSocialAttestation<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    /// CHECK: May still be in the layout without owner/attester; only closed
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"social", hashv(&[name.as_bytes()]).as_ref(), platform.as_bytes()],
+        bump
+    )]
+    pub social_attestation: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String, platform: String)]
+pub struct InvalidateSocialAttestation<'info> {
+    #[account(
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        mut,
+        close = previous_owner,
+        seeds = [b"social", hashv(&[name.as_bytes()]).as_ref(), platform.as_bytes()],
+        bump = social_attestation.bump
+    )]
+    pub social_attestation: Account<'info, SocialAttestation>,
+    
+    /// CHECK: Owner that set the attestation and paid its rent
+    #[account(mut, address = social_attestation.owner)]
+    pub previous_owner: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct LockForBridge<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    /// CHECK: Must be empty; a lessee cannot bridge the name
+    #[account(
+        seeds = [b"lease", name.as_bytes()],
+        bump
+    )]
+    pub lease_record: UncheckedAccount<'info>,
+    
+    #[account(
+        init,
+        payer = owner,
+        space = BRIDGE_LOCK_ACCOUNT_SPACE,
+        seeds = [b"bridge_lock", name.as_bytes()],
+        bump
+    )]
+    pub bridge_lock: Account<'info, BridgeLock>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = BRIDGE_STATE_ACCOUNT_SPACE,
+        seeds = [b"bridge_state"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = OWNER_NAME_COUNT_ACCOUNT_SPACE,
+        seeds = [b"owner_count", owner.key().as_ref()],
+        bump
+    )]
+    pub owner_count: Account<'info, OwnerNameCount>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = PRIMARY_NAME_ACCOUNT_SPACE,
+        seeds = [b"primary", owner.key().as_ref()],
+        bump
+    )]
+    pub primary_name: Account<'info, PrimaryNameRegistry>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct MarkBridgeClaimed<'info> {
+    pub admin: Signer<'info>,
+    
+    #[account(
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"bridge_lock", name.as_bytes()],
+        bump = bridge_lock.bump
+    )]
+    pub bridge_lock: Account<'info, BridgeLock>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct UnlockBridgeLock<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = name_record.owner == bridge_lock.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner @ ErrorCode::Unauthorized,
+        seeds = [b"bridge_lock", name.as_bytes()],
+        bump = bridge_lock.bump
+    )]
+    pub bridge_lock: Account<'info, BridgeLock>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = OWNER_NAME_COUNT_ACCOUNT_SPACE,
+        seeds = [b"owner_count", owner.key().as_ref()],
+        bump
+    )]
+    pub owner_count: Account<'info, OwnerNameCount>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct LeaseName<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(mut)]
+    pub lessee: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        init,
+        payer = owner,
+        space = LEASE_RECORD_ACCOUNT_SPACE,
+        seeds = [b"lease", name.as_bytes()],
+        bump
+    )]
+    pub lease_record: Account<'info, LeaseRecord>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct EndLease<'info> {
+    pub caller: Signer<'info>,
+    
+    /// CHECK: Receives the lease account's rent back
+    #[account(mut, address = lease_record.original_owner @ ErrorCode::Unauthorized)]
+    pub original_owner: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        mut,
+        close = original_owner,
+        seeds = [b"lease", name.as_bytes()],
+        bump = lease_record.bump
+    )]
+    pub lease_record: Account<'info, LeaseRecord>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct EscrowName<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        mut,
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    /// CHECK: Must be empty; a lessee cannot sell the name
+    #[account(
+        seeds = [b"lease", name.as_bytes()],
+        bump
+    )]
+    pub lease_record: UncheckedAccount<'info>,
+    
+    #[account(
+        init,
+        payer = owner,
+        space = ESCROW_ACCOUNT_SPACE,
+        seeds = [b"escrow", name.as_bytes()],
+        bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct CompleteEscrow<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    
+    /// CHECK: Receives the price and the escrow account's rent
+    #[account(mut, address = escrow.seller @ ErrorCode::Unauthorized)]
+    pub seller: UncheckedAccount<'info>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        mut,
+        close = seller,
+        seeds = [b"escrow", name.as_bytes()],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+    
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = OWNER_NAME_COUNT_ACCOUNT_SPACE,
+        seeds = [b"owner_count", seller.key().as_ref()],
+        bump
+    )]
+    pub seller_count: Account<'info, OwnerNameCount>,
+    
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = PRIMARY_NAME_ACCOUNT_SPACE,
+        seeds = [b"primary", seller.key().as_ref()],
+        bump
+    )]
+    pub seller_primary: Account<'info, PrimaryNameRegistry>,
+    
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = OWNER_NAME_COUNT_ACCOUNT_SPACE,
+        seeds = [b"owner_count", buyer.key().as_ref()],
+        bump
+    )]
+    pub buyer_count: Account<'info, OwnerNameCount>,
+    
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = PRIMARY_NAME_ACCOUNT_SPACE,
+        seeds = [b"primary", buyer.key().as_ref()],
+        bump
+    )]
+    pub buyer_primary: Account<'info, PrimaryNameRegistry>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct CancelEscrow<'info> {
+    pub caller: Signer<'info>,
+    
+    /// CHECK: Receives the escrow account's rent back
+    #[account(mut, address = escrow.seller @ ErrorCode::Unauthorized)]
+    pub seller: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        mut,
+        close = seller,
+        seeds = [b"escrow", name.as_bytes()],
+        bump = escrow.bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct LockName<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        mut,
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    /// CHECK: Must be empty; a lessee cannot lock the name
+    #[account(
+        constraint = lease_record.data_is_empty() @ ErrorCode::LeaseActive,
+        seeds = [b"lease", name.as_bytes()],
+        bump
+    )]
+    pub lease_record: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct SetNameTtl<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        mut,
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String, proxy_name: Option<String>)]
+pub struct SetProxy<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    // Never shrink below the current size so migrated records keep their extra space
+    #[account(
+        mut,
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        realloc = NameRecord::space_with_proxy(&name, proxy_name.as_ref())
+            .max(name_record.to_account_info().data_len()),
+        realloc::payer = owner,
+        realloc::zero = false,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    // Falls back to lowercase, digits and hyphens until the admin configures a character set
+    #[account(seeds = [b"charset"], bump = charset.bump)]
+    pub charset: Option<Account<'info, CharacterSetConfig>>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct ResolveName<'info> {
+    #[account(
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct NameExists<'info> {
+    /// CHECK: may be uninitialized; only its discriminator is read
+    #[account(
+        seeds = [b"name", name.to_ascii_lowercase().as_bytes()],
+        bump
+    )]
+    pub name_record: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(program: Pubkey)]
+pub struct ApproveSubRegistryProgram<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    #[account(
+        init,
+        payer = admin,
+        space = APPROVED_SUB_REGISTRY_PROGRAM_ACCOUNT_SPACE,
+        seeds = [b"sub_registry_program", program.as_ref()],
+        bump
+    )]
+    pub approved_program: Account<'info, ApprovedSubRegistryProgram>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(program: Pubkey)]
+pub struct RevokeSubRegistryProgram<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"sub_registry_program", program.as_ref()],
+        bump = approved_program.bump
+    )]
+    pub approved_program: Account<'info, ApprovedSubRegistryProgram>,
+}
+
+#[derive(Accounts)]
+#[instruction(parent_name: String, sub_registry_program: Pubkey)]
+pub struct CreateSubRegistry<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", parent_name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    /// CHECK: Must be empty; a lessee cannot attach a sub-registry
+    #[account(
+        constraint = lease_record.data_is_empty() @ ErrorCode::LeaseActive,
+        seeds = [b"lease", parent_name.as_bytes()],
+        bump
+    )]
+    pub lease_record: UncheckedAccount<'info>,
+    
+    #[account(
+        seeds = [b"sub_registry_program", sub_registry_program.as_ref()],
+        bump = approved_program.bump
+    )]
+    pub approved_program: Account<'info, ApprovedSubRegistryProgram>,
+    
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = SUB_REGISTRY_ACCOUNT_SPACE,
+        seeds = [b"sub_registry", parent_name.as_bytes()],
+        bump
+    )]
+    pub sub_registry: Account<'info, SubRegistry>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(parent_name: String)]
+pub struct DisableSubRegistry<'info> {
+    pub owner: Signer<'info>,
+    
+    // Checked against the current name owner, not the one that created it
+    #[account(
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", parent_name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    /// CHECK: Must be empty; a lessee cannot change the sub-registry
+    #[account(
+        constraint = lease_record.data_is_empty() @ ErrorCode::LeaseActive,
+        seeds = [b"lease", parent_name.as_bytes()],
+        bump
+    )]
+    pub lease_record: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"sub_registry", parent_name.as_bytes()],
+        bump = sub_registry.bump
+    )]
+    pub sub_registry: Account<'info, SubRegistry>,
+}
+
+#[derive(Accounts)]
+#[instruction(parent_name: String)]
+pub struct ResolveSubdomain<'info> {
+    #[account(
+        constraint = sub_registry.enabled @ ErrorCode::SubRegistryDisabled,
+        seeds = [b"sub_registry", parent_name.as_bytes()],
+        bump = sub_registry.bump
+    )]
+    pub sub_registry: Account<'info, SubRegistry>,
+    
+    /// CHECK: Program invoked for the lookup, checked by address
+    #[account(
+        executable,
+        address = sub_registry.sub_registry_program @ ErrorCode::InvalidSubRegistryProgram
+    )]
+    pub sub_registry_program: UncheckedAccount<'info>,
+    
+    // Revoking the program's approval stops resolution through it
+    #[account(
+        seeds = [b"sub_registry_program", sub_registry.sub_registry_program.as_ref()],
+        bump = approved_program.bump
+    )]
+    pub approved_program: Account<'info, ApprovedSubRegistryProgram>,
+}
+
+#[derive(Accounts)]
+pub struct BenchmarkResolve<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// CHECK: derived and deserialized inside the measured section
+    pub name_record: UncheckedAccount<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = BENCHMARK_COUNTER_ACCOUNT_SPACE,
+        seeds = [b"benchmark_counter"],
+        bump
+    )]
+    pub counter: Account<'info, BenchmarkCounter>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = BENCHMARK_RESULT_ACCOUNT_SPACE,
+        seeds = [b"benchmark", counter.count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub result: Account<'info, BenchmarkResult>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GetBenchmarkResults {}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct RecordResolution<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    #[account(
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = NAME_STATS_ACCOUNT_SPACE,
+        seeds = [b"name_stats", name.as_bytes()],
+        bump
+    )]
+    pub name_stats: Account<'info, NameStats>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct ResolveChain<'info> {
+    #[account(
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct SetPrimaryName<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    #[account(
+        constraint = name_record.owner == user.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+    // Allocate max to allow future longer primary names without resize
+    space = PRIMARY_NAME_ACCOUNT_SPACE,
+        seeds = [b"primary", user.key().as_ref()],
+        bump
+    )]
+    pub primary_name: Account<'info, PrimaryNameRegistry>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ValidatePrimaryName<'info> {
+    pub user: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"primary", user.key().as_ref()],
+        bump = primary_name.bump
+    )]
+    pub primary_name: Account<'info, PrimaryNameRegistry>,
+    
+    #[account(
+        seeds = [b"name", primary_name.name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    // Another name owned by the user to promote when the current one is stale
+    #[account(
+        constraint = replacement.owner == user.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", replacement.name.as_bytes()],
+        bump = replacement.bump
+    )]
+    pub replacement: Option<Account<'info, NameRecord>>,
+}
+
+#[derive(Accounts)]
+pub struct SetExpiryConfig<'info> {
+    pub admin: Signer<'info>,
+    
+    #[account(
+        mut,
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct RenewName<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = NAME_EXPIRY_ACCOUNT_SPACE,
+        seeds = [b"expiry", name.as_bytes()],
+        bump
+    )]
+    pub name_expiry: Account<'info, NameExpiry>,
+    
+    /// CHECK: Receives the renewal fee
+    #[account(
+        mut,
+        constraint = treasury.key() == config.treasury @ ErrorCode::InvalidTreasuryAddress
+    )]
+    pub treasury: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct ScheduleExpiryWarning<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    #[account(
+        seeds = [b"expiry", name.as_bytes()],
+        bump = name_expiry.bump
+    )]
+    pub name_expiry: Account<'info, NameExpiry>,
+    
+    #[account(
+        init,
+        payer = owner,
+        space = NOTIFY_EXPIRING_ACCOUNT_SPACE,
+        seeds = [b"notify_expiring", name.as_bytes()],
+        bump
+    )]
+    pub notify_expiring: Account<'info, NotifyExpiring>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct EmitExpiryWarning<'info> {
+    #[account(mut)]
+    pub caller: Signer<'info>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        seeds = [b"expiry", name.as_bytes()],
+        bump = name_expiry.bump
+    )]
+    pub name_expiry: Account<'info, NameExpiry>,
+    
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner @ ErrorCode::Unauthorized,
+        seeds = [b"notify_expiring", name.as_bytes()],
+        bump = notify_expiring.bump
+    )]
+    pub notify_expiring: Account<'info, NotifyExpiring>,
+    
+    /// CHECK: Gets the NotifyExpiring rent back
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"stats"],
+        bump = stats.bump
+    )]
+    pub stats: Box<Account<'info, RegistryStats>>,
+}
+
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct InvalidateStalePrimary<'info> {
+    #[account(
+        mut,
+        seeds = [b"primary", user.as_ref()],
+        bump = primary_name.bump
+    )]
+    pub primary_name: Account<'info, PrimaryNameRegistry>,
+    
+    #[account(
+        seeds = [b"name", primary_name.name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+}
+
+#[derive(Accounts)]
+pub struct SetTreasurySplit<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + 172,
+        seeds = [b"treasury_split"],
+        bump
+    )]
+    pub treasury_split: Account<'info, TreasurySplit>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    /// CHECK: Still in the original layout, so it is read by hand in migrate_config
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct MigrateNameRecord<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    
+    /// CHECK: May still be in the legacy layout, so it is read by hand in migrate_name_record
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"name", name.as_bytes()],
+        bump
+    )]
+    pub name_record: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String, challenge: [u8; 32])]
+pub struct GenerateOwnershipProof<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    #[account(
+        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    
+    /// CHECK: Must be empty; a lessee does not own the name
+    #[account(
+        constraint = lease_record.data_is_empty() @ ErrorCode::LeaseActive,
+        seeds = [b"lease", name.as_bytes()],
+        bump
+    )]
+    pub lease_record: UncheckedAccount<'info>,
+    
+    #[account(
+        init,
+        payer = owner,
+        space = OWNERSHIP_PROOF_ACCOUNT_SPACE,
+        seeds = [b"proof", name.as_bytes(), challenge.as_ref()],
+        bump
+    )]
+    pub ownership_proof: Account<'info, OwnershipProof>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddRelayer<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    #[account(
+        init,
+        payer = admin,
+        space = RELAYER_ENTRY_ACCOUNT_SPACE,
+        seeds = [b"relayer", relayer.key().as_ref()],
+        bump
+    )]
+    pub relayer_entry: Account<'info, RelayerEntry>,
+    #[account(
+        seeds = [b"bond", relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Account<'info, RelayerBond>,
+    /// CHECK: relayer key for seeds
+    pub relayer: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
 }
 
-#[account]
-pub struct PrimaryNameRegistry {
-    pub owner: Pubkey,        // 32
-    pub name: String,         // 4 + len
-    pub bump: u8,             // 1
-    // Total: 37 bytes + name length; with 8-byte discriminator allocate 8 + 37 + name.len()
+#[derive(Accounts)]
+pub struct SetMinRelayerBond<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, RegistryConfig>,
 }
 
-#[account]
-pub struct RelayerEntry {
-    pub relayer: Pubkey, // 32
-    pub bump: u8,        // 1
+#[derive(Accounts)]
+pub struct StakeRelayer<'info> {
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + 32 + 8 + 8 + 1,
+        seeds = [b"bond", relayer.key().as_ref()],
+        bump
+    )]
+    pub relayer_bond: Account<'info, RelayerBond>,
+    pub system_program: Program<'info, System>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct RegisterWithSigParams {
-    pub name: String,
-    pub owner: Pubkey,
-    pub relayer: Pubkey,
-    pub currency: Option<Pubkey>, // None = SOL, Some = token mint
-    pub amount: u64,
-    pub deadline: i64,
-    pub nonce: u64,
+#[derive(Accounts)]
+pub struct UnstakeRelayer<'info> {
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    #[account(
+        mut,
+        close = relayer,
+        constraint = relayer_bond.relayer == relayer.key() @ ErrorCode::Unauthorized,
+        seeds = [b"bond", relayer.key().as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Account<'info, RelayerBond>,
+    /// CHECK: only checked for emptiness; must not exist while unstaking
+    #[account(
+        seeds = [b"relayer", relayer.key().as_ref()],
+        bump
+    )]
+    pub relayer_entry: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SlashRelayer<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    #[account(
+        mut,
+        close = treasury,
+        seeds = [b"bond", relayer_bond.relayer.as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Account<'info, RelayerBond>,
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"relayer", relayer_bond.relayer.as_ref()],
+        bump = relayer_entry.bump
+    )]
+    pub relayer_entry: Account<'info, RelayerEntry>,
+    /// CHECK: Treasury receives the slashed bond
+    #[account(
+        mut,
+        constraint = treasury.key() == config.treasury @ ErrorCode::InvalidTreasuryAddress
+    )]
+    pub treasury: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRelayerMetadata<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    #[account(
+        mut,
+        seeds = [b"relayer", relayer_entry.relayer.as_ref()],
+        bump = relayer_entry.bump
+    )]
+    pub relayer_entry: Account<'info, RelayerEntry>,
+}
+
+#[derive(Accounts)]
+#[instruction(relayer: Pubkey)]
+pub struct SetRelayerActive<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    #[account(
+        mut,
+        seeds = [b"relayer", relayer.as_ref()],
+        bump = relayer_entry.bump
+    )]
+    pub relayer_entry: Account<'info, RelayerEntry>,
+}
+
+#[derive(Accounts)]
+#[instruction(relayer: Pubkey)]
+pub struct MigrateRelayerEntry<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    /// CHECK: Still in the legacy layout, so it is read by hand in migrate_relayer_entry
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"relayer", relayer.as_ref()],
+        bump
+    )]
+    pub relayer_entry: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetMaxRelayerViolations<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, RegistryConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(relayer: Pubkey)]
+pub struct ResetRelayerViolations<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    #[account(
+        mut,
+        seeds = [b"violation", relayer.as_ref()],
+        bump = relayer_violation.bump
+    )]
+    pub relayer_violation: Account<'info, RelayerViolation>,
+}
+
+#[derive(Accounts)]
+#[instruction(relayer: Pubkey)]
+pub struct ReportRelayerViolation<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = RELAYER_VIOLATION_ACCOUNT_SPACE,
+        seeds = [b"violation", relayer.as_ref()],
+        bump
+    )]
+    pub relayer_violation: Account<'info, RelayerViolation>,
+    // Closed once the relayer reaches max_relayer_violations
+    #[account(
+        mut,
+        seeds = [b"relayer", relayer.as_ref()],
+        bump = relayer_entry.bump
+    )]
+    pub relayer_entry: Option<Account<'info, RelayerEntry>>,
+    /// CHECK: Receives the closed RelayerEntry's rent
+    #[account(
+        mut,
+        constraint = treasury.key() == config.treasury @ ErrorCode::InvalidTreasuryAddress
+    )]
+    pub treasury: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(relayer: Pubkey)]
+pub struct MigrateRelayerViolation<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    /// CHECK: Still in the legacy layout, so it is read by hand in migrate_relayer_violation
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"violation", relayer.as_ref()],
+        bump
+    )]
+    pub relayer_violation: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(relayer: Pubkey)]
+pub struct SlashRelayerManually<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    #[account(
+        mut,
+        close = treasury,
+        seeds = [b"bond", relayer.as_ref()],
+        bump = relayer_bond.bump
+    )]
+    pub relayer_bond: Account<'info, RelayerBond>,
+    #[account(
+        mut,
+        seeds = [b"violation", relayer.as_ref()],
+        bump = relayer_violation.bump
+    )]
+    pub relayer_violation: Account<'info, RelayerViolation>,
+    /// CHECK: Treasury receives the slashed bond
+    #[account(
+        mut,
+        constraint = treasury.key() == config.treasury @ ErrorCode::InvalidTreasuryAddress
+    )]
+    pub treasury: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveRelayer<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"relayer", relayer_entry.relayer.as_ref()],
+        bump = relayer_entry.bump
+    )]
+    pub relayer_entry: Account<'info, RelayerEntry>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct StartAuction<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized
+    )]
+    pub config: Account<'info, RegistryConfig>,
+    // Falls back to lowercase, digits and hyphens until the admin configures a character set
+    #[account(seeds = [b"charset"], bump = charset.bump)]
+    pub charset: Option<Account<'info, CharacterSetConfig>>,
+    #[account(
+        init,
+        payer = admin,
+        space = AUCTION_ACCOUNT_SPACE,
+        seeds = [b"auction", name.to_ascii_lowercase().as_bytes()],
+        bump
+    )]
+    pub auction: Account<'info, Auction>,
+    // Only unregistered names can be auctioned; init fails on a live record
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + NAME_RECORD_BASE_SPACE + name.len(),
+        seeds = [b"name", name.to_ascii_lowercase().as_bytes()],
+        bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
+    pub system_program: Program<'info, System>,
 }
 
-// ========================================
-// INSTRUCTION CONTEXTS
-// ========================================
-
 #[derive(Accounts)]
-pub struct Initialize<'info> {
+#[instruction(name: String)]
+pub struct PlaceBid<'info> {
     #[account(mut)]
-    pub admin: Signer<'info>,
-    
-    /// CHECK: Treasury can be any account
-    pub treasury: UncheckedAccount<'info>,
-    
+    pub bidder: Signer<'info>,
     #[account(
-        init,
-        payer = admin,
-        space = 8 + 109,
-        seeds = [b"config"],
-        bump
+        mut,
+        seeds = [b"auction", name.to_ascii_lowercase().as_bytes()],
+        bump = auction.bump
     )]
-    pub config: Account<'info, RegistryConfig>,
-    
+    pub auction: Account<'info, Auction>,
+    /// CHECK: Refunded when the previous winner let the settle window lapse; matched against auction.winner
+    #[account(mut)]
+    pub previous_bidder: Option<UncheckedAccount<'info>>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct SetRegistrationFee<'info> {
+#[instruction(name: String)]
+pub struct CancelAuction<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
-    
     #[account(
-        mut,
-        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+        has_one = admin @ ErrorCode::Unauthorized,
         seeds = [b"config"],
         bump = config.bump
     )]
     pub config: Account<'info, RegistryConfig>,
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"auction", name.to_ascii_lowercase().as_bytes()],
+        bump = auction.bump
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(
+        mut,
+        close = admin,
+        constraint = name_record.owner == auction.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.to_ascii_lowercase().as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
 }
 
 #[derive(Accounts)]
-pub struct SetTokenFee<'info> {
+#[instruction(name: String)]
+pub struct SettleAuction<'info> {
     #[account(mut)]
-    pub admin: Signer<'info>,
-    
+    pub winner: Signer<'info>,
     #[account(
-        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
         seeds = [b"config"],
         bump = config.bump
     )]
     pub config: Account<'info, RegistryConfig>,
-    
-    pub mint: Account<'info, Mint>,
-    
+    #[account(
+        mut,
+        close = winner,
+        constraint = auction.winner == Some(winner.key()) @ ErrorCode::Unauthorized,
+        seeds = [b"auction", name.to_ascii_lowercase().as_bytes()],
+        bump = auction.bump
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(
+        mut,
+        constraint = name_record.owner == auction.key() @ ErrorCode::Unauthorized,
+        seeds = [b"name", name.to_ascii_lowercase().as_bytes()],
+        bump = name_record.bump
+    )]
+    pub name_record: Account<'info, NameRecord>,
     #[account(
         init_if_needed,
-        payer = admin,
-        space = 8 + 50,
-        seeds = [b"token_fee", mint.key().as_ref()],
+        payer = winner,
+        space = PRIMARY_NAME_ACCOUNT_SPACE,
+        seeds = [b"primary", winner.key().as_ref()],
         bump
     )]
-    pub token_fee: Account<'info, TokenFeeConfig>,
+    pub primary_name: Account<'info, PrimaryNameRegistry>,
+    #[account(
+        init_if_needed,
+        payer = winner,
+        space = OWNER_NAME_COUNT_ACCOUNT_SPACE,
+        seeds = [b"owner_count", winner.key().as_ref()],
+        bump
+    )]
+    pub owner_count: Account<'info, OwnerNameCount>,
+    
+    #[account(
+        mut,
+        seeds = [b"stats"],
+        bump = stats.bump
+    )]
+    pub stats: Option<Box<Account<'info, RegistryStats>>>,
+    /// CHECK: Treasury receives the winning bid
+    #[account(
+        mut,
+        constraint = treasury.key() == config.treasury @ ErrorCode::InvalidTreasuryAddress
+    )]
+    pub treasury: UncheckedAccount<'info>,
+    // Only required while config.whitelist_phase is active
+    #[account(
+        mut,
+        seeds = [b"whitelist", winner.key().as_ref()],
+        bump = whitelist.bump
+    )]
+    pub whitelist: Option<Account<'info, Whitelist>>,
     
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct SetTreasury<'info> {
+pub struct SetGovernanceConfig<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
-    
     #[account(
-        mut,
-        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
         seeds = [b"config"],
-        bump = config.bump
+        bump = config.bump,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized
     )]
     pub config: Account<'info, RegistryConfig>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + 32 + 8 + 8 + 8 + 1,
+        seeds = [b"governance"],
+        bump
+    )]
+    pub governance: Account<'info, GovernanceConfig>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct SetReferrerBps<'info> {
+pub struct CreateProposal<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
-    
     #[account(
-        mut,
-        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
         seeds = [b"config"],
-        bump = config.bump
+        bump = config.bump,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized
     )]
     pub config: Account<'info, RegistryConfig>,
+    #[account(
+        mut,
+        seeds = [b"governance"],
+        bump = governance.bump
+    )]
+    pub governance: Account<'info, GovernanceConfig>,
+    #[account(
+        init,
+        payer = admin,
+        space = PROPOSAL_ACCOUNT_SPACE,
+        seeds = [b"proposal", governance.proposal_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub proposal: Account<'info, Proposal>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct TransferAdmin<'info> {
+#[instruction(proposal_id: u64)]
+pub struct Vote<'info> {
     #[account(mut)]
-    pub admin: Signer<'info>,
-    
+    pub voter: Signer<'info>,
+    #[account(
+        seeds = [b"governance"],
+        bump = governance.bump
+    )]
+    pub governance: Account<'info, GovernanceConfig>,
     #[account(
         mut,
-        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
-        seeds = [b"config"],
-        bump = config.bump
+        seeds = [b"proposal", proposal_id.to_le_bytes().as_ref()],
+        bump = proposal.bump
     )]
-    pub config: Account<'info, RegistryConfig>,
+    pub proposal: Account<'info, Proposal>,
+    #[account(address = governance.voting_token_mint @ ErrorCode::TokenNotEnabled)]
+    pub voting_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        constraint = voter_token_account.owner == voter.key() @ ErrorCode::Unauthorized,
+        constraint = voter_token_account.mint == governance.voting_token_mint @ ErrorCode::TokenNotEnabled
+    )]
+    pub voter_token_account: Account<'info, TokenAccount>,
+    // Holds every vote's tokens for this proposal; the proposal PDA is its authority
+    #[account(
+        init_if_needed,
+        payer = voter,
+        token::mint = voting_mint,
+        token::authority = proposal,
+        seeds = [b"vote_escrow", proposal_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub vote_escrow: Account<'info, TokenAccount>,
+    // init fails if the voter already voted on this proposal
+    #[account(
+        init,
+        payer = voter,
+        space = VOTE_RECORD_ACCOUNT_SPACE,
+        seeds = [b"vote", proposal_id.to_le_bytes().as_ref(), voter.key().as_ref()],
+        bump
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct AcceptAdmin<'info> {
+#[instruction(proposal_id: u64)]
+pub struct WithdrawVoteTokens<'info> {
     #[account(mut)]
-    pub new_admin: Signer<'info>,
-    
+    pub voter: Signer<'info>,
+    #[account(
+        seeds = [b"proposal", proposal_id.to_le_bytes().as_ref()],
+        bump = proposal.bump
+    )]
+    pub proposal: Account<'info, Proposal>,
     #[account(
         mut,
-        seeds = [b"config"],
-        bump = config.bump
+        seeds = [b"vote_escrow", proposal_id.to_le_bytes().as_ref()],
+        bump
     )]
-    pub config: Account<'info, RegistryConfig>,
+    pub vote_escrow: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = voter_token_account.owner == voter.key() @ ErrorCode::Unauthorized,
+        constraint = voter_token_account.mint == vote_escrow.mint @ ErrorCode::TokenNotEnabled
+    )]
+    pub voter_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        close = voter,
+        seeds = [b"vote", proposal_id.to_le_bytes().as_ref(), voter.key().as_ref()],
+        bump = vote_record.bump
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-#[instruction(name: String)]
-pub struct RegisterName<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
+#[instruction(proposal_id: u64)]
+pub struct ExecuteProposal<'info> {
+    pub executor: Signer<'info>,
     #[account(
+        mut,
         seeds = [b"config"],
         bump = config.bump
     )]
     pub config: Account<'info, RegistryConfig>,
-    
     #[account(
-        init,
-        payer = user,
-        space = 8 + 77 + name.len(),
-        seeds = [b"name", name.as_bytes()],
-        bump
+        seeds = [b"governance"],
+        bump = governance.bump
     )]
-    pub name_record: Account<'info, NameRecord>,
-    
+    pub governance: Account<'info, GovernanceConfig>,
     #[account(
-        init_if_needed,
-        payer = user,
-    // Allocate max to allow future longer primary names without resize
-    space = PRIMARY_NAME_ACCOUNT_SPACE,
-        seeds = [b"primary", user.key().as_ref()],
-        bump
+        mut,
+        seeds = [b"proposal", proposal_id.to_le_bytes().as_ref()],
+        bump = proposal.bump
     )]
-    pub primary_name: Account<'info, PrimaryNameRegistry>,    /// CHECK: Treasury can be any account to receive SOL fees
-    #[account(mut)]
-    pub treasury: UncheckedAccount<'info>,
-    
-    pub system_program: Program<'info, System>,
+    pub proposal: Account<'info, Proposal>,
 }
 
 #[derive(Accounts)]
-#[instruction(name: String)]
-pub struct RegisterNameWithToken<'info> {
+pub struct SetCommitmentConfig<'info> {
     #[account(mut)]
-    pub user: Signer<'info>,
-    
+    pub admin: Signer<'info>,
     #[account(
+        has_one = admin @ ErrorCode::Unauthorized,
         seeds = [b"config"],
         bump = config.bump
     )]
     pub config: Account<'info, RegistryConfig>,
-    
-    pub mint: Account<'info, Mint>,
-    
-    #[account(
-        constraint = token_fee.mint == mint.key() @ ErrorCode::TokenNotEnabled,
-        seeds = [b"token_fee", mint.key().as_ref()],
-        bump = token_fee.bump
-    )]
-    pub token_fee: Account<'info, TokenFeeConfig>,
-    
-    #[account(
-        init,
-        payer = user,
-        space = 8 + 77 + name.len(),
-        seeds = [b"name", name.as_bytes()],
-        bump
-    )]
-    pub name_record: Account<'info, NameRecord>,
-    
     #[account(
         init_if_needed,
-        payer = user,
-    // Allocate max to allow future longer primary names without resize
-    space = PRIMARY_NAME_ACCOUNT_SPACE,
-        seeds = [b"primary", user.key().as_ref()],
+        payer = admin,
+        space = COMMITMENT_CONFIG_ACCOUNT_SPACE,
+        seeds = [b"commit_config"],
         bump
     )]
-    pub primary_name: Account<'info, PrimaryNameRegistry>,
-    
-    #[account(
-        mut,
-        constraint = user_token_account.owner == user.key(),
-        constraint = user_token_account.mint == mint.key()
-    )]
-    pub user_token_account: Account<'info, TokenAccount>,
-    
-    #[account(
-        mut,
-        constraint = treasury_token_account.mint == mint.key()
-    )]
-    pub treasury_token_account: Account<'info, TokenAccount>,
-    
-    pub token_program: Program<'info, Token>,
+    pub commit_config: Account<'info, CommitmentConfig>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(params: RegisterWithSigParams)]
-pub struct RegisterNameWithSignature<'info> {
+pub struct MigrateCommitmentConfig<'info> {
     #[account(mut)]
-    pub relayer: Signer<'info>,
-    
+    pub admin: Signer<'info>,
     #[account(
+        has_one = admin @ ErrorCode::Unauthorized,
         seeds = [b"config"],
         bump = config.bump
     )]
     pub config: Account<'info, RegistryConfig>,
-    
+    /// CHECK: Still in the legacy layout, so it is read by hand in migrate_commitment_config
     #[account(
-        init,
-        payer = relayer,
-        space = 8 + 77 + params.name.len(),
-        seeds = [b"name", params.name.as_bytes()],
+        mut,
+        owner = crate::ID,
+        seeds = [b"commit_config"],
         bump
     )]
-    pub name_record: Account<'info, NameRecord>,
-    
+    pub commit_config: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(commitment: [u8; 32])]
+pub struct CommitName<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
     #[account(
-        init_if_needed,
-        payer = relayer,
-    // Allocate max to allow future longer primary names without resize
-    space = PRIMARY_NAME_ACCOUNT_SPACE,
-        seeds = [b"primary", params.owner.as_ref()],
-        bump
+        seeds = [b"commit_config"],
+        bump = commit_config.bump
     )]
-    pub primary_name: Account<'info, PrimaryNameRegistry>,
+    pub commit_config: Account<'info, CommitmentConfig>,
     #[account(
-        seeds = [b"relayer", relayer.key().as_ref()],
-        bump = relayer_entry.bump
+        init,
+        payer = user,
+        space = 8 + 32 + 8 + 1,
+        seeds = [b"commitment", commitment.as_ref()],
+        bump
     )]
-    pub relayer_entry: Account<'info, RelayerEntry>,
-    
-    /// CHECK: Treasury receives the payment
-    #[account(mut)]
-    pub treasury: UncheckedAccount<'info>,
-    
+    pub commitment: Account<'info, Commitment>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(params: RegisterWithSigParams)]
-pub struct RegisterNameWithSignatureToken<'info> {
+#[instruction(name: String)]
+pub struct RevealName<'info> {
     #[account(mut)]
-    pub relayer: Signer<'info>,
-
+    pub user: Signer<'info>,
     #[account(
         seeds = [b"config"],
         bump = config.bump
     )]
     pub config: Account<'info, RegistryConfig>,
-
-    pub mint: Account<'info, Mint>,
-
+    // Falls back to lowercase, digits and hyphens until the admin configures a character set
+    #[account(seeds = [b"charset"], bump = charset.bump)]
+    pub charset: Option<Account<'info, CharacterSetConfig>>,
+    #[account(
+        seeds = [b"commit_config"],
+        bump = commit_config.bump
+    )]
+    pub commit_config: Account<'info, CommitmentConfig>,
+    // PDA is checked against hash(name, salt, user, committed_slot) in the handler
     #[account(
-        constraint = token_fee.mint == mint.key() @ ErrorCode::TokenNotEnabled,
-        seeds = [b"token_fee", mint.key().as_ref()],
-        bump = token_fee.bump
+        mut,
+        close = user,
+        constraint = commitment.committer == user.key() @ ErrorCode::Unauthorized
     )]
-    pub token_fee: Account<'info, TokenFeeConfig>,
-
+    pub commitment: Account<'info, Commitment>,
     #[account(
         init,
-        payer = relayer,
-        space = 8 + 77 + params.name.len(),
-        seeds = [b"name", params.name.as_bytes()],
+        payer = user,
+        space = 8 + NAME_RECORD_BASE_SPACE + name.len(),
+        seeds = [b"name", name.to_ascii_lowercase().as_bytes()],
         bump
     )]
     pub name_record: Account<'info, NameRecord>,
-
     #[account(
         init_if_needed,
-        payer = relayer,
+        payer = user,
         space = PRIMARY_NAME_ACCOUNT_SPACE,
-        seeds = [b"primary", params.owner.as_ref()],
+        seeds = [b"primary", user.key().as_ref()],
         bump
     )]
     pub primary_name: Account<'info, PrimaryNameRegistry>,
     #[account(
-        seeds = [b"relayer", relayer.key().as_ref()],
-        bump = relayer_entry.bump
+        init_if_needed,
+        payer = user,
+        space = OWNER_NAME_COUNT_ACCOUNT_SPACE,
+        seeds = [b"owner_count", user.key().as_ref()],
+        bump
     )]
-    pub relayer_entry: Account<'info, RelayerEntry>,
-
-    #[account(mut,
-        constraint = relayer_token_account.owner == relayer.key(),
-        constraint = relayer_token_account.mint == mint.key()
+    pub owner_count: Account<'info, OwnerNameCount>,
+    
+    #[account(
+        mut,
+        seeds = [b"stats"],
+        bump = stats.bump
     )]
-    pub relayer_token_account: Account<'info, TokenAccount>,
-
-    #[account(mut,
-        constraint = treasury_token_account.mint == mint.key()
+    pub stats: Option<Box<Account<'info, RegistryStats>>>,
+    /// CHECK: Treasury receives the registration fee
+    #[account(
+        mut,
+        constraint = treasury.key() == config.treasury @ ErrorCode::InvalidTreasuryAddress
     )]
-    pub treasury_token_account: Account<'info, TokenAccount>,
-
-    /// CHECK: Treasury for logging / potential SOL fallback
-    #[account(mut)]
     pub treasury: UncheckedAccount<'info>,
-
-    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(name: String)]
-pub struct TransferName<'info> {
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    
+#[instruction(commitment_bytes: [u8; 32])]
+pub struct CleanupExpiredCommitment<'info> {
+    pub caller: Signer<'info>,
+    #[account(
+        seeds = [b"commit_config"],
+        bump = commit_config.bump
+    )]
+    pub commit_config: Account<'info, CommitmentConfig>,
     #[account(
         mut,
-        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
-        seeds = [b"name", name.as_bytes()],
-        bump = name_record.bump
+        close = committer,
+        seeds = [b"commitment", commitment_bytes.as_ref()],
+        bump = commitment.bump
     )]
-    pub name_record: Account<'info, NameRecord>,
+    pub commitment: Account<'info, Commitment>,
+    /// CHECK: Original committer reclaims the rent
+    #[account(
+        mut,
+        constraint = committer.key() == commitment.committer @ ErrorCode::Unauthorized
+    )]
+    pub committer: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
-#[instruction(name: String)]
-pub struct SetResolvedAddress<'info> {
+pub struct SetWhitelistPhase<'info> {
     #[account(mut)]
-    pub owner: Signer<'info>,
-    
+    pub admin: Signer<'info>,
     #[account(
         mut,
-        constraint = name_record.owner == owner.key() @ ErrorCode::Unauthorized,
-        seeds = [b"name", name.as_bytes()],
-        bump = name_record.bump
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized
     )]
-    pub name_record: Account<'info, NameRecord>,
+    pub config: Account<'info, RegistryConfig>,
 }
 
 #[derive(Accounts)]
-#[instruction(name: String)]
-pub struct SetPrimaryName<'info> {
+#[instruction(user: Pubkey)]
+pub struct AddToWhitelist<'info> {
     #[account(mut)]
-    pub user: Signer<'info>,
-    
+    pub admin: Signer<'info>,
     #[account(
-        constraint = name_record.owner == user.key() @ ErrorCode::Unauthorized,
-        seeds = [b"name", name.as_bytes()],
-        bump = name_record.bump
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized
     )]
-    pub name_record: Account<'info, NameRecord>,
-    
+    pub config: Account<'info, RegistryConfig>,
     #[account(
         init_if_needed,
-        payer = user,
-    // Allocate max to allow future longer primary names without resize
-    space = PRIMARY_NAME_ACCOUNT_SPACE,
-        seeds = [b"primary", user.key().as_ref()],
+        payer = admin,
+        space = 8 + 32 + 1 + 1 + 1,
+        seeds = [b"whitelist", user.as_ref()],
         bump
     )]
-    pub primary_name: Account<'info, PrimaryNameRegistry>,
-    
+    pub whitelist: Account<'info, Whitelist>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct AddRelayer<'info> {
+pub struct RemoveFromWhitelist<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
     #[account(
@@ -864,62 +7451,690 @@ pub struct AddRelayer<'info> {
     )]
     pub config: Account<'info, RegistryConfig>,
     #[account(
-        init,
-        payer = admin,
-        space = 8 + 32 + 1,
-        seeds = [b"relayer", relayer.key().as_ref()],
-        bump
+        mut,
+        close = admin,
+        seeds = [b"whitelist", whitelist.user.as_ref()],
+        bump = whitelist.bump
     )]
-    pub relayer_entry: Account<'info, RelayerEntry>,
-    /// CHECK: relayer key for seeds
-    pub relayer: UncheckedAccount<'info>,
-    pub system_program: Program<'info, System>,
+    pub whitelist: Account<'info, Whitelist>,
 }
 
 #[derive(Accounts)]
-pub struct RemoveRelayer<'info> {
+pub struct SetMaxTtlSeconds<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
     #[account(
+        mut,
         seeds = [b"config"],
         bump = config.bump,
         constraint = config.admin == admin.key() @ ErrorCode::Unauthorized
     )]
     pub config: Account<'info, RegistryConfig>,
-    #[account(
-        mut,
-        close = admin,
-        seeds = [b"relayer", relayer_entry.relayer.as_ref()],
-        bump = relayer_entry.bump
-    )]
-    pub relayer_entry: Account<'info, RelayerEntry>,
 }
 
 // ========================================
 // VALIDATION & UTILITIES
 // ========================================
 
-fn validate_name(name: &str) -> Result<()> {
+// `charset` is None until the admin has configured a character set
+fn validate_name(name: &str, charset: Option<&CharacterSetConfig>) -> Result<()> {
     // Length check: 3-63 characters
-    require!(name.len() >= 3 && name.len() <= 63, ErrorCode::InvalidNameLength);
+    require!(name.len() >= MIN_NAME_LEN && name.len() <= MAX_NAME_LEN, ErrorCode::InvalidNameLength);
+    
+    let allow_uppercase = charset.map_or(false, |c| c.allow_uppercase);
+    let allow_underscore = charset.map_or(false, |c| c.allow_underscore);
+    let allow_dot = charset.map_or(false, |c| c.allow_dot);
     
     // Character validation
     for (i, c) in name.chars().enumerate() {
         let valid = c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-';
-        require!(valid, ErrorCode::InvalidCharacter);
+        if !valid {
+            let optional = c.is_ascii_uppercase() || c == '_' || c == '.';
+            require!(optional, ErrorCode::InvalidCharacter);
+            let allowed = (c.is_ascii_uppercase() && allow_uppercase)
+                || (c == '_' && allow_underscore)
+                || (c == '.' && allow_dot);
+            require!(allowed, ErrorCode::CharacterNotAllowed);
+        }
         
-        // No leading/trailing hyphens
+        // No leading/trailing hyphens or underscores
         if c == '-' {
             require!(i != 0 && i != name.len() - 1, ErrorCode::InvalidHyphenPlacement);
         }
+        if c == '_' {
+            require!(i != 0 && i != name.len() - 1, ErrorCode::InvalidUnderscorePlacement);
+        }
     }
     
-    // No consecutive hyphens
+    // No consecutive hyphens or underscores
     require!(!name.contains("--"), ErrorCode::ConsecutiveHyphens);
+    require!(!name.contains("__"), ErrorCode::ConsecutiveUnderscores);
     
     Ok(())
 }
 
+// `fee_lamports` is None for token payments. Callers that leave out the stats
+// account skip the update, which keeps them off the shared write lock.
+fn record_registration_stats(
+    stats: Option<&mut Account<RegistryStats>>,
+    name_len: usize,
+    fee_lamports: Option<u64>,
+) {
+    let Some(stats) = stats else {
+        return;
+    };
+    let bucket = match fee_lamports {
+        None => FEE_HISTOGRAM_BUCKETS - 1,
+        Some(fee) => {
+            let mut bucket = 0;
+            let mut bound = LAMPORTS_PER_SOL / 100;
+            while bucket < FEE_HISTOGRAM_BUCKETS - 2 && fee >= bound {
+                bucket += 1;
+                bound = bound.saturating_mul(10);
+            }
+            bucket
+        }
+    };
+
+    stats.total_registrations += 1;
+    stats.fee_histogram[bucket] += 1;
+    stats.name_length_histogram[name_len.min(MAX_NAME_LEN)] += 1;
+}
+
+// Applies the NftDiscount when the name's owner proves they hold an NFT from
+// its verified collection. Without a discount account the base fee is charged.
+fn nft_discounted_fee(
+    name: &str,
+    base_fee: u64,
+    holder: &Pubkey,
+    nft_discount: Option<&Account<NftDiscount>>,
+    nft_token_account: Option<&Account<TokenAccount>>,
+    nft_metadata: Option<&Account<MetadataAccount>>,
+) -> Result<u64> {
+    let Some(nft_discount) = nft_discount.filter(|discount| discount.enabled) else {
+        return Ok(base_fee);
+    };
+    let nft_token_account = nft_token_account.ok_or(ErrorCode::InvalidNftProof)?;
+    let nft_metadata = nft_metadata.ok_or(ErrorCode::InvalidNftProof)?;
+
+    require_keys_eq!(nft_token_account.owner, *holder, ErrorCode::InvalidNftProof);
+    require!(nft_token_account.amount >= 1, ErrorCode::InvalidNftProof);
+    require_keys_eq!(nft_metadata.mint, nft_token_account.mint, ErrorCode::InvalidNftProof);
+    let collection = nft_metadata.collection.as_ref().ok_or(ErrorCode::InvalidNftProof)?;
+    require_keys_eq!(collection.key, nft_discount.collection_mint, ErrorCode::InvalidNftProof);
+    require!(collection.verified, ErrorCode::InvalidNftProof);
+
+    let discount = (base_fee as u128 * nft_discount.discount_bps as u128 / 10000) as u64;
+    msg!("NftDiscountApplied: name={}, collection={}, discount_bps={}",
+         name, nft_discount.collection_mint, nft_discount.discount_bps);
+    Ok(base_fee - discount)
+}
+
+// A pinned treasury_ta wins; without one, any token account the treasury wallet owns is accepted
+fn treasury_token_account_allowed(
+    treasury_ta: Option<&TreasuryTokenAccount>,
+    token_account: Pubkey,
+    token_owner: Pubkey,
+    treasury: Pubkey,
+) -> bool {
+    match treasury_ta {
+        Some(pinned) => token_account == pinned.token_account,
+        None => token_owner == treasury,
+    }
+}
+
+// Referral payouts stay with the relayer; only tracked when it opted into a referrer account
+fn record_referral(referrer_account: Option<&mut Account<ReferrerAccount>>, sol: u64, tokens: u64) {
+    if let Some(referrer_account) = referrer_account {
+        referrer_account.total_earned_sol = referrer_account.total_earned_sol.saturating_add(sol);
+        referrer_account.total_earned_tokens = referrer_account.total_earned_tokens.saturating_add(tokens);
+        referrer_account.referral_count = referrer_account.referral_count.saturating_add(1);
+    }
+}
+
+// During the whitelist phase only allowlisted owners may register; each
+// registration uses up one of the owner's whitelist slots
+fn consume_whitelist_slot(
+    config: &RegistryConfig,
+    whitelist: Option<&mut Whitelist>,
+    owner: Pubkey,
+) -> Result<()> {
+    if !config.whitelist_phase {
+        return Ok(());
+    }
+    let whitelist = whitelist.ok_or(ErrorCode::WhitelistPhaseActive)?;
+    require!(whitelist.user == owner, ErrorCode::UserNotWhitelisted);
+    require!(whitelist.used < whitelist.max_registrations, ErrorCode::WhitelistExhausted);
+    whitelist.used += 1;
+    Ok(())
+}
+
+// `init` already refuses an existing name_record; this keeps a registration path
+// from overwriting a live record if its account constraint ever changes
+fn assert_fresh_name_record(name_record: &NameRecord) -> Result<()> {
+    require!(
+        name_record.owner == Pubkey::default() && name_record.name.is_empty(),
+        ErrorCode::NameAlreadyExists
+    );
+    Ok(())
+}
+
+// Counts a newly registered name against its owner, enforcing max_names_per_owner
+fn record_owner_name(
+    owner_count: &mut Account<OwnerNameCount>,
+    owner: Pubkey,
+    bump: u8,
+    max_names_per_owner: u32,
+) -> Result<()> {
+    require!(
+        max_names_per_owner == 0 || owner_count.count < max_names_per_owner,
+        ErrorCode::OwnerNameLimitReached
+    );
+    owner_count.owner = owner;
+    owner_count.count += 1;
+    owner_count.bump = bump;
+    Ok(())
+}
+
+fn release_owner_name(owner_count: &mut Account<OwnerNameCount>, owner: Pubkey, bump: u8) {
+    owner_count.owner = owner;
+    owner_count.count = owner_count.count.saturating_sub(1);
+    owner_count.bump = bump;
+}
+
+// NEAR account ID rules: 2-64 chars of a-z and 0-9, split by single '-', '_'
+// or '.' separators that never lead or trail
+fn is_valid_near_account_id(account: &str) -> bool {
+    if account.len() < 2 || account.len() > MAX_NEAR_ACCOUNT_LEN {
+        return false;
+    }
+    let mut after_separator = true;
+    for b in account.bytes() {
+        let separator = matches!(b, b'-' | b'_' | b'.');
+        if separator && after_separator {
+            return false;
+        }
+        if !separator && !(b.is_ascii_lowercase() || b.is_ascii_digit()) {
+            return false;
+        }
+        after_separator = separator;
+    }
+    !after_separator
+}
+
+// Returns None when the instruction's relayer gate is off; otherwise the
+// relayer's active RelayerEntry must be supplied as the first remaining account.
+fn load_allowlisted_relayer<'info>(
+    config: &RegistryConfig,
+    gate: usize,
+    relayer: &Pubkey,
+    remaining_accounts: &'info [AccountInfo<'info>],
+) -> Result<Option<Account<'info, RelayerEntry>>> {
+    if !config.relayer_allowlist_per_instruction[gate] {
+        return Ok(None);
+    }
+
+    let entry_info = remaining_accounts.first().ok_or(ErrorCode::RelayerNotAllowed)?;
+    let (expected_entry, _) = Pubkey::find_program_address(&[b"relayer", relayer.as_ref()], &crate::ID);
+    require_keys_eq!(entry_info.key(), expected_entry, ErrorCode::RelayerNotAllowed);
+
+    let entry = Account::<RelayerEntry>::try_from(entry_info)?;
+    require!(entry.relayer == *relayer, ErrorCode::RelayerNotAllowed);
+    require!(entry.active, ErrorCode::RelayerNotAllowed);
+    Ok(Some(entry))
+}
+
+// A gated direct registration must be submitted by an active allowlisted
+// relayer co-signing for the user; relayer_entry has to be that relayer's entry
+fn assert_direct_relayer_gate(
+    config: &RegistryConfig,
+    gate: usize,
+    relayer: Option<&Signer>,
+    relayer_entry: Option<&RelayerEntry>,
+) -> Result<()> {
+    if !config.relayer_allowlist_per_instruction[gate] {
+        return Ok(());
+    }
+
+    let relayer = relayer.ok_or(ErrorCode::RelayerNotAllowed)?;
+    let entry = relayer_entry.ok_or(ErrorCode::RelayerNotAllowed)?;
+    require!(entry.relayer == relayer.key() && entry.active, ErrorCode::RelayerNotAllowed);
+    Ok(())
+}
+
+// Requires a ComputeBudget SetComputeUnitPrice instruction in the transaction
+// paying at least config.min_compute_unit_price
+fn assert_min_compute_unit_price(config: &RegistryConfig, instructions_sysvar: Option<&AccountInfo>) -> Result<()> {
+    if config.min_compute_unit_price == 0 {
+        return Ok(());
+    }
+
+    let instructions_sysvar = instructions_sysvar.ok_or(ErrorCode::InsufficientComputeUnitPrice)?;
+    let mut index = 0;
+    while let Ok(ix) = load_instruction_at_checked(index, instructions_sysvar) {
+        if ix.program_id == COMPUTE_BUDGET_PROGRAM_ID && ix.data.len() == 9 && ix.data[0] == SET_COMPUTE_UNIT_PRICE_TAG {
+            let mut price = [0u8; 8];
+            price.copy_from_slice(&ix.data[1..9]);
+            let price = u64::from_le_bytes(price);
+            if price >= config.min_compute_unit_price {
+                return Ok(());
+            }
+            msg!("InsufficientComputeUnitPrice: price={}, min={}", price, config.min_compute_unit_price);
+            return err!(ErrorCode::InsufficientComputeUnitPrice);
+        }
+        index += 1;
+    }
+    err!(ErrorCode::InsufficientComputeUnitPrice)
+}
+
+// Fails up front instead of running out of compute units midway through CPIs
+fn assert_compute_budget(min_required: u64) -> Result<()> {
+    let remaining = sol_remaining_compute_units();
+    if remaining < min_required {
+        msg!("InsufficientComputeUnits: remaining={}", remaining);
+        return err!(ErrorCode::InsufficientComputeUnits);
+    }
+    Ok(())
+}
+
+fn registration_result(name_record: &NameRecord) -> RegistrationResult {
+    RegistrationResult {
+        name: name_record.name.clone(),
+        owner: name_record.owner,
+        resolved: name_record.resolved,
+        created_at: name_record.created_at,
+        bump: name_record.bump,
+    }
+}
+
+// Writes an approved proposal's value into the config field its type selects
+fn apply_proposal(config: &mut RegistryConfig, proposal_type: ProposalType, value: &[u8]) -> Result<()> {
+    match proposal_type {
+        ProposalType::RegistrationFee => {
+            let fee = u64::from_le_bytes(value.try_into().map_err(|_| error!(ErrorCode::InvalidProposal))?);
+            config.registration_fee = fee;
+        }
+        ProposalType::ReferrerBps => {
+            let bps = u16::from_le_bytes(value.try_into().map_err(|_| error!(ErrorCode::InvalidProposal))?);
+            require!(bps <= 10_000, ErrorCode::InvalidReferrerBps);
+            require!(bps >= config.min_referrer_bps, ErrorCode::ReferrerBpsBelowFloor);
+            config.referrer_bps = bps;
+        }
+        ProposalType::MinReferrerBps => {
+            let min_bps = u16::from_le_bytes(value.try_into().map_err(|_| error!(ErrorCode::InvalidProposal))?);
+            require!(min_bps <= 10_000, ErrorCode::InvalidReferrerBps);
+            config.min_referrer_bps = min_bps;
+        }
+        ProposalType::Treasury => {
+            let treasury = Pubkey::try_from(value).map_err(|_| error!(ErrorCode::InvalidProposal))?;
+            require!(treasury != Pubkey::default(), ErrorCode::InvalidTreasuryAddress);
+            config.treasury = treasury;
+        }
+    }
+    Ok(())
+}
+
+fn commitment_pda(commitment: &[u8; 32]) -> Pubkey {
+    Pubkey::find_program_address(&[b"commitment", commitment.as_ref()], &crate::ID).0
+}
+
+// Reads the SPL Governance GovernanceV2 and ProposalV2 accounts by hand; the
+// governance program has no query instruction and its crate pins an older
+// solana-program. Returns the label of the proposal's first option once the
+// proposal has Completed under the gated realm.
+fn governance_approved_name(
+    gate: &GovernanceGate,
+    governance: &AccountInfo,
+    proposal: &AccountInfo,
+) -> Result<String> {
+    const GOVERNANCE_V2: u8 = 18;
+    const PROPOSAL_V2: u8 = 14;
+    const PROPOSAL_STATE_COMPLETED: u8 = 5;
+
+    let governance_data = governance.try_borrow_data()?;
+    require!(governance_data.len() >= 33 && governance_data[0] == GOVERNANCE_V2, ErrorCode::ProposalNotApproved);
+    require!(governance_data[1..33] == gate.realm.to_bytes(), ErrorCode::ProposalNotApproved);
+
+    // account_type, governance, governing_token_mint, state, token_owner_record,
+    // signatories_count, signatories_signed_off_count, vote_type, options
+    let data = proposal.try_borrow_data()?;
+    require!(data.len() >= 102 && data[0] == PROPOSAL_V2, ErrorCode::ProposalNotApproved);
+    require!(data[1..33] == governance.key.to_bytes(), ErrorCode::ProposalNotApproved);
+    require!(data[33..65] == gate.governing_token_mint.to_bytes(), ErrorCode::ProposalNotApproved);
+    require!(data[65] == PROPOSAL_STATE_COMPLETED, ErrorCode::ProposalNotApproved);
+
+    let (vote_type, mut offset) = match data[100] {
+        0 => (SplVoteType::SingleChoice, 101),
+        1 => (SplVoteType::MultiChoice, 105),
+        _ => return err!(ErrorCode::ProposalNotApproved),
+    };
+    require!(vote_type == gate.proposal_type, ErrorCode::ProposalNotApproved);
+
+    let read_u32 = |at: usize| -> Result<usize> {
+        let bytes = data.get(at..at + 4).ok_or(ErrorCode::ProposalNotApproved)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+    };
+    require!(read_u32(offset)? > 0, ErrorCode::ProposalNotApproved);
+    offset += 4;
+    let label_len = read_u32(offset)?;
+    offset += 4;
+    let label = data.get(offset..offset + label_len).ok_or(ErrorCode::ProposalNotApproved)?;
+    String::from_utf8(label.to_vec()).map_err(|_| error!(ErrorCode::ProposalNotApproved))
+}
+
+// Option label a gate proposal must carry to approve `name` for `recipient`
+pub fn governance_proposal_label(name: &str, recipient: &Pubkey) -> String {
+    format!("{}:{}", name, recipient)
+}
+
+// Authority PDA Squads signs with when executing an approved proposal
+pub fn squads_executor_pda(multisig: Pubkey, authority_index: u32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"squad", multisig.as_ref(), &authority_index.to_le_bytes(), b"authority"],
+        &SQUADS_PROGRAM_ID,
+    ).0
+}
+
+// Creates a program-owned PDA at `target`, funded by `payer`
+fn create_pda_account<'info>(
+    payer: &AccountInfo<'info>,
+    target: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+    seeds: &[&[u8]],
+) -> Result<()> {
+    let lamports = Rent::get()?.minimum_balance(space);
+    let signer_seeds = [seeds];
+    let current_lamports = target.lamports();
+    if current_lamports == 0 {
+        let cpi_accounts = system_program::CreateAccount {
+            from: payer.clone(),
+            to: target.clone(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(system_program.clone(), cpi_accounts, &signer_seeds);
+        return system_program::create_account(cpi_ctx, lamports, space as u64, &crate::ID);
+    }
+
+    // Someone already sent lamports to the address, which makes create_account
+    // fail. Top it up and allocate/assign instead, as Anchor's `init` does.
+    let top_up = lamports.saturating_sub(current_lamports);
+    if top_up > 0 {
+        let cpi_accounts = system_program::Transfer {
+            from: payer.clone(),
+            to: target.clone(),
+        };
+        system_program::transfer(CpiContext::new(system_program.clone(), cpi_accounts), top_up)?;
+    }
+    let cpi_accounts = system_program::Allocate { account_to_allocate: target.clone() };
+    system_program::allocate(
+        CpiContext::new_with_signer(system_program.clone(), cpi_accounts, &signer_seeds),
+        space as u64,
+    )?;
+    let cpi_accounts = system_program::Assign { account_to_assign: target.clone() };
+    system_program::assign(
+        CpiContext::new_with_signer(system_program.clone(), cpi_accounts, &signer_seeds),
+        &crate::ID,
+    )
+}
+
+// Appends a NameHistoryEntry for `name`, creating the history head on first use.
+// `history_accounts` must start with the head PDA followed by the entry PDA at
+// the head's next_index.
+fn record_history_entry<'info>(
+    history_accounts: &'info [AccountInfo<'info>],
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    name: &str,
+    old_resolved: Pubkey,
+    new_resolved: Pubkey,
+    changed_at: i64,
+) -> Result<()> {
+    let (head_info, entry_info) = match history_accounts {
+        [head_info, entry_info, ..] => (head_info, entry_info),
+        _ => return err!(ErrorCode::InvalidHistoryAccount),
+    };
+
+    let name_hash = hashv(&[name.as_bytes()]).to_bytes();
+    let (expected_head, head_bump) = Pubkey::find_program_address(&[b"history_head", &name_hash], &crate::ID);
+    require_keys_eq!(head_info.key(), expected_head, ErrorCode::InvalidHistoryAccount);
+
+    let mut head = if head_info.data_is_empty() {
+        create_pda_account(
+            payer,
+            head_info,
+            system_program,
+            NAME_HISTORY_HEAD_ACCOUNT_SPACE,
+            &[b"history_head", &name_hash, &[head_bump]],
+        )?;
+        NameHistoryHead { name_hash, next_index: 0, bump: head_bump }
+    } else {
+        Account::<NameHistoryHead>::try_from(head_info)?.into_inner()
+    };
+
+    let index_bytes = head.next_index.to_le_bytes();
+    let (expected_entry, entry_bump) =
+        Pubkey::find_program_address(&[b"history", &name_hash, &index_bytes], &crate::ID);
+    require_keys_eq!(entry_info.key(), expected_entry, ErrorCode::InvalidHistoryAccount);
+    create_pda_account(
+        payer,
+        entry_info,
+        system_program,
+        NAME_HISTORY_ENTRY_ACCOUNT_SPACE,
+        &[b"history", &name_hash, &index_bytes, &[entry_bump]],
+    )?;
+
+    let entry = NameHistoryEntry {
+        name_hash,
+        entry_index: head.next_index,
+        old_resolved,
+        new_resolved,
+        changed_at,
+        changed_by: payer.key(),
+        bump: entry_bump,
+    };
+    entry.try_serialize(&mut &mut entry_info.try_borrow_mut_data()?[..])?;
+
+    head.next_index = head.next_index.checked_add(1).ok_or(ErrorCode::InvalidHistoryAccount)?;
+    head.try_serialize(&mut &mut head_info.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+// The entry must have been granted by the name's current owner
+fn require_acl_permission(name: &str, owner: &Pubkey, member: &Pubkey, acl_info: &AccountInfo, permission: u8) -> Result<()> {
+    let name_hash = hashv(&[name.as_bytes()]).to_bytes();
+    let (expected_acl, _) = Pubkey::find_program_address(&[b"acl", &name_hash, owner.as_ref(), member.as_ref()], &crate::ID);
+    require_keys_eq!(acl_info.key(), expected_acl, ErrorCode::Unauthorized);
+    require_keys_eq!(*acl_info.owner, crate::ID, ErrorCode::Unauthorized);
+
+    let data = acl_info.try_borrow_data()?;
+    let acl = NameAcl::try_deserialize(&mut &data[..])?;
+    require!(acl.permissions & permission == permission, ErrorCode::Unauthorized);
+    Ok(())
+}
+
+fn validate_relayer_metadata(name: &str, endpoint_url: &str) -> Result<()> {
+    require!(
+        name.len() <= MAX_RELAYER_NAME_LEN && endpoint_url.len() <= MAX_RELAYER_URL_LEN,
+        ErrorCode::RelayerMetadataTooLong
+    );
+    Ok(())
+}
+
+// Remaining accounts layout: [treasury_split, recipient_0, .., recipient_{count-1}]
+fn distribute_treasury_split<'info>(
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    amount: u64,
+) -> Result<()> {
+    let (split_info, recipient_infos) = remaining_accounts
+        .split_first()
+        .ok_or(ErrorCode::TreasurySplitMismatch)?;
+    let (expected_split, _) = Pubkey::find_program_address(&[b"treasury_split"], &crate::ID);
+    require_keys_eq!(split_info.key(), expected_split, ErrorCode::TreasurySplitMismatch);
+    let split: Account<'info, TreasurySplit> = Account::try_from(split_info)?;
+
+    let count = split.count as usize;
+    require!(recipient_infos.len() == count, ErrorCode::TreasurySplitMismatch);
+
+    let mut distributed: u64 = 0;
+    for (i, entry) in split.recipients[..count].iter().enumerate() {
+        let recipient_info = &recipient_infos[i];
+        require_keys_eq!(recipient_info.key(), entry.recipient, ErrorCode::TreasurySplitMismatch);
+
+        // Last recipient absorbs rounding dust
+        let share = if i == count - 1 {
+            amount - distributed
+        } else {
+            ((amount as u128) * (entry.bps as u128) / 10_000) as u64
+        };
+        distributed += share;
+
+        if share > 0 {
+            let cpi_accounts = system_program::Transfer {
+                from: payer.clone(),
+                to: recipient_info.clone(),
+            };
+            let cpi_ctx = CpiContext::new(system_program.clone(), cpi_accounts);
+            system_program::transfer(cpi_ctx, share)?;
+        }
+    }
+    Ok(())
+}
+
+// Signed deadlines must be in the future but no further out than the configured window
+fn check_deadline(config: &RegistryConfig, deadline: i64) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    require!(now <= deadline, ErrorCode::DeadlineExpired);
+    require!(deadline - now <= config.max_deadline_window_seconds, ErrorCode::DeadlineTooFar);
+    require!(deadline - now <= config.max_signature_validity_seconds, ErrorCode::SignatureTooOld);
+    Ok(())
+}
+
+// Both signature types sign build_registration_message, which starts with the
+// domain separator and covers the deadline check_deadline validated. Ed25519
+// signatures are checked by the Ed25519 program instruction placed right
+// before this one in the transaction
+#[allow(deprecated)]
+fn verify_signature(params: &RegisterWithSigParams, signature: &[u8], instructions_sysvar: &AccountInfo) -> Result<()> {
+    use anchor_lang::solana_program::keccak;
+
+    match params.signature_type {
+        SignatureType::Ed25519 => {
+            let signature: &[u8; 64] = signature.try_into().map_err(|_| error!(ErrorCode::InvalidSignature))?;
+            let message = build_registration_message(params, &DOMAIN_SEPARATOR);
+            verify_ed25519_instruction(instructions_sysvar, &params.owner, &message, signature)?;
+        }
+        SignatureType::Secp256k1 => {
+            let evm_address = params.evm_address.ok_or(ErrorCode::InvalidSignatureType)?;
+            require!(signature.len() == 65, ErrorCode::InvalidSignature);
+
+            // Accept both raw (0/1) and EVM-style (27/28) recovery ids
+            let recovery_id = match signature[64] {
+                v @ 0..=1 => v,
+                v @ 27..=28 => v - 27,
+                _ => return err!(ErrorCode::InvalidSignature),
+            };
+            // The signed deadline is the one check_deadline validated
+            let message_hash = keccak::hash(&build_registration_message(params, &DOMAIN_SEPARATOR));
+            let recovered = secp256k1_recover(message_hash.as_ref(), recovery_id, &signature[..64])
+                .map_err(|_| error!(ErrorCode::InvalidSignature))?;
+
+            // EVM address is the last 20 bytes of keccak256(uncompressed public key)
+            let recovered_hash = keccak::hash(&recovered.to_bytes());
+            require!(recovered_hash.as_ref()[12..] == evm_address, ErrorCode::InvalidSignature);
+            // The recovered key only vouches for its own identity, never an arbitrary owner
+            require_keys_eq!(params.owner, evm_owner_pda(&evm_address, &crate::ID).0, ErrorCode::InvalidSignature);
+        }
+    }
+    Ok(())
+}
+
+// Checks that the instruction right before the current one is an Ed25519
+// program instruction verifying exactly (signer, message, signature) with
+// all data inline in that instruction.
+fn verify_ed25519_instruction(
+    instructions_sysvar: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+    signature: &[u8; 64],
+) -> Result<()> {
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    require!(current_index > 0, ErrorCode::InvalidSignature);
+    let ix = load_instruction_at_checked(current_index as usize - 1, instructions_sysvar)?;
+    require_keys_eq!(ix.program_id, ed25519_program::ID, ErrorCode::InvalidSignature);
+
+    // Header: num_signatures (u8), padding (u8), then one 14-byte offsets struct
+    let data = &ix.data;
+    require!(data.len() >= 16 && data[0] == 1, ErrorCode::InvalidSignature);
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let signature_offset = read_u16(2) as usize;
+    let public_key_offset = read_u16(6) as usize;
+    let message_offset = read_u16(10) as usize;
+    let message_size = read_u16(12) as usize;
+    // u16::MAX means the data lives in the Ed25519 instruction itself
+    require!(
+        read_u16(4) == u16::MAX && read_u16(8) == u16::MAX && read_u16(14) == u16::MAX,
+        ErrorCode::InvalidSignature
+    );
+
+    let slice = |offset: usize, len: usize| data.get(offset..offset + len).ok_or(ErrorCode::InvalidSignature);
+    require!(slice(public_key_offset, 32)? == signer.as_ref(), ErrorCode::InvalidSignature);
+    require!(slice(signature_offset, 64)? == signature.as_ref(), ErrorCode::InvalidSignature);
+    require!(message_size == message.len() && slice(message_offset, message_size)? == message, ErrorCode::InvalidSignature);
+    Ok(())
+}
+
+// ========================================
+// CPI CLIENT
+// ========================================
+
+/// Helpers for other programs resolving names against this registry, either by
+/// reading the name record directly or by CPI into `resolve_name` (build with
+/// the `cpi` feature and read the result with `get_return_data`).
+pub mod nominal_cpi_client {
+    use super::*;
+
+    pub fn resolve_name_pda(name: &str, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"name", name.as_bytes()], program_id)
+    }
+
+    /// NameRecord PDA for `name` under this program. A name is available when
+    /// `getAccountInfo` on this address returns no account.
+    pub fn name_record_pda(name: &str) -> (Pubkey, u8) {
+        resolve_name_pda(name, &crate::ID)
+    }
+
+    /// Head of the resolved-address history for `name`; `next_index` is the
+    /// number of entries written so far.
+    pub fn get_history_head(name: &str, program_id: &Pubkey) -> (Pubkey, u8) {
+        let name_hash = hashv(&[name.as_bytes()]).to_bytes();
+        Pubkey::find_program_address(&[b"history_head", &name_hash], program_id)
+    }
+
+    /// `NameHistoryEntry` number `index` for `name`, oldest first.
+    pub fn get_history_entry(name: &str, index: u32, program_id: &Pubkey) -> (Pubkey, u8) {
+        let name_hash = hashv(&[name.as_bytes()]).to_bytes();
+        Pubkey::find_program_address(&[b"history", &name_hash, &index.to_le_bytes()], program_id)
+    }
+
+    /// PDA holding the `NameStats` written by `record_resolution`.
+    pub fn get_name_stats(name: &str, program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"name_stats", name.as_bytes()], program_id)
+    }
+
+    /// Verifies `name_record` is the registry PDA for `name` and returns its resolved address.
+    pub fn read_resolved(name: &str, name_record: &AccountInfo) -> Result<Pubkey> {
+        let (expected, _) = resolve_name_pda(name, &crate::ID);
+        require_keys_eq!(name_record.key(), expected, ErrorCode::NameNotFound);
+        require_keys_eq!(*name_record.owner, crate::ID, ErrorCode::NameNotFound);
+
+        let data = name_record.try_borrow_data()?;
+        let record = NameRecord::try_deserialize(&mut &data[..])?;
+        Ok(record.resolved)
+    }
+}
+
 // ========================================
 // ERROR CODES
 // ========================================
@@ -958,4 +8173,178 @@ pub enum ErrorCode {
     InvalidTreasuryAddress,
     #[msg("Token fee amount mismatch")]
     TokenFeeMismatch,
+    #[msg("Referrer basis points below configured floor")]
+    ReferrerBpsBelowFloor,
+    #[msg("Invalid signature type")]
+    InvalidSignatureType,
+    #[msg("Treasury split BPS must sum to 10000")]
+    TreasurySplitMismatch,
+    #[msg("Invalid treasury split recipients (1-5 required)")]
+    InvalidTreasuryRecipients,
+    #[msg("Name record already migrated")]
+    AlreadyMigrated,
+    #[msg("Resolution forcibly updated by admin")]
+    ForcedResolutionUpdated,
+    #[msg("Admin override disabled")]
+    AdminOverrideDisabled,
+    #[msg("Relayer bond below configured minimum")]
+    InsufficientRelayerBond,
+    #[msg("Invalid bond amount")]
+    InvalidBondAmount,
+    #[msg("Relayer bond still locked")]
+    BondLocked,
+    #[msg("Relayer must be removed before unstaking")]
+    RelayerStillActive,
+    #[msg("Invalid auction parameters")]
+    InvalidAuctionParams,
+    #[msg("Auction already closed")]
+    AuctionClosed,
+    #[msg("Whitelist phase active: whitelist account required")]
+    WhitelistPhaseActive,
+    #[msg("User not whitelisted")]
+    UserNotWhitelisted,
+    #[msg("Whitelist registrations exhausted")]
+    WhitelistExhausted,
+    #[msg("TTL exceeds configured maximum")]
+    TtlTooHigh,
+    #[msg("Circular proxy chain detected")]
+    CircularProxyDetected,
+    #[msg("Proxy chain exceeds maximum depth")]
+    ProxyDepthExceeded,
+    #[msg("Withdrawal exceeds lamports above rent-exempt minimum")]
+    InsufficientSurplus,
+    #[msg("Name is locked")]
+    NameIsLocked,
+    #[msg("Relayer name or endpoint URL too long")]
+    RelayerMetadataTooLong,
+    #[msg("Relayer violation limit must be greater than zero")]
+    InvalidViolationLimit,
+    #[msg("Minimum reveal delay exceeds maximum")]
+    InvalidCommitmentConfig,
+    #[msg("Commitment does not match revealed name")]
+    CommitmentMismatch,
+    #[msg("Commitment too fresh to reveal")]
+    CommitmentTooFresh,
+    #[msg("Commitment expired, commit again")]
+    CommitmentExpired,
+    #[msg("Commitment has not expired")]
+    CommitmentNotExpired,
+    #[msg("Mint registry full")]
+    MintRegistryFull,
+    #[msg("Invalid proposal")]
+    InvalidProposal,
+    #[msg("Voting period has ended")]
+    VotingClosed,
+    #[msg("Voting period still open")]
+    VotingStillOpen,
+    #[msg("Proposal already executed")]
+    ProposalAlreadyExecuted,
+    #[msg("No pending admin transfer")]
+    NoPendingAdminTransfer,
+    #[msg("Treasury token account does not match the configured account for this mint")]
+    InvalidTreasuryTokenAccount,
+    #[msg("Name is currently leased")]
+    LeaseActive,
+    #[msg("Lease has expired, end it first")]
+    LeaseExpired,
+    #[msg("Lease needs a positive price and duration")]
+    LeasePriced,
+    #[msg("Owner has reached the maximum number of names")]
+    OwnerNameLimitReached,
+    #[msg("Bio too long (max 280 bytes)")]
+    BioTooLong,
+    #[msg("Invalid token program for this mint")]
+    InvalidTokenProgram,
+    #[msg("Character not allowed by the registry character set")]
+    CharacterNotAllowed,
+    #[msg("Missing or invalid name history account")]
+    InvalidHistoryAccount,
+    #[msg("Token fee outside the configured bounds")]
+    TokenFeeOutOfBounds,
+    #[msg("SOL fee outside the configured bounds")]
+    SolFeeOutOfBounds,
+    #[msg("Benchmark result account does not match its index")]
+    InvalidBenchmarkAccount,
+    #[msg("Primary name is still owned by its account")]
+    PrimaryNameNotStale,
+    #[msg("Multisig admin is not enabled")]
+    MultisigNotEnabled,
+    #[msg("Multisig threshold must be at least 1")]
+    InvalidMultisigThreshold,
+    #[msg("Name is held in escrow")]
+    NameInEscrow,
+    #[msg("Escrow price and timeout must be positive")]
+    InvalidEscrowParams,
+    #[msg("Escrow has timed out")]
+    EscrowExpired,
+    #[msg("Escrow still active")]
+    EscrowActive,
+    #[msg("Sub-registry is disabled")]
+    SubRegistryDisabled,
+    #[msg("Invalid sub-registry program")]
+    InvalidSubRegistryProgram,
+    #[msg("Sub-registry did not return a resolved address")]
+    InvalidSubRegistryResponse,
+    #[msg("Discount BPS must be <= 10000")]
+    InvalidDiscountBps,
+    #[msg("NFT does not qualify for the collection discount")]
+    InvalidNftProof,
+    #[msg("Avatar URI too long (max 200 bytes)")]
+    AvatarUriTooLong,
+    #[msg("Unsupported avatar MIME type")]
+    UnsupportedMimeType,
+    #[msg("Invalid NEAR account ID")]
+    InvalidNearAccount,
+    #[msg("Registration goes through governance while the gate is active")]
+    GovernanceGateActive,
+    #[msg("Governance proposal has not approved this name")]
+    ProposalNotApproved,
+    #[msg("Not enough compute units left; raise the compute unit limit")]
+    InsufficientComputeUnits,
+    #[msg("Invalid social platform (1-32 bytes)")]
+    InvalidSocialPlatform,
+    #[msg("Social handle is longer than 64 bytes")]
+    SocialHandleTooLong,
+    #[msg("Instruction index must be 0-3")]
+    InvalidInstructionIndex,
+    #[msg("Commitment slot is outside the slot commitment window")]
+    CommitmentSlotOutOfWindow,
+    #[msg("Deadline is too far in the future")]
+    DeadlineTooFar,
+    #[msg("Deadline window must be positive")]
+    InvalidDeadlineWindow,
+    #[msg("Signature deadline exceeds the maximum signature validity")]
+    SignatureTooOld,
+    #[msg("Name cannot start or end with an underscore")]
+    InvalidUnderscorePlacement,
+    #[msg("Name cannot contain consecutive underscores")]
+    ConsecutiveUnderscores,
+    #[msg("Compute unit price is below the registry minimum")]
+    InsufficientComputeUnitPrice,
+    #[msg("Bridge lock has not timed out")]
+    BridgeLockActive,
+    #[msg("Bridged name was already claimed on NEAR")]
+    BridgeLockClaimed,
+    #[msg("Config account is not in the original layout")]
+    InvalidConfigAccount,
+    #[msg("Names do not expire")]
+    NamesDoNotExpire,
+    #[msg("Invalid registration period or warning window")]
+    InvalidRegistrationPeriod,
+    #[msg("Name is not inside the expiry warning window")]
+    ExpiryWarningNotDue,
+    #[msg("Expiry warning was emitted too recently")]
+    ExpiryWarningTooRecent,
+    #[msg("Name record is not in a known layout")]
+    InvalidNameRecordAccount,
+    #[msg("No governance gate is active")]
+    GovernanceGateInactive,
+    #[msg("The previous bidder's account is required for the refund")]
+    PreviousBidderRequired,
+    #[msg("Social handle must not be empty")]
+    SocialHandleEmpty,
+    #[msg("Social attestation still belongs to the name's owner")]
+    SocialAttestationNotStale,
+    #[msg("Multisig account is not owned by Squads or executor does not match")]
+    InvalidMultisigAccount,
 }
@@ -0,0 +1,59 @@
+//! PDA derivations for off-chain clients.
+//!
+//! Each helper mirrors the seeds used by the program's account constraints so
+//! clients don't have to re-implement them. Pass `&nominal_registry::ID` as the
+//! program id unless targeting a separate deployment.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::hashv;
+
+/// Registry configuration, seeded `[b"config"]`.
+pub fn config_pda(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"config"], program_id)
+}
+
+/// Name record, seeded `[b"name", name]`. Names are stored lowercase, so pass
+/// the normalized name.
+pub fn name_record_pda(name: &str, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"name", name.as_bytes()], program_id)
+}
+
+/// Primary name of `owner`, seeded `[b"primary", owner]`.
+pub fn primary_name_pda(owner: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"primary", owner.as_ref()], program_id)
+}
+
+/// Token fee configuration for `mint`, seeded `[b"token_fee", mint]`.
+pub fn token_fee_pda(mint: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"token_fee", mint.as_ref()], program_id)
+}
+
+/// Allowlist entry for `relayer`, seeded `[b"relayer", relayer]`.
+pub fn relayer_entry_pda(relayer: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"relayer", relayer.as_ref()], program_id)
+}
+
+/// Signature nonce of `owner`, seeded `[b"owner_nonce", owner]`.
+pub fn owner_nonce_pda(owner: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"owner_nonce", owner.as_ref()], program_id)
+}
+
+/// Solana identity of an EVM wallet, seeded `[b"evm_owner", evm_address]`.
+/// Names registered with a Secp256k1 signature must be owned by this address,
+/// which is also where the program keeps the wallet's Secp256k1OwnerAddress.
+pub fn evm_owner_pda(evm_address: &[u8; 20], program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"evm_owner", evm_address], program_id)
+}
+
+/// Avatar of `name`, seeded `[b"avatar", sha256(name)]`.
+pub fn get_avatar_pda(name: &str, program_id: &Pubkey) -> (Pubkey, u8) {
+    let name_hash = hashv(&[name.as_bytes()]).to_bytes();
+    Pubkey::find_program_address(&[b"avatar", &name_hash], program_id)
+}
+
+/// Social attestation of `name` on `platform`, seeded
+/// `[b"social", sha256(name), platform]`.
+pub fn get_social_attestation_pda(name: &str, platform: &str, program_id: &Pubkey) -> (Pubkey, u8) {
+    let name_hash = hashv(&[name.as_bytes()]).to_bytes();
+    Pubkey::find_program_address(&[b"social", &name_hash, platform.as_bytes()], program_id)
+}
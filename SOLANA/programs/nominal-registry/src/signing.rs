@@ -0,0 +1,63 @@
+//! Canonical messages signed for relayed instructions.
+//!
+//! Every message is a 32-byte sha256 digest that starts with DOMAIN_SEPARATOR
+//! and an instruction tag, so a signature made for one instruction (or for
+//! another protocol) can't be replayed against a different one. Off-chain SDKs
+//! should build messages with these functions instead of re-implementing them.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::hashv;
+
+use crate::RegisterWithSigParams;
+
+/// `sha256(b"NominalProtocol_v1_Solana")`, also stored in ProgramMeta.
+pub const DOMAIN_SEPARATOR: [u8; 32] = [
+    249, 195, 141, 29, 88, 95, 89, 35, 32, 31, 218, 54, 43, 146, 239, 93,
+    127, 38, 190, 90, 188, 91, 190, 120, 20, 123, 72, 1, 33, 89, 51, 54,
+];
+
+/// Message for `register_name_with_signature` and
+/// `register_name_with_signature_token`:
+/// `sha256(domain || "registration" || program_id || name || owner || relayer
+/// || currency || amount || deadline || nonce)`. SOL payments encode the
+/// currency as the default pubkey; integers are little-endian.
+pub fn build_registration_message(params: &RegisterWithSigParams, domain: &[u8; 32]) -> [u8; 32] {
+    let currency = params.currency.unwrap_or_default();
+    hashv(&[
+        domain,
+        b"registration",
+        crate::ID.as_ref(),
+        params.name.as_bytes(),
+        params.owner.as_ref(),
+        params.relayer.as_ref(),
+        currency.as_ref(),
+        &params.amount.to_le_bytes(),
+        &params.deadline.to_le_bytes(),
+        &params.nonce.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Message for `set_resolved_with_signature`:
+/// `sha256(domain || "set_resolved" || program_id || name || new_resolved
+/// || owner || deadline || nonce)`.
+pub fn build_set_resolved_message(
+    name: &str,
+    new_resolved: &Pubkey,
+    owner: &Pubkey,
+    deadline: i64,
+    nonce: u64,
+    domain: &[u8; 32],
+) -> [u8; 32] {
+    hashv(&[
+        domain,
+        b"set_resolved",
+        crate::ID.as_ref(),
+        name.as_bytes(),
+        new_resolved.as_ref(),
+        owner.as_ref(),
+        &deadline.to_le_bytes(),
+        &nonce.to_le_bytes(),
+    ])
+    .to_bytes()
+}
@@ -0,0 +1,126 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AnchorDeserialize, AnchorSerialize};
+use nominal_registry::{
+    LegacyNameRecord, LegacyRegistryConfig, LegacyRelayerEntry, LegacyRelayerViolation, NameRecord,
+    RegistryConfig, RelayerEntry, RelayerViolation, LEGACY_REGISTRY_CONFIG_SPACE,
+    LEGACY_RELAYER_ENTRY_SPACE, LEGACY_RELAYER_VIOLATION_SPACE, MAX_RELAYER_NAME_LEN,
+    MAX_RELAYER_URL_LEN, RELAYER_ENTRY_ACCOUNT_SPACE, RELAYER_VIOLATION_ACCOUNT_SPACE,
+};
+
+// Catches fields added to RegistryConfig without growing RegistryConfig::LEN.
+// pending_admin is set so the Option is measured at its largest.
+#[test]
+fn test_config_space() {
+    let config = RegistryConfig {
+        pending_admin: Some(Pubkey::new_unique()),
+        ..RegistryConfig::default()
+    };
+    let mut bytes = Vec::new();
+    config.serialize(&mut bytes).unwrap();
+    assert!(bytes.len() <= RegistryConfig::LEN - 8);
+}
+
+// migrate_config relies on the original fields keeping their offsets
+#[test]
+fn test_config_keeps_legacy_prefix() {
+    let admin = Pubkey::new_unique();
+    let pending_admin = Some(Pubkey::new_unique());
+    let treasury = Pubkey::new_unique();
+
+    let legacy = LegacyRegistryConfig {
+        admin,
+        pending_admin,
+        treasury,
+        registration_fee: 1_000,
+        referrer_bps: 250,
+        require_allowlisted_relayer: true,
+        bump: 254,
+    };
+    let mut config = RegistryConfig {
+        admin,
+        pending_admin,
+        treasury,
+        registration_fee: 1_000,
+        referrer_bps: 250,
+        legacy_relayer_allowlist: true,
+        bump: 254,
+        ..Default::default()
+    };
+    config.set_appended_defaults();
+
+    let mut legacy_bytes = Vec::new();
+    legacy.serialize(&mut legacy_bytes).unwrap();
+    let mut config_bytes = Vec::new();
+    config.serialize(&mut config_bytes).unwrap();
+    assert_eq!(legacy_bytes.len(), LEGACY_REGISTRY_CONFIG_SPACE - 8);
+    assert_eq!(&config_bytes[..legacy_bytes.len()], &legacy_bytes[..]);
+}
+
+// migrate_name_record falls back to the legacy layout only when the current one fails to read
+#[test]
+fn test_legacy_name_record_is_not_read_as_current() {
+    let legacy = LegacyNameRecord {
+        name: "alice".to_string(),
+        owner: Pubkey::new_unique(),
+        resolved: Pubkey::new_unique(),
+        updated_at: 1_700_000_000,
+        bump: 255,
+    };
+    let mut bytes = Vec::new();
+    legacy.serialize(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), 77 + legacy.name.len());
+
+    assert!(NameRecord::deserialize(&mut &bytes[..]).is_err());
+    let read = LegacyNameRecord::deserialize(&mut &bytes[..]).unwrap();
+    assert_eq!(read.owner, legacy.owner);
+    assert_eq!(read.updated_at, legacy.updated_at);
+}
+
+// migrate_relayer_entry relies on the metadata fields being appended after bump
+#[test]
+fn test_relayer_entry_keeps_legacy_prefix() {
+    let relayer = Pubkey::new_unique();
+    let legacy = LegacyRelayerEntry { relayer, bump: 253 };
+    let entry = RelayerEntry {
+        relayer,
+        bump: 253,
+        name: "a".repeat(MAX_RELAYER_NAME_LEN),
+        endpoint_url: "b".repeat(MAX_RELAYER_URL_LEN),
+        active: true,
+    };
+
+    let mut legacy_bytes = Vec::new();
+    legacy.serialize(&mut legacy_bytes).unwrap();
+    let mut entry_bytes = Vec::new();
+    entry.serialize(&mut entry_bytes).unwrap();
+    assert_eq!(legacy_bytes.len(), LEGACY_RELAYER_ENTRY_SPACE - 8);
+    assert_eq!(&entry_bytes[..legacy_bytes.len()], &legacy_bytes[..]);
+    assert_eq!(entry_bytes.len(), RELAYER_ENTRY_ACCOUNT_SPACE - 8);
+}
+
+// migrate_relayer_violation only appends last_violation_at
+#[test]
+fn test_relayer_violation_keeps_legacy_prefix() {
+    let relayer = Pubkey::new_unique();
+    let legacy = LegacyRelayerViolation {
+        relayer,
+        violations: 3,
+        slashed_at: Some(1_700_000_000),
+        bump: 254,
+    };
+    let violation = RelayerViolation {
+        relayer,
+        violations: 3,
+        slashed_at: Some(1_700_000_000),
+        bump: 254,
+        last_violation_at: 1_700_000_100,
+    };
+
+    let mut legacy_bytes = Vec::new();
+    legacy.serialize(&mut legacy_bytes).unwrap();
+    let mut violation_bytes = Vec::new();
+    violation.serialize(&mut violation_bytes).unwrap();
+    assert_eq!(legacy_bytes.len(), LEGACY_RELAYER_VIOLATION_SPACE - 8);
+    assert_eq!(&violation_bytes[..legacy_bytes.len()], &legacy_bytes[..]);
+    assert_eq!(violation_bytes.len(), RELAYER_VIOLATION_ACCOUNT_SPACE - 8);
+}
@@ -0,0 +1,134 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::hashv;
+use nominal_registry::{
+    config_pda, evm_owner_pda, get_avatar_pda, get_social_attestation_pda, name_record_pda,
+    owner_nonce_pda, primary_name_pda, relayer_entry_pda, token_fee_pda,
+};
+
+// Seeds below are copied from the program's account constraints so a change on
+// either side shows up here.
+fn expected(seeds: &[&[u8]]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(seeds, &nominal_registry::ID)
+}
+
+fn assert_valid_bump(seeds: &[&[u8]], (address, bump): (Pubkey, u8)) {
+    let mut with_bump = seeds.to_vec();
+    let bump_seed = [bump];
+    with_bump.push(&bump_seed);
+    let recreated = Pubkey::create_program_address(&with_bump, &nominal_registry::ID).unwrap();
+    assert_eq!(recreated, address);
+}
+
+#[test]
+fn config_pda_matches_program_seeds() {
+    let derived = config_pda(&nominal_registry::ID);
+    assert_eq!(derived, expected(&[b"config"]));
+    assert_valid_bump(&[b"config"], derived);
+}
+
+#[test]
+fn name_record_pda_matches_program_seeds() {
+    let longest = "a".repeat(nominal_registry::MAX_NAME_LEN);
+    for name in ["abc", "alice", "my-name-2024", longest.as_str()] {
+        let derived = name_record_pda(name, &nominal_registry::ID);
+        assert_eq!(derived, expected(&[b"name", name.as_bytes()]));
+        assert_valid_bump(&[b"name", name.as_bytes()], derived);
+    }
+    assert_ne!(
+        name_record_pda("alice", &nominal_registry::ID).0,
+        name_record_pda("alicf", &nominal_registry::ID).0
+    );
+}
+
+#[test]
+fn name_record_pda_matches_cpi_client() {
+    assert_eq!(
+        name_record_pda("alice", &nominal_registry::ID),
+        nominal_registry::nominal_cpi_client::name_record_pda("alice")
+    );
+}
+
+#[test]
+fn primary_name_pda_matches_program_seeds() {
+    let owner = Pubkey::new_unique();
+    let derived = primary_name_pda(&owner, &nominal_registry::ID);
+    assert_eq!(derived, expected(&[b"primary", owner.as_ref()]));
+    assert_valid_bump(&[b"primary", owner.as_ref()], derived);
+}
+
+#[test]
+fn token_fee_pda_matches_program_seeds() {
+    let mint = Pubkey::new_unique();
+    let derived = token_fee_pda(&mint, &nominal_registry::ID);
+    assert_eq!(derived, expected(&[b"token_fee", mint.as_ref()]));
+    assert_valid_bump(&[b"token_fee", mint.as_ref()], derived);
+}
+
+#[test]
+fn relayer_entry_pda_matches_program_seeds() {
+    let relayer = Pubkey::new_unique();
+    let derived = relayer_entry_pda(&relayer, &nominal_registry::ID);
+    assert_eq!(derived, expected(&[b"relayer", relayer.as_ref()]));
+    assert_valid_bump(&[b"relayer", relayer.as_ref()], derived);
+}
+
+#[test]
+fn owner_nonce_pda_matches_program_seeds() {
+    let owner = Pubkey::new_unique();
+    let derived = owner_nonce_pda(&owner, &nominal_registry::ID);
+    assert_eq!(derived, expected(&[b"owner_nonce", owner.as_ref()]));
+    assert_valid_bump(&[b"owner_nonce", owner.as_ref()], derived);
+}
+
+#[test]
+fn evm_owner_pda_matches_program_seeds() {
+    let evm_address = [0x42u8; 20];
+    let derived = evm_owner_pda(&evm_address, &nominal_registry::ID);
+    assert_eq!(derived, expected(&[b"evm_owner", &evm_address]));
+    assert_valid_bump(&[b"evm_owner", &evm_address], derived);
+    assert_ne!(derived.0, evm_owner_pda(&[0x43u8; 20], &nominal_registry::ID).0);
+}
+
+#[test]
+fn avatar_pda_matches_program_seeds() {
+    let name_hash = hashv(&[b"alice"]).to_bytes();
+    let derived = get_avatar_pda("alice", &nominal_registry::ID);
+    assert_eq!(derived, expected(&[b"avatar", &name_hash]));
+    assert_valid_bump(&[b"avatar", &name_hash], derived);
+}
+
+#[test]
+fn social_attestation_pda_matches_program_seeds() {
+    let name_hash = hashv(&[b"alice"]).to_bytes();
+    let derived = get_social_attestation_pda("alice", "github", &nominal_registry::ID);
+    assert_eq!(derived, expected(&[b"social", &name_hash, b"github"]));
+    assert_valid_bump(&[b"social", &name_hash, b"github"], derived);
+    assert_ne!(derived.0, get_social_attestation_pda("alice", "twitter", &nominal_registry::ID).0);
+}
+
+#[test]
+fn derivations_depend_on_program_id() {
+    let other_program = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    assert_ne!(config_pda(&other_program).0, config_pda(&nominal_registry::ID).0);
+    assert_ne!(
+        primary_name_pda(&owner, &other_program).0,
+        primary_name_pda(&owner, &nominal_registry::ID).0
+    );
+}
+
+#[test]
+fn seed_namespaces_do_not_collide() {
+    let key = Pubkey::new_unique();
+    let addresses = [
+        primary_name_pda(&key, &nominal_registry::ID).0,
+        token_fee_pda(&key, &nominal_registry::ID).0,
+        relayer_entry_pda(&key, &nominal_registry::ID).0,
+        owner_nonce_pda(&key, &nominal_registry::ID).0,
+    ];
+    for (i, a) in addresses.iter().enumerate() {
+        for b in &addresses[i + 1..] {
+            assert_ne!(a, b);
+        }
+    }
+}
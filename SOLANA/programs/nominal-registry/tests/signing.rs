@@ -0,0 +1,52 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::hashv;
+use nominal_registry::signing::{build_registration_message, build_set_resolved_message, DOMAIN_SEPARATOR};
+use nominal_registry::{RegisterWithSigParams, SignatureType};
+
+#[test]
+fn domain_separator_matches_its_preimage() {
+    assert_eq!(DOMAIN_SEPARATOR, hashv(&[b"NominalProtocol_v1_Solana"]).to_bytes());
+}
+
+#[test]
+fn messages_are_separated_by_instruction_and_domain() {
+    let owner = Pubkey::new_unique();
+    let relayer = Pubkey::new_unique();
+    let params = RegisterWithSigParams {
+        name: "alice".to_string(),
+        owner,
+        relayer,
+        currency: None,
+        amount: 0,
+        deadline: 1_700_000_000,
+        nonce: 0,
+        signature_type: SignatureType::Ed25519,
+        evm_address: None,
+    };
+
+    let registration = build_registration_message(&params, &DOMAIN_SEPARATOR);
+    let set_resolved = build_set_resolved_message("alice", &relayer, &owner, 1_700_000_000, 0, &DOMAIN_SEPARATOR);
+    assert_ne!(registration, set_resolved);
+    assert_ne!(registration, build_registration_message(&params, &[0u8; 32]));
+}
+
+#[test]
+fn registration_message_commits_to_the_deadline() {
+    let params = RegisterWithSigParams {
+        name: "alice".to_string(),
+        owner: Pubkey::new_unique(),
+        relayer: Pubkey::new_unique(),
+        currency: None,
+        amount: 0,
+        deadline: 1_700_000_000,
+        nonce: 0,
+        signature_type: SignatureType::Ed25519,
+        evm_address: None,
+    };
+    let extended = RegisterWithSigParams { deadline: params.deadline + 1, ..params.clone() };
+
+    assert_ne!(
+        build_registration_message(&params, &DOMAIN_SEPARATOR),
+        build_registration_message(&extended, &DOMAIN_SEPARATOR)
+    );
+}
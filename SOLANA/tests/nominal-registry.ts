@@ -8,7 +8,9 @@ import {
   SystemProgram,
   LAMPORTS_PER_SOL,
   ComputeBudgetProgram,
-  Transaction
+  Transaction,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  Ed25519Program
 } from "@solana/web3.js";
 import {
   createMint,
@@ -16,6 +18,7 @@ import {
   mintTo,
   getAccount
 } from "@solana/spl-token";
+import { createHash } from "crypto";
 
 describe("nominal-registry", () => {
   const provider = anchor.AnchorProvider.env();
@@ -40,6 +43,59 @@ describe("nominal-registry", () => {
     return transaction;
   };
 
+  // Helper to bond a relayer so it can be added to the allowlist
+  const stakeRelayer = async (relayer: Keypair) => {
+    const [relayerBondPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("bond"), relayer.publicKey.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .stakeRelayer(new anchor.BN(0.01 * LAMPORTS_PER_SOL))
+      .accounts({
+        relayer: relayer.publicKey,
+        relayerBond: relayerBondPda,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([relayer])
+      .rpc();
+    return relayerBondPda;
+  };
+
+  // sha256("NominalProtocol_v1_Solana"), mirrors signing::DOMAIN_SEPARATOR
+  const DOMAIN_SEPARATOR = createHash("sha256").update("NominalProtocol_v1_Solana").digest();
+
+  // Owner's Ed25519 signature over signing::build_registration_message, plus the
+  // Ed25519 program instruction that has to sit right before the registration
+  const signRegistration = (params: any, signer: Keypair = user) => {
+    const currency: PublicKey = params.currency ?? PublicKey.default;
+    const message = createHash("sha256").update(Buffer.concat([
+      DOMAIN_SEPARATOR,
+      Buffer.from("registration"),
+      program.programId.toBuffer(),
+      Buffer.from(params.name),
+      params.owner.toBuffer(),
+      params.relayer.toBuffer(),
+      currency.toBuffer(),
+      params.amount.toArrayLike(Buffer, "le", 8),
+      params.deadline.toTwos(64).toArrayLike(Buffer, "le", 8),
+      params.nonce.toArrayLike(Buffer, "le", 8),
+    ])).digest();
+    const ed25519Ix = Ed25519Program.createInstructionWithPrivateKey({ privateKey: signer.secretKey, message });
+    // web3.js lays the data out as header (16) + public key (32) + signature (64) + message
+    const signature = Buffer.from(ed25519Ix.data.subarray(48, 112));
+    return { signature, ed25519Ix };
+  };
+
+  // Next nonce the program expects in an owner-signed message
+  const currentNonce = async (owner: PublicKey): Promise<anchor.BN> => {
+    const [ownerNoncePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("owner_nonce"), owner.toBuffer()],
+      program.programId
+    );
+    const ownerNonce = await program.account.ownerNonce.fetchNullable(ownerNoncePda);
+    return ownerNonce ? ownerNonce.nonce : new anchor.BN(0);
+  };
+
   before(async () => {
     // Fund accounts
     await provider.connection.requestAirdrop(admin.publicKey, 2 * LAMPORTS_PER_SOL);
@@ -60,6 +116,10 @@ describe("nominal-registry", () => {
     it("Initializes the registry", async () => {
       const registrationFee = new anchor.BN(0.001 * LAMPORTS_PER_SOL); // 0.001 SOL
       const referrerBps = 300; // 3%
+      const [programData] = PublicKey.findProgramAddressSync(
+        [program.programId.toBuffer()],
+        new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+      );
 
       const tx = await program.methods
         .initialize(registrationFee, referrerBps)
@@ -67,8 +127,9 @@ describe("nominal-registry", () => {
           admin: admin.publicKey,
           treasury: treasury.publicKey,
           config: configPda,
+          programData,
           systemProgram: SystemProgram.programId,
-        })
+        } as any)
         .signers([admin])
         .preInstructions([
           ComputeBudgetProgram.setComputeUnitLimit({ units: 400000 })
@@ -80,7 +141,76 @@ describe("nominal-registry", () => {
       expect(config.treasury.toString()).to.equal(treasury.publicKey.toString());
       expect(config.registrationFee.toNumber()).to.equal(registrationFee.toNumber());
       expect(config.referrerBps).to.equal(referrerBps);
-      expect(config.requireAllowlistedRelayer).to.equal(false);
+      expect(config.relayerAllowlistPerInstruction).to.deep.equal([false, false, false, false]);
+
+      // The deployer holds the upgrade authority, not the registry admin
+      const [programMetaPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("program_meta")],
+        program.programId
+      );
+      const programMeta = await program.account.programMeta.fetch(programMetaPda);
+      expect(programMeta.upgradeAuthority.toString()).to.equal(provider.wallet.publicKey.toString());
+    });
+
+    it("Initializes the stats account", async () => {
+      const [statsPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("stats")],
+        program.programId
+      );
+      await program.methods
+        .initializeStats()
+        .accounts({
+          payer: admin.publicKey,
+          stats: statsPda,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([admin])
+        .rpc();
+
+      const stats = await program.account.registryStats.fetch(statsPda);
+      expect(stats.totalRegistrations.toNumber()).to.equal(0);
+    });
+
+    it("Refuses to migrate a config that is already current", async () => {
+      try {
+        await program.methods
+          .migrateConfig()
+          .accounts({
+            admin: admin.publicKey,
+            config: configPda,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .signers([admin])
+          .rpc();
+        expect.fail("Should reject a current config");
+      } catch (e: any) {
+        expect(e.toString()).to.include("AlreadyMigrated");
+      }
+    });
+
+    it("Refuses to migrate a ProgramMeta that already has a domain separator", async () => {
+      const [programMetaPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("program_meta")],
+        program.programId
+      );
+      const programMeta = await program.account.programMeta.fetch(programMetaPda);
+      expect(Buffer.from(programMeta.domainSeparator)).to.deep.equal(DOMAIN_SEPARATOR);
+
+      try {
+        await program.methods
+          .migrateProgramMeta()
+          .accounts({
+            admin: admin.publicKey,
+            config: configPda,
+            programMeta: programMetaPda,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .signers([admin])
+          .rpc();
+        expect.fail("Should reject a current ProgramMeta");
+      } catch (e: any) {
+        expect(e.toString()).to.include("AlreadyMigrated");
+      }
     });
 
     it("Sets registration fee", async () => {
@@ -102,6 +232,57 @@ describe("nominal-registry", () => {
       expect(config.registrationFee.toNumber()).to.equal(newFee.toNumber());
     });
 
+    it("Configures the name character set", async () => {
+      const [charsetPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("charset")],
+        program.programId
+      );
+
+      await program.methods
+        .setCharacterSet(false, true, false)
+        .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+          charset: charsetPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([admin])
+        .rpc();
+
+      const charset = await program.account.characterSetConfig.fetch(charsetPda);
+      expect(charset.allowUppercase).to.equal(false);
+      expect(charset.allowUnderscore).to.equal(true);
+      expect(charset.allowDot).to.equal(false);
+    });
+
+    it("Rejects a registration fee outside the SOL fee bounds", async () => {
+      await program.methods
+        .setSolFeeBounds(new anchor.BN(1), new anchor.BN(LAMPORTS_PER_SOL))
+        .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+        })
+        .signers([admin])
+        .rpc();
+
+      try {
+        await program.methods
+          .setRegistrationFee(new anchor.BN(2 * LAMPORTS_PER_SOL))
+          .accounts({
+            admin: admin.publicKey,
+            config: configPda,
+          })
+          .signers([admin])
+          .rpc();
+        expect.fail("Should have rejected the out-of-bounds fee");
+      } catch (error: any) {
+        expect(error.toString()).to.include("SolFeeOutOfBounds");
+      }
+
+      const config = await program.account.registryConfig.fetch(configPda);
+      expect(config.maxSolFee.toNumber()).to.equal(LAMPORTS_PER_SOL);
+    });
+
     it("Sets treasury address", async () => {
       const newTreasury = Keypair.generate();
 
@@ -170,7 +351,7 @@ describe("nominal-registry", () => {
           .rpc();
         expect.fail("Should have failed");
       } catch (error) {
-        expect(error.toString()).to.include("AnchorError"); // Check for any anchor error
+        expect(error.toString()).to.include("Unauthorized");
       }
     });
 
@@ -320,6 +501,7 @@ describe("nominal-registry", () => {
       expect(nameRecord.name).to.equal(name);
       expect(nameRecord.owner.toString()).to.equal(user.publicKey.toString());
       expect(nameRecord.resolved.toString()).to.equal(user.publicKey.toString());
+      expect(nameRecord.createdAt.toNumber()).to.be.greaterThan(0);
 
       // Verify primary name
       const primaryName = await program.account.primaryNameRegistry.fetch(primaryNamePda);
@@ -332,6 +514,46 @@ describe("nominal-registry", () => {
       expect(treasuryBalanceAfter - treasuryBalanceBefore).to.equal(config.registrationFee.toNumber());
     });
 
+    it("Only updates stats when the stats account is passed", async () => {
+      const [statsPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("stats")],
+        program.programId
+      );
+      const register = (name: string, stats: PublicKey | null) => {
+        const [nameRecordPda] = PublicKey.findProgramAddressSync(
+          [Buffer.from("name"), Buffer.from(name)],
+          program.programId
+        );
+        const [primaryNamePda] = PublicKey.findProgramAddressSync(
+          [Buffer.from("primary"), user.publicKey.toBuffer()],
+          program.programId
+        );
+        return program.methods
+          .registerName(name)
+          .accounts({
+            user: user.publicKey,
+            config: configPda,
+            nameRecord: nameRecordPda,
+            primaryName: primaryNamePda,
+            stats,
+            treasury: treasury.publicKey,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .signers([user])
+          .rpc();
+      };
+      const suffix = Math.floor(Math.random() * 1_000_000);
+
+      const before = (await program.account.registryStats.fetch(statsPda)).totalRegistrations.toNumber();
+      await register(`withstats${suffix}`, statsPda);
+      const counted = (await program.account.registryStats.fetch(statsPda)).totalRegistrations.toNumber();
+      expect(counted).to.equal(before + 1);
+
+      await register(`nostats${suffix}`, null);
+      const skipped = (await program.account.registryStats.fetch(statsPda)).totalRegistrations.toNumber();
+      expect(skipped).to.equal(counted);
+    });
+
     it("Fails to register duplicate name", async () => {
       const name = "alice"; // Same name as above
       const [nameRecordPda] = PublicKey.findProgramAddressSync(
@@ -358,7 +580,67 @@ describe("nominal-registry", () => {
         expect.fail("Should have failed");
       } catch (error) {
         // Name already exists because PDA already initialized
-        expect(error).to.exist;
+        expect(error.toString()).to.include("already in use");
+      }
+    });
+
+    it("Reports whether a name exists", async () => {
+      const nameRecordFor = (name: string) =>
+        PublicKey.findProgramAddressSync([Buffer.from("name"), Buffer.from(name)], program.programId)[0];
+
+      const taken = await program.methods
+        .nameExists("alice")
+        .accounts({ nameRecord: nameRecordFor("alice") })
+        .view();
+      expect(taken).to.equal(true);
+
+      const free = await program.methods
+        .nameExists("unregistered")
+        .accounts({ nameRecord: nameRecordFor("unregistered") })
+        .view();
+      expect(free).to.equal(false);
+    });
+
+    it("Enforces the minimum compute unit price", async () => {
+      const setMinCuPrice = (price: number) =>
+        program.methods
+          .setMinCuPrice(new anchor.BN(price))
+          .accounts({ admin: admin.publicKey, config: configPda } as any)
+          .signers([admin])
+          .rpc();
+      const registerName = (name: string) =>
+        program.methods
+          .registerName(name)
+          .accounts({
+            user: user.publicKey,
+            config: configPda,
+            nameRecord: PublicKey.findProgramAddressSync([Buffer.from("name"), Buffer.from(name)], program.programId)[0],
+            primaryName: PublicKey.findProgramAddressSync([Buffer.from("primary"), user.publicKey.toBuffer()], program.programId)[0],
+            treasury: treasury.publicKey,
+            instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .signers([user]);
+
+      await setMinCuPrice(1_000);
+      try {
+        try {
+          await registerName("cheapcu").rpc();
+          expect.fail("Should fail without a compute unit price");
+        } catch (error: any) {
+          expect(error.toString()).to.include("InsufficientComputeUnitPrice");
+        }
+
+        await registerName("pricedcu")
+          .preInstructions([ComputeBudgetProgram.setComputeUnitPrice({ microLamports: 1_000 })])
+          .rpc();
+        const nameRecord = await program.account.nameRecord.fetch(
+          PublicKey.findProgramAddressSync([Buffer.from("name"), Buffer.from("pricedcu")], program.programId)[0]
+        );
+        expect(nameRecord.name).to.equal("pricedcu");
+      } finally {
+        // Later tests register without a priority fee
+        await setMinCuPrice(0);
       }
     });
 
@@ -452,18 +734,79 @@ describe("nominal-registry", () => {
 
     it("Sets resolved address", async () => {
       const newResolved = Keypair.generate().publicKey;
+      const nameHash = createHash("sha256").update(testName).digest();
+      const [historyHeadPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("history_head"), nameHash],
+        program.programId
+      );
+      const entryIndex = Buffer.alloc(4);
+      entryIndex.writeUInt32LE(0);
+      const [historyEntryPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("history"), nameHash, entryIndex],
+        program.programId
+      );
 
       await program.methods
         .setResolvedAddress(testName, newResolved)
         .accounts({
-          owner: newOwner.publicKey,
+          authority: newOwner.publicKey,
           nameRecord: nameRecordPda,
         })
+        .remainingAccounts([
+          { pubkey: historyHeadPda, isSigner: false, isWritable: true },
+          { pubkey: historyEntryPda, isSigner: false, isWritable: true },
+        ])
         .signers([newOwner])
         .rpc();
 
       const nameRecord = await program.account.nameRecord.fetch(nameRecordPda);
       expect(nameRecord.resolved.toString()).to.equal(newResolved.toString());
+
+      const history = await program.account.nameHistoryEntry.fetch(historyEntryPda);
+      expect(history.newResolved.toString()).to.equal(newResolved.toString());
+      expect(history.entryIndex).to.equal(0);
+    });
+
+    it("Records history even if the next entry address was pre-funded", async () => {
+      const newResolved = Keypair.generate().publicKey;
+      const nameHash = createHash("sha256").update(testName).digest();
+      const [historyHeadPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("history_head"), nameHash],
+        program.programId
+      );
+      const head = await program.account.nameHistoryHead.fetch(historyHeadPda);
+      const entryIndex = Buffer.alloc(4);
+      entryIndex.writeUInt32LE(head.nextIndex);
+      const [historyEntryPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("history"), nameHash, entryIndex],
+        program.programId
+      );
+
+      // A plain transfer to the PDA used to make create_account fail
+      const lamports = await provider.connection.getMinimumBalanceForRentExemption(0);
+      await provider.sendAndConfirm(
+        new Transaction().add(
+          SystemProgram.transfer({ fromPubkey: admin.publicKey, toPubkey: historyEntryPda, lamports })
+        ),
+        [admin]
+      );
+
+      await program.methods
+        .setResolvedAddress(testName, newResolved)
+        .accounts({
+          authority: newOwner.publicKey,
+          nameRecord: nameRecordPda,
+        })
+        .remainingAccounts([
+          { pubkey: historyHeadPda, isSigner: false, isWritable: true },
+          { pubkey: historyEntryPda, isSigner: false, isWritable: true },
+        ])
+        .signers([newOwner])
+        .rpc();
+
+      const history = await program.account.nameHistoryEntry.fetch(historyEntryPda);
+      expect(history.newResolved.toString()).to.equal(newResolved.toString());
+      expect(history.entryIndex).to.equal(head.nextIndex);
     });
 
     it("Fails to transfer from non-owner", async () => {
@@ -481,92 +824,289 @@ describe("nominal-registry", () => {
         expect(error.toString()).to.include("Unauthorized");
       }
     });
-  });
 
-  describe("Edge Cases and Security", () => {
-    it("Validates all name rules correctly", async () => {
-      const validNames = [
-        `alice-${Math.floor(Math.random() * 10000)}`,
-        `bob-2024-${Math.floor(Math.random() * 10000)}`, 
-        `my-name-${Math.floor(Math.random() * 10000)}`,
-        `a1b2c3-${Math.floor(Math.random() * 10000)}`,
-        `valid-name-${Math.floor(Math.random() * 1000)}`
-      ];      // These should not throw during validation
-      for (const name of validNames) {
-        try {
-          const [nameRecordPda] = PublicKey.findProgramAddressSync(
-            [Buffer.from("name"), Buffer.from(name)],
-            program.programId
-          );
-          
-          const tempUser = Keypair.generate();
-          await provider.connection.requestAirdrop(tempUser.publicKey, LAMPORTS_PER_SOL);
-          await new Promise(resolve => setTimeout(resolve, 500));
+    it("Escrows a name and restores it on cancel", async () => {
+      const [escrowPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), Buffer.from(testName)],
+        program.programId
+      );
 
-          await program.methods
-            .registerName(name)
-            .accounts({
-              user: tempUser.publicKey,
-              config: configPda,
-              nameRecord: nameRecordPda,
-              primaryName: PublicKey.findProgramAddressSync(
-                [Buffer.from("primary"), tempUser.publicKey.toBuffer()],
-                program.programId
-              )[0],
-              treasury: treasury.publicKey,
-              systemProgram: SystemProgram.programId,
-            })
-            .signers([tempUser])
-            .preInstructions([
-              ComputeBudgetProgram.setComputeUnitLimit({ units: 400000 })
-            ])
-            .rpc();
+      await program.methods
+        .escrowName(testName, user.publicKey, new anchor.BN(LAMPORTS_PER_SOL / 10), new anchor.BN(3600))
+        .accounts({
+          owner: newOwner.publicKey,
+          nameRecord: nameRecordPda,
+        })
+        .signers([newOwner])
+        .rpc();
 
-          const nameRecord = await program.account.nameRecord.fetch(nameRecordPda);
-          expect(nameRecord.name).to.equal(name);
-        } catch (error: any) {
-          console.error(`Valid name failed: ${name}`, error.toString());
-          throw error;
-        }
-      }
-    });
+      let nameRecord = await program.account.nameRecord.fetch(nameRecordPda);
+      expect(nameRecord.owner.toString()).to.equal(escrowPda.toString());
 
-    it("Tests multiple name registrations", async () => {
-      const names = ["test1", "test2", "test3"];
-      const users = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
-      
-      // Fund users
-      for (const user of users) {
-        await provider.connection.requestAirdrop(user.publicKey, LAMPORTS_PER_SOL);
-      }
-      await new Promise(resolve => setTimeout(resolve, 1000));
+      await program.methods
+        .cancelEscrow(testName)
+        .accounts({
+          caller: newOwner.publicKey,
+          seller: newOwner.publicKey,
+          nameRecord: nameRecordPda,
+        })
+        .signers([newOwner])
+        .rpc();
 
-      // Register multiple names
-      for (let i = 0; i < names.length; i++) {
-        const name = names[i];
-        const user = users[i];
-        
-        const [nameRecordPda] = PublicKey.findProgramAddressSync(
-          [Buffer.from("name"), Buffer.from(name)],
-          program.programId
-        );
-        const [primaryNamePda] = PublicKey.findProgramAddressSync(
-          [Buffer.from("primary"), user.publicKey.toBuffer()],
-          program.programId
-        );
+      nameRecord = await program.account.nameRecord.fetch(nameRecordPda);
+      expect(nameRecord.owner.toString()).to.equal(newOwner.publicKey.toString());
+    });
 
-        await program.methods
-          .registerName(name)
+    it("Only the owner or admin can migrate a name record", async () => {
+      const migrate = (payer: Keypair) =>
+        program.methods
+          .migrateNameRecord(testName)
           .accounts({
-            user: user.publicKey,
+            payer: payer.publicKey,
             config: configPda,
             nameRecord: nameRecordPda,
-            primaryName: primaryNamePda,
-            treasury: treasury.publicKey,
             systemProgram: SystemProgram.programId,
-          })
-          .signers([user])
-          .preInstructions([
+          } as any)
+          .signers([payer])
+          .rpc();
+
+      try {
+        await migrate(user);
+        expect.fail("Should reject a payer who is neither owner nor admin");
+      } catch (e: any) {
+        expect(e.toString()).to.include("Unauthorized");
+      }
+
+      await migrate(newOwner);
+
+      const nameRecord = await program.account.nameRecord.fetch(nameRecordPda);
+      expect(nameRecord.programVersion).to.equal(2);
+      expect(nameRecord.owner.toString()).to.equal(newOwner.publicKey.toString());
+      const accountInfo = await provider.connection.getAccountInfo(nameRecordPda);
+      expect(accountInfo!.data.length).to.equal(8 + 92 + 63 + 128);
+
+      try {
+        await migrate(admin);
+        expect.fail("Should reject a record that was already migrated");
+      } catch (e: any) {
+        expect(e.toString()).to.include("AlreadyMigrated");
+      }
+    });
+
+    it("Rejects a proxy to the same name in a different case", async () => {
+      try {
+        await program.methods
+          .setProxy(testName, testName.toUpperCase())
+          .accounts({
+            owner: newOwner.publicKey,
+            nameRecord: nameRecordPda,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .signers([newOwner])
+          .rpc();
+        expect.fail("Should reject a proxy pointing back at the name");
+      } catch (e: any) {
+        expect(e.toString()).to.include("CircularProxyDetected");
+      }
+    });
+
+    it("Moves the name count and primary name when an escrow completes", async () => {
+      const buyer = Keypair.generate();
+      await provider.connection.requestAirdrop(buyer.publicKey, LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      const ownerCountPda = (owner: PublicKey) => PublicKey.findProgramAddressSync(
+        [Buffer.from("owner_count"), owner.toBuffer()],
+        program.programId
+      )[0];
+      const primaryPda = (owner: PublicKey) => PublicKey.findProgramAddressSync(
+        [Buffer.from("primary"), owner.toBuffer()],
+        program.programId
+      )[0];
+
+      await program.methods
+        .escrowName(testName, buyer.publicKey, new anchor.BN(LAMPORTS_PER_SOL / 10), new anchor.BN(3600))
+        .accounts({
+          owner: newOwner.publicKey,
+          nameRecord: nameRecordPda,
+        } as any)
+        .signers([newOwner])
+        .rpc();
+      await program.methods
+        .completeEscrow(testName)
+        .accounts({
+          buyer: buyer.publicKey,
+          seller: newOwner.publicKey,
+          config: configPda,
+          nameRecord: nameRecordPda,
+          sellerCount: ownerCountPda(newOwner.publicKey),
+          sellerPrimary: primaryPda(newOwner.publicKey),
+          buyerCount: ownerCountPda(buyer.publicKey),
+          buyerPrimary: primaryPda(buyer.publicKey),
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([buyer])
+        .rpc();
+
+      const nameRecord = await program.account.nameRecord.fetch(nameRecordPda);
+      expect(nameRecord.owner.toString()).to.equal(buyer.publicKey.toString());
+      const buyerCount = await program.account.ownerNameCount.fetch(ownerCountPda(buyer.publicKey));
+      expect(buyerCount.count).to.equal(1);
+      const sellerCount = await program.account.ownerNameCount.fetch(ownerCountPda(newOwner.publicKey));
+      expect(sellerCount.count).to.equal(0);
+      const buyerPrimary = await program.account.primaryNameRegistry.fetch(primaryPda(buyer.publicKey));
+      expect(buyerPrimary.name).to.equal(testName);
+    });
+
+    it("Keeps a locked name locked through escrow", async () => {
+      const lockedName = `lockedesc${Math.floor(Math.random() * 1_000_000)}`;
+      const [lockedRecordPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("name"), Buffer.from(lockedName)],
+        program.programId
+      );
+      const buyer = Keypair.generate();
+      await provider.connection.requestAirdrop(buyer.publicKey, LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      await program.methods
+        .registerName(lockedName)
+        .accounts({
+          user: user.publicKey,
+          config: configPda,
+          nameRecord: lockedRecordPda,
+          primaryName: PublicKey.findProgramAddressSync(
+            [Buffer.from("primary"), user.publicKey.toBuffer()],
+            program.programId
+          )[0],
+          treasury: treasury.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([user])
+        .rpc();
+      await program.methods
+        .lockName(lockedName)
+        .accounts({ owner: user.publicKey, nameRecord: lockedRecordPda } as any)
+        .signers([user])
+        .rpc();
+
+      const setLockedNamesTransferable = (transferable: boolean) =>
+        program.methods
+          .setLockedNamesTransferable(transferable)
+          .accounts({ admin: admin.publicKey, config: configPda } as any)
+          .signers([admin])
+          .rpc();
+      await setLockedNamesTransferable(true);
+      try {
+        await program.methods
+          .escrowName(lockedName, buyer.publicKey, new anchor.BN(LAMPORTS_PER_SOL / 10), new anchor.BN(3600))
+          .accounts({ owner: user.publicKey, nameRecord: lockedRecordPda } as any)
+          .signers([user])
+          .rpc();
+        await program.methods
+          .completeEscrow(lockedName)
+          .accounts({
+            buyer: buyer.publicKey,
+            seller: user.publicKey,
+            config: configPda,
+            nameRecord: lockedRecordPda,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .signers([buyer])
+          .rpc();
+      } finally {
+        await setLockedNamesTransferable(false);
+      }
+
+      const nameRecord = await program.account.nameRecord.fetch(lockedRecordPda);
+      expect(nameRecord.owner.toString()).to.equal(buyer.publicKey.toString());
+      expect(nameRecord.locked).to.equal(true);
+    });
+  });
+
+  describe("Edge Cases and Security", () => {
+    it("Validates all name rules correctly", async () => {
+      const validNames = [
+        `alice-${Math.floor(Math.random() * 10000)}`,
+        `bob-2024-${Math.floor(Math.random() * 10000)}`, 
+        `my-name-${Math.floor(Math.random() * 10000)}`,
+        `a1b2c3-${Math.floor(Math.random() * 10000)}`,
+        `valid-name-${Math.floor(Math.random() * 1000)}`
+      ];      // These should not throw during validation
+      for (const name of validNames) {
+        try {
+          const [nameRecordPda] = PublicKey.findProgramAddressSync(
+            [Buffer.from("name"), Buffer.from(name)],
+            program.programId
+          );
+          
+          const tempUser = Keypair.generate();
+          await provider.connection.requestAirdrop(tempUser.publicKey, LAMPORTS_PER_SOL);
+          await new Promise(resolve => setTimeout(resolve, 500));
+
+          await program.methods
+            .registerName(name)
+            .accounts({
+              user: tempUser.publicKey,
+              config: configPda,
+              nameRecord: nameRecordPda,
+              primaryName: PublicKey.findProgramAddressSync(
+                [Buffer.from("primary"), tempUser.publicKey.toBuffer()],
+                program.programId
+              )[0],
+              treasury: treasury.publicKey,
+              systemProgram: SystemProgram.programId,
+            })
+            .signers([tempUser])
+            .preInstructions([
+              ComputeBudgetProgram.setComputeUnitLimit({ units: 400000 })
+            ])
+            .rpc();
+
+          const nameRecord = await program.account.nameRecord.fetch(nameRecordPda);
+          expect(nameRecord.name).to.equal(name);
+        } catch (error: any) {
+          console.error(`Valid name failed: ${name}`, error.toString());
+          throw error;
+        }
+      }
+    });
+
+    it("Tests multiple name registrations", async () => {
+      const names = ["test1", "test2", "test3"];
+      const users = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+      
+      // Fund users
+      for (const user of users) {
+        await provider.connection.requestAirdrop(user.publicKey, LAMPORTS_PER_SOL);
+      }
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      // Register multiple names
+      for (let i = 0; i < names.length; i++) {
+        const name = names[i];
+        const user = users[i];
+        
+        const [nameRecordPda] = PublicKey.findProgramAddressSync(
+          [Buffer.from("name"), Buffer.from(name)],
+          program.programId
+        );
+        const [primaryNamePda] = PublicKey.findProgramAddressSync(
+          [Buffer.from("primary"), user.publicKey.toBuffer()],
+          program.programId
+        );
+
+        await program.methods
+          .registerName(name)
+          .accounts({
+            user: user.publicKey,
+            config: configPda,
+            nameRecord: nameRecordPda,
+            primaryName: primaryNamePda,
+            treasury: treasury.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([user])
+          .preInstructions([
             ComputeBudgetProgram.setComputeUnitLimit({ units: 400000 })
           ])
           .rpc();
@@ -648,6 +1188,56 @@ describe("nominal-registry", () => {
         expect(error.toString()).to.include("Invalid referrer basis points");
       }
     });
+
+    it("Enforces referrer BPS floor", async () => {
+      await program.methods
+        .setMinReferrerBps(200)
+        .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+        })
+        .signers([admin])
+        .preInstructions([
+          ComputeBudgetProgram.setComputeUnitLimit({ units: 200000 })
+        ])
+        .rpc();
+
+      let config = await program.account.registryConfig.fetch(configPda);
+      expect(config.minReferrerBps).to.equal(200);
+
+      try {
+        await program.methods
+          .setReferrerBps(100) // Invalid: below floor
+          .accounts({
+            admin: admin.publicKey,
+            config: configPda,
+          })
+          .signers([admin])
+          .preInstructions([
+            ComputeBudgetProgram.setComputeUnitLimit({ units: 200000 })
+          ])
+          .rpc();
+        expect.fail("Should have failed");
+      } catch (error: any) {
+        expect(error.toString()).to.include("Referrer basis points below configured floor");
+      }
+
+      // Reset floor for other tests
+      await program.methods
+        .setMinReferrerBps(0)
+        .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+        })
+        .signers([admin])
+        .preInstructions([
+          ComputeBudgetProgram.setComputeUnitLimit({ units: 200000 })
+        ])
+        .rpc();
+
+      config = await program.account.registryConfig.fetch(configPda);
+      expect(config.minReferrerBps).to.equal(0);
+    });
   });
 
   // =============================================================
@@ -658,6 +1248,7 @@ describe("nominal-registry", () => {
     let tokenFeePda: PublicKey;
     let userTokenAccount: PublicKey;
     let treasuryTokenAccount: PublicKey;
+    let treasuryTaPda: PublicKey;
     const tokenDecimals = 6;
     const tokenFeeAmount = new anchor.BN(10_000); // 0.01 tokens if 6 decimals
 
@@ -718,6 +1309,46 @@ describe("nominal-registry", () => {
       expect(tokenFeeAcct.mint.toString()).to.equal(tokenMint.toString());
       expect(tokenFeeAcct.amount.toString()).to.equal(tokenFeeAmount.toString());
       expect(tokenFeeAcct.enabled).to.equal(true);
+
+      // Pin the treasury token account for this mint
+      treasuryTaPda = PublicKey.findProgramAddressSync(
+        [Buffer.from("treasury_ta"), tokenMint.toBuffer()],
+        program.programId
+      )[0];
+      await program.methods
+        .setTreasuryTokenAccount(tokenMint, treasuryTokenAccount)
+        .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+          treasuryTa: treasuryTaPda,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([admin])
+        .rpc();
+
+      const treasuryTa = await program.account.treasuryTokenAccount.fetch(treasuryTaPda);
+      expect(treasuryTa.tokenAccount.toString()).to.equal(treasuryTokenAccount.toString());
+    });
+
+    it("Rejects the default pubkey as the treasury token account", async () => {
+      try {
+        await program.methods
+          .setTreasuryTokenAccount(tokenMint, PublicKey.default)
+          .accounts({
+            admin: admin.publicKey,
+            config: configPda,
+            treasuryTa: treasuryTaPda,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .signers([admin])
+          .rpc();
+        expect.fail("Should reject the default pubkey");
+      } catch (e: any) {
+        expect(e.toString()).to.include("InvalidTreasuryTokenAccount");
+      }
+
+      const treasuryTa = await program.account.treasuryTokenAccount.fetch(treasuryTaPda);
+      expect(treasuryTa.tokenAccount.toString()).to.equal(treasuryTokenAccount.toString());
     });
 
     it("Registers a name with token payment", async () => {
@@ -744,6 +1375,7 @@ describe("nominal-registry", () => {
           nameRecord: nameRecordPda,
             primaryName: primaryNamePda,
           userTokenAccount,
+          treasuryTa: treasuryTaPda,
           treasuryTokenAccount,
           tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
@@ -760,20 +1392,8 @@ describe("nominal-registry", () => {
       expect(Number(treasuryAfter - treasuryBefore)).to.equal(tokenFeeAmount.toNumber());
     });
 
-    it("Disables token and fails registration", async () => {
-      await program.methods
-        .setTokenFee(tokenFeeAmount, false)
-        .accounts({
-          admin: admin.publicKey,
-          config: configPda,
-          mint: tokenMint,
-          tokenFee: tokenFeePda,
-          systemProgram: SystemProgram.programId,
-        })
-        .signers([admin])
-        .rpc();
-
-      const name = `tokdisabled${Math.floor(Math.random()*1_000_000)}`;
+    it("Accepts a treasury-owned token account without the pinned treasury_ta", async () => {
+      const name = `toknopin${Math.floor(Math.random()*1_000_000)}`;
       const [nameRecordPda] = PublicKey.findProgramAddressSync(
         [Buffer.from("name"), Buffer.from(name)],
         program.programId
@@ -782,9 +1402,8 @@ describe("nominal-registry", () => {
         [Buffer.from("primary"), user.publicKey.toBuffer()],
         program.programId
       );
-
-      try {
-        await program.methods
+      const register = (treasuryAccount: PublicKey) =>
+        program.methods
           .registerNameWithToken(name)
           .accounts({
             user: user.publicKey,
@@ -794,14 +1413,116 @@ describe("nominal-registry", () => {
             nameRecord: nameRecordPda,
             primaryName: primaryNamePda,
             userTokenAccount,
-            treasuryTokenAccount,
+            treasuryTa: null,
+            treasuryTokenAccount: treasuryAccount,
             tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
             systemProgram: SystemProgram.programId,
-          })
+          } as any)
           .signers([user])
           .rpc();
-        expect.fail("Should fail when token fee disabled");
-      } catch (e) {
+
+      // Without a pin, the fee may only land in an account the treasury wallet owns
+      try {
+        await register(userTokenAccount);
+        expect.fail("Expected InvalidTreasuryTokenAccount");
+      } catch (e) {
+        expect(e.toString()).to.include("InvalidTreasuryTokenAccount");
+      }
+
+      const treasuryBefore = (await getAccount(provider.connection, treasuryTokenAccount)).amount;
+      await register(treasuryTokenAccount);
+      const treasuryAfter = (await getAccount(provider.connection, treasuryTokenAccount)).amount;
+      expect(Number(treasuryAfter - treasuryBefore)).to.equal(tokenFeeAmount.toNumber());
+    });
+
+    it("Enforces the whitelist phase on token registration", async () => {
+      const setWhitelistPhase = (active: boolean) =>
+        program.methods
+          .setWhitelistPhase(active)
+          .accounts({
+            admin: admin.publicKey,
+            config: configPda,
+          })
+          .signers([admin])
+          .rpc();
+
+      const name = `tokwhitelist${Math.floor(Math.random()*1_000_000)}`;
+      await setWhitelistPhase(true);
+      try {
+        await program.methods
+          .registerNameWithToken(name)
+          .accounts({
+            user: user.publicKey,
+            config: configPda,
+            mint: tokenMint,
+            tokenFee: tokenFeePda,
+            nameRecord: PublicKey.findProgramAddressSync(
+              [Buffer.from("name"), Buffer.from(name)],
+              program.programId
+            )[0],
+            primaryName: PublicKey.findProgramAddressSync(
+              [Buffer.from("primary"), user.publicKey.toBuffer()],
+              program.programId
+            )[0],
+            userTokenAccount,
+            treasuryTa: treasuryTaPda,
+            treasuryTokenAccount,
+            tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([user])
+          .rpc();
+        expect.fail("Should reject a non-whitelisted user during the whitelist phase");
+      } catch (e: any) {
+        expect(e.toString()).to.include("WhitelistPhaseActive");
+      } finally {
+        await setWhitelistPhase(false);
+      }
+    });
+
+    it("Disables token and fails registration", async () => {
+      await program.methods
+        .setTokenFee(tokenFeeAmount, false)
+        .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+          mint: tokenMint,
+          tokenFee: tokenFeePda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([admin])
+        .rpc();
+
+      const name = `tokdisabled${Math.floor(Math.random()*1_000_000)}`;
+      const [nameRecordPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("name"), Buffer.from(name)],
+        program.programId
+      );
+      const [primaryNamePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("primary"), user.publicKey.toBuffer()],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .registerNameWithToken(name)
+          .accounts({
+            user: user.publicKey,
+            config: configPda,
+            mint: tokenMint,
+            tokenFee: tokenFeePda,
+            nameRecord: nameRecordPda,
+            primaryName: primaryNamePda,
+            userTokenAccount,
+            treasuryTa: treasuryTaPda,
+            treasuryTokenAccount,
+            tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([user])
+          .rpc();
+        expect.fail("Should fail when token fee disabled");
+      } catch (e) {
         expect(e.toString()).to.include("Token not enabled");
       }
 
@@ -819,6 +1540,104 @@ describe("nominal-registry", () => {
         .rpc();
     });
 
+    it("Enables the relayer allowlist for signature registrations (admin)", async () => {
+      // register_name_with_sig and register_name_with_sig_token
+      for (const instructionIndex of [2, 3]) {
+        await program.methods
+          .setInstructionRelayerGate(instructionIndex, true)
+          .accounts({
+            admin: admin.publicKey,
+            config: configPda,
+          })
+          .signers([admin])
+          .rpc();
+      }
+
+      const config = await program.account.registryConfig.fetch(configPda);
+      expect(config.relayerAllowlistPerInstruction).to.deep.equal([false, false, true, true]);
+    });
+
+    it("Rejects an out-of-range relayer gate index", async () => {
+      try {
+        await program.methods
+          .setInstructionRelayerGate(4, true)
+          .accounts({
+            admin: admin.publicKey,
+            config: configPda,
+          })
+          .signers([admin])
+          .rpc();
+        expect.fail("Should have failed");
+      } catch (error: any) {
+        expect(error.toString()).to.include("InvalidInstructionIndex");
+      }
+    });
+
+    it("Gates direct registrations on the submitting relayer", async () => {
+      const relayer = Keypair.generate();
+      await provider.connection.requestAirdrop(relayer.publicKey, 2 * LAMPORTS_PER_SOL);
+      await new Promise(r => setTimeout(r, 800));
+      const [relayerEntryPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("relayer"), relayer.publicKey.toBuffer()],
+        program.programId
+      );
+      const relayerBondPda = await stakeRelayer(relayer);
+      await program.methods
+        .addRelayer(relayer.publicKey, "direct-relayer", "https://relayer.example.com")
+        .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+          relayerEntry: relayerEntryPda,
+          relayerBond: relayerBondPda,
+          relayer: relayer.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([admin])
+        .rpc();
+
+      const setGate = (enabled: boolean) =>
+        program.methods
+          .setInstructionRelayerGate(0, enabled)
+          .accounts({ admin: admin.publicKey, config: configPda } as any)
+          .signers([admin])
+          .rpc();
+      const registerName = (name: string, accounts: any) =>
+        program.methods
+          .registerName(name)
+          .accounts({
+            user: user.publicKey,
+            config: configPda,
+            nameRecord: PublicKey.findProgramAddressSync([Buffer.from("name"), Buffer.from(name)], program.programId)[0],
+            primaryName: PublicKey.findProgramAddressSync([Buffer.from("primary"), user.publicKey.toBuffer()], program.programId)[0],
+            treasury: treasury.publicKey,
+            systemProgram: SystemProgram.programId,
+            ...accounts,
+          } as any);
+
+      await setGate(true);
+      try {
+        // An allowlist entry alone is not enough; its relayer has to sign
+        try {
+          await registerName("gatedunsigned", { relayer: null, relayerEntry: relayerEntryPda })
+            .signers([user])
+            .rpc();
+          expect.fail("Should require the relayer to sign");
+        } catch (e: any) {
+          expect(e.toString()).to.include("RelayerNotAllowed");
+        }
+
+        await registerName("gatedsigned", { relayer: relayer.publicKey, relayerEntry: relayerEntryPda })
+          .signers([user, relayer])
+          .rpc();
+        const nameRecord = await program.account.nameRecord.fetch(
+          PublicKey.findProgramAddressSync([Buffer.from("name"), Buffer.from("gatedsigned")], program.programId)[0]
+        );
+        expect(nameRecord.owner.toString()).to.equal(user.publicKey.toString());
+      } finally {
+        await setGate(false);
+      }
+    });
+
     it("Registers a name with signature (SOL payment)", async () => {
       const relayer = Keypair.generate();
       await provider.connection.requestAirdrop(relayer.publicKey, 2 * LAMPORTS_PER_SOL);
@@ -829,26 +1648,47 @@ describe("nominal-registry", () => {
         [Buffer.from("relayer"), relayer.publicKey.toBuffer()],
         program.programId
       );
+      const relayerBondPda = await stakeRelayer(relayer);
       await program.methods
-        .addRelayer(relayer.publicKey)
+        .addRelayer(relayer.publicKey, "test-relayer", "https://relayer.example.com")
   .accounts({
           admin: admin.publicKey,
           config: configPda,
           relayerEntry: relayerEntryPda,
+          relayerBond: relayerBondPda,
           relayer: relayer.publicKey,
           systemProgram: SystemProgram.programId,
   } as any)
         .signers([admin])
         .rpc();
 
+      // Entries created with the metadata fields have nothing to migrate
+      try {
+        await program.methods
+          .migrateRelayerEntry(relayer.publicKey)
+          .accounts({
+            admin: admin.publicKey,
+            config: configPda,
+            relayerEntry: relayerEntryPda,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .signers([admin])
+          .rpc();
+        expect.fail("Should reject a current relayer entry");
+      } catch (e: any) {
+        expect(e.toString()).to.include("AlreadyMigrated");
+      }
+
       const params = {
         name: `sig${Math.floor(Math.random()*1_000_000)}`,
         owner: user.publicKey,
         relayer: relayer.publicKey,
         currency: null, // None = SOL path
         amount: new anchor.BN(0),
-        deadline: new anchor.BN(Math.floor(Date.now()/1000) + 600),
-        nonce: new anchor.BN(0),
+        deadline: new anchor.BN(Math.floor(Date.now()/1000) + 30),
+        nonce: await currentNonce(user.publicKey),
+        signatureType: { ed25519: {} },
+        evmAddress: null,
       } as any; // cast for IDL mapping
 
       const [nameRecordPda] = PublicKey.findProgramAddressSync(
@@ -866,19 +1706,22 @@ describe("nominal-registry", () => {
       const referrerAmount = Math.floor(config.registrationFee.toNumber() * config.referrerBps / 10_000);
       const expectedTreasuryDelta = config.registrationFee.toNumber() - referrerAmount;
 
-      const dummySig = Buffer.alloc(64, 1);
+      const { signature, ed25519Ix } = signRegistration(params);
 
       await program.methods
-        .registerNameWithSignature(params, dummySig)
+        .registerNameWithSignature(params, signature)
   .accounts({
           relayer: relayer.publicKey,
           config: configPda,
           nameRecord: nameRecordPda,
           primaryName: primaryNamePda,
-      relayerEntry: relayerEntryPda,
+      evmOwner: null,
           treasury: treasury.publicKey,
+          instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
           systemProgram: SystemProgram.programId,
   } as any)
+        .remainingAccounts([{ pubkey: relayerEntryPda, isSigner: false, isWritable: true }])
+        .preInstructions([ed25519Ix])
         .signers([relayer])
         .rpc();
 
@@ -907,12 +1750,14 @@ describe("nominal-registry", () => {
         [Buffer.from("relayer"), relayer.publicKey.toBuffer()],
         program.programId
       );
+      const relayerBondPda = await stakeRelayer(relayer);
       await program.methods
-        .addRelayer(relayer.publicKey)
+        .addRelayer(relayer.publicKey, "test-relayer", "https://relayer.example.com")
   .accounts({
           admin: admin.publicKey,
           config: configPda,
           relayerEntry: relayerEntryPda,
+          relayerBond: relayerBondPda,
           relayer: relayer.publicKey,
           systemProgram: SystemProgram.programId,
   } as any)
@@ -941,8 +1786,10 @@ describe("nominal-registry", () => {
         relayer: relayer.publicKey,
         currency: tokenMint,
         amount: tokenFeeAmount,
-        deadline: new anchor.BN(Math.floor(Date.now()/1000) + 600),
-        nonce: new anchor.BN(0),
+        deadline: new anchor.BN(Math.floor(Date.now()/1000) + 30),
+        nonce: await currentNonce(user.publicKey),
+        signatureType: { ed25519: {} },
+        evmAddress: null,
       } as any;
 
       const [nameRecordPda] = PublicKey.findProgramAddressSync(
@@ -961,24 +1808,46 @@ describe("nominal-registry", () => {
       const referrerAmount = Math.floor(tokenFeeAmount.toNumber() * config.referrerBps / 10_000);
       const expectedTreasuryDelta = tokenFeeAmount.toNumber() - referrerAmount;
 
-      const dummySig = Buffer.alloc(64, 2);
+      const accounts = {
+        relayer: relayer.publicKey,
+        config: configPda,
+        mint: tokenMint,
+        tokenFee: tokenFeePda,
+        nameRecord: nameRecordPda,
+        primaryName: primaryNamePda,
+        evmOwner: null,
+        relayerTokenAccount,
+        treasuryTa: treasuryTaPda,
+        treasuryTokenAccount,
+        treasury: treasury.publicKey,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+        systemProgram: SystemProgram.programId,
+      } as any;
+
+      // A signature naming another relayer can't be submitted by this one
+      const otherParams = { ...params, relayer: Keypair.generate().publicKey };
+      const other = signRegistration(otherParams);
+      try {
+        await program.methods
+          .registerNameWithSignatureToken(otherParams, other.signature)
+          .accounts(accounts)
+          .remainingAccounts([{ pubkey: relayerEntryPda, isSigner: false, isWritable: true }])
+          .preInstructions([other.ed25519Ix])
+          .signers([relayer])
+          .rpc();
+        expect.fail("Should reject a signature meant for another relayer");
+      } catch (e: any) {
+        expect(e.toString()).to.include("Unauthorized");
+      }
+
+      const { signature, ed25519Ix } = signRegistration(params);
 
       await program.methods
-        .registerNameWithSignatureToken(params, dummySig)
-  .accounts({
-          relayer: relayer.publicKey,
-          config: configPda,
-          mint: tokenMint,
-          tokenFee: tokenFeePda,
-          nameRecord: nameRecordPda,
-          primaryName: primaryNamePda,
-          relayerEntry: relayerEntryPda,
-          relayerTokenAccount,
-          treasuryTokenAccount,
-          treasury: treasury.publicKey,
-          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
-          systemProgram: SystemProgram.programId,
-  } as any)
+        .registerNameWithSignatureToken(params, signature)
+        .accounts(accounts)
+        .remainingAccounts([{ pubkey: relayerEntryPda, isSigner: false, isWritable: true }])
+        .preInstructions([ed25519Ix])
         .signers([relayer])
         .rpc();
 
@@ -992,49 +1861,7 @@ describe("nominal-registry", () => {
       expect(nameRecord.name).to.equal(params.name);
     });
 
-    it("Fails signature registration with non-allowlisted relayer", async () => {
-      const relayer = Keypair.generate();
-      await provider.connection.requestAirdrop(relayer.publicKey, LAMPORTS_PER_SOL);
-      await new Promise(r => setTimeout(r, 600));
-      const params = {
-        name: `failrelayer${Math.floor(Math.random()*1_000_000)}`,
-        owner: user.publicKey,
-        relayer: relayer.publicKey,
-        currency: null,
-        amount: new anchor.BN(0),
-        deadline: new anchor.BN(Math.floor(Date.now()/1000) + 600),
-        nonce: new anchor.BN(0),
-      } as any;
-      const [nameRecordPda] = PublicKey.findProgramAddressSync(
-        [Buffer.from("name"), Buffer.from(params.name)],
-        program.programId
-      );
-      const [primaryNamePda] = PublicKey.findProgramAddressSync(
-        [Buffer.from("primary"), user.publicKey.toBuffer()],
-        program.programId
-      );
-      const dummySig = Buffer.alloc(64, 3);
-      try {
-        await program.methods
-          .registerNameWithSignature(params, dummySig)
-          .accounts({
-            relayer: relayer.publicKey,
-            config: configPda,
-            nameRecord: nameRecordPda,
-            primaryName: primaryNamePda,
-            // intentionally omit relayerEntry
-            treasury: treasury.publicKey,
-            systemProgram: SystemProgram.programId,
-          } as any)
-          .signers([relayer])
-          .rpc();
-        expect.fail("Should fail without allowlisted relayer");
-      } catch (e:any) {
-        expect(e.toString()).to.satisfy((s:string) => s.includes("Relayer") || s.includes("Account"));
-      }
-    });
-
-    it("Removes relayer and blocks further signature registrations", async () => {
+    it("Rejects a replayed signature registration", async () => {
       const relayer = Keypair.generate();
       await provider.connection.requestAirdrop(relayer.publicKey, 2 * LAMPORTS_PER_SOL);
       await new Promise(r => setTimeout(r, 800));
@@ -1042,27 +1869,30 @@ describe("nominal-registry", () => {
         [Buffer.from("relayer"), relayer.publicKey.toBuffer()],
         program.programId
       );
-      // Add relayer
+      const relayerBondPda = await stakeRelayer(relayer);
       await program.methods
-        .addRelayer(relayer.publicKey)
+        .addRelayer(relayer.publicKey, "test-relayer", "https://relayer.example.com")
   .accounts({
           admin: admin.publicKey,
           config: configPda,
           relayerEntry: relayerEntryPda,
+          relayerBond: relayerBondPda,
           relayer: relayer.publicKey,
           systemProgram: SystemProgram.programId,
   } as any)
         .signers([admin])
         .rpc();
-      // Perform one signature registration (SOL)
+
       const params = {
-        name: `onetime${Math.floor(Math.random()*1_000_000)}`,
+        name: `replay${Math.floor(Math.random()*1_000_000)}`,
         owner: user.publicKey,
         relayer: relayer.publicKey,
         currency: null,
         amount: new anchor.BN(0),
-        deadline: new anchor.BN(Math.floor(Date.now()/1000) + 600),
-        nonce: new anchor.BN(0),
+        deadline: new anchor.BN(Math.floor(Date.now()/1000) + 30),
+        nonce: await currentNonce(user.publicKey),
+        signatureType: { ed25519: {} },
+        evmAddress: null,
       } as any;
       const [nameRecordPda] = PublicKey.findProgramAddressSync(
         [Buffer.from("name"), Buffer.from(params.name)],
@@ -1072,54 +1902,1840 @@ describe("nominal-registry", () => {
         [Buffer.from("primary"), user.publicKey.toBuffer()],
         program.programId
       );
-      const dummySig = Buffer.alloc(64, 4);
-      await program.methods
-        .registerNameWithSignature(params, dummySig)
+      const { signature, ed25519Ix } = signRegistration(params);
+      const submit = () => program.methods
+        .registerNameWithSignature(params, signature)
   .accounts({
           relayer: relayer.publicKey,
           config: configPda,
           nameRecord: nameRecordPda,
           primaryName: primaryNamePda,
-          relayerEntry: relayerEntryPda,
+          evmOwner: null,
           treasury: treasury.publicKey,
+          instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
           systemProgram: SystemProgram.programId,
   } as any)
+        .remainingAccounts([{ pubkey: relayerEntryPda, isSigner: false, isWritable: true }])
+        .preInstructions([ed25519Ix])
         .signers([relayer])
         .rpc();
-      // Remove relayer
-      await program.methods
-        .removeRelayer(relayer.publicKey)
-  .accounts({
-          admin: admin.publicKey,
-          config: configPda,
-          relayerEntry: relayerEntryPda,
-  } as any)
-        .signers([admin])
-        .rpc();
-      // Attempt another signature registration -> should fail
-      const params2 = { ...params, name: `blocked${Math.floor(Math.random()*1_000_000)}` };
-      const [nameRecordPda2] = PublicKey.findProgramAddressSync(
-        [Buffer.from("name"), Buffer.from(params2.name)],
+
+      await submit();
+      const before = await program.account.nameRecord.fetch(nameRecordPda);
+      expect((await currentNonce(user.publicKey)).toString()).to.equal(params.nonce.addn(1).toString());
+
+      // A fresh, validly signed message that reuses the spent nonce
+      const stale = { ...params, name: `${params.name}x` };
+      const { signature: staleSignature, ed25519Ix: staleIx } = signRegistration(stale);
+      const [staleRecordPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("name"), Buffer.from(stale.name)],
         program.programId
       );
       try {
         await program.methods
-          .registerNameWithSignature(params2, Buffer.alloc(64,5))
+          .registerNameWithSignature(stale, staleSignature)
           .accounts({
             relayer: relayer.publicKey,
             config: configPda,
-            nameRecord: nameRecordPda2,
+            nameRecord: staleRecordPda,
             primaryName: primaryNamePda,
-            relayerEntry: relayerEntryPda, // closed, should error
+            evmOwner: null,
             treasury: treasury.publicKey,
+            instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
             systemProgram: SystemProgram.programId,
           } as any)
+          .remainingAccounts([{ pubkey: relayerEntryPda, isSigner: false, isWritable: true }])
+          .preInstructions([staleIx])
           .signers([relayer])
           .rpc();
-        expect.fail("Should fail after relayer removal");
-      } catch (e:any) {
-        expect(e.toString()).to.satisfy((s:string) => s.includes("closed") || s.includes("Account") || s.includes("Relayer"));
+        expect.fail("Replayed nonce should fail");
+      } catch (e: any) {
+        expect(e.toString()).to.include("InvalidNonce");
       }
+      expect(await provider.connection.getAccountInfo(staleRecordPda)).to.be.null;
+
+      const after = await program.account.nameRecord.fetch(nameRecordPda);
+      expect(after.owner.toString()).to.equal(before.owner.toString());
+      expect(after.createdAt.toString()).to.equal(before.createdAt.toString());
+    });
+
+    it("Rejects a signature deadline beyond the deadline window", async () => {
+      const relayer = Keypair.generate();
+      await provider.connection.requestAirdrop(relayer.publicKey, 2 * LAMPORTS_PER_SOL);
+      await new Promise(r => setTimeout(r, 800));
+
+      const params = {
+        name: `faraway${Math.floor(Math.random()*1_000_000)}`,
+        owner: user.publicKey,
+        relayer: relayer.publicKey,
+        currency: null,
+        amount: new anchor.BN(0),
+        // Default window is 60 seconds
+        deadline: new anchor.BN(Math.floor(Date.now()/1000) + 3600),
+        nonce: await currentNonce(user.publicKey),
+        signatureType: { ed25519: {} },
+        evmAddress: null,
+      } as any;
+      const [nameRecordPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("name"), Buffer.from(params.name)],
+        program.programId
+      );
+      const [primaryNamePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("primary"), user.publicKey.toBuffer()],
+        program.programId
+      );
+
+      const { signature, ed25519Ix } = signRegistration(params);
+      try {
+        await program.methods
+          .registerNameWithSignature(params, signature)
+  .accounts({
+            relayer: relayer.publicKey,
+            config: configPda,
+            nameRecord: nameRecordPda,
+            primaryName: primaryNamePda,
+            evmOwner: null,
+            treasury: treasury.publicKey,
+            instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+  } as any)
+          .preInstructions([ed25519Ix])
+          .signers([relayer])
+          .rpc();
+        expect.fail("Should have failed");
+      } catch (error: any) {
+        expect(error.toString()).to.include("DeadlineTooFar");
+      }
+    });
+
+    it("Rejects a signature made for a different deadline", async () => {
+      const relayer = Keypair.generate();
+      await provider.connection.requestAirdrop(relayer.publicKey, 2 * LAMPORTS_PER_SOL);
+      await new Promise(r => setTimeout(r, 800));
+      const [relayerEntryPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("relayer"), relayer.publicKey.toBuffer()],
+        program.programId
+      );
+      const relayerBondPda = await stakeRelayer(relayer);
+      await program.methods
+        .addRelayer(relayer.publicKey, "test-relayer", "https://relayer.example.com")
+        .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+          relayerEntry: relayerEntryPda,
+          relayerBond: relayerBondPda,
+          relayer: relayer.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([admin])
+        .rpc();
+
+      const signed = {
+        name: `deadline${Math.floor(Math.random()*1_000_000)}`,
+        owner: user.publicKey,
+        relayer: relayer.publicKey,
+        currency: null,
+        amount: new anchor.BN(0),
+        deadline: new anchor.BN(Math.floor(Date.now()/1000) + 20),
+        nonce: await currentNonce(user.publicKey),
+        signatureType: { ed25519: {} },
+        evmAddress: null,
+      } as any;
+      const { signature, ed25519Ix } = signRegistration(signed);
+      // The relayer stretches the deadline but reuses the owner's signature
+      const params = { ...signed, deadline: signed.deadline.addn(10) };
+      const [nameRecordPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("name"), Buffer.from(params.name)],
+        program.programId
+      );
+      const [primaryNamePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("primary"), user.publicKey.toBuffer()],
+        program.programId
+      );
+
+      try {
+        await program.methods
+          .registerNameWithSignature(params, signature)
+          .accounts({
+            relayer: relayer.publicKey,
+            config: configPda,
+            nameRecord: nameRecordPda,
+            primaryName: primaryNamePda,
+            evmOwner: null,
+            treasury: treasury.publicKey,
+            instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .remainingAccounts([{ pubkey: relayerEntryPda, isSigner: false, isWritable: true }])
+          .preInstructions([ed25519Ix])
+          .signers([relayer])
+          .rpc();
+        expect.fail("Expected InvalidSignature");
+      } catch (e: any) {
+        expect(e.toString()).to.include("InvalidSignature");
+      }
+
+      // The failed registration rolls back entirely
+      expect(await provider.connection.getAccountInfo(nameRecordPda)).to.be.null;
+
+      // Violations are reported by the admin and removed from the allowlist at the limit
+      const [violationPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("violation"), relayer.publicKey.toBuffer()],
+        program.programId
+      );
+      const report = () =>
+        program.methods
+          .reportRelayerViolation(relayer.publicKey)
+          .accounts({
+            admin: admin.publicKey,
+            config: configPda,
+            relayerViolation: violationPda,
+            relayerEntry: relayerEntryPda,
+            treasury: treasury.publicKey,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .signers([admin])
+          .rpc();
+      await report();
+      const violation = await program.account.relayerViolation.fetch(violationPda);
+      expect(violation.violations).to.equal(1);
+      expect(violation.lastViolationAt.toNumber()).to.be.greaterThan(0);
+
+      const config = await program.account.registryConfig.fetch(configPda);
+      for (let i = 1; i < config.maxRelayerViolations; i++) {
+        await report();
+      }
+      expect(await provider.connection.getAccountInfo(relayerEntryPda)).to.be.null;
+    });
+
+    it("Fails token signature registration with a low compute budget", async () => {
+      const relayer = Keypair.generate();
+      await provider.connection.requestAirdrop(relayer.publicKey, 2 * LAMPORTS_PER_SOL);
+      await new Promise(r => setTimeout(r, 800));
+      const [relayerEntryPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("relayer"), relayer.publicKey.toBuffer()],
+        program.programId
+      );
+      const relayerBondPda = await stakeRelayer(relayer);
+      await program.methods
+        .addRelayer(relayer.publicKey, "test-relayer", "https://relayer.example.com")
+  .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+          relayerEntry: relayerEntryPda,
+          relayerBond: relayerBondPda,
+          relayer: relayer.publicKey,
+          systemProgram: SystemProgram.programId,
+  } as any)
+        .signers([admin])
+        .rpc();
+      const relayerTokenAccount = (await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        admin,
+        tokenMint,
+        relayer.publicKey
+      )).address;
+
+      const params = {
+        name: `lowcu${Math.floor(Math.random()*1_000_000)}`,
+        owner: user.publicKey,
+        relayer: relayer.publicKey,
+        currency: tokenMint,
+        amount: tokenFeeAmount,
+        deadline: new anchor.BN(Math.floor(Date.now()/1000) + 30),
+        nonce: await currentNonce(user.publicKey),
+        signatureType: { ed25519: {} },
+        evmAddress: null,
+      } as any;
+      const [nameRecordPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("name"), Buffer.from(params.name)],
+        program.programId
+      );
+      const [primaryNamePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("primary"), user.publicKey.toBuffer()],
+        program.programId
+      );
+
+      const { signature, ed25519Ix } = signRegistration(params);
+      try {
+        await program.methods
+          .registerNameWithSignatureToken(params, signature)
+          .accounts({
+            relayer: relayer.publicKey,
+            config: configPda,
+            mint: tokenMint,
+            tokenFee: tokenFeePda,
+            nameRecord: nameRecordPda,
+            primaryName: primaryNamePda,
+            evmOwner: null,
+            relayerTokenAccount,
+            treasuryTa: treasuryTaPda,
+            treasuryTokenAccount,
+            treasury: treasury.publicKey,
+            tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+            instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .remainingAccounts([{ pubkey: relayerEntryPda, isSigner: false, isWritable: true }])
+          .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 50_000 }), ed25519Ix])
+          .signers([relayer])
+          .rpc();
+        expect.fail("Should fail with a 50k compute unit limit");
+      } catch (e:any) {
+        expect(e.toString()).to.include("InsufficientComputeUnits");
+      }
+      const nameRecord = await provider.connection.getAccountInfo(nameRecordPda);
+      expect(nameRecord).to.be.null;
+    });
+
+    it("Fails signature registration with non-allowlisted relayer", async () => {
+      const relayer = Keypair.generate();
+      await provider.connection.requestAirdrop(relayer.publicKey, LAMPORTS_PER_SOL);
+      await new Promise(r => setTimeout(r, 600));
+      const params = {
+        name: `failrelayer${Math.floor(Math.random()*1_000_000)}`,
+        owner: user.publicKey,
+        relayer: relayer.publicKey,
+        currency: null,
+        amount: new anchor.BN(0),
+        deadline: new anchor.BN(Math.floor(Date.now()/1000) + 30),
+        nonce: await currentNonce(user.publicKey),
+        signatureType: { ed25519: {} },
+        evmAddress: null,
+      } as any;
+      const [nameRecordPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("name"), Buffer.from(params.name)],
+        program.programId
+      );
+      const [primaryNamePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("primary"), user.publicKey.toBuffer()],
+        program.programId
+      );
+      const { signature, ed25519Ix } = signRegistration(params);
+      try {
+        await program.methods
+          .registerNameWithSignature(params, signature)
+          .accounts({
+            relayer: relayer.publicKey,
+            config: configPda,
+            nameRecord: nameRecordPda,
+            primaryName: primaryNamePda,
+            // intentionally omit relayerEntry
+            evmOwner: null,
+            treasury: treasury.publicKey,
+            instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .preInstructions([ed25519Ix])
+          .signers([relayer])
+          .rpc();
+        expect.fail("Should fail without allowlisted relayer");
+      } catch (e:any) {
+        expect(e.toString()).to.satisfy((s:string) => s.includes("Relayer") || s.includes("Account"));
+      }
+    });
+
+    it("Removes relayer and blocks further signature registrations", async () => {
+      const relayer = Keypair.generate();
+      await provider.connection.requestAirdrop(relayer.publicKey, 2 * LAMPORTS_PER_SOL);
+      await new Promise(r => setTimeout(r, 800));
+      const [relayerEntryPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("relayer"), relayer.publicKey.toBuffer()],
+        program.programId
+      );
+      // Add relayer
+      const relayerBondPda = await stakeRelayer(relayer);
+      await program.methods
+        .addRelayer(relayer.publicKey, "test-relayer", "https://relayer.example.com")
+  .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+          relayerEntry: relayerEntryPda,
+          relayerBond: relayerBondPda,
+          relayer: relayer.publicKey,
+          systemProgram: SystemProgram.programId,
+  } as any)
+        .signers([admin])
+        .rpc();
+      // Perform one signature registration (SOL)
+      const params = {
+        name: `onetime${Math.floor(Math.random()*1_000_000)}`,
+        owner: user.publicKey,
+        relayer: relayer.publicKey,
+        currency: null,
+        amount: new anchor.BN(0),
+        deadline: new anchor.BN(Math.floor(Date.now()/1000) + 30),
+        nonce: await currentNonce(user.publicKey),
+        signatureType: { ed25519: {} },
+        evmAddress: null,
+      } as any;
+      const [nameRecordPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("name"), Buffer.from(params.name)],
+        program.programId
+      );
+      const [primaryNamePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("primary"), user.publicKey.toBuffer()],
+        program.programId
+      );
+      const { signature, ed25519Ix } = signRegistration(params);
+      await program.methods
+        .registerNameWithSignature(params, signature)
+  .accounts({
+          relayer: relayer.publicKey,
+          config: configPda,
+          nameRecord: nameRecordPda,
+          primaryName: primaryNamePda,
+          evmOwner: null,
+          treasury: treasury.publicKey,
+          instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: SystemProgram.programId,
+  } as any)
+        .remainingAccounts([{ pubkey: relayerEntryPda, isSigner: false, isWritable: true }])
+        .preInstructions([ed25519Ix])
+        .signers([relayer])
+        .rpc();
+      // Remove relayer
+      await program.methods
+        .removeRelayer(relayer.publicKey)
+  .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+          relayerEntry: relayerEntryPda,
+  } as any)
+        .signers([admin])
+        .rpc();
+      // Attempt another signature registration -> should fail
+      const params2 = { ...params, name: `blocked${Math.floor(Math.random()*1_000_000)}` };
+      const [nameRecordPda2] = PublicKey.findProgramAddressSync(
+        [Buffer.from("name"), Buffer.from(params2.name)],
+        program.programId
+      );
+      const { signature: signature2, ed25519Ix: ed25519Ix2 } = signRegistration(params2);
+      try {
+        await program.methods
+          .registerNameWithSignature(params2, signature2)
+          .accounts({
+            relayer: relayer.publicKey,
+            config: configPda,
+            nameRecord: nameRecordPda2,
+            primaryName: primaryNamePda,
+            evmOwner: null,
+            treasury: treasury.publicKey,
+            instructionsSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .remainingAccounts([{ pubkey: relayerEntryPda, isSigner: false, isWritable: true }])
+          .preInstructions([ed25519Ix2])
+          .signers([relayer])
+          .rpc();
+        expect.fail("Should fail after relayer removal");
+      } catch (e:any) {
+        expect(e.toString()).to.satisfy((s:string) => s.includes("closed") || s.includes("Account") || s.includes("Relayer"));
+      }
+    });
+  });
+
+  describe("Name Expiry", () => {
+    const expiring = Keypair.generate();
+    const cranker = Keypair.generate();
+    const expiringName = `expiring${Math.floor(Math.random() * 1_000_000)}`;
+    let nameRecordPda: PublicKey;
+    let nameExpiryPda: PublicKey;
+    let notifyExpiringPda: PublicKey;
+    let statsPda: PublicKey;
+
+    before(async () => {
+      await provider.connection.requestAirdrop(expiring.publicKey, LAMPORTS_PER_SOL);
+      await provider.connection.requestAirdrop(cranker.publicKey, LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      [nameRecordPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("name"), Buffer.from(expiringName)],
+        program.programId
+      );
+      [nameExpiryPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("expiry"), Buffer.from(expiringName)],
+        program.programId
+      );
+      [notifyExpiringPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("notify_expiring"), Buffer.from(expiringName)],
+        program.programId
+      );
+      [statsPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("stats")],
+        program.programId
+      );
+      const [primaryNamePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("primary"), expiring.publicKey.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .registerName(expiringName)
+        .accounts({
+          user: expiring.publicKey,
+          config: configPda,
+          nameRecord: nameRecordPda,
+          primaryName: primaryNamePda,
+          treasury: treasury.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([expiring])
+        .preInstructions([
+          ComputeBudgetProgram.setComputeUnitLimit({ units: 400000 })
+        ])
+        .rpc();
+    });
+
+    const setExpiryConfig = (periodSeconds: number, windowSeconds: number) =>
+      program.methods
+        .setExpiryConfig(new anchor.BN(periodSeconds), new anchor.BN(windowSeconds))
+        .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+        } as any)
+        .signers([admin])
+        .rpc();
+
+    const renew = () =>
+      program.methods
+        .renewName(expiringName)
+        .accounts({
+          payer: expiring.publicKey,
+          config: configPda,
+          nameRecord: nameRecordPda,
+          nameExpiry: nameExpiryPda,
+          treasury: treasury.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([expiring])
+        .rpc();
+
+    const emitWarning = () =>
+      program.methods
+        .emitExpiryWarning(expiringName)
+        .accounts({
+          caller: cranker.publicKey,
+          config: configPda,
+          nameExpiry: nameExpiryPda,
+          notifyExpiring: notifyExpiringPda,
+          owner: expiring.publicKey,
+          stats: statsPda,
+        } as any)
+        .signers([cranker])
+        .rpc();
+
+    it("Refuses to renew while names do not expire", async () => {
+      try {
+        await renew();
+        expect.fail("Should reject a renewal without a registration period");
+      } catch (e: any) {
+        expect(e.toString()).to.include("NamesDoNotExpire");
+      }
+    });
+
+    it("Warns an owner whose name is inside the warning window", async () => {
+      const day = 24 * 60 * 60;
+      try {
+        // A 3 day period lands the renewed expiry inside the 7 day window
+        await setExpiryConfig(3 * day, 7 * day);
+        await renew();
+
+        const nameExpiry = await program.account.nameExpiry.fetch(nameExpiryPda);
+        expect(nameExpiry.expiresAt.toNumber()).to.be.greaterThan(Math.floor(Date.now() / 1000));
+
+        await program.methods
+          .scheduleExpiryWarning(expiringName)
+          .accounts({
+            owner: expiring.publicKey,
+            nameRecord: nameRecordPda,
+            nameExpiry: nameExpiryPda,
+            notifyExpiring: notifyExpiringPda,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .signers([expiring])
+          .rpc();
+
+        // Fund the crank reward
+        const fund = new Transaction().add(
+          SystemProgram.transfer({ fromPubkey: admin.publicKey, toPubkey: statsPda, lamports: 100_000 })
+        );
+        await provider.sendAndConfirm(fund, [admin]);
+
+        const crankerBefore = await provider.connection.getBalance(cranker.publicKey);
+        await emitWarning();
+        const crankerAfter = await provider.connection.getBalance(cranker.publicKey);
+        // The provider wallet pays the fee, so the cranker only sees the reward
+        expect(crankerAfter - crankerBefore).to.equal(10_000);
+
+        const notify = await provider.connection.getAccountInfo(notifyExpiringPda);
+        expect(notify).to.be.null;
+      } finally {
+        await setExpiryConfig(0, 7 * day);
+      }
+    });
+
+    it("Rejects a warning that is not due", async () => {
+      const day = 24 * 60 * 60;
+      try {
+        await setExpiryConfig(30 * day, 7 * day);
+        await renew();
+        await program.methods
+          .scheduleExpiryWarning(expiringName)
+          .accounts({
+            owner: expiring.publicKey,
+            nameRecord: nameRecordPda,
+            nameExpiry: nameExpiryPda,
+            notifyExpiring: notifyExpiringPda,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .signers([expiring])
+          .rpc();
+
+        try {
+          await emitWarning();
+          expect.fail("Should reject a warning outside the window");
+        } catch (e: any) {
+          expect(e.toString()).to.include("ExpiryWarningNotDue");
+        }
+      } finally {
+        await setExpiryConfig(0, 7 * day);
+      }
+    });
+  });
+
+  describe("Bridge", () => {
+    const bridger = Keypair.generate();
+    const bridgeName = `bridge${Math.floor(Math.random() * 1_000_000)}`;
+    let nameRecordPda: PublicKey;
+    let bridgeLockPda: PublicKey;
+    let ownerCountPda: PublicKey;
+    let primaryNamePda: PublicKey;
+
+    before(async () => {
+      await provider.connection.requestAirdrop(bridger.publicKey, LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      [nameRecordPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("name"), Buffer.from(bridgeName)],
+        program.programId
+      );
+      [bridgeLockPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("bridge_lock"), Buffer.from(bridgeName)],
+        program.programId
+      );
+      [ownerCountPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("owner_count"), bridger.publicKey.toBuffer()],
+        program.programId
+      );
+      [primaryNamePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("primary"), bridger.publicKey.toBuffer()],
+        program.programId
+      );
+
+      await program.methods
+        .registerName(bridgeName)
+        .accounts({
+          user: bridger.publicKey,
+          config: configPda,
+          nameRecord: nameRecordPda,
+          primaryName: primaryNamePda,
+          treasury: treasury.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([bridger])
+        .preInstructions([
+          ComputeBudgetProgram.setComputeUnitLimit({ units: 400000 })
+        ])
+        .rpc();
+    });
+
+    const lockForBridge = (nearOwner: string) =>
+      program.methods
+        .lockForBridge(bridgeName, nearOwner)
+        .accounts({
+          owner: bridger.publicKey,
+          nameRecord: nameRecordPda,
+          bridgeLock: bridgeLockPda,
+          ownerCount: ownerCountPda,
+          primaryName: primaryNamePda,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([bridger])
+        .rpc();
+
+    it("Rejects a malformed NEAR account ID", async () => {
+      for (const nearOwner of ["Alice.near", "alice..near", ".alice", "alice-", "a"]) {
+        try {
+          await lockForBridge(nearOwner);
+          expect.fail(`Should reject ${nearOwner}`);
+        } catch (e: any) {
+          expect(e.toString()).to.include("InvalidNearAccount");
+        }
+      }
+    });
+
+    it("Locks a name and releases the owner's count and primary name", async () => {
+      const countBefore = await program.account.ownerNameCount.fetch(ownerCountPda);
+
+      await lockForBridge("alice.near");
+
+      const nameRecord = await program.account.nameRecord.fetch(nameRecordPda);
+      expect(nameRecord.owner.toString()).to.equal(bridgeLockPda.toString());
+
+      const bridgeLock = await program.account.bridgeLock.fetch(bridgeLockPda);
+      expect(bridgeLock.owner.toString()).to.equal(bridger.publicKey.toString());
+      expect(bridgeLock.nearOwner).to.equal("alice.near");
+      expect(bridgeLock.claimed).to.equal(false);
+
+      const countAfter = await program.account.ownerNameCount.fetch(ownerCountPda);
+      expect(countAfter.count).to.equal(countBefore.count - 1);
+
+      const primaryName = await program.account.primaryNameRegistry.fetch(primaryNamePda);
+      expect(primaryName.name).to.equal("");
+    });
+
+    it("Rejects unlocking before the timeout", async () => {
+      try {
+        await program.methods
+          .unlockBridgeLock(bridgeName)
+          .accounts({
+            owner: bridger.publicKey,
+            nameRecord: nameRecordPda,
+            bridgeLock: bridgeLockPda,
+            ownerCount: ownerCountPda,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .signers([bridger])
+          .rpc();
+        expect.fail("Should reject an early unlock");
+      } catch (e: any) {
+        expect(e.toString()).to.include("BridgeLockActive");
+      }
+    });
+
+    it("Only the admin can mark a lock claimed", async () => {
+      try {
+        await program.methods
+          .markBridgeClaimed(bridgeName)
+          .accounts({
+            admin: bridger.publicKey,
+            config: configPda,
+            bridgeLock: bridgeLockPda,
+          } as any)
+          .signers([bridger])
+          .rpc();
+        expect.fail("Should reject a non-admin");
+      } catch (e: any) {
+        expect(e.toString()).to.include("Unauthorized");
+      }
+
+      await program.methods
+        .markBridgeClaimed(bridgeName)
+        .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+          bridgeLock: bridgeLockPda,
+        } as any)
+        .signers([admin])
+        .rpc();
+
+      const bridgeLock = await program.account.bridgeLock.fetch(bridgeLockPda);
+      expect(bridgeLock.claimed).to.equal(true);
+    });
+  });
+
+  describe("Commit-Reveal", () => {
+    const committer = Keypair.generate();
+    const revealedName = `revealed${Math.floor(Math.random() * 1_000_000)}`;
+    const salt = Buffer.alloc(32, 7);
+    let commitConfigPda: PublicKey;
+
+    before(async () => {
+      await provider.connection.requestAirdrop(committer.publicKey, LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      [commitConfigPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("commit_config")],
+        program.programId
+      );
+    });
+
+    // sha256(name || salt || owner || slot), mirrors reveal_name
+    const commitmentFor = (name: string, owner: PublicKey, slot: number) => {
+      const slotBytes = Buffer.alloc(8);
+      slotBytes.writeBigUInt64LE(BigInt(slot));
+      return createHash("sha256")
+        .update(Buffer.concat([Buffer.from(name), salt, owner.toBuffer(), slotBytes]))
+        .digest();
+    };
+
+    const commitmentPdaFor = (commitment: Buffer) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("commitment"), commitment],
+        program.programId
+      )[0];
+
+    const commit = (commitment: Buffer, slot: number) =>
+      program.methods
+        .commitName(Array.from(commitment), new anchor.BN(slot))
+        .accounts({
+          user: committer.publicKey,
+          commitConfig: commitConfigPda,
+          commitment: commitmentPdaFor(commitment),
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([committer])
+        .rpc();
+
+    it("Only the admin can set the commitment config", async () => {
+      try {
+        await program.methods
+          .setCommitmentConfig(new anchor.BN(0), new anchor.BN(1000), new anchor.BN(0))
+          .accounts({
+            admin: committer.publicKey,
+            config: configPda,
+            commitConfig: commitConfigPda,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .signers([committer])
+          .rpc();
+        expect.fail("Should reject a non-admin");
+      } catch (e: any) {
+        expect(e.toString()).to.include("Unauthorized");
+      }
+    });
+
+    it("Falls back to the default slot window when given 0", async () => {
+      await program.methods
+        .setCommitmentConfig(new anchor.BN(0), new anchor.BN(1000), new anchor.BN(0))
+        .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+          commitConfig: commitConfigPda,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([admin])
+        .rpc();
+
+      const commitConfig = await program.account.commitmentConfig.fetch(commitConfigPda);
+      expect(commitConfig.minRevealSlots.toNumber()).to.equal(0);
+      expect(commitConfig.maxRevealSlots.toNumber()).to.equal(1000);
+      expect(commitConfig.slotCommitmentWindow.toNumber()).to.equal(150);
+      expect(commitConfig.legacyCommitmentCutoffSlot.toNumber()).to.equal(0);
+    });
+
+    it("Refuses to migrate a current commitment config", async () => {
+      try {
+        await program.methods
+          .migrateCommitmentConfig()
+          .accounts({
+            admin: admin.publicKey,
+            config: configPda,
+            commitConfig: commitConfigPda,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .signers([admin])
+          .rpc();
+        expect.fail("Should reject a current commitment config");
+      } catch (e: any) {
+        expect(e.toString()).to.include("AlreadyMigrated");
+      }
+    });
+
+    it("Rejects a commitment hashed with a stale slot", async () => {
+      const staleSlot = Math.max(0, (await provider.connection.getSlot()) - 10_000);
+      try {
+        await commit(commitmentFor(revealedName, committer.publicKey, staleSlot), staleSlot);
+        expect.fail("Should reject a slot outside the window");
+      } catch (e: any) {
+        expect(e.toString()).to.include("CommitmentSlotOutOfWindow");
+      }
+    });
+
+    it("Commits and reveals a name", async () => {
+      const slot = await provider.connection.getSlot();
+      const commitment = commitmentFor(revealedName, committer.publicKey, slot);
+      await commit(commitment, slot);
+
+      const commitmentRecord = await program.account.commitment.fetch(commitmentPdaFor(commitment));
+      expect(commitmentRecord.committer.toString()).to.equal(committer.publicKey.toString());
+      expect(commitmentRecord.committedSlot.toNumber()).to.equal(slot);
+
+      const [nameRecordPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("name"), Buffer.from(revealedName)],
+        program.programId
+      );
+      const reveal = (name: string) =>
+        program.methods
+          .revealName(name, Array.from(salt))
+          .accounts({
+            user: committer.publicKey,
+            config: configPda,
+            charset: PublicKey.findProgramAddressSync([Buffer.from("charset")], program.programId)[0],
+            commitConfig: commitConfigPda,
+            commitment: commitmentPdaFor(commitment),
+            nameRecord: PublicKey.findProgramAddressSync(
+              [Buffer.from("name"), Buffer.from(name)],
+              program.programId
+            )[0],
+            treasury: treasury.publicKey,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .signers([committer])
+          .preInstructions([
+            ComputeBudgetProgram.setComputeUnitLimit({ units: 400000 })
+          ])
+          .rpc();
+
+      try {
+        await reveal(`${revealedName}x`);
+        expect.fail("Should reject a name that doesn't match the commitment");
+      } catch (e: any) {
+        expect(e.toString()).to.include("CommitmentMismatch");
+      }
+
+      await reveal(revealedName);
+
+      const nameRecord = await program.account.nameRecord.fetch(nameRecordPda);
+      expect(nameRecord.owner.toString()).to.equal(committer.publicKey.toString());
+      expect(await provider.connection.getAccountInfo(commitmentPdaFor(commitment))).to.equal(null);
+    });
+  });
+
+  describe("Governance Gate", () => {
+    const applicant = Keypair.generate();
+    const realm = Keypair.generate().publicKey;
+    const governingTokenMint = Keypair.generate().publicKey;
+    let govGatePda: PublicKey;
+
+    before(async () => {
+      await provider.connection.requestAirdrop(applicant.publicKey, LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      [govGatePda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("gov_gate")],
+        program.programId
+      );
+    });
+
+    const setGate = (signer: Keypair) =>
+      program.methods
+        .setGovernanceGate(realm, governingTokenMint)
+        .accounts({
+          admin: signer.publicKey,
+          config: configPda,
+          govGate: govGatePda,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([signer])
+        .rpc();
+
+    const clearGate = () =>
+      program.methods
+        .clearGovernanceGate()
+        .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+          govGate: govGatePda,
+        } as any)
+        .signers([admin])
+        .rpc();
+
+    const registerDirectly = (name: string) =>
+      program.methods
+        .registerName(name)
+        .accounts({
+          user: applicant.publicKey,
+          config: configPda,
+          nameRecord: PublicKey.findProgramAddressSync(
+            [Buffer.from("name"), Buffer.from(name)],
+            program.programId
+          )[0],
+          primaryName: PublicKey.findProgramAddressSync(
+            [Buffer.from("primary"), applicant.publicKey.toBuffer()],
+            program.programId
+          )[0],
+          treasury: treasury.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([applicant])
+        .preInstructions([
+          ComputeBudgetProgram.setComputeUnitLimit({ units: 400000 })
+        ])
+        .rpc();
+
+    it("Only the admin can set the gate", async () => {
+      try {
+        await setGate(applicant);
+        expect.fail("Should reject a non-admin");
+      } catch (e: any) {
+        expect(e.toString()).to.include("Unauthorized");
+      }
+    });
+
+    it("Closes direct registration while the gate is set and reopens it when cleared", async () => {
+      await setGate(admin);
+      try {
+        let config = await program.account.registryConfig.fetch(configPda);
+        expect(config.governanceGated).to.equal(true);
+        const gate = await program.account.governanceGate.fetch(govGatePda);
+        expect(gate.realm.toString()).to.equal(realm.toString());
+
+        // Pointing the gate at another mint is allowed while it is active
+        await setGate(admin);
+
+        try {
+          await registerDirectly(`gated${Math.floor(Math.random() * 1_000_000)}`);
+          expect.fail("Should reject registration outside governance");
+        } catch (e: any) {
+          expect(e.toString()).to.include("GovernanceGateActive");
+        }
+      } finally {
+        await clearGate();
+      }
+
+      const config = await program.account.registryConfig.fetch(configPda);
+      expect(config.governanceGated).to.equal(false);
+      expect(await provider.connection.getAccountInfo(govGatePda)).to.equal(null);
+
+      await registerDirectly(`ungated${Math.floor(Math.random() * 1_000_000)}`);
+    });
+  });
+
+  describe("Auctions", () => {
+    const bidder = Keypair.generate();
+    const auctionName = `auction${Math.floor(Math.random() * 1_000_000)}`;
+    let auctionPda: PublicKey;
+    let nameRecordPda: PublicKey;
+
+    before(async () => {
+      await provider.connection.requestAirdrop(bidder.publicKey, 2 * LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      [auctionPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("auction"), Buffer.from(auctionName)],
+        program.programId
+      );
+      [nameRecordPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("name"), Buffer.from(auctionName)],
+        program.programId
+      );
+    });
+
+    it("Holds the name record while the auction runs", async () => {
+      await program.methods
+        .startAuction(
+          auctionName.toUpperCase(),
+          new anchor.BN(LAMPORTS_PER_SOL / 10),
+          new anchor.BN(LAMPORTS_PER_SOL / 100),
+          new anchor.BN(1000)
+        )
+        .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+          charset: null,
+          auction: auctionPda,
+          nameRecord: nameRecordPda,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([admin])
+        .rpc();
+
+      const nameRecord = await program.account.nameRecord.fetch(nameRecordPda);
+      expect(nameRecord.name).to.equal(auctionName);
+      expect(nameRecord.owner.toString()).to.equal(auctionPda.toString());
+
+      try {
+        await program.methods
+          .registerName(auctionName)
+          .accounts({
+            user: bidder.publicKey,
+            config: configPda,
+            nameRecord: nameRecordPda,
+            primaryName: PublicKey.findProgramAddressSync(
+              [Buffer.from("primary"), bidder.publicKey.toBuffer()],
+              program.programId
+            )[0],
+            treasury: treasury.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([bidder])
+          .preInstructions([
+            ComputeBudgetProgram.setComputeUnitLimit({ units: 400000 })
+          ])
+          .rpc();
+        expect.fail("Should reject registering a name under auction");
+      } catch (e: any) {
+        expect(e.toString()).to.include("already in use");
+      }
+    });
+
+    it("Settles to the winning bidder under the lowercased seeds", async () => {
+      await program.methods
+        .placeBid(auctionName)
+        .accounts({
+          bidder: bidder.publicKey,
+          auction: auctionPda,
+          previousBidder: null,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([bidder])
+        .rpc();
+
+      const auction = await program.account.auction.fetch(auctionPda);
+      expect(auction.winner!.toString()).to.equal(bidder.publicKey.toString());
+
+      // A second bid inside the settle window is refused
+      try {
+        await program.methods
+          .placeBid(auctionName)
+          .accounts({
+            bidder: admin.publicKey,
+            auction: auctionPda,
+            previousBidder: bidder.publicKey,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .signers([admin])
+          .rpc();
+        expect.fail("Should reject a bid on a closed auction");
+      } catch (e: any) {
+        expect(e.toString()).to.include("AuctionClosed");
+      }
+
+      const treasuryBefore = await provider.connection.getBalance(treasury.publicKey);
+      await program.methods
+        .settleAuction(auctionName.toUpperCase())
+        .accounts({
+          winner: bidder.publicKey,
+          config: configPda,
+          auction: auctionPda,
+          nameRecord: nameRecordPda,
+          treasury: treasury.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([bidder])
+        .rpc();
+
+      const nameRecord = await program.account.nameRecord.fetch(nameRecordPda);
+      expect(nameRecord.owner.toString()).to.equal(bidder.publicKey.toString());
+      expect(nameRecord.resolved.toString()).to.equal(bidder.publicKey.toString());
+      const treasuryAfter = await provider.connection.getBalance(treasury.publicKey);
+      expect(treasuryAfter - treasuryBefore).to.equal(auction.winningBid.toNumber());
+      expect(await provider.connection.getAccountInfo(auctionPda)).to.equal(null);
+    });
+  });
+
+  describe("Avatars", () => {
+    const avatarOwner = Keypair.generate();
+    const name = `avatar${Math.floor(Math.random() * 1_000_000)}`;
+    const [nameRecordPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("name"), Buffer.from(name)],
+      program.programId
+    );
+    const [avatarPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("avatar"), createHash("sha256").update(name).digest()],
+      program.programId
+    );
+    const setAvatar = (signer: Keypair, uri: string, mimeType: string) =>
+      program.methods
+        .setAvatar(name, uri, mimeType)
+        .accounts({
+          owner: signer.publicKey,
+          nameRecord: nameRecordPda,
+          avatar: avatarPda,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([signer])
+        .rpc();
+
+    before(async () => {
+      await provider.connection.requestAirdrop(avatarOwner.publicKey, LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      await program.methods
+        .registerName(name)
+        .accounts({
+          user: avatarOwner.publicKey,
+          config: configPda,
+          nameRecord: nameRecordPda,
+          primaryName: PublicKey.findProgramAddressSync(
+            [Buffer.from("primary"), avatarOwner.publicKey.toBuffer()],
+            program.programId
+          )[0],
+          treasury: treasury.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([avatarOwner])
+        .rpc();
+    });
+
+    it("Sets an avatar with a supported MIME type", async () => {
+      await setAvatar(avatarOwner, "ipfs://avatar.png", "image/png");
+
+      const avatar = await program.account.avatarRecord.fetch(avatarPda);
+      expect(avatar.uri).to.equal("ipfs://avatar.png");
+      expect(avatar.mimeType).to.equal("image/png");
+    });
+
+    it("Rejects an unsupported MIME type", async () => {
+      try {
+        await setAvatar(avatarOwner, "ipfs://avatar.bmp", "image/bmp");
+        expect.fail("Should reject image/bmp");
+      } catch (e: any) {
+        expect(e.toString()).to.include("UnsupportedMimeType");
+      }
+    });
+
+    it("Rejects a URI over 200 bytes", async () => {
+      try {
+        await setAvatar(avatarOwner, "ipfs://" + "a".repeat(200), "image/png");
+        expect.fail("Should reject a long URI");
+      } catch (e: any) {
+        expect(e.toString()).to.include("AvatarUriTooLong");
+      }
+    });
+
+    it("Only the owner can set the avatar", async () => {
+      try {
+        await setAvatar(user, "ipfs://other.png", "image/png");
+        expect.fail("Should reject a non-owner");
+      } catch (e: any) {
+        expect(e.toString()).to.include("Unauthorized");
+      }
+    });
+
+    it("Deletes the avatar", async () => {
+      await program.methods
+        .deleteAvatar(name)
+        .accounts({
+          owner: avatarOwner.publicKey,
+          nameRecord: nameRecordPda,
+          avatar: avatarPda,
+        } as any)
+        .signers([avatarOwner])
+        .rpc();
+
+      expect(await provider.connection.getAccountInfo(avatarPda)).to.be.null;
+    });
+  });
+
+  describe("Multisig Admin", () => {
+    const SQUADS_PROGRAM_ID = new PublicKey("SMPLecH534NA9acpos4G6x7uf3LWbCAwZQE9e8ZekMu");
+
+    it("Rejects a multisig account not owned by Squads", async () => {
+      const fakeMultisig = Keypair.generate();
+      await provider.connection.requestAirdrop(fakeMultisig.publicKey, LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      const indexBytes = Buffer.alloc(4);
+      indexBytes.writeUInt32LE(1);
+      const [executor] = PublicKey.findProgramAddressSync(
+        [Buffer.from("squad"), fakeMultisig.publicKey.toBuffer(), indexBytes, Buffer.from("authority")],
+        SQUADS_PROGRAM_ID
+      );
+
+      try {
+        await program.methods
+          .enableMultisig(fakeMultisig.publicKey, 2)
+          .accounts({
+            admin: admin.publicKey,
+            multisig: fakeMultisig.publicKey,
+            executor,
+            config: configPda,
+          } as any)
+          .signers([admin])
+          .rpc();
+        expect.fail("Should have rejected a multisig not owned by Squads");
+      } catch (e: any) {
+        expect(e.toString()).to.include("InvalidMultisigAccount");
+      }
+
+      const config = await program.account.registryConfig.fetch(configPda);
+      expect(config.admin.toString()).to.equal(admin.publicKey.toString());
+    });
+  });
+
+  describe("Leases", () => {
+    const lessor = Keypair.generate();
+    const lessee = Keypair.generate();
+    const name = `lease${Math.floor(Math.random() * 1_000_000)}`;
+    const [nameRecordPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("name"), Buffer.from(name)],
+      program.programId
+    );
+    const [leaseRecordPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("lease"), Buffer.from(name)],
+      program.programId
+    );
+
+    before(async () => {
+      await provider.connection.requestAirdrop(lessor.publicKey, LAMPORTS_PER_SOL);
+      await provider.connection.requestAirdrop(lessee.publicKey, LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      await program.methods
+        .registerName(name)
+        .accounts({
+          user: lessor.publicKey,
+          config: configPda,
+          nameRecord: nameRecordPda,
+          primaryName: PublicKey.findProgramAddressSync(
+            [Buffer.from("primary"), lessor.publicKey.toBuffer()],
+            program.programId
+          )[0],
+          treasury: treasury.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([lessor])
+        .rpc();
+      await program.methods
+        .leaseName(name, lessee.publicKey, new anchor.BN(3600), new anchor.BN(1_000))
+        .accounts({
+          owner: lessor.publicKey,
+          lessee: lessee.publicKey,
+          nameRecord: nameRecordPda,
+          leaseRecord: leaseRecordPda,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([lessor, lessee])
+        .rpc();
+    });
+
+    it("Lets the lessee resolve the name", async () => {
+      const newResolved = Keypair.generate().publicKey;
+      const nameHash = createHash("sha256").update(name).digest();
+      const [historyHeadPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("history_head"), nameHash],
+        program.programId
+      );
+      const [historyEntryPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("history"), nameHash, Buffer.alloc(4)],
+        program.programId
+      );
+      await program.methods
+        .setResolvedAddress(name, newResolved)
+        .accounts({
+          authority: lessee.publicKey,
+          nameRecord: nameRecordPda,
+        } as any)
+        .remainingAccounts([
+          { pubkey: historyHeadPda, isSigner: false, isWritable: true },
+          { pubkey: historyEntryPda, isSigner: false, isWritable: true },
+        ])
+        .signers([lessee])
+        .rpc();
+
+      const nameRecord = await program.account.nameRecord.fetch(nameRecordPda);
+      expect(nameRecord.resolved.toString()).to.equal(newResolved.toString());
+    });
+
+    it("Refuses owner-only actions from the lessee", async () => {
+      const attempts: [string, () => Promise<unknown>][] = [
+        ["transfer_name", () => program.methods
+          .transferName(name, Keypair.generate().publicKey)
+          .accounts({ owner: lessee.publicKey, nameRecord: nameRecordPda } as any)
+          .signers([lessee])
+          .rpc()],
+        ["lock_name", () => program.methods
+          .lockName(name)
+          .accounts({ owner: lessee.publicKey, nameRecord: nameRecordPda, leaseRecord: leaseRecordPda } as any)
+          .signers([lessee])
+          .rpc()],
+        ["grant_acl", () => program.methods
+          .grantAcl(name, Keypair.generate().publicKey, 1)
+          .accounts({
+            owner: lessee.publicKey,
+            nameRecord: nameRecordPda,
+            leaseRecord: leaseRecordPda,
+            systemProgram: SystemProgram.programId,
+          } as any)
+          .signers([lessee])
+          .rpc()],
+      ];
+      for (const [instruction, attempt] of attempts) {
+        try {
+          await attempt();
+          expect.fail(`${instruction} should be refused during a lease`);
+        } catch (e: any) {
+          expect(e.toString(), instruction).to.include("LeaseActive");
+        }
+      }
+    });
+
+    it("Returns the name to the original owner when the lease ends", async () => {
+      await program.methods
+        .endLease(name)
+        .accounts({
+          caller: lessor.publicKey,
+          originalOwner: lessor.publicKey,
+          nameRecord: nameRecordPda,
+          leaseRecord: leaseRecordPda,
+        } as any)
+        .signers([lessor])
+        .rpc();
+
+      const nameRecord = await program.account.nameRecord.fetch(nameRecordPda);
+      expect(nameRecord.owner.toString()).to.equal(lessor.publicKey.toString());
+      expect(await provider.connection.getAccountInfo(leaseRecordPda)).to.be.null;
+    });
+  });
+
+  describe("Social Attestations", () => {
+    const holder = Keypair.generate();
+    const buyer = Keypair.generate();
+    const attesterKey = Keypair.generate().publicKey;
+    const name = `social${Math.floor(Math.random() * 1_000_000)}`;
+    const platform = "github";
+    const nameHash = createHash("sha256").update(name).digest();
+    const [nameRecordPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("name"), Buffer.from(name)],
+      program.programId
+    );
+    const [attesterPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("attester"), Buffer.from(platform)],
+      program.programId
+    );
+    const [attestationPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("social"), nameHash, Buffer.from(platform)],
+      program.programId
+    );
+
+    const setAttestation = (owner: Keypair, handle: string) => program.methods
+      .setSocialAttestation(name, platform, handle, Array.from(createHash("sha256").update(handle).digest()))
+      .accounts({
+        owner: owner.publicKey,
+        nameRecord: nameRecordPda,
+        attester: attesterPda,
+        socialAttestation: attestationPda,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([owner])
+      .rpc();
+    const invalidate = () => program.methods
+      .invalidateSocialAttestation(name, platform)
+      .accounts({
+        nameRecord: nameRecordPda,
+        socialAttestation: attestationPda,
+        previousOwner: holder.publicKey,
+      } as any)
+      .rpc();
+
+    before(async () => {
+      await provider.connection.requestAirdrop(holder.publicKey, LAMPORTS_PER_SOL);
+      await provider.connection.requestAirdrop(buyer.publicKey, LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      await program.methods
+        .registerName(name)
+        .accounts({
+          user: holder.publicKey,
+          config: configPda,
+          nameRecord: nameRecordPda,
+          primaryName: PublicKey.findProgramAddressSync(
+            [Buffer.from("primary"), holder.publicKey.toBuffer()],
+            program.programId
+          )[0],
+          treasury: treasury.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([holder])
+        .rpc();
+      await program.methods
+        .setAttester(platform, attesterKey)
+        .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+          attester: attesterPda,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([admin])
+        .rpc();
+    });
+
+    it("Rejects an empty handle with SocialHandleEmpty", async () => {
+      try {
+        await setAttestation(holder, "");
+        expect.fail("Expected SocialHandleEmpty");
+      } catch (e: any) {
+        expect(e.toString()).to.include("SocialHandleEmpty");
+      }
+    });
+
+    it("Stores the owner and attester with the attestation", async () => {
+      await setAttestation(holder, "holder-gh");
+      const attestation = await program.account.socialAttestation.fetch(attestationPda);
+      expect(attestation.handle).to.equal("holder-gh");
+      expect(attestation.owner.toString()).to.equal(holder.publicKey.toString());
+      expect(attestation.attester.toString()).to.equal(attesterKey.toString());
+
+      try {
+        await invalidate();
+        expect.fail("Expected SocialAttestationNotStale");
+      } catch (e: any) {
+        expect(e.toString()).to.include("SocialAttestationNotStale");
+      }
+    });
+
+    it("Lets anyone close the previous owner's attestation after a transfer", async () => {
+      await program.methods
+        .transferName(name, buyer.publicKey)
+        .accounts({
+          owner: holder.publicKey,
+          nameRecord: nameRecordPda,
+        } as any)
+        .signers([holder])
+        .rpc();
+
+      const holderBefore = await provider.connection.getBalance(holder.publicKey);
+      await invalidate();
+      expect(await provider.connection.getAccountInfo(attestationPda)).to.be.null;
+      expect(await provider.connection.getBalance(holder.publicKey)).to.be.greaterThan(holderBefore);
+
+      // The new owner links their own handle and can delete it again
+      await setAttestation(buyer, "buyer-gh");
+      const attestation = await program.account.socialAttestation.fetch(attestationPda);
+      expect(attestation.owner.toString()).to.equal(buyer.publicKey.toString());
+      await program.methods
+        .deleteSocialAttestation(name, platform)
+        .accounts({
+          owner: buyer.publicKey,
+          nameRecord: nameRecordPda,
+          socialAttestation: attestationPda,
+        } as any)
+        .signers([buyer])
+        .rpc();
+      expect(await provider.connection.getAccountInfo(attestationPda)).to.be.null;
+    });
+  });
+
+  describe("Governance Voting", () => {
+    const voter = Keypair.generate();
+    const [governancePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("governance")],
+      program.programId
+    );
+    let votingMint: PublicKey;
+    let voterTokenAccount: PublicKey;
+
+    before(async () => {
+      await provider.connection.requestAirdrop(voter.publicKey, LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+      votingMint = await createMint(provider.connection, admin, admin.publicKey, null, 6);
+      voterTokenAccount = (await getOrCreateAssociatedTokenAccount(
+        provider.connection,
+        admin,
+        votingMint,
+        voter.publicKey
+      )).address;
+      await mintTo(provider.connection, admin, votingMint, voterTokenAccount, admin, 1_000_000);
+
+      // Quorum out of reach, so the proposal is defeated and config stays untouched
+      await program.methods
+        .setGovernanceConfig(votingMint, new anchor.BN(20), new anchor.BN("18446744073709551615"))
+        .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+          governance: governancePda,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([admin])
+        .rpc();
+    });
+
+    it("Locks the voter's tokens until voting ends", async () => {
+      const proposalId = (await program.account.governanceConfig.fetch(governancePda)).proposalCount;
+      const idBytes = proposalId.toArrayLike(Buffer, "le", 8);
+      const [proposalPda] = PublicKey.findProgramAddressSync([Buffer.from("proposal"), idBytes], program.programId);
+      const [voteEscrowPda] = PublicKey.findProgramAddressSync([Buffer.from("vote_escrow"), idBytes], program.programId);
+      const [voteRecordPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vote"), idBytes, voter.publicKey.toBuffer()],
+        program.programId
+      );
+
+      const value = Buffer.alloc(2);
+      value.writeUInt16LE(250);
+      await program.methods
+        .createProposal({ referrerBps: {} }, value, new anchor.BN(0))
+        .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+          governance: governancePda,
+          proposal: proposalPda,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([admin])
+        .rpc();
+
+      await program.methods
+        .vote(proposalId, true)
+        .accounts({
+          voter: voter.publicKey,
+          governance: governancePda,
+          proposal: proposalPda,
+          votingMint,
+          voterTokenAccount,
+          voteEscrow: voteEscrowPda,
+          voteRecord: voteRecordPda,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([voter])
+        .rpc();
+
+      const proposal = await program.account.proposal.fetch(proposalPda);
+      expect(proposal.votesFor.toNumber()).to.equal(1_000_000);
+      expect(Number((await getAccount(provider.connection, voterTokenAccount)).amount)).to.equal(0);
+      expect(Number((await getAccount(provider.connection, voteEscrowPda)).amount)).to.equal(1_000_000);
+
+      const withdraw = () => program.methods
+        .withdrawVoteTokens(proposalId)
+        .accounts({
+          voter: voter.publicKey,
+          proposal: proposalPda,
+          voteEscrow: voteEscrowPda,
+          voterTokenAccount,
+          voteRecord: voteRecordPda,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        } as any)
+        .signers([voter])
+        .rpc();
+
+      try {
+        await withdraw();
+        expect.fail("Expected VotingStillOpen");
+      } catch (e: any) {
+        expect(e.toString()).to.include("VotingStillOpen");
+      }
+
+      while ((await provider.connection.getSlot()) <= proposal.endSlot.toNumber()) {
+        await new Promise(resolve => setTimeout(resolve, 400));
+      }
+      await withdraw();
+      expect(Number((await getAccount(provider.connection, voterTokenAccount)).amount)).to.equal(1_000_000);
+      expect(await provider.connection.getAccountInfo(voteRecordPda)).to.be.null;
+    });
+  });
+
+  describe("Sub-registries", () => {
+    const childProgram = anchor.utils.token.TOKEN_PROGRAM_ID;
+    const [approvedProgramPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("sub_registry_program"), childProgram.toBuffer()],
+      program.programId
+    );
+    const [aliceRecordPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("name"), Buffer.from("alice")],
+      program.programId
+    );
+    const [subRegistryPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("sub_registry"), Buffer.from("alice")],
+      program.programId
+    );
+
+    const approve = (signer: Keypair) => program.methods
+      .approveSubRegistryProgram(childProgram)
+      .accounts({
+        admin: signer.publicKey,
+        config: configPda,
+        approvedProgram: approvedProgramPda,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([signer])
+      .rpc();
+    const createSubRegistry = () => program.methods
+      .createSubRegistry("alice", childProgram)
+      .accounts({
+        owner: user.publicKey,
+        nameRecord: aliceRecordPda,
+        approvedProgram: approvedProgramPda,
+        subRegistry: subRegistryPda,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([user])
+      .rpc();
+
+    it("Only lets name owners point at admin-approved programs", async () => {
+      try {
+        await createSubRegistry();
+        expect.fail("Expected the unapproved program to be rejected");
+      } catch (e: any) {
+        expect(e.toString()).to.include("AccountNotInitialized");
+      }
+
+      try {
+        await approve(user);
+        expect.fail("Expected Unauthorized");
+      } catch (e: any) {
+        expect(e.toString()).to.include("Unauthorized");
+      }
+
+      await approve(admin);
+      await createSubRegistry();
+      const subRegistry = await program.account.subRegistry.fetch(subRegistryPda);
+      expect(subRegistry.subRegistryProgram.toString()).to.equal(childProgram.toString());
+      expect(subRegistry.enabled).to.equal(true);
+    });
+
+    it("Stops resolving through a program once its approval is revoked", async () => {
+      await program.methods
+        .revokeSubRegistryProgram(childProgram)
+        .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+          approvedProgram: approvedProgramPda,
+        } as any)
+        .signers([admin])
+        .rpc();
+
+      try {
+        await program.methods
+          .resolveSubdomain("alice", "pay")
+          .accounts({
+            subRegistry: subRegistryPda,
+            subRegistryProgram: childProgram,
+            approvedProgram: approvedProgramPda,
+          } as any)
+          // Passed as a writable signer; the CPI must not forward either flag
+          .remainingAccounts([{ pubkey: user.publicKey, isSigner: true, isWritable: true }])
+          .signers([user])
+          .rpc();
+        expect.fail("Expected the revoked program to be rejected");
+      } catch (e: any) {
+        expect(e.toString()).to.include("AccountNotInitialized");
+      }
+    });
+  });
+
+  describe("Error Codes", () => {
+    const outsider = Keypair.generate();
+    const [alicePda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("name"), Buffer.from("alice")],
+      program.programId
+    );
+
+    // Runs `send` and requires it to fail with the named program error
+    const expectError = async (send: () => Promise<unknown>, code: string) => {
+      try {
+        await send();
+        expect.fail(`Expected ${code}`);
+      } catch (e: any) {
+        expect(e.toString()).to.include(code);
+      }
+    };
+
+    before(async () => {
+      await provider.connection.requestAirdrop(outsider.publicKey, 2 * LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+    });
+
+    it("set_token_fee: Unauthorized for a non-admin", async () => {
+      const mint = await createMint(provider.connection, outsider, outsider.publicKey, null, 6);
+      await expectError(() => program.methods
+        .setTokenFee(new anchor.BN(1), true)
+        .accounts({
+          admin: outsider.publicKey,
+          config: configPda,
+          mint,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([outsider])
+        .rpc(), "Unauthorized");
+    });
+
+    it("set_resolved_address: Unauthorized for a non-owner without an ACL entry", async () => {
+      await expectError(() => program.methods
+        .setResolvedAddress("alice", outsider.publicKey)
+        .accounts({
+          authority: outsider.publicKey,
+          nameRecord: alicePda,
+        } as any)
+        .signers([outsider])
+        .rpc(), "Unauthorized");
+    });
+
+    it("set_primary_name: Unauthorized for a name the signer does not own", async () => {
+      await expectError(() => program.methods
+        .setPrimaryName("alice")
+        .accounts({
+          user: outsider.publicKey,
+          nameRecord: alicePda,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([outsider])
+        .rpc(), "Unauthorized");
+    });
+
+    it("add_relayer / remove_relayer: Unauthorized for a non-admin", async () => {
+      const relayer = Keypair.generate();
+      await provider.connection.requestAirdrop(relayer.publicKey, LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 800));
+      const [relayerEntryPda] = PublicKey.findProgramAddressSync(
+        [Buffer.from("relayer"), relayer.publicKey.toBuffer()],
+        program.programId
+      );
+      const relayerBondPda = await stakeRelayer(relayer);
+      const addRelayer = (signer: Keypair) => program.methods
+        .addRelayer(relayer.publicKey, "test-relayer", "https://relayer.example.com")
+        .accounts({
+          admin: signer.publicKey,
+          config: configPda,
+          relayerEntry: relayerEntryPda,
+          relayerBond: relayerBondPda,
+          relayer: relayer.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([signer])
+        .rpc();
+
+      await expectError(() => addRelayer(outsider), "Unauthorized");
+      await addRelayer(admin);
+      await expectError(() => program.methods
+        .removeRelayer(relayer.publicKey)
+        .accounts({
+          admin: outsider.publicKey,
+          config: configPda,
+          relayerEntry: relayerEntryPda,
+        } as any)
+        .signers([outsider])
+        .rpc(), "Unauthorized");
+      expect(await provider.connection.getAccountInfo(relayerEntryPda)).to.not.be.null;
+    });
+
+    it("accept_admin: NoPendingAdminTransfer without a transfer, Unauthorized for the wrong signer", async () => {
+      const acceptAdmin = (signer: Keypair) => program.methods
+        .acceptAdmin()
+        .accounts({
+          newAdmin: signer.publicKey,
+          config: configPda,
+        } as any)
+        .signers([signer])
+        .rpc();
+
+      await expectError(() => acceptAdmin(outsider), "NoPendingAdminTransfer");
+
+      const nominee = Keypair.generate();
+      await program.methods
+        .transferAdmin(nominee.publicKey)
+        .accounts({
+          admin: admin.publicKey,
+          config: configPda,
+        } as any)
+        .signers([admin])
+        .rpc();
+      try {
+        await expectError(() => acceptAdmin(outsider), "Unauthorized");
+      } finally {
+        await program.methods
+          .cancelAdminTransfer()
+          .accounts({
+            admin: admin.publicKey,
+            config: configPda,
+          } as any)
+          .signers([admin])
+          .rpc();
+      }
+      const config = await program.account.registryConfig.fetch(configPda);
+      expect(config.admin.toString()).to.equal(admin.publicKey.toString());
     });
   });
 });
\ No newline at end of file